@@ -45,10 +45,37 @@ pub fn is_reset() -> bool {
     return MANAGER.as_ref().clap_matches.is_present("reset");
 }
 
+#[cfg(feature = "onvif")]
+pub fn is_onvif_enabled() -> bool {
+    return MANAGER.as_ref().clap_matches.is_present("onvif");
+}
+
 #[allow(dead_code)]
-// Return the mavlink connection string
-pub fn mavlink_connection_string() -> Option<&'static str> {
-    return MANAGER.as_ref().clap_matches.value_of("mavlink");
+// Return the mavlink connection strings, the first one is the main endpoint
+pub fn mavlink_connection_strings() -> Vec<&'static str> {
+    MANAGER
+        .as_ref()
+        .clap_matches
+        .values_of("mavlink")
+        .unwrap_or_default()
+        .collect()
+}
+
+// The system ID and the component ID of the first camera, when set
+pub fn mavlink_system_id() -> Option<u8> {
+    MANAGER
+        .as_ref()
+        .clap_matches
+        .value_of("mavlink-system-id")
+        .and_then(|id| id.parse().ok())
+}
+
+pub fn mavlink_component_id() -> Option<u8> {
+    MANAGER
+        .as_ref()
+        .clap_matches
+        .value_of("mavlink-component-id")
+        .and_then(|id| id.parse().ok())
 }
 
 pub fn log_path() -> String {
@@ -60,6 +87,19 @@ pub fn log_path() -> String {
         .to_string()
 }
 
+// Directory of the images and videos captured by request of the GCS
+#[cfg(any(feature = "mavlink", feature = "recording"))]
+pub fn capture_path() -> String {
+    MANAGER
+        .as_ref()
+        .clap_matches
+        .value_of("capture-path")
+        .expect(
+            "Clap arg \"capture-path\" should always be \"Some(_)\" because of the default value.",
+        )
+        .to_string()
+}
+
 // Return the desired address for the REST API
 pub fn server_address() -> &'static str {
     return MANAGER
@@ -69,10 +109,54 @@ pub fn server_address() -> &'static str {
         .unwrap();
 }
 
+// Return the certificate and private key files used by the RTSP server, if TLS is enabled
+pub fn rtsp_tls_files() -> Option<(&'static str, &'static str)> {
+    let matches = &MANAGER.as_ref().clap_matches;
+    Some((
+        matches.value_of("rtsp-tls-certificate")?,
+        matches.value_of("rtsp-tls-key")?,
+    ))
+}
+
 pub fn vehicle_ddns() -> Option<&'static str> {
     MANAGER.as_ref().clap_matches.value_of("vehicle-ddns")
 }
 
+pub fn event_buffer_size() -> usize {
+    MANAGER
+        .as_ref()
+        .clap_matches
+        .value_of("event-buffer-size")
+        .expect("Clap arg \"event-buffer-size\" should always be \"Some(_)\" because of the default value.")
+        .parse::<usize>()
+        .expect("Clap arg \"event-buffer-size\" should be validated as a positive integer.")
+}
+
+#[cfg(target_os = "linux")]
+pub fn camera_blacklist() -> Vec<&'static str> {
+    MANAGER
+        .as_ref()
+        .clap_matches
+        .values_of("camera-blacklist")
+        .unwrap_or_default()
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+pub fn camera_whitelist() -> Vec<&'static str> {
+    MANAGER
+        .as_ref()
+        .clap_matches
+        .values_of("camera-whitelist")
+        .unwrap_or_default()
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+pub fn camera_quirks_file() -> Option<&'static str> {
+    MANAGER.as_ref().clap_matches.value_of("camera-quirks")
+}
+
 pub fn default_settings() -> Option<&'static str> {
     return MANAGER.as_ref().clap_matches.value_of("default-settings");
 }
@@ -137,9 +221,28 @@ fn get_clap_matches<'a>() -> clap::ArgMatches<'a> {
             clap::Arg::with_name("mavlink")
                 .long("mavlink")
                 .value_name("TYPE>:<IP/SERIAL>:<PORT/BAUDRATE")
-                .help("Sets the mavlink connection string")
+                .help("Sets the mavlink connection string. \"udpout:192.168.2.1:14550\" sends to the address, which may be a broadcast one like \"udpout:192.168.2.255:14550\", \"udpin:0.0.0.0:14550\" listens and answers to the last sender, \"tcpout:192.168.2.2:5777\" connects to a TCP server and \"tcpin:0.0.0.0:5777\" waits for a TCP client. Connections are restarted when closed. A server address can only be used by a single camera. Can be used more than once, e.g: for the autopilot and a secondary GCS, the cameras are seen through every endpoint.")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(mavlink_connection_string_validator)
                 .takes_value(true)
         )
+        .arg(
+            clap::Arg::with_name("mavlink-system-id")
+                .long("mavlink-system-id")
+                .value_name("ID")
+                .help("Sets the MAVLink system ID of the cameras, the same of the vehicle.")
+                .validator(|value| mavlink_id_validator(value, 255))
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("mavlink-component-id")
+                .long("mavlink-component-id")
+                .value_name("ID")
+                .help("Sets the MAVLink component ID of the first camera, the next ones use the following IDs. The default is 100 (MAV_COMP_ID_CAMERA).")
+                .validator(|value| mavlink_id_validator(value, 250))
+                .takes_value(true),
+        )
         .arg(
             clap::Arg::with_name("default-settings")
                 .long("default-settings")
@@ -154,6 +257,28 @@ fn get_clap_matches<'a>() -> clap::ArgMatches<'a> {
                 .help("Deletes settings file before starting.")
                 .takes_value(false),
         )
+        .arg(
+            clap::Arg::with_name("onvif")
+                .long("onvif")
+                .help("Enables the ONVIF Device and Media services, exposing the RTSP streams to VMS/NVR systems.")
+                .takes_value(false),
+        )
+        .arg(
+            clap::Arg::with_name("rtsp-tls-certificate")
+                .long("rtsp-tls-certificate")
+                .value_name("PATH")
+                .help("Sets the PEM certificate used by the RTSP server, enabling TLS. RTSP endpoints should then use the \"rtsps\" scheme.")
+                .requires("rtsp-tls-key")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("rtsp-tls-key")
+                .long("rtsp-tls-key")
+                .value_name("PATH")
+                .help("Sets the PEM private key of the RTSP server certificate.")
+                .requires("rtsp-tls-certificate")
+                .takes_value(true),
+        )
         .arg(
             clap::Arg::with_name("rest-server")
                 .long("rest-server")
@@ -187,6 +312,55 @@ fn get_clap_matches<'a>() -> clap::ArgMatches<'a> {
                 .default_value("./logs")
                 .takes_value(true),
         )
+        .arg(
+            clap::Arg::with_name("capture-path")
+                .long("capture-path")
+                .value_name("PATH")
+                .help("Specifies the path in which the images and videos captured by request of the GCS will be stored.")
+                .default_value("./captures")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("event-buffer-size")
+                .long("event-buffer-size")
+                .value_name("SIZE")
+                .help("Sets how many of the latest internal events are kept to be served by the REST API.")
+                .default_value("1000")
+                .validator(|value| {
+                    value
+                        .parse::<usize>()
+                        .map(|_| ())
+                        .map_err(|_| "SIZE should be a positive integer.".to_string())
+                })
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("camera-blacklist")
+                .long("camera-blacklist")
+                .value_name("PATTERN")
+                .help("Ignores the local cameras with bus information, name or path matching this regular expression, e.g: \"bcm2835-isp\".")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(camera_pattern_validator)
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("camera-whitelist")
+                .long("camera-whitelist")
+                .value_name("PATTERN")
+                .help("Uses only the local cameras with bus information, name or path matching this regular expression.")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(camera_pattern_validator)
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("camera-quirks")
+                .long("camera-quirks")
+                .value_name("FILE")
+                .help("Loads known problems of camera models, and how to work around them, from this JSON file.")
+                .takes_value(true),
+        )
         .arg(
             clap::Arg::with_name("vehicle-ddns")
                 .long("vehicle-ddns")
@@ -197,6 +371,33 @@ fn get_clap_matches<'a>() -> clap::ArgMatches<'a> {
     matches.get_matches()
 }
 
+// Up to six cameras use consecutive component IDs, so the first one is limited
+fn mavlink_id_validator(val: String, max: u8) -> Result<(), String> {
+    match val.parse::<u8>() {
+        Ok(id) if (1..=max).contains(&id) => Ok(()),
+        _ => Err(format!("ID should be a number from 1 to {max}.")),
+    }
+}
+
+pub fn mavlink_connection_string_validator(val: String) -> Result<(), String> {
+    const TYPES: [&str; 7] = [
+        "tcpin", "tcpout", "udpin", "udpout", "udpbcast", "serial", "file",
+    ];
+    match val.split_once(':') {
+        Some((kind, address)) if TYPES.contains(&kind) && !address.is_empty() => Ok(()),
+        _ => Err(format!(
+            "Connection string should be <TYPE>:<ADDRESS>, where TYPE is one of {}.",
+            TYPES.join(", ")
+        )),
+    }
+}
+
+fn camera_pattern_validator(val: String) -> Result<(), String> {
+    regex::Regex::new(&val)
+        .map(|_| ())
+        .map_err(|error| format!("Invalid regular expression: {error}"))
+}
+
 fn gst_feature_rank_validator(val: String) -> Result<(), String> {
     if let Some((_key, value_str)) = val.split_once('=') {
         if let Err(_) = value_str.parse::<i32>() {
@@ -216,4 +417,13 @@ mod tests {
     fn default_arguments() {
         assert_eq!(is_verbose(), false);
     }
+
+    #[test]
+    fn mavlink_connection_strings() {
+        assert!(mavlink_connection_string_validator("tcpout:192.168.2.2:5777".into()).is_ok());
+        assert!(mavlink_connection_string_validator("tcpin:0.0.0.0:5777".into()).is_ok());
+        assert!(mavlink_connection_string_validator("udpin:0.0.0.0:14550".into()).is_ok());
+        assert!(mavlink_connection_string_validator("tcp:192.168.2.2:5777".into()).is_err());
+        assert!(mavlink_connection_string_validator("tcpout:".into()).is_err());
+    }
 }
@@ -2,11 +2,14 @@ use url::Url;
 
 use crate::network::utils::get_visible_qgc_address;
 use crate::stream::types::*;
-use crate::video::{self, types::*, video_source::VideoSourceAvailable};
+use crate::video::{
+    platform::{Platform, PlatformCameras},
+    types::*,
+};
 use crate::video_stream::types::VideoAndStreamInformation;
 
 pub fn udp() -> Vec<VideoAndStreamInformation> {
-    video::video_source_local::VideoSourceLocal::cameras_available()
+    Platform::cameras_available()
         .iter()
         .filter(|cam| {
             cam.inner()
@@ -41,8 +44,18 @@ pub fn udp() -> Vec<VideoAndStreamInformation> {
                         height: size.height,
                         width: size.width,
                         frame_interval: size.intervals.first().unwrap().clone(),
+                        transcode: None,
+                        bitrate_kbps: None,
+                        keyframe_interval: None,
+                        jpeg_quality: None,
+                        deinterlace: None,
+                        rotation: None,
+                        flip: None,
+                        crop: None,
+                        output_size: None,
                     }),
                     extended_configuration: None,
+                    audio: None,
                 },
                 video_source: cam.clone(),
             }
@@ -51,7 +64,7 @@ pub fn udp() -> Vec<VideoAndStreamInformation> {
 }
 
 pub fn rtsp() -> Vec<VideoAndStreamInformation> {
-    video::video_source_local::VideoSourceLocal::cameras_available()
+    Platform::cameras_available()
         .iter()
         .filter(|cam| {
             cam.inner()
@@ -89,8 +102,18 @@ pub fn rtsp() -> Vec<VideoAndStreamInformation> {
                         height: size.height,
                         width: size.width,
                         frame_interval: size.intervals.first().unwrap().clone(),
+                        transcode: None,
+                        bitrate_kbps: None,
+                        keyframe_interval: None,
+                        jpeg_quality: None,
+                        deinterlace: None,
+                        rotation: None,
+                        flip: None,
+                        crop: None,
+                        output_size: None,
                     }),
                     extended_configuration: None,
+                    audio: None,
                 },
                 video_source: cam.clone(),
             }
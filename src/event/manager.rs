@@ -0,0 +1,100 @@
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::cli;
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Stream,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    Hotplug,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    Control,
+    #[cfg_attr(not(feature = "mavlink"), allow(dead_code))]
+    Mavlink,
+    Error,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, Serialize)]
+pub struct Event {
+    pub id: u64,
+    pub timestamp: String,
+    pub kind: EventKind,
+    pub message: String,
+}
+
+struct Manager {
+    next_id: u64,
+    capacity: usize,
+    events: VecDeque<Event>,
+}
+
+lazy_static! {
+    static ref MANAGER: Arc<Mutex<Manager>> = Arc::new(Mutex::new(Manager {
+        next_id: 0,
+        capacity: cli::manager::event_buffer_size(),
+        events: VecDeque::new(),
+    }));
+}
+
+// Record a new event, dropping the oldest one if the buffer is full
+pub fn push(kind: EventKind, message: impl Into<String>) {
+    let mut manager = MANAGER.lock().unwrap();
+
+    let event = Event {
+        id: manager.next_id,
+        timestamp: chrono::Local::now().to_rfc3339(),
+        kind,
+        message: message.into(),
+    };
+    manager.next_id += 1;
+
+    if manager.capacity == 0 {
+        return;
+    }
+    while manager.events.len() >= manager.capacity {
+        manager.events.pop_front();
+    }
+    manager.events.push_back(event);
+}
+
+// Return all events newer than the given id, or all of them if no id is given
+pub fn events(since: Option<u64>) -> Vec<Event> {
+    let manager = MANAGER.lock().unwrap();
+    manager
+        .events
+        .iter()
+        .filter(|event| since.map_or(true, |since| event.id > since))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_buffer() {
+        let capacity = MANAGER.lock().unwrap().capacity;
+        let first_id = MANAGER.lock().unwrap().next_id;
+
+        for index in 0..capacity + 10 {
+            push(EventKind::Stream, format!("Event {index}"));
+        }
+
+        let all_events = events(None);
+        assert_eq!(all_events.len(), capacity);
+        assert_eq!(
+            all_events.last().unwrap().id,
+            first_id + capacity as u64 + 9
+        );
+
+        let last_id = all_events.last().unwrap().id;
+        let recent_events = events(Some(last_id - 3));
+        assert_eq!(recent_events.len(), 3);
+        assert!(events(Some(last_id)).is_empty());
+    }
+}
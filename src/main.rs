@@ -7,9 +7,13 @@ extern crate tracing;
 
 mod cli;
 mod custom;
+mod event;
 mod logger;
+#[cfg(feature = "mavlink")]
 mod mavlink;
 mod network;
+#[cfg(feature = "onvif")]
+mod onvif;
 mod server;
 mod settings;
 mod stream;
@@ -26,11 +30,37 @@ async fn main() -> Result<(), std::io::Error> {
     settings::manager::init(None);
 
     stream::manager::init();
-    if let Some(endpoint) = cli::manager::mavlink_connection_string() {
+    if let Some((endpoint, extra_endpoints)) =
+        cli::manager::mavlink_connection_strings().split_first()
+    {
         settings::manager::set_mavlink_endpoint(endpoint);
+        settings::manager::set_mavlink_extra_endpoints(
+            &extra_endpoints
+                .iter()
+                .map(|endpoint| endpoint.to_string())
+                .collect::<Vec<String>>(),
+        );
+    }
+    if cli::manager::mavlink_system_id().is_some() || cli::manager::mavlink_component_id().is_some()
+    {
+        let mut ids = settings::manager::mavlink_ids();
+        ids.system_id = cli::manager::mavlink_system_id().unwrap_or(ids.system_id);
+        ids.component_id = cli::manager::mavlink_component_id().unwrap_or(ids.component_id);
+        settings::manager::set_mavlink_ids(&ids);
     }
 
+    video::video_source::apply_control_defaults();
     stream::manager::start_default();
+    // Cameras are only watched through V4L2 and the kernel events
+    #[cfg(target_os = "linux")]
+    {
+        video::control_events::watch_cameras();
+        video::hotplug::start(|| {
+            video::video_source::apply_control_defaults();
+            stream::manager::update_devices();
+            video::control_events::watch_cameras();
+        });
+    }
 
     server::manager::run(cli::manager::server_address()).await
 }
@@ -0,0 +1,361 @@
+use mavlink::common::MavMessage;
+use mavlink::error::{MessageReadError, MessageWriteError};
+use mavlink::{MavConnection, MavHeader, MavlinkVersion};
+
+use std::io::{BufReader, ErrorKind};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub type Connection = Box<dyn MavConnection<MavMessage> + Sync + Send>;
+
+// Like the tcpout connection of the mavlink crate, so the receive thread can check its state
+// and a removed camera frees its port
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// Time waiting for a client before giving up the listener, to not block the camera forever
+const ACCEPT_TIMEOUT: Duration = Duration::from_secs(1);
+
+// The network connections of the mavlink crate panic when the host can't be resolved, and
+// their servers block until a client shows up, so these are handled here
+pub fn connect(address: &str) -> std::io::Result<Connection> {
+    match address.split_once(':') {
+        Some(("tcpout", address)) => Ok(Box::new(TcpConnection::tcpout(address)?)),
+        Some(("tcpin", address)) => Ok(Box::new(TcpConnection::tcpin(address)?)),
+        Some(("udpout", address)) | Some(("udpbcast", address)) => {
+            Ok(Box::new(UdpConnection::udpout(address)?))
+        }
+        Some(("udpin", address)) => Ok(Box::new(UdpConnection::udpin(address)?)),
+        _ => mavlink::connect(address),
+    }
+}
+
+// Without a connection to close, a silent TCP peer is only noticed when nothing is received
+pub fn is_tcp(address: &str) -> bool {
+    address.starts_with("tcpout:") || address.starts_with("tcpin:")
+}
+
+// The network connections time out while nothing is received
+pub fn is_timeout(error: &MessageReadError) -> bool {
+    match error {
+        MessageReadError::Io(io_error) => {
+            matches!(io_error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+        }
+        _ => false,
+    }
+}
+
+// State of the link of a camera, kept across reconnections for troubleshooting
+#[derive(Debug, Default)]
+pub struct LinkStatus {
+    pub connected: bool,
+    // Successful connections, more than one when the link was restarted
+    pub connections: u32,
+    // Why the last connection failed or was restarted
+    pub last_error: Option<String>,
+    pub messages_received: u64,
+    pub messages_sent: u64,
+    pub receive_errors: u64,
+    pub send_errors: u64,
+    pub last_received: Option<Instant>,
+    pub last_heartbeat_sent: Option<chrono::DateTime<chrono::Local>>,
+    pub last_vehicle_heartbeat: Option<chrono::DateTime<chrono::Local>>,
+    pub last_gcs_heartbeat: Option<chrono::DateTime<chrono::Local>>,
+}
+
+// Counts the messages of the connection in the status of the link
+pub fn monitored(connection: Connection, status: Arc<Mutex<LinkStatus>>) -> Connection {
+    Box::new(Monitored { connection, status })
+}
+
+struct Monitored {
+    connection: Connection,
+    status: Arc<Mutex<LinkStatus>>,
+}
+
+impl MavConnection<MavMessage> for Monitored {
+    fn recv(&self) -> Result<(MavHeader, MavMessage), MessageReadError> {
+        let result = self.connection.recv();
+        let mut status = self.status.lock().unwrap();
+        match &result {
+            Ok(_) => {
+                status.messages_received += 1;
+                status.last_received = Some(Instant::now());
+            }
+            Err(error) if is_timeout(error) => (),
+            Err(_) => status.receive_errors += 1,
+        }
+        result
+    }
+
+    fn send(&self, header: &MavHeader, data: &MavMessage) -> Result<usize, MessageWriteError> {
+        let result = self.connection.send(header, data);
+        let mut status = self.status.lock().unwrap();
+        match &result {
+            // UDP servers don't send until a client is heard
+            Ok(0) => (),
+            Ok(_) => status.messages_sent += 1,
+            Err(_) => status.send_errors += 1,
+        }
+        result
+    }
+
+    fn set_protocol_version(&mut self, version: MavlinkVersion) {
+        self.connection.set_protocol_version(version);
+    }
+
+    fn get_protocol_version(&self) -> MavlinkVersion {
+        self.connection.get_protocol_version()
+    }
+}
+
+// Placeholder until the thread of the camera connects to the endpoint
+pub fn disconnected() -> Connection {
+    Box::new(Disconnected {})
+}
+
+struct Disconnected {}
+
+impl MavConnection<MavMessage> for Disconnected {
+    fn recv(&self) -> Result<(MavHeader, MavMessage), MessageReadError> {
+        Err(MessageReadError::Io(not_connected()))
+    }
+
+    fn send(&self, _header: &MavHeader, _data: &MavMessage) -> Result<usize, MessageWriteError> {
+        Err(MessageWriteError::Io(not_connected()))
+    }
+
+    fn set_protocol_version(&mut self, _version: MavlinkVersion) {}
+
+    fn get_protocol_version(&self) -> MavlinkVersion {
+        MavlinkVersion::V2
+    }
+}
+
+fn not_connected() -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::NotConnected,
+        "Not connected to the MAVLink endpoint",
+    )
+}
+
+struct TcpConnection {
+    reader: Mutex<BufReader<TcpStream>>,
+    writer: Mutex<TcpWriter>,
+    protocol_version: MavlinkVersion,
+}
+
+struct TcpWriter {
+    socket: TcpStream,
+    sequence: u8,
+}
+
+impl TcpConnection {
+    fn tcpout(address: &str) -> std::io::Result<Self> {
+        let address = resolve(address)?;
+        let socket = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        Self::try_from_socket(socket)
+    }
+
+    fn tcpin(address: &str) -> std::io::Result<Self> {
+        let address = resolve(address)?;
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+
+        // Only one client is served, the listener is closed when it connects
+        let deadline = Instant::now() + ACCEPT_TIMEOUT;
+        loop {
+            match listener.accept() {
+                Ok((socket, _client_address)) => {
+                    socket.set_nonblocking(false)?;
+                    return Self::try_from_socket(socket);
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                    if Instant::now() > deadline {
+                        // Not a failure, the connection is tried again right away
+                        return Err(std::io::Error::new(
+                            ErrorKind::NotConnected,
+                            format!("No client connected to {address}"),
+                        ));
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn try_from_socket(socket: TcpStream) -> std::io::Result<Self> {
+        socket.set_read_timeout(Some(READ_TIMEOUT))?;
+        socket.set_nodelay(true)?;
+
+        Ok(Self {
+            reader: Mutex::new(BufReader::new(socket.try_clone()?)),
+            writer: Mutex::new(TcpWriter {
+                socket,
+                sequence: 0,
+            }),
+            protocol_version: MavlinkVersion::V2,
+        })
+    }
+}
+
+impl MavConnection<MavMessage> for TcpConnection {
+    fn recv(&self) -> Result<(MavHeader, MavMessage), MessageReadError> {
+        let mut reader = self.reader.lock().unwrap();
+        mavlink::read_versioned_msg(&mut *reader, self.protocol_version)
+    }
+
+    fn send(&self, header: &MavHeader, data: &MavMessage) -> Result<usize, MessageWriteError> {
+        let mut writer = self.writer.lock().unwrap();
+
+        let header = MavHeader {
+            sequence: writer.sequence,
+            system_id: header.system_id,
+            component_id: header.component_id,
+        };
+        writer.sequence = writer.sequence.wrapping_add(1);
+
+        mavlink::write_versioned_msg(&mut writer.socket, self.protocol_version, header, data)
+    }
+
+    fn set_protocol_version(&mut self, version: MavlinkVersion) {
+        self.protocol_version = version;
+    }
+
+    fn get_protocol_version(&self) -> MavlinkVersion {
+        self.protocol_version
+    }
+}
+
+struct UdpConnection {
+    reader: Mutex<UdpReader>,
+    writer: Mutex<UdpWriter>,
+    // Servers answer to the last client they heard from
+    server: bool,
+    protocol_version: MavlinkVersion,
+}
+
+struct UdpReader {
+    socket: UdpSocket,
+    // A datagram may have more than one message
+    datagram: Vec<u8>,
+    start: usize,
+    end: usize,
+}
+
+struct UdpWriter {
+    socket: UdpSocket,
+    destination: Option<SocketAddr>,
+    sequence: u8,
+}
+
+impl UdpConnection {
+    // Sends to the address, which may be a broadcast one, receiving the answers in a random port
+    fn udpout(address: &str) -> std::io::Result<Self> {
+        let address = resolve(address)?;
+        let socket = UdpSocket::bind(match address {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        })?;
+        if address.is_ipv4() {
+            socket.set_broadcast(true)?;
+        }
+        Self::try_from_socket(socket, false, Some(address))
+    }
+
+    // Listens on the address, sending only after a client is heard
+    fn udpin(address: &str) -> std::io::Result<Self> {
+        let address = resolve(address)?;
+        let socket = UdpSocket::bind(address)?;
+        Self::try_from_socket(socket, true, None)
+    }
+
+    fn try_from_socket(
+        socket: UdpSocket,
+        server: bool,
+        destination: Option<SocketAddr>,
+    ) -> std::io::Result<Self> {
+        socket.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        Ok(Self {
+            reader: Mutex::new(UdpReader {
+                socket: socket.try_clone()?,
+                datagram: vec![0; u16::MAX as usize],
+                start: 0,
+                end: 0,
+            }),
+            writer: Mutex::new(UdpWriter {
+                socket,
+                destination,
+                sequence: 0,
+            }),
+            server,
+            protocol_version: MavlinkVersion::V2,
+        })
+    }
+}
+
+impl MavConnection<MavMessage> for UdpConnection {
+    fn recv(&self) -> Result<(MavHeader, MavMessage), MessageReadError> {
+        let mut reader = self.reader.lock().unwrap();
+        let reader = &mut *reader;
+
+        loop {
+            if reader.start == reader.end {
+                let (size, client_address) = reader.socket.recv_from(&mut reader.datagram)?;
+                reader.start = 0;
+                reader.end = size;
+
+                if self.server {
+                    self.writer.lock().unwrap().destination = Some(client_address);
+                }
+            }
+
+            // Invalid messages are skipped, like the rest of a truncated one
+            let mut remaining = &reader.datagram[reader.start..reader.end];
+            let message = mavlink::read_versioned_msg(&mut remaining, self.protocol_version);
+            reader.start = reader.end - remaining.len();
+            if message.is_ok() {
+                return message;
+            }
+        }
+    }
+
+    fn send(&self, header: &MavHeader, data: &MavMessage) -> Result<usize, MessageWriteError> {
+        let mut writer = self.writer.lock().unwrap();
+
+        let header = MavHeader {
+            sequence: writer.sequence,
+            system_id: header.system_id,
+            component_id: header.component_id,
+        };
+        writer.sequence = writer.sequence.wrapping_add(1);
+
+        let destination = match writer.destination {
+            Some(destination) => destination,
+            None => return Ok(0),
+        };
+
+        let mut datagram = vec![];
+        mavlink::write_versioned_msg(&mut datagram, self.protocol_version, header, data)?;
+        Ok(writer.socket.send_to(&datagram, destination)?)
+    }
+
+    fn set_protocol_version(&mut self, version: MavlinkVersion) {
+        self.protocol_version = version;
+    }
+
+    fn get_protocol_version(&self) -> MavlinkVersion {
+        self.protocol_version
+    }
+}
+
+fn resolve(address: &str) -> std::io::Result<SocketAddr> {
+    address.to_socket_addrs()?.next().ok_or_else(|| {
+        std::io::Error::new(
+            ErrorKind::AddrNotAvailable,
+            format!("Failed to resolve {address:?}"),
+        )
+    })
+}
@@ -1,25 +1,105 @@
 use crate::cli;
+use crate::event::{self, manager::EventKind};
 use crate::network::utils::get_visible_qgc_address;
 use crate::settings;
-use crate::stream::types::StreamType;
+use crate::stream::gst::pipeline_builder::Pipeline;
+#[cfg(feature = "recording")]
+use crate::stream::recording;
+use crate::stream::types::{FieldOfView, RedirectHealth, StreamType};
+use crate::stream::{captures, osd, snapshot};
 use crate::video::types::VideoSourceType;
+use crate::video::xml;
 use crate::video_stream::types::VideoAndStreamInformation;
 
+use super::connection::{self, Connection, LinkStatus};
+
 use mavlink::common::MavMessage;
-use mavlink::MavConnection;
+use mavlink::Message;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
 use simple_error::simple_error;
 use tracing::*;
 use url::Url;
 
 use std::convert::TryInto;
-use std::marker::Send;
 use std::ops::ControlFlow;
 use std::sync::{Arc, Mutex, RwLock};
 
 lazy_static! {
+    // Component IDs of the running cameras
     static ref ID_CONTROL: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(vec![]));
 }
 
+// The boot time is not available on Windows, the time is counted from the first message instead
+#[cfg(windows)]
+lazy_static! {
+    static ref START_TIME: std::time::Instant = std::time::Instant::now();
+}
+
+// GCSs show each component as a different camera, by default from MAV_COMP_ID_CAMERA to MAV_COMP_ID_CAMERA6
+const MAX_CAMERAS: u8 = 6;
+
+fn camera_component_ids() -> std::ops::RangeInclusive<u8> {
+    let first = settings::manager::mavlink_ids().component_id;
+    first..=first.saturating_add(MAX_CAMERAS - 1)
+}
+
+// The component stored for the stream is used when free, otherwise the first one
+// not stored for another stream, or at last any free one. The choice is stored.
+fn allocate_component_id(stream_name: &str) -> Option<u8> {
+    let mut in_use = ID_CONTROL.lock().unwrap();
+    let stored = settings::manager::mavlink_components();
+    let stream_names: Vec<String> = settings::manager::streams()
+        .into_iter()
+        .map(|stream| stream.name)
+        .collect();
+    let is_free = |id: &u8| !in_use.contains(id);
+    let is_stored_for_other_stream = |id: &u8| {
+        stored.iter().any(|component| {
+            component.component_id == *id
+                && component.stream_name != stream_name
+                && stream_names.contains(&component.stream_name)
+        })
+    };
+
+    let component_id = stored
+        .iter()
+        .find(|component| component.stream_name == stream_name)
+        .map(|component| component.component_id)
+        .filter(|id| camera_component_ids().contains(id) && is_free(id))
+        .or_else(|| {
+            camera_component_ids().find(|id| is_free(id) && !is_stored_for_other_stream(id))
+        })
+        .or_else(|| camera_component_ids().find(is_free));
+
+    let component_id = match component_id {
+        Some(component_id) => component_id,
+        None => {
+            warn!("There is no MAVLink camera component left for stream {stream_name:?}, all of them are in use.");
+            return None;
+        }
+    };
+
+    in_use.push(component_id);
+    drop(in_use);
+    if !stored.iter().any(|component| {
+        component.stream_name == stream_name && component.component_id == component_id
+    }) {
+        settings::manager::set_mavlink_component(stream_name, component_id);
+    }
+    Some(component_id)
+}
+
+fn release_component_id(component_id: u8) {
+    let mut in_use = ID_CONTROL.lock().unwrap();
+    match in_use.iter().position(|id| *id == component_id) {
+        Some(position) => {
+            in_use.remove(position);
+        }
+        None => error!("Component {component_id} not found when releasing it."),
+    }
+}
+
 #[derive(Clone, Debug)]
 #[allow(dead_code)]
 pub struct MavlinkCameraComponent {
@@ -37,18 +117,171 @@ pub struct MavlinkCameraComponent {
     bitrate: u32,
     rotation: u16,
     hfov: u16,
+    // Reported by CAMERA_FOV_STATUS when defined by the user
+    fov: Option<FieldOfView>,
     thermal: bool,
 }
 
 #[derive(Clone)]
 pub struct MavlinkCameraInformation {
     component: MavlinkCameraComponent,
-    mavlink_connection_string: String,
     mavlink_stream_type: mavlink::common::VideoStreamType,
     video_stream_uri: Url,
     video_stream_name: String,
     video_source_type: VideoSourceType,
-    vehicle: Arc<RwLock<Box<dyn MavConnection<MavMessage> + Sync + Send>>>,
+    // Checked by the redirect stream, not set for the streams served by us
+    redirect_health: Option<Arc<Mutex<RedirectHealth>>>,
+    // Chosen by the GCS, it only changes which capture buttons the GCS shows
+    mode: mavlink::common::CameraMode,
+    // Stream stopped with VIDEO_STOP_STREAMING or the REST API
+    paused: bool,
+    // Set by the tracking commands, the tracking itself is left to an external tracker
+    tracking: Option<TrackingRegion>,
+    image_capture: Arc<Mutex<ImageCapture>>,
+    endpoints: Endpoints,
+    // To handle only once the commands that arrive through more than one endpoint
+    received_commands: Arc<Mutex<Vec<ReceivedCommand>>>,
+}
+
+// A MAVLink endpoint of the camera, connected and read by its own receive thread
+#[derive(Clone)]
+struct Endpoint {
+    address: String,
+    // Connecting may block until a TCP client shows up
+    connection: Arc<RwLock<Connection>>,
+    link: Arc<Mutex<LinkStatus>>,
+    state: Arc<Mutex<ThreadState>>,
+}
+
+// The camera is seen by the GCSs of every endpoint, so the messages are sent through all of them
+#[derive(Clone, Debug)]
+struct Endpoints(Vec<Endpoint>);
+
+// Commands that arrive again through another endpoint within this time are copies of the first one
+const DUPLICATED_COMMAND_WINDOW: std::time::Duration = std::time::Duration::from_secs(1);
+
+#[derive(Debug)]
+struct ReceivedCommand {
+    time: std::time::Instant,
+    endpoint: usize,
+    system_id: u8,
+    component_id: u8,
+    command: Command,
+}
+
+// Photos taken by request of the GCS, shared with the thread capturing them
+#[derive(Debug, Default)]
+struct ImageCapture {
+    // Index of the next image, GCSs use it to find the lost CAMERA_IMAGE_CAPTURED messages
+    image_index: i32,
+    // Sequence number of the last single capture, to ignore retransmitted commands
+    last_sequence: u32,
+    // Seconds between the images, while capturing at an interval
+    interval: Option<f32>,
+    // Changed to stop the running capture
+    generation: u32,
+    // Last position and attitude of the vehicle, to geotag the images
+    position: Option<mavlink::common::GLOBAL_POSITION_INT_DATA>,
+    attitude: Option<[f32; 4]>,
+}
+
+// Region of the image the GCS asked to track, for an external tracker or the OSD to follow.
+// Coordinates are normalized from 0 to 1, from the top left corner of the image.
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Serialize)]
+pub enum TrackingRegion {
+    Point(TrackingPoint),
+    Rectangle(TrackingRectangle),
+}
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Serialize)]
+pub struct TrackingPoint {
+    pub x: f32,
+    pub y: f32,
+    // Relative to the width of the image
+    pub radius: f32,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Serialize)]
+pub struct TrackingRectangle {
+    pub top_x: f32,
+    pub top_y: f32,
+    pub bottom_x: f32,
+    pub bottom_y: f32,
+}
+
+impl TrackingRegion {
+    // From the parameters of MAV_CMD_CAMERA_TRACK_POINT or MAV_CMD_CAMERA_TRACK_RECTANGLE,
+    // None when they are out of the image
+    fn try_from_command(command: &Command) -> Option<Self> {
+        let params = &command.params;
+        let region = match command.command {
+            mavlink::common::MavCmd::MAV_CMD_CAMERA_TRACK_POINT => Self::Point(TrackingPoint {
+                x: params[0],
+                y: params[1],
+                radius: params[2],
+            }),
+            mavlink::common::MavCmd::MAV_CMD_CAMERA_TRACK_RECTANGLE => {
+                Self::Rectangle(TrackingRectangle {
+                    top_x: params[0],
+                    top_y: params[1],
+                    bottom_x: params[2],
+                    bottom_y: params[3],
+                })
+            }
+            _ => return None,
+        };
+
+        let is_normalized = |value: f32| (0.0..=1.0).contains(&value);
+        let is_valid = match &region {
+            Self::Point(point) => [point.x, point.y, point.radius]
+                .into_iter()
+                .all(is_normalized),
+            Self::Rectangle(rectangle) => {
+                [
+                    rectangle.top_x,
+                    rectangle.top_y,
+                    rectangle.bottom_x,
+                    rectangle.bottom_y,
+                ]
+                .into_iter()
+                .all(is_normalized)
+                    && rectangle.top_x <= rectangle.bottom_x
+                    && rectangle.top_y <= rectangle.bottom_y
+            }
+        };
+        is_valid.then_some(region)
+    }
+}
+
+// Tracking requested on a camera, as shown by the REST API
+#[derive(Apiv2Schema, Clone, Debug, Serialize)]
+pub struct MavlinkCameraTracking {
+    pub stream_name: String,
+    pub system_id: u8,
+    pub component_id: u8,
+    // None while not tracking
+    pub region: Option<TrackingRegion>,
+}
+
+// State of the link of a camera to one of its MAVLink endpoints, as shown by the REST API
+#[derive(Apiv2Schema, Clone, Debug, Serialize)]
+pub struct MavlinkCameraStatus {
+    pub stream_name: String,
+    pub system_id: u8,
+    pub component_id: u8,
+    pub endpoint: String,
+    pub connected: bool,
+    pub connections: u32,
+    pub last_error: Option<String>,
+    pub messages_received: u64,
+    pub messages_sent: u64,
+    pub receive_errors: u64,
+    pub send_errors: u64,
+    pub seconds_since_last_message: Option<f64>,
+    // RFC 3339 timestamps
+    pub last_heartbeat_sent: Option<String>,
+    pub last_vehicle_heartbeat: Option<String>,
+    pub last_gcs_heartbeat: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -65,7 +298,8 @@ pub struct MavlinkCameraHandle {
     mavlink_camera_information: Arc<Mutex<MavlinkCameraInformation>>,
     thread_state: Arc<Mutex<ThreadState>>,
     heartbeat_thread: std::thread::JoinHandle<()>,
-    receive_message_thread: std::thread::JoinHandle<()>,
+    // One for each endpoint
+    receive_message_threads: Vec<std::thread::JoinHandle<()>>,
 }
 
 // Debug definition to avoid problems with vehicle type
@@ -73,136 +307,317 @@ impl std::fmt::Debug for MavlinkCameraInformation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("MavlinkCameraInformation")
             .field("component", &self.component)
-            .field("mavlink_connection_string", &self.mavlink_connection_string)
             .field("mavlink_stream_type", &self.mavlink_stream_type)
             .field("video_stream_uri", &self.video_stream_uri)
             .field("video_source_type", &self.video_source_type)
+            .field("redirect_health", &self.redirect_health)
+            .field("mode", &self.mode)
+            .field("paused", &self.paused)
+            .field("image_capture", &self.image_capture)
+            .field("endpoints", &self.endpoints)
             .finish()
     }
 }
 
-impl MavlinkCameraComponent {
-    fn try_new(video_and_stream_information: &VideoAndStreamInformation) -> Option<Self> {
-        let mut vector = ID_CONTROL.lock().unwrap();
+impl std::fmt::Debug for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Endpoint")
+            .field("address", &self.address)
+            .field("link", &self.link)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl Endpoint {
+    fn new(address: String) -> Self {
+        Self {
+            address,
+            connection: Arc::new(RwLock::new(connection::disconnected())),
+            link: Default::default(),
+            state: Arc::new(Mutex::new(ThreadState::RESTART)),
+        }
+    }
+}
 
-        // Find the closer ID available
-        let mut id: u8 = 0;
-        loop {
-            if vector.contains(&id) {
-                id += 1;
+impl Endpoints {
+    // Succeeds when sent through any of the connected endpoints
+    fn send(
+        &self,
+        header: &mavlink::MavHeader,
+        message: &MavMessage,
+    ) -> Result<usize, mavlink::error::MessageWriteError> {
+        let mut result = Err(mavlink::error::MessageWriteError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotConnected,
+            "Not connected to any MAVLink endpoint",
+        )));
+        for endpoint in &self.0 {
+            if *endpoint.state.lock().unwrap() != ThreadState::RUNNING {
                 continue;
-            } else {
-                vector.push(id);
-                break;
+            }
+            match endpoint.connection.read().unwrap().send(header, message) {
+                Ok(size) => result = Ok(size),
+                Err(error) if result.is_err() => result = Err(error),
+                Err(_) => (),
             }
         }
+        result
+    }
+}
 
-        let (resolution_h, resolution_v, framerate) = match &video_and_stream_information
-            .stream_information
-            .configuration
-        {
-            crate::stream::types::CaptureConfiguration::VIDEO(cfg) => {
-                let framerate =
-                    cfg.frame_interval.denominator as f32 / cfg.frame_interval.numerator as f32;
-                (cfg.height as u16, cfg.width as u16, framerate)
-            }
-            crate::stream::types::CaptureConfiguration::REDIRECT(_) => (0, 0, 0.0),
-        };
+impl MavlinkCameraComponent {
+    fn try_new(
+        video_and_stream_information: &VideoAndStreamInformation,
+        component_id: u8,
+    ) -> Option<Self> {
+        let VideoParameters {
+            resolution_h,
+            resolution_v,
+            framerate,
+            bitrate,
+            hfov,
+            fov,
+        } = VideoParameters::try_new(video_and_stream_information)?;
 
+        // Y16 captures only come from thermal cameras
         let thermal = video_and_stream_information
             .stream_information
             .extended_configuration
             .clone()
             .unwrap_or_default()
-            .thermal;
-
-        Some(Self {
-            system_id: 1,
-            component_id: mavlink::common::MavComponent::MAV_COMP_ID_CAMERA as u8 + id,
-            stream_id: 1, // Starts at 1, 0 is for broadcast.
+            .thermal
+            || matches!(
+                &video_and_stream_information.stream_information.configuration,
+                crate::stream::types::CaptureConfiguration::VIDEO(cfg)
+                    if cfg.encode == crate::video::types::VideoEncodeType::Y16
+            );
 
-            vendor_name: video_and_stream_information
+        // USB cameras identify themselves, other sources are named after the source and the stream
+        let (manufacturer, product, firmware_version): (
+            Option<String>,
+            Option<String>,
+            Option<String>,
+        ) = match &video_and_stream_information.video_source {
+            #[cfg(target_os = "linux")]
+            VideoSourceType::Local(source) => match &source.usb {
+                Some(usb) => (
+                    usb.manufacturer.clone(),
+                    usb.product.clone(),
+                    usb.firmware_version.clone(),
+                ),
+                None => (None, None, None),
+            },
+            _ => (None, None, None),
+        };
+        let vendor_name = manufacturer.unwrap_or_else(|| {
+            video_and_stream_information
                 .video_source
                 .inner()
                 .name()
-                .to_string(), // TODO: see what is more appropriate
-            model_name: video_and_stream_information.name.clone(), // TODO: see what is more appropriate
-            firmware_version: 0,
+                .to_string()
+        });
+        let model_name = product.unwrap_or_else(|| video_and_stream_information.name.clone());
+        let firmware_version = firmware_version
+            .as_deref()
+            .map(encode_firmware_version)
+            .unwrap_or(0);
+
+        Some(Self {
+            system_id: settings::manager::mavlink_ids().system_id,
+            component_id,
+            stream_id: 1, // Starts at 1, 0 is for broadcast.
+
+            vendor_name,
+            model_name,
+            firmware_version,
             resolution_h,
             resolution_v,
-            bitrate: 5000,
+            bitrate,
             rotation: 0,
-            hfov: 90,
+            hfov,
+            fov,
             framerate,
             thermal,
         })
     }
 }
 
-impl Drop for MavlinkCameraComponent {
-    fn drop(&mut self) {
-        // Remove id from used ids
-        let id = self.component_id - mavlink::common::MavComponent::MAV_COMP_ID_CAMERA as u8;
-        let mut vector = ID_CONTROL.lock().unwrap();
-        if let Some(position) = vector.iter().position(|&vec_id| vec_id == id) {
-            vector.remove(position);
-        } else {
-            error!("Id {id} not found when Dropping MavlinkCameraComponent {self:#?}.");
+// Streamed video, as reported in VIDEO_STREAM_INFORMATION and VIDEO_STREAM_STATUS
+struct VideoParameters {
+    resolution_h: u16,
+    resolution_v: u16,
+    framerate: f32,
+    // In bits/s
+    bitrate: u32,
+    // In degrees
+    hfov: u16,
+    fov: Option<FieldOfView>,
+}
+
+impl VideoParameters {
+    fn try_new(video_and_stream_information: &VideoAndStreamInformation) -> Option<Self> {
+        let fov = video_and_stream_information
+            .stream_information
+            .extended_configuration
+            .as_ref()
+            .and_then(|configuration| configuration.fov.clone());
+        let hfov = fov
+            .as_ref()
+            .map(|fov| fov.horizontal.round() as u16)
+            .unwrap_or(90);
+
+        match &video_and_stream_information
+            .stream_information
+            .configuration
+        {
+            crate::stream::types::CaptureConfiguration::VIDEO(cfg) => {
+                let (width, height) = cfg.output_resolution();
+                let (bitrate_kbps, _) =
+                    Pipeline::get_encoder_settings(video_and_stream_information);
+                Some(Self {
+                    resolution_h: width as u16,
+                    resolution_v: height as u16,
+                    framerate: cfg.frame_interval.denominator as f32
+                        / cfg.frame_interval.numerator as f32,
+                    bitrate: bitrate_kbps * 1000,
+                    hfov,
+                    fov,
+                })
+            }
+            // Not known, the stream is not ours
+            crate::stream::types::CaptureConfiguration::REDIRECT(_) => Some(Self {
+                resolution_h: 0,
+                resolution_v: 0,
+                framerate: 0.0,
+                bitrate: 0,
+                hfov,
+                fov,
+            }),
+            // There is no such thing as an audio-only MAVLink camera
+            crate::stream::types::CaptureConfiguration::AUDIO(_) => None,
         }
     }
 }
 
+// From "major.minor" to (minor << 8) | major, as expected by CAMERA_INFORMATION
+fn encode_firmware_version(version: &str) -> u32 {
+    let mut numbers = version
+        .split('.')
+        .map(|number| number.parse::<u32>().unwrap_or(0) & 0xff);
+    let major = numbers.next().unwrap_or(0);
+    let minor = numbers.next().unwrap_or(0);
+    (minor << 8) | major
+}
+
 impl From<&StreamType> for mavlink::common::VideoStreamType {
     fn from(stream: &StreamType) -> Self {
         match stream {
             StreamType::UDP(_) => mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_RTPUDP,
+            #[cfg(feature = "rtsp")]
             StreamType::RTSP(_) => mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_RTSP,
+            StreamType::PIPELINE(video_stream_pipeline) => {
+                video_stream_type_from_scheme(&video_stream_pipeline.scheme)
+            }
+            StreamType::TEE(video_stream_tee) => {
+                video_stream_type_from_scheme(video_stream_tee.scheme.as_deref().unwrap_or(""))
+            }
             StreamType::REDIRECT(video_strem_redirect) => {
-                match video_strem_redirect.scheme.as_str() {
-                    "rtsp" => mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_RTSP,
-                    "mpegts" => mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_MPEG_TS_H264,
-                    "tcp" => mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_TCP_MPEG,
-                    "udp" => mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_RTPUDP,
-                    format @ _ => {
-                        debug!("Unknown format: {format:#?}, using UDP as fallback.");
-                        mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_RTPUDP
-                    }
-                }
+                video_stream_type_from_scheme(&video_strem_redirect.scheme)
             }
         }
     }
 }
 
+fn video_stream_type_from_scheme(scheme: &str) -> mavlink::common::VideoStreamType {
+    match scheme {
+        "rtsp" | "rtsps" => mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_RTSP,
+        "mpegts" => mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_MPEG_TS_H264,
+        "tcp" => mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_TCP_MPEG,
+        "udp" | "udp265" => mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_RTPUDP,
+        // Not advertised, see MavlinkCameraInformation::try_new
+        "ndi" | "srt" | "rtmp" | "file" => {
+            mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_RTPUDP
+        }
+        format @ _ => {
+            debug!("Unknown format: {format:#?}, using UDP as fallback.");
+            mavlink::common::VideoStreamType::VIDEO_STREAM_TYPE_RTPUDP
+        }
+    }
+}
+
 impl MavlinkCameraInformation {
     fn try_new(
         video_and_stream_information: &VideoAndStreamInformation,
         stream: &StreamType,
     ) -> Option<Self> {
-        let video_stream_uri = video_and_stream_information
-            .stream_information
-            .endpoints
-            .first()?
-            .to_owned();
+        // There is no MAVLink video stream type that GCSs could use to receive NDI, SRT or RTMP,
+        // and file streams are recorded to disk, there is nothing to be received
+        if let StreamType::PIPELINE(video_stream_pipeline) = stream {
+            if matches!(
+                video_stream_pipeline.scheme.as_str(),
+                "ndi" | "srt" | "rtmp" | "file"
+            ) {
+                return None;
+            }
+        }
+
+        let endpoints = &video_and_stream_information.stream_information.endpoints;
+        let video_stream_uri = match stream {
+            // Only one of the endpoints is advertised, the first one GCSs can receive
+            StreamType::TEE(video_stream_tee) => {
+                let scheme = video_stream_tee.scheme.as_deref()?;
+                endpoints
+                    .iter()
+                    .find(|endpoint| endpoint.scheme() == scheme)?
+            }
+            _ => endpoints.first()?,
+        }
+        .to_owned();
 
         let video_stream_name = video_and_stream_information.name.clone();
 
         let mavlink_stream_type = mavlink::common::VideoStreamType::from(stream);
         let video_source_type = video_and_stream_information.video_source.clone();
 
-        let component = MavlinkCameraComponent::try_new(video_and_stream_information)?;
+        let redirect_health = match stream {
+            StreamType::REDIRECT(video_stream_redirect) => {
+                Some(video_stream_redirect.health_handle())
+            }
+            _ => None,
+        };
 
-        let mavlink_connection_string = settings::manager::mavlink_endpoint()?;
+        let endpoints = settings::manager::mavlink_endpoints();
+        if endpoints.is_empty() {
+            return None;
+        }
 
-        let vehicle = Arc::new(RwLock::new(connect(&component, &mavlink_connection_string)));
+        // Released when the handle of the camera is dropped
+        let component_id = allocate_component_id(&video_stream_name)?;
+        let component =
+            match MavlinkCameraComponent::try_new(video_and_stream_information, component_id) {
+                Some(component) => component,
+                None => {
+                    release_component_id(component_id);
+                    return None;
+                }
+            };
+
+        // Connected by the receive threads
+        let endpoints = Endpoints(endpoints.into_iter().map(Endpoint::new).collect());
 
         let this = Self {
             component,
-            mavlink_connection_string,
             mavlink_stream_type,
             video_stream_uri,
             video_stream_name,
             video_source_type,
-            vehicle,
+            redirect_health,
+            mode: mavlink::common::CameraMode::CAMERA_MODE_VIDEO,
+            paused: false,
+            tracking: None,
+            image_capture: Default::default(),
+            endpoints,
+            received_commands: Default::default(),
         };
 
         debug!("Starting new MAVLink camera: {this:#?}");
@@ -210,25 +625,60 @@ impl MavlinkCameraInformation {
         Some(this)
     }
 
+    // The status flags can't tell that a stream is not running, so paused streams and
+    // unavailable redirected streams are reported without framerate and bitrate
+    fn is_available(&self) -> bool {
+        !self.paused
+            && self
+                .redirect_health
+                .as_ref()
+                .and_then(|health| health.lock().unwrap().available)
+                .unwrap_or(true)
+    }
+
+    // Redirected streams are not encoded by us
+    fn bitrate_kbps(&self) -> Option<u32> {
+        (self.component.bitrate > 0).then(|| self.component.bitrate / 1000)
+    }
+
+    // Frames are received from the video of the stream, so only the streams encoded by us can be captured
+    fn can_capture(&self) -> bool {
+        self.bitrate_kbps().is_some()
+    }
+
     pub fn cam_definition_uri(&self) -> Option<Url> {
-        // Get the current remotely accessible link (from default interface)
-        // to our camera XML file.
-        // This can't be a parameter because the default network route might
-        // change between the time of the MavlinkCameraInformation creation
-        // and the time MAVLink connection is negotiated with the other MAVLink
-        // systems.
-        let visible_qgc_ip_address = get_visible_qgc_address().to_string();
-        let server_port = cli::manager::server_address()
-            .split(':')
-            .collect::<Vec<&str>>()[1];
+        // The definition only describes the controls of the camera, and the bitrate of the stream
+        if self.video_source_type.inner().controls().is_empty() && self.bitrate_kbps().is_none() {
+            return None;
+        }
+
         let video_source_path = self.video_source_type.inner().source_string();
-        Url::parse(&format!(
-            "http://{visible_qgc_ip_address}:{server_port}/xml?file={video_source_path}"
+        let mut uri = Url::parse(&format!(
+            "{}/xml?file={video_source_path}",
+            visible_server_address()
         ))
-        .ok()
+        .ok()?;
+        if self.bitrate_kbps().is_some() {
+            uri.query_pairs_mut()
+                .append_pair("stream", &self.video_stream_name);
+        }
+        Some(uri)
     }
 }
 
+// Get the current remotely accessible address of our REST API (from default interface).
+// This can't be stored because the default network route might
+// change between the time of the MavlinkCameraInformation creation
+// and the time MAVLink connection is negotiated with the other MAVLink
+// systems.
+fn visible_server_address() -> String {
+    let visible_qgc_ip_address = get_visible_qgc_address().to_string();
+    let server_port = cli::manager::server_address()
+        .split(':')
+        .collect::<Vec<&str>>()[1];
+    format!("http://{visible_qgc_ip_address}:{server_port}")
+}
+
 impl MavlinkCameraHandle {
     pub fn try_new(
         video_and_stream_information: &VideoAndStreamInformation,
@@ -240,13 +690,11 @@ impl MavlinkCameraHandle {
                 stream,
             )?));
 
+        // The endpoints are restarted on their own, this one only tells when the camera is removed
         let thread_state = Arc::new(Mutex::new(ThreadState::RUNNING));
 
         let heartbeat_mavlink_information = mavlink_camera_information.clone();
-        let receive_message_mavlink_information = mavlink_camera_information.clone();
-
         let heartbeat_state = thread_state.clone();
-        let receive_message_state = thread_state.clone();
 
         let system_id = heartbeat_mavlink_information
             .lock()
@@ -258,22 +706,111 @@ impl MavlinkCameraHandle {
             .unwrap()
             .component
             .component_id;
+        let endpoints_count = heartbeat_mavlink_information
+            .lock()
+            .unwrap()
+            .endpoints
+            .0
+            .len();
 
         Some(Self {
-            mavlink_camera_information,
-            thread_state,
             heartbeat_thread: std::thread::Builder::new()
                 .name(format!("heartbeat_{system_id:#?}:{component_id:#?}"))
                 .spawn(move || heartbeat_loop(heartbeat_state, heartbeat_mavlink_information))
                 .unwrap(),
-            receive_message_thread: std::thread::Builder::new()
-                .name(format!("receive_message_{system_id:#?}:{component_id:#?}"))
-                .spawn(move || {
-                    receive_message_loop(receive_message_state, receive_message_mavlink_information)
+            receive_message_threads: (0..endpoints_count)
+                .map(|endpoint| {
+                    let receive_message_state = thread_state.clone();
+                    let receive_message_mavlink_information = mavlink_camera_information.clone();
+                    std::thread::Builder::new()
+                        .name(format!(
+                            "receive_message_{system_id:#?}:{component_id:#?}_{endpoint}"
+                        ))
+                        .spawn(move || {
+                            receive_message_loop(
+                                receive_message_state,
+                                endpoint,
+                                receive_message_mavlink_information,
+                            )
+                        })
+                        .unwrap()
                 })
-                .unwrap(),
+                .collect(),
+            mavlink_camera_information,
+            thread_state,
         })
     }
+
+    // The streamed video changes when the stream is downgraded or its HDMI source changes mode,
+    // and its field of view can be changed by the user
+    pub fn update_video_parameters(
+        &self,
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) {
+        let parameters = match VideoParameters::try_new(video_and_stream_information) {
+            Some(parameters) => parameters,
+            None => return,
+        };
+
+        let mut information = self.mavlink_camera_information.lock().unwrap();
+        information.component.resolution_h = parameters.resolution_h;
+        information.component.resolution_v = parameters.resolution_v;
+        information.component.framerate = parameters.framerate;
+        information.component.bitrate = parameters.bitrate;
+        information.component.hfov = parameters.hfov;
+        information.component.fov = parameters.fov;
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.mavlink_camera_information.lock().unwrap().paused = paused;
+    }
+
+    // One for each endpoint
+    pub fn status(&self) -> Vec<MavlinkCameraStatus> {
+        let information = self.mavlink_camera_information.lock().unwrap();
+        information
+            .endpoints
+            .0
+            .iter()
+            .map(|endpoint| endpoint_status(&information, endpoint))
+            .collect()
+    }
+
+    pub fn tracking(&self) -> MavlinkCameraTracking {
+        let information = self.mavlink_camera_information.lock().unwrap();
+        MavlinkCameraTracking {
+            stream_name: information.video_stream_name.clone(),
+            system_id: information.component.system_id,
+            component_id: information.component.component_id,
+            region: information.tracking.clone(),
+        }
+    }
+}
+
+fn endpoint_status(
+    information: &MavlinkCameraInformation,
+    endpoint: &Endpoint,
+) -> MavlinkCameraStatus {
+    let link = endpoint.link.lock().unwrap();
+    MavlinkCameraStatus {
+        stream_name: information.video_stream_name.clone(),
+        system_id: information.component.system_id,
+        component_id: information.component.component_id,
+        endpoint: endpoint.address.clone(),
+        connected: link.connected,
+        connections: link.connections,
+        last_error: link.last_error.clone(),
+        messages_received: link.messages_received,
+        messages_sent: link.messages_sent,
+        receive_errors: link.receive_errors,
+        send_errors: link.send_errors,
+        seconds_since_last_message: link
+            .last_received
+            .map(|instant| instant.elapsed().as_secs_f64()),
+        last_heartbeat_sent: link.last_heartbeat_sent.map(|time| time.to_rfc3339()),
+        last_vehicle_heartbeat: link.last_vehicle_heartbeat.map(|time| time.to_rfc3339()),
+        last_gcs_heartbeat: link.last_gcs_heartbeat.map(|time| time.to_rfc3339()),
+    }
 }
 
 impl Drop for MavlinkCameraHandle {
@@ -281,6 +818,12 @@ impl Drop for MavlinkCameraHandle {
         debug!("Dropping {self:#?}");
         let mut state = self.thread_state.lock().unwrap();
         *state = ThreadState::DEAD;
+        let information = self.mavlink_camera_information.lock().unwrap();
+        for endpoint in &information.endpoints.0 {
+            *endpoint.state.lock().unwrap() = ThreadState::DEAD;
+        }
+        // The information is cloned by the threads, so the component can't release its own ID
+        release_component_id(information.component.component_id);
     }
 }
 
@@ -292,7 +835,7 @@ fn heartbeat_loop(
     let information = mavlink_camera_information.lock().unwrap();
     header.system_id = information.component.system_id;
     header.component_id = information.component.component_id;
-    let vehicle = information.vehicle.clone();
+    let endpoints = information.endpoints.clone();
     drop(information);
 
     loop {
@@ -302,328 +845,176 @@ fn heartbeat_loop(
             match state {
                 ThreadState::DEAD => break,
                 ThreadState::RUNNING => (),
-                ThreadState::RESTART => {
-                    *vehicle.write().as_deref_mut().unwrap() =
-                        reconnect(&mavlink_camera_information.lock().unwrap().clone());
-                    *state = ThreadState::RUNNING;
-                }
+                ThreadState::RESTART => continue,
                 ThreadState::ZOMBIE => continue,
             }
         } else {
             continue;
         }
 
-        if let Err(error) = vehicle.read().unwrap().send(&header, &heartbeat_message()) {
-            error!(
-                "Failed to send heartbeat as {:#?}:{:#?}. Reason: {error}",
-                header.system_id, header.component_id
-            );
-            {
-                let mavlink::error::MessageWriteError::Io(io_error) = &error;
-                if io_error.kind() == std::io::ErrorKind::WouldBlock {
-                    continue;
-                }
+        for endpoint in &endpoints.0 {
+            send_heartbeat(&header, endpoint);
+        }
+
+        // Streamed while tracking, so the GCS keeps showing the tracked region, and while the
+        // vehicle position is known, so it can draw the footprint of the camera on the map
+        let messages = {
+            let information = mavlink_camera_information.lock().unwrap();
+            let is_located = {
+                let image_capture = information.image_capture.lock().unwrap();
+                image_capture.position.is_some() && image_capture.attitude.is_some()
+            };
+            [
+                information
+                    .tracking
+                    .is_some()
+                    .then(|| camera_tracking_image_status(&information)),
+                is_located.then(|| camera_fov_status(&information)),
+            ]
+        };
+        for message in messages.iter().flatten() {
+            if let Err(error) = endpoints.send(&header, message) {
+                warn!(
+                    "Failed to send {} as {:#?}:{:#?}. Reason: {error:?}.",
+                    message.message_name(),
+                    header.system_id,
+                    header.component_id
+                );
             }
-            *atomic_thread_state.lock().unwrap() = ThreadState::RESTART;
-        } else {
-            debug!(
-                "Sent heartbeat as {:#?}:{:#?}.",
-                header.system_id, header.component_id
-            );
         }
     }
 }
 
+// Each endpoint is restarted on its own, the others keep running
+fn send_heartbeat(header: &mavlink::MavHeader, endpoint: &Endpoint) {
+    // Reconnected by the receive thread
+    if *endpoint.state.lock().unwrap() != ThreadState::RUNNING {
+        return;
+    }
+
+    // A TCP peer that went away without closing the connection is only noticed here
+    let last_received = endpoint.link.lock().unwrap().last_received;
+    if connection::is_tcp(&endpoint.address)
+        && last_received.map_or(false, |time| time.elapsed() > LINK_TIMEOUT)
+    {
+        restart_connection(
+            &endpoint.state,
+            &endpoint.link,
+            format!("Nothing received for {LINK_TIMEOUT:?}"),
+        );
+        return;
+    }
+
+    let result = endpoint
+        .connection
+        .read()
+        .unwrap()
+        .send(header, &heartbeat_message());
+    if let Err(error) = result {
+        error!(
+            "Failed to send heartbeat as {:#?}:{:#?} to {:#?}. Reason: {error}",
+            header.system_id, header.component_id, endpoint.address
+        );
+        {
+            let mavlink::error::MessageWriteError::Io(io_error) = &error;
+            if io_error.kind() == std::io::ErrorKind::WouldBlock {
+                return;
+            }
+        }
+        restart_connection(
+            &endpoint.state,
+            &endpoint.link,
+            format!("Failed to send heartbeat: {error}"),
+        );
+    } else {
+        endpoint.link.lock().unwrap().last_heartbeat_sent = Some(chrono::Local::now());
+        debug!(
+            "Sent heartbeat as {:#?}:{:#?} to {:#?}.",
+            header.system_id, header.component_id, endpoint.address
+        );
+    }
+}
+
+// Marks the link as down, the receive thread connects again
+fn restart_connection(
+    atomic_thread_state: &Arc<Mutex<ThreadState>>,
+    link: &Arc<Mutex<LinkStatus>>,
+    reason: String,
+) {
+    {
+        let mut link = link.lock().unwrap();
+        link.connected = false;
+        link.last_error = Some(reason);
+    }
+
+    let mut state = atomic_thread_state.lock().unwrap();
+    if *state == ThreadState::RUNNING {
+        *state = ThreadState::RESTART;
+    }
+}
+
+// Receives the messages of one of the endpoints, the answers are sent through all of them
 fn receive_message_loop(
     atomic_thread_state: Arc<Mutex<ThreadState>>,
+    endpoint_index: usize,
     mavlink_camera_information: Arc<Mutex<MavlinkCameraInformation>>,
 ) {
     let mut our_header = mavlink::MavHeader::default();
     let information = mavlink_camera_information.lock().unwrap();
     our_header.system_id = information.component.system_id;
     our_header.component_id = information.component.component_id;
-    let vehicle = information.vehicle.clone();
+    let vehicle = information.endpoints.clone();
+    let endpoint = information.endpoints.0[endpoint_index].clone();
     drop(information);
 
     loop {
-        if let Ok(state) = atomic_thread_state.lock().as_deref_mut() {
-            match state {
-                ThreadState::DEAD => break,
-                ThreadState::RUNNING => (),
-                ThreadState::RESTART => {
-                    *vehicle.write().as_deref_mut().unwrap() =
-                        reconnect(&mavlink_camera_information.lock().unwrap().clone());
-                    *state = ThreadState::RUNNING;
+        // Not kept locked while connecting, so the camera can be removed meanwhile
+        let state = endpoint.state.lock().unwrap().clone();
+        match state {
+            ThreadState::DEAD => break,
+            ThreadState::RUNNING => (),
+            ThreadState::RESTART => {
+                let component = mavlink_camera_information.lock().unwrap().component.clone();
+                match reconnect(&component, &endpoint) {
+                    Some(connection) => *endpoint.connection.write().unwrap() = connection,
+                    None => break,
                 }
-                ThreadState::ZOMBIE => {
-                    std::thread::sleep(std::time::Duration::from_secs(1));
-                    continue;
+
+                let mut state = endpoint.state.lock().unwrap();
+                if *state == ThreadState::RESTART {
+                    *state = ThreadState::RUNNING;
                 }
+                continue;
+            }
+            ThreadState::ZOMBIE => {
+                std::thread::sleep(std::time::Duration::from_secs(1));
+                continue;
             }
-        } else {
-            std::thread::sleep(std::time::Duration::from_millis(10));
-            continue;
         }
 
-        match vehicle.read().unwrap().recv() {
+        // The connection is not kept locked while handling the message, it may be replaced
+        let message = endpoint.connection.read().unwrap().recv();
+        match message {
             Ok((their_header, msg)) => {
                 match &msg {
-                    MavMessage::COMMAND_LONG(command_long) => {
-                        let command_name = format!("COMMAND_LONG({:#?})", command_long.command);
-                        if let ControlFlow::Break(_) = break_if_wrong_ids(
-                            command_long.target_system,
-                            command_long.target_component,
-                            our_header.system_id,
-                            our_header.component_id,
-                            command_name.as_str(),
-                        ) {
-                            send_command_ack(
-                                &vehicle,
-                                &our_header,
-                                &their_header,
-                                command_long.command,
-                                mavlink::common::MavResult::MAV_RESULT_DENIED,
-                            );
-                            continue;
-                        }
-
-                        debug!(
-                            "Received {:#?} from {:#?}:{:#?} as {:#?}:{:#?}.",
-                            command_name,
-                            their_header.system_id,
-                            their_header.component_id,
-                            our_header.system_id,
-                            our_header.component_id,
-                        );
-
-                        match command_long.command {
-                            mavlink::common::MavCmd::MAV_CMD_REQUEST_CAMERA_INFORMATION => {
-                                let information = mavlink_camera_information.lock().unwrap();
-
-                                send_command_ack(
-                                    &vehicle,
-                                    &our_header,
-                                    &their_header,
-                                    command_long.command,
-                                    mavlink::common::MavResult::MAV_RESULT_ACCEPTED,
-                                );
-
-                                if let Err(error) = vehicle
-                                    .read()
-                                    .unwrap()
-                                    .send(&our_header, &camera_information(&information))
-                                {
-                                    warn!(
-                                        "Failed to send camera_informationfrom {:#?}:{:#?}. Reason: {error:?}.",
-                                        our_header.system_id,
-                                        our_header.component_id,
-                                    );
-                                }
-                                debug!(
-                                    "Sent camera_information as {:#?}:{:#?}.",
-                                    our_header.system_id, our_header.component_id,
-                                );
-                            }
-                            mavlink::common::MavCmd::MAV_CMD_REQUEST_CAMERA_SETTINGS => {
-                                send_command_ack(
-                                    &vehicle,
-                                    &our_header,
-                                    &their_header,
-                                    command_long.command,
-                                    mavlink::common::MavResult::MAV_RESULT_ACCEPTED,
-                                );
-
-                                if let Err(error) = vehicle
-                                    .read()
-                                    .unwrap()
-                                    .send(&our_header, &camera_settings())
-                                {
-                                    warn!(
-                                        "Failed to send camera_settings as {:#?}:{:#?}. Reason: {error:?}.",
-                                        our_header.system_id,
-                                        our_header.component_id,
-                                    );
-                                }
-                                debug!(
-                                    "Sent camera_settings as {:#?}:{:#?}.",
-                                    our_header.system_id, our_header.component_id,
-                                );
-                            }
-                            mavlink::common::MavCmd::MAV_CMD_REQUEST_STORAGE_INFORMATION => {
-                                send_command_ack(
-                                    &vehicle,
-                                    &our_header,
-                                    &their_header,
-                                    command_long.command,
-                                    mavlink::common::MavResult::MAV_RESULT_ACCEPTED,
-                                );
-
-                                if let Err(error) = vehicle
-                                    .read()
-                                    .unwrap()
-                                    .send(&our_header, &camera_storage_information())
-                                {
-                                    warn!("Failed to send camera_storage_information as {:#?}:{:#?} Reason: {error:?}.", our_header.system_id, our_header.component_id);
-                                }
-                                debug!(
-                                    "Sent camera_storage_information as {:#?}:{:#?}.",
-                                    our_header.system_id, our_header.component_id
-                                );
-                            }
-                            mavlink::common::MavCmd::MAV_CMD_REQUEST_CAMERA_CAPTURE_STATUS => {
-                                send_command_ack(
-                                    &vehicle,
-                                    &our_header,
-                                    &their_header,
-                                    command_long.command,
-                                    mavlink::common::MavResult::MAV_RESULT_ACCEPTED,
-                                );
-
-                                if let Err(error) = vehicle
-                                    .read()
-                                    .unwrap()
-                                    .send(&our_header, &camera_capture_status())
-                                {
-                                    warn!("Failed to send camera_capture_status as {:#?}:{:#?} Reason: {error:?}.", our_header.system_id, our_header.component_id);
-                                }
-                                debug!(
-                                    "Sent camera_capture_status as {:#?}:{:#?}.",
-                                    our_header.system_id, our_header.component_id
-                                );
-                            }
-                            mavlink::common::MavCmd::MAV_CMD_REQUEST_VIDEO_STREAM_INFORMATION => {
-                                let information = mavlink_camera_information.lock().unwrap();
-
-                                const ALL_CAMERAS: u8 = 0u8;
-                                if command_long.param2 != (information.component.stream_id as f32)
-                                    && command_long.param2 != (ALL_CAMERAS as f32)
-                                {
-                                    warn!(
-                                        "Received {:#?} from {:#?}:{:#?} as {:#?}{:#?} asking for an unknown stream id: {:#?}.",
-                                        their_header.system_id,their_header.component_id,
-                                        our_header.system_id, our_header.component_id,
-                                        command_long.command, command_long.param2
-                                    );
-                                    send_command_ack(
-                                        &vehicle,
-                                        &our_header,
-                                        &their_header,
-                                        command_long.command,
-                                        mavlink::common::MavResult::MAV_RESULT_UNSUPPORTED,
-                                    );
-                                    continue;
-                                }
-
-                                send_command_ack(
-                                    &vehicle,
-                                    &our_header,
-                                    &their_header,
-                                    command_long.command,
-                                    mavlink::common::MavResult::MAV_RESULT_ACCEPTED,
-                                );
-
-                                if let Err(error) = vehicle
-                                    .read()
-                                    .unwrap()
-                                    .send(&our_header, &video_stream_information(&information))
-                                {
-                                    warn!("Failed to send video_stream_information as {:#?}:{:#?} Reason: {error:?}.", our_header.system_id, our_header.component_id);
-                                }
-                                debug!(
-                                    "Sent video_stream_information as {:#?}:{:#?}.",
-                                    our_header.system_id, our_header.component_id
-                                );
-                            }
-                            mavlink::common::MavCmd::MAV_CMD_RESET_CAMERA_SETTINGS => {
-                                let information = &mavlink_camera_information.lock().unwrap();
-                                let source_string =
-                                    information.video_source_type.inner().source_string();
-                                drop(information);
-
-                                send_command_ack(
-                                    &vehicle,
-                                    &our_header,
-                                    &their_header,
-                                    command_long.command,
-                                    mavlink::common::MavResult::MAV_RESULT_ACCEPTED,
-                                );
-
-                                let mut param_result =
-                                    mavlink::common::MavResult::MAV_RESULT_ACCEPTED;
-                                if let Err(error) =
-                                    crate::video::video_source::reset_controls(source_string)
-                                {
-                                    error!("Failed to reset {source_string:?} controls with its default values as {:#?}:{:#?}. Reason: {error:?}.", our_header.system_id, our_header.component_id);
-                                    param_result = mavlink::common::MavResult::MAV_RESULT_DENIED;
-                                }
-
-                                send_command_ack(
-                                    &vehicle,
-                                    &our_header,
-                                    &their_header,
-                                    command_long.command,
-                                    param_result,
-                                );
-                            }
-                            mavlink::common::MavCmd::MAV_CMD_REQUEST_VIDEO_STREAM_STATUS => {
-                                let information = mavlink_camera_information.lock().unwrap();
-
-                                send_command_ack(
-                                    &vehicle,
-                                    &our_header,
-                                    &their_header,
-                                    command_long.command,
-                                    mavlink::common::MavResult::MAV_RESULT_ACCEPTED,
-                                );
-
-                                if let Err(error) = vehicle.read().unwrap().send(
-                                    &our_header,
-                                    &MavMessage::VIDEO_STREAM_STATUS(
-                                        mavlink::common::VIDEO_STREAM_STATUS_DATA {
-                                            framerate: information.component.framerate,
-                                            bitrate: information.component.bitrate,
-                                            flags: get_stream_status_flag(&information.component),
-                                            resolution_h: information.component.resolution_h,
-                                            resolution_v: information.component.resolution_v,
-                                            rotation: information.component.rotation,
-                                            hfov: information.component.hfov,
-                                            stream_id: information.component.stream_id,
-                                        },
-                                    ),
-                                ) {
-                                    warn!("Failed to send video_stream_information as {:#?}:{:#?} Reason: {error:?}.", our_header.system_id, our_header.component_id);
-                                }
-                                debug!(
-                                    "Sent video stream status as {:#?}:{:#?}.",
-                                    our_header.system_id, our_header.component_id
-                                );
-                            }
-                            mavlink::common::MavCmd::MAV_CMD_REQUEST_MESSAGE => {
-                                send_command_ack(
-                                    &vehicle,
-                                    &our_header,
-                                    &their_header,
-                                    command_long.command,
-                                    mavlink::common::MavResult::MAV_RESULT_UNSUPPORTED,
-                                );
-
-                                error!("MAVLink message \"MAV_CMD_REQUEST_MESSAGE\" is not supported yet, please report this issue so we can prioritize it. Meanwhile, you can use the original definitions for the MAVLink Camera Protocol. Read more in: https://mavlink.io/en/services/camera.html#migration-notes-for-gcs--mavlink-sdks");
-                            }
-                            message => {
-                                send_command_ack(
-                                    &vehicle,
-                                    &our_header,
-                                    &their_header,
-                                    command_long.command,
-                                    mavlink::common::MavResult::MAV_RESULT_UNSUPPORTED,
-                                );
-
-                                warn!(
-                                    "Message {message:#?}, Camera: {:#?}:{:#?}, ignoring command: {:#?}", our_header.system_id, our_header.component_id, command_long.command
-                                );
-                            }
-                        }
-                    }
+                    MavMessage::COMMAND_LONG(command_long) => receive_command(
+                        &vehicle,
+                        endpoint_index,
+                        &our_header,
+                        &their_header,
+                        &mavlink_camera_information,
+                        &atomic_thread_state,
+                        Command::from(command_long),
+                    ),
+                    MavMessage::COMMAND_INT(command_int) => receive_command(
+                        &vehicle,
+                        endpoint_index,
+                        &our_header,
+                        &their_header,
+                        &mavlink_camera_information,
+                        &atomic_thread_state,
+                        Command::from(command_int),
+                    ),
                     MavMessage::PARAM_EXT_SET(param_ext_set) => {
                         if let ControlFlow::Break(_) = break_if_wrong_ids(
                             param_ext_set.target_system,
@@ -642,8 +1033,13 @@ fn receive_message_loop(
                             continue;
                         }
 
-                        let control_id = match control_id_from_param_id(&param_ext_set.param_id) {
-                            Some(value) => value,
+                        let information = mavlink_camera_information.lock().unwrap().clone();
+                        let parameters = camera_parameters(&information);
+                        let parameter = match parameters
+                            .iter()
+                            .find(|parameter| parameter.param_id() == param_ext_set.param_id)
+                        {
+                            Some(parameter) => parameter,
                             None => {
                                 send_param_ext_ack(
                                     &vehicle,
@@ -660,8 +1056,8 @@ fn receive_message_loop(
                             &param_ext_set.param_value,
                             &param_ext_set.param_type,
                         ) {
-                            Some(value) => value,
-                            None => {
+                            Some(value) if parameter.accepts(value) => value,
+                            _ => {
                                 send_param_ext_ack(
                                     &vehicle,
                                     &our_header,
@@ -680,24 +1076,33 @@ fn receive_message_loop(
                             mavlink::common::ParamAck::PARAM_ACK_IN_PROGRESS,
                         );
 
-                        let mut param_result = mavlink::common::ParamAck::PARAM_ACK_ACCEPTED;
-                        if let Err(error) = mavlink_camera_information
-                            .as_ref()
-                            .lock()
-                            .unwrap()
-                            .video_source_type
-                            .inner()
-                            .set_control_by_id(control_id, control_value)
-                        {
-                            error!("Failed to set parameter {control_id:?} with value {control_value:?} for {:#?}. Reason: {error:?}.", our_header.component_id);
-                            param_result = mavlink::common::ParamAck::PARAM_ACK_FAILED;
-                        }
+                        // The camera lock is not held, changing the bitrate restarts the stream
+                        let param_result = match set_parameter(
+                            &information,
+                            parameter,
+                            control_value,
+                        ) {
+                            Ok(_) => mavlink::common::ParamAck::PARAM_ACK_ACCEPTED,
+                            Err(error) => {
+                                error!("Failed to set parameter {parameter:?} with value {control_value:?} for {:#?}. Reason: {error}.", our_header.component_id);
+                                mavlink::common::ParamAck::PARAM_ACK_FAILED
+                            }
+                        };
 
+                        // The GCS is answered with the value in use, which may differ from the requested one
+                        let information = mavlink_camera_information.lock().unwrap().clone();
+                        let param_value = parameter_value(&information, parameter)
+                            .map(|value| param_value_from_control_value(value))
+                            .unwrap_or_else(|_| param_ext_set.param_value.clone());
                         send_param_ext_ack(
                             &vehicle,
                             &our_header,
                             &their_header,
-                            &param_ext_set,
+                            &mavlink::common::PARAM_EXT_SET_DATA {
+                                param_value,
+                                param_type: parameter.param_type(),
+                                ..param_ext_set.clone()
+                            },
                             param_result,
                         );
                     }
@@ -712,47 +1117,39 @@ fn receive_message_loop(
                             continue;
                         }
 
-                        let information = mavlink_camera_information.lock().unwrap();
-                        let controls = &information.video_source_type.inner().controls();
-                        let (param_index, control_id) =
-                            match get_param_index_and_control_id(param_ext_req, controls) {
+                        let information = mavlink_camera_information.lock().unwrap().clone();
+                        let parameters = camera_parameters(&information);
+                        let (param_index, parameter) =
+                            match find_parameter(param_ext_req, &parameters) {
                                 Some(value) => value,
                                 None => continue,
                             };
 
-                        let param_id = param_id_from_control_id(control_id);
-
-                        let control_value = match information
-                            .video_source_type
-                            .inner()
-                            .control_value_by_id(control_id)
-                        {
+                        let control_value = match parameter_value(&information, parameter) {
                             Ok(value) => value,
                             Err(error) => {
-                                error!("Failed to get parameter {control_id:?} from {:#?}. Reason: {error:?}.", our_header.component_id);
+                                error!("Failed to get parameter {parameter:?} from {:#?}. Reason: {error:?}.", our_header.component_id);
                                 continue;
                             }
                         };
 
-                        let param_value = param_value_from_control_value(control_value, 128);
-
-                        if let Err(error) = vehicle.read().unwrap().send(
+                        if let Err(error) = vehicle.send(
                             &our_header,
                             &MavMessage::PARAM_EXT_VALUE(mavlink::common::PARAM_EXT_VALUE_DATA {
-                                param_count: 1,
+                                param_count: parameters.len() as u16,
                                 param_index,
-                                param_id,
-                                param_value,
-                                param_type:
-                                    mavlink::common::MavParamExtType::MAV_PARAM_EXT_TYPE_INT64,
+                                param_id: parameter.param_id(),
+                                param_value: param_value_from_control_value(control_value),
+                                param_type: parameter.param_type(),
                             }),
                         ) {
                             warn!(
-                                "Failed to send video_stream_information as {:#?}:{:#?}: {error:?}.", our_header.system_id, our_header.component_id
+                                "Failed to send PARAM_EXT_VALUE as {:#?}:{:#?}: {error:?}.",
+                                our_header.system_id, our_header.component_id
                             );
                         }
                         debug!(
-                            "Sent video_stream_information as {:#?}:{:#?}.",
+                            "Sent PARAM_EXT_VALUE as {:#?}:{:#?}.",
                             our_header.system_id, our_header.component_id
                         );
                     }
@@ -767,84 +1164,773 @@ fn receive_message_loop(
                             continue;
                         }
 
-                        let controls = mavlink_camera_information
-                            .as_ref()
-                            .lock()
-                            .unwrap()
-                            .video_source_type
-                            .inner()
-                            .controls();
+                        let information = mavlink_camera_information.lock().unwrap().clone();
+                        let parameters = camera_parameters(&information);
+
+                        let mut no_errors = true;
+                        parameters
+                        .iter()
+                        .enumerate()
+                        .for_each(|(param_index, parameter)| {
+                            let param_value = param_value_from_control_value(parameter.value());
+
+                            if let Err(error) = vehicle.send(
+                                &our_header,
+                                &MavMessage::PARAM_EXT_VALUE(
+                                    mavlink::common::PARAM_EXT_VALUE_DATA {
+                                        param_count: parameters.len() as u16,
+                                        param_index: param_index as u16,
+                                        param_id: parameter.param_id(),
+                                        param_value,
+                                        param_type: parameter.param_type(),
+                                    },
+                                ),
+                            ) {
+                                warn!("Failed to send PARAM_EXT_VALUE as {:#?}:{:#?} Reason: {error:?}.", our_header.system_id, our_header.component_id);
+                                no_errors = false;
+                            }
+                        });
+                        if no_errors {
+                            debug!(
+                                "Sent PARAM_EXT_VALUE as {:#?}:{:#?}.",
+                                our_header.system_id, our_header.component_id
+                            );
+                        }
+                    }
+                    MavMessage::HEARTBEAT(heartbeat_data) => {
+                        {
+                            let mut link = endpoint.link.lock().unwrap();
+                            if heartbeat_data.mavtype == mavlink::common::MavType::MAV_TYPE_GCS {
+                                link.last_gcs_heartbeat = Some(chrono::Local::now());
+                            } else if heartbeat_data.autopilot
+                                != mavlink::common::MavAutopilot::MAV_AUTOPILOT_INVALID
+                            {
+                                link.last_vehicle_heartbeat = Some(chrono::Local::now());
+                            }
+                        }
+
+                        // We receive a bunch of heartbeat messages, we can ignore it, but as it can be useful for debugging...
+                        trace!(
+                            "Received heartbeat from {:#?}:{:#?} as camera: {:#?}:{:#?}: {heartbeat_data:#?}",
+                            their_header.system_id, their_header.component_id,
+                            our_header.system_id, our_header.component_id
+                        );
+                    }
+                    MavMessage::VFR_HUD(vfr_hud) => {
+                        osd::update_telemetry(|telemetry| {
+                            telemetry.altitude = Some(vfr_hud.alt);
+                            telemetry.heading = Some(vfr_hud.heading);
+                        });
+                    }
+                    MavMessage::GLOBAL_POSITION_INT(global_position_int) => {
+                        let information = mavlink_camera_information.lock().unwrap();
+                        information.image_capture.lock().unwrap().position =
+                            Some(global_position_int.clone());
+                    }
+                    MavMessage::ATTITUDE_QUATERNION(attitude_quaternion) => {
+                        let information = mavlink_camera_information.lock().unwrap();
+                        information.image_capture.lock().unwrap().attitude = Some([
+                            attitude_quaternion.q1,
+                            attitude_quaternion.q2,
+                            attitude_quaternion.q3,
+                            attitude_quaternion.q4,
+                        ]);
+                    }
+                    MavMessage::SYS_STATUS(sys_status) => {
+                        // Unknown values are sent as UINT16_MAX and -1
+                        osd::update_telemetry(|telemetry| {
+                            telemetry.battery_voltage = (sys_status.voltage_battery != u16::MAX)
+                                .then(|| sys_status.voltage_battery as f32 / 1000.0);
+                            telemetry.battery_remaining = (sys_status.battery_remaining >= 0)
+                                .then_some(sys_status.battery_remaining);
+                        });
+                    }
+                    other_message => {
+                        // Any other message that is not a heartbeat or command_long
+                        trace!(
+                            "Received an unsupported message from {:#?}:{:#?} as {:#?}:{:#?}, ignoring it. Message: {other_message:#?}",
+                            their_header.system_id, their_header.component_id,
+                            our_header.system_id, our_header.component_id
+                        );
+                    }
+                }
+            }
+            Err(error) => {
+                if connection::is_timeout(&error) {
+                    continue;
+                }
+                // Messages of newer dialects fail to parse, but the link is still fine
+                if let mavlink::error::MessageReadError::Parse(parse_error) = &error {
+                    debug!(
+                        "Ignoring a message not supported by the MAVLink dialect as {:#?}:{:#?}. Reason: {parse_error}.",
+                        our_header.system_id, our_header.component_id
+                    );
+                    continue;
+                }
+                let information = mavlink_camera_information.lock().unwrap().clone();
+                error!("Error receiving a message as {:#?}:{:#?} from {:#?}. Reason: {error:#?}. Camera: {information:#?}",
+                    our_header.system_id, our_header.component_id, endpoint.address
+                );
+                restart_connection(
+                    &endpoint.state,
+                    &endpoint.link,
+                    format!("Failed to receive: {error:?}"),
+                );
+            }
+        }
+    }
+}
+
+// Parameters of a COMMAND_LONG or COMMAND_INT, the camera commands don't use the position of the later
+#[derive(Clone, Debug)]
+struct Command {
+    command: mavlink::common::MavCmd,
+    target_system: u8,
+    target_component: u8,
+    params: [f32; 7],
+}
+
+impl Command {
+    // The parameters are compared by their bits, since unused ones are usually NaN
+    fn is_same(&self, other: &Self) -> bool {
+        self.command == other.command
+            && self.target_system == other.target_system
+            && self.target_component == other.target_component
+            && self
+                .params
+                .iter()
+                .zip(other.params.iter())
+                .all(|(param, other_param)| param.to_bits() == other_param.to_bits())
+    }
+}
+
+impl From<&mavlink::common::COMMAND_LONG_DATA> for Command {
+    fn from(command_long: &mavlink::common::COMMAND_LONG_DATA) -> Self {
+        Self {
+            command: command_long.command,
+            target_system: command_long.target_system,
+            target_component: command_long.target_component,
+            params: [
+                command_long.param1,
+                command_long.param2,
+                command_long.param3,
+                command_long.param4,
+                command_long.param5,
+                command_long.param6,
+                command_long.param7,
+            ],
+        }
+    }
+}
+
+impl From<&mavlink::common::COMMAND_INT_DATA> for Command {
+    fn from(command_int: &mavlink::common::COMMAND_INT_DATA) -> Self {
+        Self {
+            command: command_int.command,
+            target_system: command_int.target_system,
+            target_component: command_int.target_component,
+            params: [
+                command_int.param1,
+                command_int.param2,
+                command_int.param3,
+                command_int.param4,
+                command_int.x as f32,
+                command_int.y as f32,
+                command_int.z,
+            ],
+        }
+    }
+}
+
+// Final result of a command, sent before the messages it requested
+struct CommandResponse {
+    result: mavlink::common::MavResult,
+    messages: Vec<MavMessage>,
+    // Started after the acknowledgement, like captures that are answered with their own messages
+    then: Option<Box<dyn FnOnce()>>,
+}
+
+impl CommandResponse {
+    fn result(result: mavlink::common::MavResult) -> Self {
+        Self {
+            result,
+            messages: vec![],
+            then: None,
+        }
+    }
+
+    fn accepted(messages: Vec<MavMessage>) -> Self {
+        Self {
+            result: mavlink::common::MavResult::MAV_RESULT_ACCEPTED,
+            messages,
+            then: None,
+        }
+    }
+}
+
+// Sent with MAV_RESULT_IN_PROGRESS when the progress of a command is not tracked
+const UNKNOWN_PROGRESS: u8 = u8::MAX;
+
+// Every command addressed to us is answered exactly once with its final result, GCSs
+// send them again until then. Commands to other components are left for them to answer.
+fn receive_command(
+    vehicle: &Endpoints,
+    endpoint_index: usize,
+    our_header: &mavlink::MavHeader,
+    their_header: &mavlink::MavHeader,
+    mavlink_camera_information: &Arc<Mutex<MavlinkCameraInformation>>,
+    atomic_thread_state: &Arc<Mutex<ThreadState>>,
+    command: Command,
+) {
+    const BROADCAST_ID: u8 = 0;
+    let command_name = format!("{:#?}", command.command);
+    if ![BROADCAST_ID, our_header.system_id].contains(&command.target_system)
+        || ![BROADCAST_ID, our_header.component_id].contains(&command.target_component)
+    {
+        trace!(
+            "Ignoring {command_name}, sent to {:#?}:{:#?} instead of {:#?}:{:#?}.",
+            command.target_system,
+            command.target_component,
+            our_header.system_id,
+            our_header.component_id
+        );
+        return;
+    }
+
+    // The answer of the first one was sent through every endpoint
+    if is_duplicated_command(
+        &mavlink_camera_information.lock().unwrap(),
+        endpoint_index,
+        their_header,
+        &command,
+    ) {
+        debug!(
+            "Ignoring {command_name} from {:#?}:{:#?}, already received through another endpoint.",
+            their_header.system_id, their_header.component_id
+        );
+        return;
+    }
+
+    debug!(
+        "Received {:#?} from {:#?}:{:#?} as {:#?}:{:#?}.",
+        command_name,
+        their_header.system_id,
+        their_header.component_id,
+        our_header.system_id,
+        our_header.component_id,
+    );
+    event::manager::push(
+        EventKind::Mavlink,
+        format!(
+            "Received {command_name} from {}:{}",
+            their_header.system_id, their_header.component_id
+        ),
+    );
+
+    let response = handle_command(
+        vehicle,
+        our_header,
+        their_header,
+        mavlink_camera_information,
+        atomic_thread_state,
+        &command,
+    );
+
+    send_command_ack(
+        vehicle,
+        our_header,
+        their_header,
+        command.command,
+        response.result,
+        0,
+    );
+
+    for message in &response.messages {
+        if let Err(error) = vehicle.send(our_header, message) {
+            warn!(
+                "Failed to send {} as {:#?}:{:#?}. Reason: {error:?}.",
+                message.message_name(),
+                our_header.system_id,
+                our_header.component_id
+            );
+        } else {
+            debug!(
+                "Sent {} as {:#?}:{:#?}.",
+                message.message_name(),
+                our_header.system_id,
+                our_header.component_id
+            );
+        }
+    }
+
+    if let Some(then) = response.then {
+        then();
+    }
+}
+
+// Remembers the command, telling if it is a copy of one received through another endpoint.
+// Commands received again through the same endpoint are sent again by the GCS, e.g: when
+// the answer was lost, so they are handled again.
+fn is_duplicated_command(
+    information: &MavlinkCameraInformation,
+    endpoint_index: usize,
+    their_header: &mavlink::MavHeader,
+    command: &Command,
+) -> bool {
+    let mut received_commands = information.received_commands.lock().unwrap();
+    received_commands.retain(|received| received.time.elapsed() < DUPLICATED_COMMAND_WINDOW);
+
+    let duplicated = received_commands.iter().any(|received| {
+        received.endpoint != endpoint_index
+            && received.system_id == their_header.system_id
+            && received.component_id == their_header.component_id
+            && received.command.is_same(command)
+    });
+    if !duplicated {
+        received_commands.push(ReceivedCommand {
+            time: std::time::Instant::now(),
+            endpoint: endpoint_index,
+            system_id: their_header.system_id,
+            component_id: their_header.component_id,
+            command: command.clone(),
+        });
+    }
+    duplicated
+}
+
+fn handle_command(
+    vehicle: &Endpoints,
+    our_header: &mavlink::MavHeader,
+    their_header: &mavlink::MavHeader,
+    mavlink_camera_information: &Arc<Mutex<MavlinkCameraInformation>>,
+    atomic_thread_state: &Arc<Mutex<ThreadState>>,
+    command: &Command,
+) -> CommandResponse {
+    use mavlink::common::{MavCmd, MavResult};
+
+    // Keeps the GCS waiting, instead of sending the command again, while it's handled
+    let send_in_progress = || {
+        send_command_ack(
+            vehicle,
+            our_header,
+            their_header,
+            command.command,
+            MavResult::MAV_RESULT_IN_PROGRESS,
+            UNKNOWN_PROGRESS,
+        )
+    };
+
+    let information = mavlink_camera_information.lock().unwrap().clone();
+    let params = &command.params;
+
+    match command.command {
+        MavCmd::MAV_CMD_REQUEST_CAMERA_INFORMATION => {
+            CommandResponse::accepted(vec![camera_information(&information)])
+        }
+        MavCmd::MAV_CMD_REQUEST_CAMERA_SETTINGS => {
+            CommandResponse::accepted(vec![camera_settings(&information)])
+        }
+        MavCmd::MAV_CMD_REQUEST_STORAGE_INFORMATION => {
+            // param1: storage ID, zero for all of them
+            if params[0] != 0.0 && params[0] != CAPTURE_STORAGE_ID as f32 {
+                return CommandResponse::result(MavResult::MAV_RESULT_DENIED);
+            }
+            CommandResponse::accepted(vec![camera_storage_information()])
+        }
+        MavCmd::MAV_CMD_STORAGE_FORMAT => {
+            // param1: storage ID, param2: format storage, param3: reset image log
+            let format = params[1] == 1.0;
+            let reset_image_log = format || params[2] == 1.0;
+            if params[0] != CAPTURE_STORAGE_ID as f32 || !(format || reset_image_log) {
+                return CommandResponse::result(MavResult::MAV_RESULT_DENIED);
+            }
+
+            // Only the photos and recordings are removed, the storage itself is not formatted
+            if format {
+                #[cfg(feature = "recording")]
+                if recording::is_any_running() {
+                    return CommandResponse::result(MavResult::MAV_RESULT_TEMPORARILY_REJECTED);
+                }
+
+                send_in_progress();
+                if let Err(error) = captures::clear() {
+                    warn!(
+                        "Failed to clear captures as {:#?}:{:#?}. Reason: {error}",
+                        our_header.system_id, our_header.component_id
+                    );
+                    return CommandResponse::result(MavResult::MAV_RESULT_FAILED);
+                }
+            }
+            if reset_image_log {
+                information.image_capture.lock().unwrap().image_index = 0;
+            }
 
-                        let mut no_errors = true;
-                        controls
-                        .iter()
-                        .enumerate()
-                        .for_each(|(param_index, control)| {
-                            let param_id = param_id_from_control_id(control.id);
+            CommandResponse::accepted(vec![camera_storage_information()])
+        }
+        MavCmd::MAV_CMD_REQUEST_CAMERA_CAPTURE_STATUS => {
+            CommandResponse::accepted(vec![camera_capture_status(&information)])
+        }
+        MavCmd::MAV_CMD_REQUEST_VIDEO_STREAM_INFORMATION => {
+            // param1: stream ID, zero for all of them
+            if !is_our_stream(params[0], &information) {
+                warn!(
+                    "Received {:#?} from {:#?}:{:#?} as {:#?}:{:#?} asking for an unknown stream id: {:#?}.",
+                    command.command,
+                    their_header.system_id, their_header.component_id,
+                    our_header.system_id, our_header.component_id,
+                    params[0]
+                );
+                return CommandResponse::result(MavResult::MAV_RESULT_DENIED);
+            }
+            CommandResponse::accepted(vec![video_stream_information(&information)])
+        }
+        MavCmd::MAV_CMD_REQUEST_VIDEO_STREAM_STATUS => {
+            // param1: stream ID, zero for all of them
+            if !is_our_stream(params[0], &information) {
+                return CommandResponse::result(MavResult::MAV_RESULT_DENIED);
+            }
+            CommandResponse::accepted(vec![video_stream_status(&information)])
+        }
+        MavCmd::MAV_CMD_RESET_CAMERA_SETTINGS => {
+            let source_string = information.video_source_type.inner().source_string();
+
+            send_in_progress();
+            match crate::video::video_source::reset_controls(source_string) {
+                Ok(_) => CommandResponse::accepted(vec![]),
+                Err(error) => {
+                    error!("Failed to reset {source_string:?} controls with its default values as {:#?}:{:#?}. Reason: {error:?}.", our_header.system_id, our_header.component_id);
+                    CommandResponse::result(MavResult::MAV_RESULT_FAILED)
+                }
+            }
+        }
+        MavCmd::MAV_CMD_SET_CAMERA_MODE => {
+            if !information.can_capture() {
+                return CommandResponse::result(MavResult::MAV_RESULT_UNSUPPORTED);
+            }
 
-                            let control_value = match &control.configuration {
-                                crate::video::types::ControlType::Bool(bool) => bool.value,
-                                crate::video::types::ControlType::Slider(slider) => slider.value,
-                                crate::video::types::ControlType::Menu(menu) => menu.value,
-                            };
+            // param2: camera mode
+            let mode = match params[1] as u32 {
+                0 => mavlink::common::CameraMode::CAMERA_MODE_IMAGE,
+                1 => mavlink::common::CameraMode::CAMERA_MODE_VIDEO,
+                2 => mavlink::common::CameraMode::CAMERA_MODE_IMAGE_SURVEY,
+                _ => return CommandResponse::result(MavResult::MAV_RESULT_DENIED),
+            };
+
+            let information = {
+                let mut information = mavlink_camera_information.lock().unwrap();
+                information.mode = mode;
+                information.clone()
+            };
+
+            // The GCS updates its camera panel with the new settings
+            CommandResponse::accepted(vec![camera_settings(&information)])
+        }
+        MavCmd::MAV_CMD_IMAGE_START_CAPTURE => {
+            if !information.can_capture() {
+                return CommandResponse::result(MavResult::MAV_RESULT_UNSUPPORTED);
+            }
 
-                            let param_value = param_value_from_control_value(control_value, 128);
+            // param2: seconds between the images, param3: number of images, zero
+            // to capture until stopped, param4: sequence number of single captures
+            let interval = params[1].max(0.0);
+            let total_images = params[2].max(0.0) as u32;
+            let sequence = params[3].max(0.0) as u32;
+            if total_images != 1 && interval == 0.0 {
+                return CommandResponse::result(MavResult::MAV_RESULT_DENIED);
+            }
+            if !information.is_available() {
+                return CommandResponse::result(MavResult::MAV_RESULT_TEMPORARILY_REJECTED);
+            }
 
-                            if let Err(error) = vehicle.read().unwrap().send(
-                                &our_header,
-                                &MavMessage::PARAM_EXT_VALUE(
-                                    mavlink::common::PARAM_EXT_VALUE_DATA {
-                                        param_count: controls.len() as u16,
-                                        param_index: param_index as u16,
-                                        param_id,
-                                        param_value,
-                                        param_type:
-                                            mavlink::common::MavParamExtType::MAV_PARAM_EXT_TYPE_INT64,
-                                    },
-                                ),
-                            ) {
-                                warn!("Failed to send PARAM_EXT_VALUE as {:#?}:{:#?} Reason: {error:?}.", our_header.system_id, our_header.component_id);
-                                no_errors = false;
-                            }
-                        });
-                        if !no_errors {
-                            debug!(
-                                "Sent PARAM_EXT_VALUE as {:#?}:{:#?}.",
-                                our_header.system_id, our_header.component_id
-                            );
-                        }
+            let generation = {
+                let mut image_capture = information.image_capture.lock().unwrap();
+                if total_images == 1 && sequence != 0 {
+                    if sequence == image_capture.last_sequence {
+                        debug!("Ignoring retransmitted image capture {sequence}.");
+                        return CommandResponse::accepted(vec![]);
                     }
-                    MavMessage::HEARTBEAT(heartbeat_data) => {
-                        // We receive a bunch of heartbeat messages, we can ignore it, but as it can be useful for debugging...
-                        trace!(
-                            "Received heartbeat from {:#?}:{:#?} as camera: {:#?}:{:#?}: {heartbeat_data:#?}",
-                            their_header.system_id, their_header.component_id,
+                    image_capture.last_sequence = sequence;
+                }
+                // A new capture replaces the running one
+                image_capture.generation = image_capture.generation.wrapping_add(1);
+                image_capture.interval = (total_images != 1).then_some(interval);
+                image_capture.generation
+            };
+
+            let capture_state = atomic_thread_state.clone();
+            let our_header = *our_header;
+            CommandResponse {
+                then: Some(Box::new(move || {
+                    if let Err(error) = std::thread::Builder::new()
+                        .name(format!(
+                            "image_capture_{:#?}:{:#?}",
                             our_header.system_id, our_header.component_id
-                        );
-                    }
-                    other_message => {
-                        // Any other message that is not a heartbeat or command_long
-                        trace!(
-                            "Received an unsupported message from {:#?}:{:#?} as {:#?}:{:#?}, ignoring it. Message: {other_message:#?}",
-                            their_header.system_id, their_header.component_id,
+                        ))
+                        .spawn(move || {
+                            image_capture_loop(
+                                capture_state,
+                                information,
+                                our_header,
+                                generation,
+                                interval,
+                                total_images,
+                            )
+                        })
+                    {
+                        error!(
+                            "Failed to start image capture as {:#?}:{:#?}. Reason: {error:?}.",
                             our_header.system_id, our_header.component_id
                         );
                     }
+                })),
+                ..CommandResponse::accepted(vec![])
+            }
+        }
+        MavCmd::MAV_CMD_IMAGE_STOP_CAPTURE => {
+            let mut image_capture = information.image_capture.lock().unwrap();
+            image_capture.generation = image_capture.generation.wrapping_add(1);
+            image_capture.interval = None;
+            CommandResponse::accepted(vec![])
+        }
+        #[cfg(feature = "recording")]
+        MavCmd::MAV_CMD_VIDEO_START_CAPTURE => {
+            // param1: stream ID, zero for all of them
+            if !information.can_capture() {
+                return CommandResponse::result(MavResult::MAV_RESULT_UNSUPPORTED);
+            }
+            if !is_our_stream(params[0], &information) {
+                return CommandResponse::result(MavResult::MAV_RESULT_DENIED);
+            }
+            // Answered again when the acknowledgement was lost
+            if recording::file_name(&information.video_stream_name).is_some() {
+                return CommandResponse::accepted(vec![]);
+            }
+            if !information.is_available() {
+                return CommandResponse::result(MavResult::MAV_RESULT_TEMPORARILY_REJECTED);
+            }
+
+            match recording::start(&information.video_stream_name) {
+                Ok(_) => CommandResponse::accepted(vec![]),
+                Err(error) => {
+                    error!(
+                        "Failed to start recording as {:#?}:{:#?}. Reason: {error}",
+                        our_header.system_id, our_header.component_id
+                    );
+                    CommandResponse::result(MavResult::MAV_RESULT_FAILED)
                 }
             }
-            Err(error) => {
-                let information = mavlink_camera_information.lock().unwrap();
-                error!("Error receiving a message as {:#?}:{:#?}. Reason: {error:#?}. Camera: {information:#?}",
-                    our_header.system_id, our_header.component_id
+        }
+        #[cfg(feature = "recording")]
+        MavCmd::MAV_CMD_VIDEO_STOP_CAPTURE => {
+            // param1: stream ID, zero for all of them
+            if !is_our_stream(params[0], &information) {
+                return CommandResponse::result(MavResult::MAV_RESULT_DENIED);
+            }
+            // Answered again when the acknowledgement was lost
+            if recording::file_name(&information.video_stream_name).is_none() {
+                return CommandResponse::accepted(vec![]);
+            }
+
+            match recording::stop(&information.video_stream_name) {
+                Ok(_) => CommandResponse::accepted(vec![]),
+                Err(error) => {
+                    warn!(
+                        "Failed to stop recording as {:#?}:{:#?}. Reason: {error}",
+                        our_header.system_id, our_header.component_id
+                    );
+                    CommandResponse::result(MavResult::MAV_RESULT_FAILED)
+                }
+            }
+        }
+        MavCmd::MAV_CMD_VIDEO_START_STREAMING | MavCmd::MAV_CMD_VIDEO_STOP_STREAMING => {
+            // param1: stream ID, zero for all of them
+            if !is_our_stream(params[0], &information) {
+                return CommandResponse::result(MavResult::MAV_RESULT_DENIED);
+            }
+            if information.redirect_health.is_some() {
+                return CommandResponse::result(MavResult::MAV_RESULT_UNSUPPORTED);
+            }
+
+            // The camera lock is not held, the stream manager updates the camera
+            let paused = command.command == MavCmd::MAV_CMD_VIDEO_STOP_STREAMING;
+            if let Err(error) =
+                crate::stream::manager::set_stream_paused(&information.video_stream_name, paused)
+            {
+                warn!(
+                    "Failed to {} stream as {:#?}:{:#?}. Reason: {error}",
+                    match paused {
+                        true => "pause",
+                        false => "resume",
+                    },
+                    our_header.system_id,
+                    our_header.component_id
                 );
-                if let mavlink::error::MessageReadError::Io(io_error) = &error {
-                    if io_error.kind() == std::io::ErrorKind::WouldBlock {
-                        continue;
-                    }
+                return CommandResponse::result(MavResult::MAV_RESULT_FAILED);
+            }
+
+            let information = mavlink_camera_information.lock().unwrap().clone();
+            CommandResponse::accepted(vec![video_stream_status(&information)])
+        }
+        MavCmd::MAV_CMD_CAMERA_TRACK_POINT | MavCmd::MAV_CMD_CAMERA_TRACK_RECTANGLE => {
+            let region = match TrackingRegion::try_from_command(command) {
+                Some(region) => region,
+                None => return CommandResponse::result(MavResult::MAV_RESULT_DENIED),
+            };
+
+            let information = {
+                let mut information = mavlink_camera_information.lock().unwrap();
+                information.tracking = Some(region);
+                information.clone()
+            };
+
+            CommandResponse::accepted(vec![camera_tracking_image_status(&information)])
+        }
+        MavCmd::MAV_CMD_CAMERA_STOP_TRACKING => {
+            let information = {
+                let mut information = mavlink_camera_information.lock().unwrap();
+                information.tracking = None;
+                information.clone()
+            };
+
+            CommandResponse::accepted(vec![camera_tracking_image_status(&information)])
+        }
+        MavCmd::MAV_CMD_REQUEST_MESSAGE => {
+            // param1: message ID, param2: stream ID for the video stream messages
+            let message_id = params[0] as u32;
+            let message = [
+                camera_information(&information),
+                camera_settings(&information),
+                camera_storage_information(),
+                camera_capture_status(&information),
+                video_stream_information(&information),
+                video_stream_status(&information),
+                camera_tracking_image_status(&information),
+                camera_fov_status(&information),
+            ]
+            .into_iter()
+            .find(|message| message.message_id() == message_id);
+
+            match message {
+                Some(
+                    MavMessage::VIDEO_STREAM_INFORMATION(_) | MavMessage::VIDEO_STREAM_STATUS(_),
+                ) if !is_our_stream(params[1], &information) => {
+                    CommandResponse::result(MavResult::MAV_RESULT_DENIED)
+                }
+                Some(message) => CommandResponse::accepted(vec![message]),
+                None => {
+                    debug!(
+                        "Message {message_id} requested as {:#?}:{:#?} is not provided by cameras.",
+                        our_header.system_id, our_header.component_id
+                    );
+                    CommandResponse::result(MavResult::MAV_RESULT_DENIED)
                 }
-                *atomic_thread_state.lock().unwrap() = ThreadState::RESTART;
             }
         }
+        command => {
+            warn!(
+                "Camera: {:#?}:{:#?}, ignoring unsupported command: {command:#?}",
+                our_header.system_id, our_header.component_id
+            );
+            CommandResponse::result(MavResult::MAV_RESULT_UNSUPPORTED)
+        }
+    }
+}
+
+// Stream commands address a single stream by its ID, or all of them with zero
+fn is_our_stream(stream_id: f32, information: &MavlinkCameraInformation) -> bool {
+    stream_id == 0.0 || stream_id == information.component.stream_id as f32
+}
+
+// Takes the images requested by IMAGE_START_CAPTURE, in its own thread since each one waits
+// for a frame of the stream. Stops after `total_images`, when zero only when stopped by the GCS.
+fn image_capture_loop(
+    atomic_thread_state: Arc<Mutex<ThreadState>>,
+    information: MavlinkCameraInformation,
+    our_header: mavlink::MavHeader,
+    generation: u32,
+    interval: f32,
+    total_images: u32,
+) {
+    let is_stopped = || {
+        *atomic_thread_state.lock().unwrap() == ThreadState::DEAD
+            || information.image_capture.lock().unwrap().generation != generation
+    };
+
+    let mut captured = 0;
+    while !is_stopped() {
+        let started = std::time::Instant::now();
+        let capture = snapshot::save_jpeg(&information.video_stream_name);
+        if let Err(error) = &capture {
+            error!(
+                "Failed to capture image of stream {:?} as {:#?}:{:#?}. Reason: {error}",
+                information.video_stream_name, our_header.system_id, our_header.component_id
+            );
+        }
+        send_image_captured(&information, &our_header, capture.ok());
+
+        captured += 1;
+        if total_images != 0 && captured >= total_images {
+            break;
+        }
+
+        let period = std::time::Duration::from_secs_f32(interval);
+        while started.elapsed() < period && !is_stopped() {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+    }
+
+    let mut image_capture = information.image_capture.lock().unwrap();
+    if image_capture.generation == generation {
+        image_capture.interval = None;
+    }
+}
+
+fn send_image_captured(
+    information: &MavlinkCameraInformation,
+    our_header: &mavlink::MavHeader,
+    file_name: Option<String>,
+) {
+    let (image_index, position, attitude) = {
+        let mut image_capture = information.image_capture.lock().unwrap();
+        let image_index = image_capture.image_index;
+        image_capture.image_index += 1;
+        (
+            image_index,
+            image_capture.position.clone().unwrap_or_default(),
+            image_capture.attitude,
+        )
+    };
+
+    let file_url = file_name
+        .as_ref()
+        .map(|file_name| format!("{}/captures/{file_name}", visible_server_address()))
+        .unwrap_or_default();
+
+    let message = MavMessage::CAMERA_IMAGE_CAPTURED(mavlink::common::CAMERA_IMAGE_CAPTURED_DATA {
+        time_utc: chrono::Utc::now().timestamp_micros() as u64,
+        time_boot_ms: sys_info().time_boot_ms,
+        lat: position.lat,
+        lon: position.lon,
+        alt: position.alt,
+        relative_alt: position.relative_alt,
+        // The camera is considered to be aligned with the vehicle, NaN when its attitude is unknown
+        q: attitude.unwrap_or([f32::NAN; 4]),
+        image_index,
+        camera_id: 0,
+        capture_result: file_name.is_some() as i8,
+        file_url: from_string_to_u8_vec_with_null_terminator(&file_url),
+    });
+
+    if let Err(error) = information.endpoints.send(our_header, &message) {
+        warn!(
+            "Failed to send CAMERA_IMAGE_CAPTURED as {:#?}:{:#?}. Reason: {error:?}.",
+            our_header.system_id, our_header.component_id
+        );
+    } else {
+        debug!(
+            "Sent CAMERA_IMAGE_CAPTURED {image_index} as {:#?}:{:#?}.",
+            our_header.system_id, our_header.component_id
+        );
     }
 }
 
@@ -871,21 +1957,19 @@ fn break_if_wrong_ids(
 }
 
 fn send_command_ack(
-    vehicle: &Arc<
-        std::sync::RwLock<
-            Box<(dyn MavConnection<MavMessage> + Send + std::marker::Sync + 'static)>,
-        >,
-    >,
+    vehicle: &Endpoints,
     our_header: &mavlink::MavHeader,
     their_header: &mavlink::MavHeader,
     command: mavlink::common::MavCmd,
     result: mavlink::common::MavResult,
+    progress: u8,
 ) {
-    if let Err(error) = vehicle.read().unwrap().send(
+    if let Err(error) = vehicle.send(
         our_header,
         &MavMessage::COMMAND_ACK(mavlink::common::COMMAND_ACK_DATA {
             command,
             result,
+            progress,
             target_system: their_header.system_id,
             target_component: their_header.component_id,
             ..Default::default()
@@ -898,7 +1982,7 @@ fn send_command_ack(
         );
     } else {
         debug!(
-            "Sent COMMAND_ACK answering {command:#?} from {:#?}:{:#?} as {:#?}:{:#?}.",
+            "Sent COMMAND_ACK {result:#?} answering {command:#?} from {:#?}:{:#?} as {:#?}:{:#?}.",
             their_header.system_id,
             their_header.component_id,
             our_header.system_id,
@@ -908,17 +1992,13 @@ fn send_command_ack(
 }
 
 fn send_param_ext_ack(
-    vehicle: &Arc<
-        std::sync::RwLock<
-            Box<(dyn MavConnection<MavMessage> + Send + std::marker::Sync + 'static)>,
-        >,
-    >,
+    vehicle: &Endpoints,
     our_header: &mavlink::MavHeader,
     their_header: &mavlink::MavHeader,
     param_ext_set: &mavlink::common::PARAM_EXT_SET_DATA,
     param_result: mavlink::common::ParamAck,
 ) {
-    if let Err(error) = vehicle.read().unwrap().send(
+    if let Err(error) = vehicle.send(
         our_header,
         &MavMessage::PARAM_EXT_ACK(mavlink::common::PARAM_EXT_ACK_DATA {
             param_id: param_ext_set.param_id,
@@ -943,64 +2023,95 @@ fn send_param_ext_ack(
     }
 }
 
+// The delay between connection attempts doubles after each failure, up to the maximum
+const RECONNECT_DELAY_MIN: std::time::Duration = std::time::Duration::from_secs(1);
+const RECONNECT_DELAY_MAX: std::time::Duration = std::time::Duration::from_secs(30);
+// TCP links without any message received for this long are restarted
+const LINK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+// Tries again until connected or the camera is removed
 fn connect(
     component: &MavlinkCameraComponent,
     mavlink_connection_string: &str,
-) -> Box<dyn MavConnection<MavMessage> + Send + std::marker::Sync> {
+    link: &Arc<Mutex<LinkStatus>>,
+    atomic_thread_state: &Arc<Mutex<ThreadState>>,
+) -> Option<Connection> {
+    let mut delay = RECONNECT_DELAY_MIN;
     loop {
-        std::thread::sleep(std::time::Duration::from_secs(1));
-
-        match mavlink::connect(mavlink_connection_string) {
-            Ok(connection) => {
+        match connection::connect(mavlink_connection_string) {
+            Ok(vehicle) => {
                 info!(
-                    "Component {:#?}:{:#?} successfully reconnected to MAVLink endpoint {:#?}.",
+                    "Component {:#?}:{:#?} successfully connected to MAVLink endpoint {:#?}.",
                     component.system_id, component.component_id, mavlink_connection_string
                 );
-                return connection;
+                let mut link_status = link.lock().unwrap();
+                link_status.connected = true;
+                link_status.connections += 1;
+                link_status.last_received = Some(std::time::Instant::now());
+                return Some(connection::monitored(vehicle, link.clone()));
+            }
+            // Servers wait for their clients without delay
+            Err(error) if error.kind() == std::io::ErrorKind::NotConnected => {
+                trace!(
+                    "Component {:#?}:{:#?} is waiting on MAVLink endpoint {:#?}. Reason: {error}.",
+                    component.system_id,
+                    component.component_id,
+                    mavlink_connection_string
+                );
+                if *atomic_thread_state.lock().unwrap() == ThreadState::DEAD {
+                    return None;
+                }
+                continue;
             }
             Err(error) => {
                 error!(
-                    "Component {:#?}:{:#?} failed to reconnect to MAVLink endpoint {:#?}, trying again in one second. Reason: {:#?}.",
+                    "Component {:#?}:{:#?} failed to connect to MAVLink endpoint {:#?}, trying again in {delay:?}. Reason: {error}.",
                     component.system_id, component.component_id,
-                    mavlink_connection_string,
-                    error.kind()
+                    mavlink_connection_string
                 );
+                link.lock().unwrap().last_error = Some(error.to_string());
+            }
+        }
+
+        // Slept in small steps, so removing the camera is not held by the delay
+        let deadline = std::time::Instant::now() + delay;
+        while std::time::Instant::now() < deadline {
+            if *atomic_thread_state.lock().unwrap() == ThreadState::DEAD {
+                return None;
             }
+            std::thread::sleep(std::time::Duration::from_millis(100));
         }
+        delay = std::cmp::min(delay * 2, RECONNECT_DELAY_MAX);
     }
 }
 
-fn reconnect(
-    information: &MavlinkCameraInformation,
-) -> Box<dyn MavConnection<MavMessage> + Send + std::marker::Sync> {
+fn reconnect(component: &MavlinkCameraComponent, endpoint: &Endpoint) -> Option<Connection> {
     debug!(
         "Restarting connection of component {:#?}:{:#?} to MAVLink endpoint {:#?}.",
-        information.component.system_id,
-        information.component.component_id,
-        information.mavlink_connection_string
+        component.system_id, component.component_id, endpoint.address
     );
     connect(
-        &information.component,
-        &information.mavlink_connection_string,
+        component,
+        &endpoint.address,
+        &endpoint.link,
+        &endpoint.state,
     )
 }
 
-fn param_value_from_control_value(control_value: i64, length: usize) -> Vec<char> {
-    let mut param_value = control_value
-        .to_le_bytes()
-        .iter()
-        .map(|&byte| byte as char)
-        .collect::<Vec<char>>();
-    // Workaround for https://github.com/mavlink/rust-mavlink/issues/111
-    param_value.resize(length, Default::default());
+fn param_value_from_control_value(control_value: i64) -> heapless::Vec<u8, 128> {
+    let mut param_value = heapless::Vec::from_slice(&control_value.to_le_bytes()).unwrap();
+    // The field is serialized with the length of the vector, so it has to be padded
+    param_value.resize_default(128).unwrap();
     param_value
 }
 
 fn control_value_from_param_value(
-    param_value: &Vec<char>,
+    param_value: &heapless::Vec<u8, 128>,
     param_type: &mavlink::common::MavParamExtType,
 ) -> Option<i64> {
-    let bytes: Vec<u8> = param_value.iter().map(|c| *c as u8).collect();
+    let mut bytes = param_value.to_vec();
+    // Trailing zeros are removed from MAVLink 2 payloads
+    bytes.resize(std::cmp::max(bytes.len(), 8), 0);
     let control_value = match param_type {
         mavlink::common::MavParamExtType::MAV_PARAM_EXT_TYPE_UINT8 => {
             Ok(u8::from_ne_bytes(bytes[0..1].try_into().unwrap()) as i64)
@@ -1022,83 +2133,207 @@ fn control_value_from_param_value(
     control_value.ok()
 }
 
-fn get_param_index_and_control_id(
+// PARAM_EXT parameters of the camera: its numeric controls, string controls are left out,
+// and the bitrate of the stream when it is known
+#[derive(Debug)]
+enum CameraParameter {
+    Control(crate::video::types::Control),
+    // In kbps
+    Bitrate(u32),
+}
+
+impl CameraParameter {
+    // Controls are named after their ID, as in the camera definition
+    fn param_id(&self) -> [u8; 16] {
+        match self {
+            CameraParameter::Control(control) => param_id_from_str(&control.id.to_string()),
+            CameraParameter::Bitrate(_) => param_id_from_str(xml::BITRATE_PARAMETER),
+        }
+    }
+
+    fn param_type(&self) -> mavlink::common::MavParamExtType {
+        match self {
+            CameraParameter::Control(control) => match control.cpp_type.as_str() {
+                "bool" => mavlink::common::MavParamExtType::MAV_PARAM_EXT_TYPE_UINT8,
+                "int32" => mavlink::common::MavParamExtType::MAV_PARAM_EXT_TYPE_INT32,
+                _ => mavlink::common::MavParamExtType::MAV_PARAM_EXT_TYPE_INT64,
+            },
+            CameraParameter::Bitrate(_) => {
+                mavlink::common::MavParamExtType::MAV_PARAM_EXT_TYPE_INT32
+            }
+        }
+    }
+
+    // Value when the parameters were listed
+    fn value(&self) -> i64 {
+        match self {
+            CameraParameter::Control(control) => match &control.configuration {
+                crate::video::types::ControlType::Bool(bool) => bool.value,
+                crate::video::types::ControlType::Slider(slider) => slider.value,
+                crate::video::types::ControlType::Menu(menu) => menu.value,
+                crate::video::types::ControlType::String(_) => unreachable!(),
+            },
+            CameraParameter::Bitrate(bitrate_kbps) => *bitrate_kbps as i64,
+        }
+    }
+
+    fn accepts(&self, value: i64) -> bool {
+        match self {
+            CameraParameter::Control(control) => match &control.configuration {
+                crate::video::types::ControlType::Bool(_) => matches!(value, 0 | 1),
+                crate::video::types::ControlType::Slider(slider) => {
+                    (slider.min..=slider.max).contains(&value)
+                }
+                crate::video::types::ControlType::Menu(menu) => {
+                    menu.options.iter().any(|option| option.value == value)
+                }
+                crate::video::types::ControlType::String(_) => false,
+            },
+            CameraParameter::Bitrate(_) => xml::BITRATE_RANGE_KBPS.contains(&value),
+        }
+    }
+}
+
+fn camera_parameters(information: &MavlinkCameraInformation) -> Vec<CameraParameter> {
+    let mut parameters: Vec<CameraParameter> = information
+        .video_source_type
+        .inner()
+        .controls()
+        .into_iter()
+        .filter(|control| {
+            !matches!(
+                control.configuration,
+                crate::video::types::ControlType::String(_)
+            )
+        })
+        .map(CameraParameter::Control)
+        .collect();
+
+    if let Some(bitrate_kbps) = information.bitrate_kbps() {
+        parameters.push(CameraParameter::Bitrate(bitrate_kbps));
+    }
+
+    parameters
+}
+
+// Current value, read from the camera for the controls
+fn parameter_value(
+    information: &MavlinkCameraInformation,
+    parameter: &CameraParameter,
+) -> std::io::Result<i64> {
+    match parameter {
+        CameraParameter::Control(control) => information
+            .video_source_type
+            .inner()
+            .control_value_by_id(control.id),
+        CameraParameter::Bitrate(_) => Ok(information.bitrate_kbps().unwrap_or_default() as i64),
+    }
+}
+
+// Controls are stored as the ones changed by the REST API, so they are restored when the camera reconnects
+fn set_parameter(
+    information: &MavlinkCameraInformation,
+    parameter: &CameraParameter,
+    value: i64,
+) -> Result<(), String> {
+    match parameter {
+        CameraParameter::Control(control) => {
+            let source = information.video_source_type.inner().source_string();
+            information
+                .video_source_type
+                .inner()
+                .set_control_by_id(control.id, value)
+                .map_err(|error| error.to_string())?;
+            let bus = crate::video::video_source::usb_bus(source);
+            settings::manager::set_control_default(source, bus.as_deref(), control.id, value);
+            Ok(())
+        }
+        CameraParameter::Bitrate(_) => {
+            crate::stream::manager::set_stream_bitrate(&information.video_stream_name, value as u32)
+                .map_err(|error| error.to_string())
+        }
+    }
+}
+
+fn find_parameter<'a>(
     param_ext_req: &mavlink::common::PARAM_EXT_REQUEST_READ_DATA,
-    controls: &Vec<crate::video::types::Control>,
-) -> Option<(u16, u64)> {
+    parameters: &'a [CameraParameter],
+) -> Option<(u16, &'a CameraParameter)> {
     let param_index = param_ext_req.param_index;
     // Use param_index if it is !=1, otherwise, use param_id. For more information: https://mavlink.io/en/messages/common.html#PARAM_EXT_REQUEST_READ
-    let (param_index, control_id) = if param_index == -1 {
-        let control_id = match control_id_from_param_id(&param_ext_req.param_id) {
-            Some(value) => value,
-            None => return None,
-        };
-
-        match &controls.iter().position(|control| control_id == control.id) {
-            Some(param_index) => (*param_index as i16, control_id),
+    if param_index == -1 {
+        let position = parameters
+            .iter()
+            .position(|parameter| parameter.param_id() == param_ext_req.param_id);
+        match position {
+            Some(param_index) => Some((param_index as u16, &parameters[param_index])),
             None => {
-                error!("Failed to find control id {control_id}.");
-                return None;
+                error!(
+                    "Failed to find parameter {:?}.",
+                    String::from_utf8_lossy(&param_ext_req.param_id)
+                );
+                None
             }
         }
     } else {
-        match &controls.get(param_index as usize) {
-            Some(control) => (param_index, control.id),
+        match parameters.get(param_index as usize) {
+            Some(parameter) => Some((param_index as u16, parameter)),
             None => {
-                error!("Failed to find control index {param_index}.");
-                return None;
+                error!("Failed to find parameter index {param_index}.");
+                None
             }
         }
-    };
-    Some((param_index as u16, control_id))
+    }
 }
 
-fn param_id_from_control_id(id: u64) -> [char; 16] {
-    let mut param_id: [char; 16] = Default::default();
-    id.to_string()
-        .chars()
+fn param_id_from_str(name: &str) -> [u8; 16] {
+    let mut param_id: [u8; 16] = Default::default();
+    name.bytes()
         .zip(param_id.iter_mut())
         .for_each(|(a, b)| *b = a);
     param_id
 }
 
-fn control_id_from_param_id(param_id: &[char; 16]) -> Option<u64> {
-    let control_id = param_id
-        .iter()
-        .collect::<String>()
-        .trim_end_matches(char::from(0))
-        .parse::<u64>();
-    if let Err(error) = control_id {
-        error!("Failed to parse control id: {error:#?}.");
-        return None;
-    }
-    control_id.ok()
-}
-
 #[derive(Debug)]
 struct SysInfo {
     time_boot_ms: u32,
+}
+
+// Storage of the captures, capacities in MiB
+#[derive(Debug)]
+struct StorageInfo {
     total_capacity: f32,
     used_capacity: f32,
     available_capacity: f32,
+    status: mavlink::common::StorageStatus,
 }
 
-fn sys_info() -> SysInfo {
-    //Both uses KB
-    let mut local_total_capacity = 0;
-    let mut local_available_capacity = 0;
-
-    match sys_info::disk_info() {
-        Ok(disk_info) => {
-            local_available_capacity = disk_info.free;
-            local_total_capacity = disk_info.total;
-        }
+fn storage_info() -> StorageInfo {
+    const MIB: f32 = 1024.0 * 1024.0;
 
+    match captures::storage() {
+        Ok(storage) => StorageInfo {
+            total_capacity: storage.total_bytes as f32 / MIB,
+            used_capacity: storage.total_bytes.saturating_sub(storage.available_bytes) as f32 / MIB,
+            available_capacity: storage.available_bytes as f32 / MIB,
+            status: mavlink::common::StorageStatus::STORAGE_STATUS_READY,
+        },
         Err(error) => {
-            warn!("Failed to fetch disk info: {error:#?}.");
+            warn!("Failed to fetch capture storage info: {error:#?}.");
+            StorageInfo {
+                total_capacity: 0.0,
+                used_capacity: 0.0,
+                available_capacity: 0.0,
+                status: mavlink::common::StorageStatus::STORAGE_STATUS_NOT_SUPPORTED,
+            }
         }
     }
+}
 
+fn sys_info() -> SysInfo {
+    #[cfg(windows)]
+    let boottime_ms = START_TIME.elapsed().as_millis();
+    #[cfg(not(windows))]
     let boottime_ms = match sys_info::boottime() {
         Ok(bootime) => bootime.tv_usec / 1000,
         Err(error) => {
@@ -1109,10 +2344,6 @@ fn sys_info() -> SysInfo {
 
     SysInfo {
         time_boot_ms: boottime_ms as u32,
-        total_capacity: local_total_capacity as f32 / f32::powf(2.0, 10.0),
-        used_capacity: ((local_total_capacity - local_available_capacity) as f32)
-            / f32::powf(2.0, 10.0),
-        available_capacity: local_available_capacity as f32 / f32::powf(2.0, 10.0),
     }
 }
 
@@ -1130,24 +2361,32 @@ fn heartbeat_message() -> MavMessage {
 
 fn camera_information(information: &MavlinkCameraInformation) -> MavMessage {
     let vendor_name = from_string_to_u8_array_with_size_32(&information.component.vendor_name);
-    let model_name = from_string_to_u8_array_with_size_32(&information.component.vendor_name);
-    let cam_definition_uri = from_string_to_vec_char_with_defined_size_and_null_terminator(
-        &information.cam_definition_uri().unwrap().to_string(),
-        140,
+    let model_name = from_string_to_u8_array_with_size_32(&information.component.model_name);
+    let definition_uri = information.cam_definition_uri();
+    let cam_definition_version = match definition_uri {
+        Some(_) => {
+            xml::definition_version(&information.video_source_type, information.bitrate_kbps())
+        }
+        None => 0,
+    };
+    let cam_definition_uri = from_string_to_u8_vec_with_null_terminator(
+        &definition_uri
+            .map(|uri| uri.to_string())
+            .unwrap_or_default(),
     );
 
     let sys_info = sys_info();
 
     MavMessage::CAMERA_INFORMATION(mavlink::common::CAMERA_INFORMATION_DATA {
         time_boot_ms: sys_info.time_boot_ms,
-        firmware_version: 0,
+        firmware_version: information.component.firmware_version,
         focal_length: 0.0,
         sensor_size_h: 0.0,
         sensor_size_v: 0.0,
-        flags: mavlink::common::CameraCapFlags::CAMERA_CAP_FLAGS_HAS_VIDEO_STREAM,
+        flags: capability_flags(information),
         resolution_h: information.component.resolution_h,
         resolution_v: information.component.resolution_v,
-        cam_definition_version: 0,
+        cam_definition_version,
         vendor_name,
         model_name,
         lens_id: 0,
@@ -1155,55 +2394,134 @@ fn camera_information(information: &MavlinkCameraInformation) -> MavMessage {
     })
 }
 
-fn camera_settings() -> MavMessage {
+// Images and videos can be captured in any mode, since both are taken from the stream
+fn capability_flags(information: &MavlinkCameraInformation) -> mavlink::common::CameraCapFlags {
+    use mavlink::common::CameraCapFlags;
+
+    let mut flags = CameraCapFlags::CAMERA_CAP_FLAGS_HAS_VIDEO_STREAM
+        | CameraCapFlags::CAMERA_CAP_FLAGS_HAS_TRACKING_POINT
+        | CameraCapFlags::CAMERA_CAP_FLAGS_HAS_TRACKING_RECTANGLE;
+    if information.can_capture() {
+        flags |= CameraCapFlags::CAMERA_CAP_FLAGS_CAPTURE_IMAGE
+            | CameraCapFlags::CAMERA_CAP_FLAGS_HAS_MODES
+            | CameraCapFlags::CAMERA_CAP_FLAGS_HAS_IMAGE_SURVEY_MODE
+            | CameraCapFlags::CAMERA_CAP_FLAGS_CAN_CAPTURE_IMAGE_IN_VIDEO_MODE;
+        if cfg!(feature = "recording") {
+            flags |= CameraCapFlags::CAMERA_CAP_FLAGS_CAPTURE_VIDEO
+                | CameraCapFlags::CAMERA_CAP_FLAGS_CAN_CAPTURE_VIDEO_IN_IMAGE_MODE;
+        }
+    }
+    flags
+}
+
+fn camera_settings(information: &MavlinkCameraInformation) -> MavMessage {
     let sys_info = sys_info();
+    let controls = information.video_source_type.inner().controls();
 
     MavMessage::CAMERA_SETTINGS(mavlink::common::CAMERA_SETTINGS_DATA {
         time_boot_ms: sys_info.time_boot_ms,
-        zoomLevel: 0.0,
-        focusLevel: 0.0,
-        mode_id: mavlink::common::CameraMode::CAMERA_MODE_VIDEO,
+        zoomLevel: control_percentage(&controls, V4L2_CID_ZOOM_ABSOLUTE),
+        focusLevel: control_percentage(&controls, V4L2_CID_FOCUS_ABSOLUTE),
+        mode_id: information.mode,
     })
 }
 
+const V4L2_CID_FOCUS_ABSOLUTE: u64 = 0x009a090a;
+const V4L2_CID_ZOOM_ABSOLUTE: u64 = 0x009a090d;
+
+// Value of a slider control, from 0 to 100% of its range, NaN when the camera doesn't have it
+fn control_percentage(controls: &[crate::video::types::Control], control_id: u64) -> f32 {
+    let slider = controls
+        .iter()
+        .find(|control| control.id == control_id)
+        .and_then(|control| match &control.configuration {
+            crate::video::types::ControlType::Slider(slider) => Some(slider),
+            _ => None,
+        });
+    match slider {
+        Some(slider) if slider.max > slider.min => {
+            100.0 * (slider.value - slider.min) as f32 / (slider.max - slider.min) as f32
+        }
+        _ => f32::NAN,
+    }
+}
+
+// The capture path is reported as the only storage of the camera
+const CAPTURE_STORAGE_ID: u8 = 1;
+
 fn camera_storage_information() -> MavMessage {
     let sys_info = sys_info();
+    let storage_info = storage_info();
 
     MavMessage::STORAGE_INFORMATION(mavlink::common::STORAGE_INFORMATION_DATA {
         time_boot_ms: sys_info.time_boot_ms,
-        total_capacity: sys_info.total_capacity,
-        used_capacity: sys_info.used_capacity,
-        available_capacity: sys_info.available_capacity,
+        total_capacity: storage_info.total_capacity,
+        used_capacity: storage_info.used_capacity,
+        available_capacity: storage_info.available_capacity,
         read_speed: 1000.0,
         write_speed: 1000.0,
-        storage_id: 0,
-        storage_count: 0,
-        status: mavlink::common::StorageStatus::STORAGE_STATUS_READY,
+        storage_id: CAPTURE_STORAGE_ID,
+        storage_count: 1,
+        status: storage_info.status,
+        mavtype: mavlink::common::StorageType::STORAGE_TYPE_OTHER,
+        name: from_string_to_u8_array_with_size_32(&"captures".to_string()),
+        // The dialect takes a single usage flag, while the storage holds both images and videos
+        storage_usage: Default::default(),
     })
 }
 
-fn camera_capture_status() -> MavMessage {
+fn camera_capture_status(information: &MavlinkCameraInformation) -> MavMessage {
     let sys_info = sys_info();
+    let image_capture = information.image_capture.lock().unwrap();
+    #[cfg(feature = "recording")]
+    let recording_time = recording::elapsed(&information.video_stream_name);
+    #[cfg(not(feature = "recording"))]
+    let recording_time: Option<std::time::Duration> = None;
 
     MavMessage::CAMERA_CAPTURE_STATUS(mavlink::common::CAMERA_CAPTURE_STATUS_DATA {
         time_boot_ms: sys_info.time_boot_ms,
-        image_interval: 0.0,
-        recording_time_ms: 0,
-        available_capacity: sys_info.available_capacity,
-        image_status: 0,
-        video_status: 0,
-        image_count: 0,
+        image_interval: image_capture.interval.unwrap_or_default(),
+        recording_time_ms: recording_time.map_or(0, |time| time.as_millis() as u32),
+        available_capacity: storage_info().available_capacity,
+        // 3: interval set and capture in progress
+        image_status: match image_capture.interval {
+            Some(_) => 3,
+            None => 0,
+        },
+        video_status: recording_time.is_some() as u8,
+        image_count: image_capture.image_index,
     })
 }
 
+// Servers listening on all interfaces are advertised with the address visible to the GCS,
+// UDP endpoints are kept since the GCS only uses their port
+fn visible_stream_uri(endpoint: &Url) -> Url {
+    let is_server = matches!(endpoint.scheme(), "rtsp" | "rtsps" | "tcp");
+    let is_unspecified = match endpoint.host() {
+        Some(url::Host::Ipv4(address)) => address.is_unspecified(),
+        Some(url::Host::Ipv6(address)) => address.is_unspecified(),
+        Some(url::Host::Domain(_)) => false,
+        None => true,
+    };
+    if !is_server || !is_unspecified {
+        return endpoint.clone();
+    }
+
+    let mut uri = endpoint.clone();
+    if let Err(error) = uri.set_host(Some(&get_visible_qgc_address())) {
+        warn!("Failed to advertise {endpoint} with the visible address: {error}");
+        return endpoint.clone();
+    }
+    uri
+}
+
 fn video_stream_information(information: &MavlinkCameraInformation) -> MavMessage {
-    let name = from_string_to_char_array_with_size_32(&information.video_stream_name);
-    let uri = from_string_to_vec_char_with_defined_size_and_null_terminator(
-        &information.video_stream_uri.to_string(),
-        140,
+    let name = from_string_to_u8_array_with_size_32(&information.video_stream_name);
+    let uri = from_string_to_u8_vec_with_null_terminator(
+        &visible_stream_uri(&information.video_stream_uri).to_string(),
     );
 
-    //The only important information here is the mavtype and uri variables, everything else is fake
+    // Each camera component has a single stream
     MavMessage::VIDEO_STREAM_INFORMATION(mavlink::common::VIDEO_STREAM_INFORMATION_DATA {
         framerate: information.component.framerate,
         bitrate: information.component.bitrate,
@@ -1213,13 +2531,135 @@ fn video_stream_information(information: &MavlinkCameraInformation) -> MavMessag
         rotation: information.component.rotation,
         hfov: information.component.hfov,
         stream_id: information.component.stream_id,
-        count: 0,
+        count: 1,
         mavtype: information.mavlink_stream_type,
         name,
         uri,
     })
 }
 
+fn video_stream_status(information: &MavlinkCameraInformation) -> MavMessage {
+    MavMessage::VIDEO_STREAM_STATUS(mavlink::common::VIDEO_STREAM_STATUS_DATA {
+        framerate: match information.is_available() {
+            true => information.component.framerate,
+            false => 0.0,
+        },
+        bitrate: match information.is_available() {
+            true => information.component.bitrate,
+            false => 0,
+        },
+        flags: get_stream_status_flag(&information.component),
+        resolution_h: information.component.resolution_h,
+        resolution_v: information.component.resolution_v,
+        rotation: information.component.rotation,
+        hfov: information.component.hfov,
+        stream_id: information.component.stream_id,
+    })
+}
+
+// The fields that don't apply to the tracking mode are NaN, as are all of them while not tracking
+fn camera_tracking_image_status(information: &MavlinkCameraInformation) -> MavMessage {
+    use mavlink::common::{CameraTrackingMode, CameraTrackingStatusFlags};
+
+    let mut status = mavlink::common::CAMERA_TRACKING_IMAGE_STATUS_DATA {
+        point_x: f32::NAN,
+        point_y: f32::NAN,
+        radius: f32::NAN,
+        rec_top_x: f32::NAN,
+        rec_top_y: f32::NAN,
+        rec_bottom_x: f32::NAN,
+        rec_bottom_y: f32::NAN,
+        tracking_status: CameraTrackingStatusFlags::CAMERA_TRACKING_STATUS_FLAGS_IDLE,
+        tracking_mode: CameraTrackingMode::CAMERA_TRACKING_MODE_NONE,
+        // The region is not drawn on the video, it is only reported
+        target_data:
+            mavlink::common::CameraTrackingTargetData::CAMERA_TRACKING_TARGET_DATA_IN_STATUS,
+    };
+    match &information.tracking {
+        Some(TrackingRegion::Point(point)) => {
+            status.tracking_status = CameraTrackingStatusFlags::CAMERA_TRACKING_STATUS_FLAGS_ACTIVE;
+            status.tracking_mode = CameraTrackingMode::CAMERA_TRACKING_MODE_POINT;
+            status.point_x = point.x;
+            status.point_y = point.y;
+            status.radius = point.radius;
+        }
+        Some(TrackingRegion::Rectangle(rectangle)) => {
+            status.tracking_status = CameraTrackingStatusFlags::CAMERA_TRACKING_STATUS_FLAGS_ACTIVE;
+            status.tracking_mode = CameraTrackingMode::CAMERA_TRACKING_MODE_RECTANGLE;
+            status.rec_top_x = rectangle.top_x;
+            status.rec_top_y = rectangle.top_y;
+            status.rec_bottom_x = rectangle.bottom_x;
+            status.rec_bottom_y = rectangle.bottom_y;
+        }
+        None => (),
+    }
+
+    MavMessage::CAMERA_TRACKING_IMAGE_STATUS(status)
+}
+
+// The camera is considered to be aligned with the vehicle, its unknown values are INT32_MAX and NaN
+fn camera_fov_status(information: &MavlinkCameraInformation) -> MavMessage {
+    const UNKNOWN: i32 = i32::MAX;
+
+    let (position, attitude) = {
+        let image_capture = information.image_capture.lock().unwrap();
+        (image_capture.position.clone(), image_capture.attitude)
+    };
+    let (lat_image, lon_image, alt_image) = position
+        .as_ref()
+        .zip(attitude)
+        .and_then(|(position, attitude)| image_center(position, attitude))
+        .unwrap_or((UNKNOWN, UNKNOWN, UNKNOWN));
+    let fov = information.component.fov.as_ref();
+
+    MavMessage::CAMERA_FOV_STATUS(mavlink::common::CAMERA_FOV_STATUS_DATA {
+        time_boot_ms: sys_info().time_boot_ms,
+        lat_camera: position.as_ref().map_or(UNKNOWN, |position| position.lat),
+        lon_camera: position.as_ref().map_or(UNKNOWN, |position| position.lon),
+        alt_camera: position.as_ref().map_or(UNKNOWN, |position| position.alt),
+        lat_image,
+        lon_image,
+        alt_image,
+        q: attitude.unwrap_or([f32::NAN; 4]),
+        hfov: fov.map_or(information.component.hfov as f32, |fov| fov.horizontal),
+        vfov: fov.map_or(f32::NAN, |fov| fov.vertical),
+    })
+}
+
+// Where the forward axis of the camera meets the ground, as latitude and longitude in degE7 and
+// altitude in mm. The ground is the height of the home position, so the terrain is not considered.
+// None when the axis doesn't point below the horizon or the vehicle is not above the ground.
+fn image_center(
+    position: &mavlink::common::GLOBAL_POSITION_INT_DATA,
+    attitude: [f32; 4],
+) -> Option<(i32, i32, i32)> {
+    const EARTH_RADIUS: f64 = 6_378_137.0;
+    // Below it the center of the image is too far away to be meaningful
+    const MIN_DOWN: f64 = 0.01;
+
+    // Forward axis of the vehicle in the north, east and down frame
+    let [w, x, y, z] = attitude.map(|value| value as f64);
+    let north = 1.0 - 2.0 * (y * y + z * z);
+    let east = 2.0 * (x * y + w * z);
+    let down = 2.0 * (x * z - w * y);
+
+    let height = position.relative_alt as f64 / 1000.0;
+    if !(down > MIN_DOWN) || height <= 0.0 {
+        return None;
+    }
+
+    let distance = height / down;
+    let lat = position.lat as f64 / 1e7;
+    let lon = position.lon as f64 / 1e7;
+    let lat_image = lat + (north * distance / EARTH_RADIUS).to_degrees();
+    let lon_image = lon + (east * distance / (EARTH_RADIUS * lat.to_radians().cos())).to_degrees();
+    Some((
+        (lat_image * 1e7).round() as i32,
+        (lon_image * 1e7).round() as i32,
+        position.alt - position.relative_alt,
+    ))
+}
+
 fn from_string_to_u8_array_with_size_32(src: &String) -> [u8; 32] {
     let bytes = src.as_bytes();
     let mut dst = [0u8; 32];
@@ -1228,24 +2668,14 @@ fn from_string_to_u8_array_with_size_32(src: &String) -> [u8; 32] {
     dst
 }
 
-fn from_string_to_char_array_with_size_32(src: &String) -> [char; 32] {
-    let chars: Vec<char> = src.chars().collect();
-    let mut dst = ['\0'; 32];
-    let len = std::cmp::min(chars.len(), 32);
-    dst[..len].copy_from_slice(&chars[..len]);
+// The null terminator is left out when the string fills the field
+fn from_string_to_u8_vec_with_null_terminator<const N: usize>(src: &str) -> heapless::Vec<u8, N> {
+    let bytes = src.as_bytes();
+    let mut dst = heapless::Vec::from_slice(&bytes[..std::cmp::min(bytes.len(), N)]).unwrap();
+    let _ = dst.push(0);
     dst
 }
 
-fn from_string_to_vec_char_with_defined_size_and_null_terminator(
-    src: &String,
-    size: usize,
-) -> Vec<char> {
-    let mut uri = src.chars().collect::<Vec<char>>();
-    uri.truncate(size);
-    uri.push('\0');
-    uri
-}
-
 fn get_stream_status_flag(
     component: &MavlinkCameraComponent,
 ) -> mavlink::common::VideoStreamStatusFlags {
@@ -1254,3 +2684,97 @@ fn get_stream_status_flag(
         false => mavlink::common::VideoStreamStatusFlags::VIDEO_STREAM_STATUS_FLAGS_RUNNING,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mavlink::common::MavCmd;
+
+    fn command(command: MavCmd, params: [f32; 4]) -> Command {
+        Command {
+            command,
+            target_system: 1,
+            target_component: 100,
+            params: [params[0], params[1], params[2], params[3], 0.0, 0.0, 0.0],
+        }
+    }
+
+    #[test]
+    fn tracking_region_from_command() {
+        assert_eq!(
+            TrackingRegion::try_from_command(&command(
+                MavCmd::MAV_CMD_CAMERA_TRACK_POINT,
+                [0.5, 0.25, 0.1, 0.0]
+            )),
+            Some(TrackingRegion::Point(TrackingPoint {
+                x: 0.5,
+                y: 0.25,
+                radius: 0.1,
+            }))
+        );
+        assert_eq!(
+            TrackingRegion::try_from_command(&command(
+                MavCmd::MAV_CMD_CAMERA_TRACK_RECTANGLE,
+                [0.1, 0.2, 0.3, 0.4]
+            )),
+            Some(TrackingRegion::Rectangle(TrackingRectangle {
+                top_x: 0.1,
+                top_y: 0.2,
+                bottom_x: 0.3,
+                bottom_y: 0.4,
+            }))
+        );
+
+        // Out of the image, or with the corners swapped
+        for (mav_cmd, params) in [
+            (MavCmd::MAV_CMD_CAMERA_TRACK_POINT, [1.5, 0.5, 0.1, 0.0]),
+            (
+                MavCmd::MAV_CMD_CAMERA_TRACK_POINT,
+                [0.5, f32::NAN, 0.1, 0.0],
+            ),
+            (MavCmd::MAV_CMD_CAMERA_TRACK_RECTANGLE, [0.3, 0.2, 0.1, 0.4]),
+            (
+                MavCmd::MAV_CMD_CAMERA_TRACK_RECTANGLE,
+                [0.1, 0.2, 0.3, -0.4],
+            ),
+            (MavCmd::MAV_CMD_CAMERA_STOP_TRACKING, [0.0, 0.0, 0.0, 0.0]),
+        ] {
+            assert_eq!(
+                TrackingRegion::try_from_command(&command(mav_cmd, params)),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn image_center_from_attitude() {
+        let position = mavlink::common::GLOBAL_POSITION_INT_DATA {
+            lat: 100_000_000,
+            lon: 200_000_000,
+            alt: 150_000,
+            relative_alt: 100_000,
+            ..Default::default()
+        };
+        let pitched_down = |degrees: f32| {
+            let half = (-degrees).to_radians() / 2.0;
+            [half.cos(), 0.0, half.sin(), 0.0]
+        };
+
+        // Looking straight down, and 45 degrees down to the north, 100 m ahead
+        assert_eq!(
+            image_center(&position, pitched_down(90.0)),
+            Some((100_000_000, 200_000_000, 50_000))
+        );
+        let (lat, lon, _) = image_center(&position, pitched_down(45.0)).unwrap();
+        assert!((lat - (100_000_000 + 8_983)).abs() <= 1, "{lat}");
+        assert_eq!(lon, 200_000_000);
+
+        // Level, or below the home position
+        assert_eq!(image_center(&position, [1.0, 0.0, 0.0, 0.0]), None);
+        let underwater = mavlink::common::GLOBAL_POSITION_INT_DATA {
+            relative_alt: -5_000,
+            ..position
+        };
+        assert_eq!(image_center(&underwater, pitched_down(90.0)), None);
+    }
+}
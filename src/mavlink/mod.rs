@@ -1,2 +1,3 @@
+pub mod connection;
 pub mod manager;
 pub mod mavlink_camera;
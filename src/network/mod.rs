@@ -1 +1,2 @@
+pub mod onvif_discovery;
 pub mod utils;
@@ -0,0 +1,395 @@
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream, UdpSocket};
+use std::time::{Duration, Instant};
+
+use paperclip::actix::Apiv2Schema;
+use regex::Regex;
+use serde::Serialize;
+use simple_error::{simple_error, SimpleError};
+use tracing::*;
+
+use crate::video::types::VideoEncodeType;
+
+use super::utils::get_ipv4_addresses;
+
+// WS-Discovery scanner for ONVIF cameras (NetworkVideoTransmitter devices).
+// For more information: https://www.onvif.org/specs/core/ONVIF-Core-Specification.pdf
+
+const DISCOVERY_ADDRESS: &str = "239.255.255.250:3702";
+// Time waiting for the cameras to answer the probe
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+// Time waiting for each SOAP request to the cameras
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Apiv2Schema, Clone, Debug, Serialize)]
+pub struct OnvifCamera {
+    pub name: String,
+    // Device service address, e.g: "http://192.168.2.10/onvif/device_service"
+    pub address: String,
+    pub profiles: Vec<OnvifProfile>,
+    // Why the profiles could not be read, e.g: when the camera requires authentication
+    pub error: Option<String>,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, Serialize)]
+pub struct OnvifProfile {
+    pub token: String,
+    pub name: String,
+    pub encode: Option<VideoEncodeType>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    // RTSP address, to be used as an IP source or as a redirect endpoint
+    pub stream_uri: Option<String>,
+}
+
+// Probe the network of each interface address and query the cameras that answered
+pub fn discover() -> Vec<OnvifCamera> {
+    let threads: Vec<_> = get_ipv4_addresses()
+        .into_iter()
+        .map(|address| std::thread::spawn(move || probe(address)))
+        .collect();
+
+    let mut devices: Vec<(String, String)> = vec![];
+    for thread in threads {
+        match thread.join() {
+            Ok(Ok(found)) => devices.extend(found),
+            Ok(Err(error)) => warn!("Failed to probe for ONVIF cameras: {error:#?}"),
+            Err(error) => error!("ONVIF discovery thread panicked: {error:#?}"),
+        }
+    }
+
+    // The same camera answers on every interface that can reach it
+    devices.sort_by(|(_, first), (_, second)| first.cmp(second));
+    devices.dedup_by(|(_, first), (_, second)| first == second);
+
+    let threads: Vec<_> = devices
+        .into_iter()
+        .map(|(name, address)| std::thread::spawn(move || camera(name, address)))
+        .collect();
+
+    threads
+        .into_iter()
+        .filter_map(|thread| thread.join().ok())
+        .collect()
+}
+
+// Send a WS-Discovery probe from `address` and return the name and
+// device service address of each camera that answered
+fn probe(address: Ipv4Addr) -> std::io::Result<Vec<(String, String)>> {
+    let socket = UdpSocket::bind(SocketAddr::from((address, 0)))?;
+    socket.send_to(probe_message().as_bytes(), DISCOVERY_ADDRESS)?;
+    debug!("Sent ONVIF discovery probe from {address}.");
+
+    let mut devices = vec![];
+    let mut buffer = [0u8; 65535];
+    let deadline = Instant::now() + DISCOVERY_TIMEOUT;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let (size, sender) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                break
+            }
+            Err(error) => return Err(error),
+        };
+
+        let response = String::from_utf8_lossy(&buffer[..size]);
+        trace!("ONVIF discovery answer from {sender}: {response}");
+        devices.extend(probe_matches(&response));
+    }
+
+    Ok(devices)
+}
+
+fn probe_message() -> String {
+    // Any unique value is fine, it is only used to match the answers
+    let message_id = chrono::Utc::now().timestamp_nanos();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+        <s:Envelope \
+            xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\" \
+            xmlns:a=\"http://schemas.xmlsoap.org/ws/2004/08/addressing\" \
+            xmlns:d=\"http://schemas.xmlsoap.org/ws/2005/04/discovery\" \
+            xmlns:dn=\"http://www.onvif.org/ver10/network/wsdl\">\
+            <s:Header>\
+                <a:Action s:mustUnderstand=\"1\">http://schemas.xmlsoap.org/ws/2005/04/discovery/Probe</a:Action>\
+                <a:MessageID>uuid:00000000-0000-0000-0000-{message_id:012x}</a:MessageID>\
+                <a:ReplyTo><a:Address>http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous</a:Address></a:ReplyTo>\
+                <a:To s:mustUnderstand=\"1\">urn:schemas-xmlsoap-org:ws:2005:04:discovery</a:To>\
+            </s:Header>\
+            <s:Body><d:Probe><d:Types>dn:NetworkVideoTransmitter</d:Types></d:Probe></s:Body>\
+        </s:Envelope>",
+        message_id = message_id & 0xffff_ffff_ffff
+    )
+}
+
+// Each ProbeMatch has the device service addresses and the scopes, e.g:
+// "onvif://www.onvif.org/name/Camera%20Name onvif://www.onvif.org/hardware/Model"
+fn probe_matches(response: &str) -> Vec<(String, String)> {
+    let match_regex =
+        Regex::new(r"(?s)<(?:\w+:)?ProbeMatch>(?P<content>.*?)</(?:\w+:)?ProbeMatch>").unwrap();
+    let name_regex = Regex::new(r"onvif://www\.onvif\.org/name/(?P<name>\S+)").unwrap();
+
+    match_regex
+        .captures_iter(response)
+        .filter_map(|captures| {
+            let content = &captures["content"];
+            let address = element(content, "XAddrs")?
+                .split_whitespace()
+                .find(|address| address.starts_with("http"))?
+                .to_string();
+
+            // Names are URL encoded, the first key of a form is decoded as well
+            let name = element(content, "Scopes")
+                .and_then(|scopes| {
+                    name_regex
+                        .captures(&scopes)
+                        .map(|captures| captures["name"].to_string())
+                })
+                .and_then(|name| {
+                    url::form_urlencoded::parse(name.as_bytes())
+                        .next()
+                        .map(|(name, _)| name.into_owned())
+                })
+                .unwrap_or_else(|| {
+                    let host = url::Url::parse(&address)
+                        .ok()
+                        .and_then(|url| url.host_str().map(str::to_string))
+                        .unwrap_or_default();
+                    format!("ONVIF camera {host}")
+                });
+
+            Some((name, address))
+        })
+        .collect()
+}
+
+fn camera(name: String, address: String) -> OnvifCamera {
+    let mut camera = OnvifCamera {
+        name,
+        address,
+        profiles: vec![],
+        error: None,
+    };
+
+    match profiles(&camera.address) {
+        Ok(profiles) => camera.profiles = profiles,
+        Err(error) => {
+            warn!(
+                "Failed to get the profiles of ONVIF camera {:?} ({}): {error}",
+                camera.name, camera.address
+            );
+            camera.error = Some(error.to_string());
+        }
+    }
+
+    camera
+}
+
+fn profiles(device_address: &str) -> Result<Vec<OnvifProfile>, SimpleError> {
+    let capabilities = soap_request(
+        device_address,
+        "<tds:GetCapabilities><tds:Category>Media</tds:Category></tds:GetCapabilities>",
+    )?;
+    let media_regex =
+        Regex::new(r"(?s)<(?:\w+:)?Media>.*?<(?:\w+:)?XAddr>\s*(?P<address>[^<\s]+)\s*<").unwrap();
+    let media_address = media_regex
+        .captures(&capabilities)
+        .map(|captures| unescape(&captures["address"]))
+        .unwrap_or_else(|| device_address.to_string());
+
+    let response = soap_request(&media_address, "<trt:GetProfiles/>")?;
+    let profile_regex =
+        Regex::new(r#"(?s)<(?:\w+:)?Profiles[^>]*\btoken="(?P<token>[^"]+)"[^>]*>(?P<content>.*?)</(?:\w+:)?Profiles>"#)
+            .unwrap();
+
+    let profiles = profile_regex
+        .captures_iter(&response)
+        .map(|captures| {
+            let token = unescape(&captures["token"]);
+            let content = &captures["content"];
+            let encoder = encoder_configuration(content).unwrap_or(content);
+
+            let stream_uri = soap_request(
+                &media_address,
+                &format!(
+                    "<trt:GetStreamUri>\
+                        <trt:StreamSetup>\
+                            <tt:Stream>RTP-Unicast</tt:Stream>\
+                            <tt:Transport><tt:Protocol>RTSP</tt:Protocol></tt:Transport>\
+                        </trt:StreamSetup>\
+                        <trt:ProfileToken>{}</trt:ProfileToken>\
+                    </trt:GetStreamUri>",
+                    escape(&token)
+                ),
+            )
+            .map_err(|error| warn!("Failed to get stream URI of profile {token:?}: {error}"))
+            .ok()
+            .and_then(|response| element(&response, "Uri"));
+
+            OnvifProfile {
+                name: element(content, "Name").unwrap_or_else(|| token.clone()),
+                encode: element(encoder, "Encoding").and_then(|encoding| match encoding.as_str() {
+                    "H264" => Some(VideoEncodeType::H264),
+                    "H265" => Some(VideoEncodeType::H265),
+                    "JPEG" => Some(VideoEncodeType::MJPG),
+                    _ => None,
+                }),
+                width: element(encoder, "Width").and_then(|width| width.parse().ok()),
+                height: element(encoder, "Height").and_then(|height| height.parse().ok()),
+                token,
+                stream_uri,
+            }
+        })
+        .collect();
+
+    Ok(profiles)
+}
+
+fn encoder_configuration(profile: &str) -> Option<&str> {
+    let regex = Regex::new(
+        r"(?s)<(?:\w+:)?VideoEncoderConfiguration\b[^>]*>(?P<content>.*?)</(?:\w+:)?VideoEncoderConfiguration>",
+    )
+    .unwrap();
+    regex
+        .captures(profile)
+        .and_then(|captures| captures.name("content"))
+        .map(|content| content.as_str())
+}
+
+// Text of the first element with this name, ignoring its namespace
+fn element(content: &str, name: &str) -> Option<String> {
+    let regex = Regex::new(&format!(r"<(?:\w+:)?{name}\b[^>]*>\s*(?P<text>[^<]*?)\s*<")).unwrap();
+    regex
+        .captures(content)
+        .map(|captures| unescape(&captures["text"]))
+        .filter(|text| !text.is_empty())
+}
+
+// Minimal SOAP over HTTP client, cameras that require authentication are not supported
+fn soap_request(address: &str, body: &str) -> Result<String, SimpleError> {
+    let url = url::Url::parse(address)
+        .map_err(|error| simple_error!(format!("Invalid address: {error}")))?;
+    let socket_address = url
+        .socket_addrs(|| Some(80))
+        .map_err(|error| simple_error!(format!("Failed to resolve {address}: {error}")))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| simple_error!(format!("Failed to resolve {address}.")))?;
+
+    let content = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+        <s:Envelope \
+            xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\" \
+            xmlns:tds=\"http://www.onvif.org/ver10/device/wsdl\" \
+            xmlns:trt=\"http://www.onvif.org/ver10/media/wsdl\" \
+            xmlns:tt=\"http://www.onvif.org/ver10/schema\">\
+            <s:Body>{body}</s:Body>\
+        </s:Envelope>"
+    );
+    // HTTP/1.0 avoids chunked answers
+    let request = format!(
+        "POST {path} HTTP/1.0\r\n\
+        Host: {host}\r\n\
+        Content-Type: application/soap+xml; charset=utf-8\r\n\
+        Content-Length: {length}\r\n\
+        \r\n\
+        {content}",
+        path = url.path(),
+        host = url.host_str().unwrap_or_default(),
+        length = content.len(),
+    );
+
+    let mut stream = TcpStream::connect_timeout(&socket_address, REQUEST_TIMEOUT)
+        .map_err(|error| simple_error!(format!("Failed to connect to {address}: {error}")))?;
+    stream
+        .set_read_timeout(Some(REQUEST_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(REQUEST_TIMEOUT)))
+        .and_then(|_| stream.write_all(request.as_bytes()))
+        .map_err(|error| simple_error!(format!("Failed to send request to {address}: {error}")))?;
+
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|error| simple_error!(format!("Failed to read answer from {address}: {error}")))?;
+
+    let (header, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| simple_error!(format!("Invalid answer from {address}.")))?;
+    let status = header.lines().next().unwrap_or_default();
+    if status.contains(" 401 ") || body.contains("NotAuthorized") {
+        return Err(simple_error!("Camera requires authentication."));
+    }
+    if !status.contains(" 200 ") {
+        return Err(simple_error!(format!(
+            "Request to {address} failed: {status}"
+        )));
+    }
+
+    Ok(body.to_string())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_match_decode() {
+        let response = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <SOAP-ENV:Envelope xmlns:SOAP-ENV="http://www.w3.org/2003/05/soap-envelope">
+                <SOAP-ENV:Body>
+                    <d:ProbeMatches>
+                        <d:ProbeMatch>
+                            <wsa:EndpointReference><wsa:Address>urn:uuid:1</wsa:Address></wsa:EndpointReference>
+                            <d:Types>dn:NetworkVideoTransmitter</d:Types>
+                            <d:Scopes>onvif://www.onvif.org/type/video_encoder onvif://www.onvif.org/name/Low%20Light%20Camera onvif://www.onvif.org/hardware/IPC</d:Scopes>
+                            <d:XAddrs>http://192.168.2.10/onvif/device_service http://[fe80::1]/onvif/device_service</d:XAddrs>
+                        </d:ProbeMatch>
+                        <d:ProbeMatch>
+                            <d:XAddrs>http://192.168.2.11:8080/onvif/device_service</d:XAddrs>
+                        </d:ProbeMatch>
+                    </d:ProbeMatches>
+                </SOAP-ENV:Body>
+            </SOAP-ENV:Envelope>"#;
+
+        assert_eq!(
+            probe_matches(response),
+            vec![
+                (
+                    "Low Light Camera".to_string(),
+                    "http://192.168.2.10/onvif/device_service".to_string()
+                ),
+                (
+                    "ONVIF camera 192.168.2.11".to_string(),
+                    "http://192.168.2.11:8080/onvif/device_service".to_string()
+                ),
+            ]
+        );
+    }
+}
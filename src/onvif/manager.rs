@@ -0,0 +1,299 @@
+use chrono::{Datelike, Timelike};
+use regex::Regex;
+
+use crate::stream::{manager as stream_manager, types::CaptureConfiguration};
+use crate::video::types::VideoEncodeType;
+
+// Minimal ONVIF Device and Media services (Profile S subset), enough for
+// VMS/NVR systems to list the RTSP streams and record them.
+// For more information: https://www.onvif.org/profiles/specifications/
+
+const DEVICE_SERVICE_PATH: &str = "/onvif/device_service";
+const MEDIA_SERVICE_PATH: &str = "/onvif/media_service";
+
+#[derive(Debug)]
+struct Profile {
+    token: String,
+    name: String,
+    encoding: &'static str,
+    width: u32,
+    height: u32,
+    framerate: u32,
+    uri: url::Url,
+}
+
+// Each RTSP stream with an ONVIF compatible encoding is exposed as a profile
+fn profiles() -> Vec<Profile> {
+    stream_manager::streams()
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, stream)| {
+            let information = stream.video_and_stream;
+            let uri = information
+                .stream_information
+                .endpoints
+                .iter()
+                .find(|endpoint| matches!(endpoint.scheme(), "rtsp" | "rtsps"))?
+                .clone();
+
+            let configuration = match &information.stream_information.configuration {
+                CaptureConfiguration::VIDEO(configuration) => configuration,
+                _ => return None,
+            };
+            let encoding = match configuration.stream_encode() {
+                VideoEncodeType::H264 => "H264",
+                VideoEncodeType::MJPG => "JPEG",
+                _ => return None,
+            };
+
+            let (width, height) = configuration.output_resolution();
+
+            Some(Profile {
+                token: format!("profile_{index}"),
+                name: information.name,
+                encoding,
+                width,
+                height,
+                framerate: configuration.frame_interval.denominator
+                    / configuration.frame_interval.numerator.max(1),
+                uri,
+            })
+        })
+        .collect()
+}
+
+// Handle a SOAP request for the Device service.
+// `host` is the address used by the client to reach us, as in the HTTP Host header.
+pub fn device_service(request: &str, host: &str) -> Result<String, String> {
+    let body = match action(request).as_deref() {
+        Some("GetDeviceInformation") => format!(
+            "<tds:GetDeviceInformationResponse>\
+                <tds:Manufacturer>Blue Robotics</tds:Manufacturer>\
+                <tds:Model>{model}</tds:Model>\
+                <tds:FirmwareVersion>{version}</tds:FirmwareVersion>\
+                <tds:SerialNumber>{serial}</tds:SerialNumber>\
+                <tds:HardwareId>{serial}</tds:HardwareId>\
+            </tds:GetDeviceInformationResponse>",
+            model = env!("CARGO_PKG_NAME"),
+            version = env!("CARGO_PKG_VERSION"),
+            serial = escape(&sys_info::hostname().unwrap_or_default()),
+        ),
+        Some("GetSystemDateAndTime") => {
+            let now = chrono::Utc::now();
+            format!(
+                "<tds:GetSystemDateAndTimeResponse><tds:SystemDateAndTime>\
+                    <tt:DateTimeType>NTP</tt:DateTimeType>\
+                    <tt:DaylightSavings>false</tt:DaylightSavings>\
+                    <tt:TimeZone><tt:TZ>UTC</tt:TZ></tt:TimeZone>\
+                    <tt:UTCDateTime>\
+                        <tt:Time><tt:Hour>{}</tt:Hour><tt:Minute>{}</tt:Minute><tt:Second>{}</tt:Second></tt:Time>\
+                        <tt:Date><tt:Year>{}</tt:Year><tt:Month>{}</tt:Month><tt:Day>{}</tt:Day></tt:Date>\
+                    </tt:UTCDateTime>\
+                </tds:SystemDateAndTime></tds:GetSystemDateAndTimeResponse>",
+                now.hour(),
+                now.minute(),
+                now.second(),
+                now.year(),
+                now.month(),
+                now.day()
+            )
+        }
+        Some("GetCapabilities") => format!(
+            "<tds:GetCapabilitiesResponse><tds:Capabilities>\
+                <tt:Device><tt:XAddr>http://{host}{DEVICE_SERVICE_PATH}</tt:XAddr></tt:Device>\
+                <tt:Media><tt:XAddr>http://{host}{MEDIA_SERVICE_PATH}</tt:XAddr>\
+                    <tt:StreamingCapabilities>\
+                        <tt:RTPMulticast>false</tt:RTPMulticast>\
+                        <tt:RTP_TCP>true</tt:RTP_TCP>\
+                        <tt:RTP_RTSP_TCP>true</tt:RTP_RTSP_TCP>\
+                    </tt:StreamingCapabilities>\
+                </tt:Media>\
+            </tds:Capabilities></tds:GetCapabilitiesResponse>"
+        ),
+        Some("GetServices") => format!(
+            "<tds:GetServicesResponse>\
+                <tds:Service>\
+                    <tds:Namespace>http://www.onvif.org/ver10/device/wsdl</tds:Namespace>\
+                    <tds:XAddr>http://{host}{DEVICE_SERVICE_PATH}</tds:XAddr>\
+                    <tds:Version><tt:Major>2</tt:Major><tt:Minor>0</tt:Minor></tds:Version>\
+                </tds:Service>\
+                <tds:Service>\
+                    <tds:Namespace>http://www.onvif.org/ver10/media/wsdl</tds:Namespace>\
+                    <tds:XAddr>http://{host}{MEDIA_SERVICE_PATH}</tds:XAddr>\
+                    <tds:Version><tt:Major>2</tt:Major><tt:Minor>0</tt:Minor></tds:Version>\
+                </tds:Service>\
+            </tds:GetServicesResponse>"
+        ),
+        action => return Err(action_not_supported(action)),
+    };
+
+    Ok(envelope(&body))
+}
+
+// Handle a SOAP request for the Media service.
+// `host` is the address used by the client to reach us, as in the HTTP Host header.
+pub fn media_service(request: &str, host: &str) -> Result<String, String> {
+    let body = match action(request).as_deref() {
+        Some("GetProfiles") => {
+            let profiles: String = profiles().iter().map(profile_to_xml).collect();
+            format!("<trt:GetProfilesResponse>{profiles}</trt:GetProfilesResponse>")
+        }
+        Some("GetVideoSources") => {
+            let sources: String = profiles()
+                .iter()
+                .map(|profile| {
+                    format!(
+                        "<trt:VideoSources token=\"{token}\">\
+                            <tt:Framerate>{framerate}</tt:Framerate>\
+                            <tt:Resolution><tt:Width>{width}</tt:Width><tt:Height>{height}</tt:Height></tt:Resolution>\
+                        </trt:VideoSources>",
+                        token = profile.token,
+                        framerate = profile.framerate,
+                        width = profile.width,
+                        height = profile.height,
+                    )
+                })
+                .collect();
+            format!("<trt:GetVideoSourcesResponse>{sources}</trt:GetVideoSourcesResponse>")
+        }
+        Some("GetStreamUri") => {
+            let token = profile_token(request).ok_or_else(|| fault("Invalid profile token."))?;
+            let profile = profiles()
+                .into_iter()
+                .find(|profile| profile.token == token)
+                .ok_or_else(|| fault("Invalid profile token."))?;
+
+            // The streams are usually bound to 0.0.0.0, which is not reachable by the client
+            let mut uri = profile.uri;
+            let hostname = host
+                .rsplit_once(':')
+                .map_or(host, |(hostname, _port)| hostname);
+            let _ = uri.set_host(Some(hostname));
+
+            format!(
+                "<trt:GetStreamUriResponse><trt:MediaUri>\
+                    <tt:Uri>{uri}</tt:Uri>\
+                    <tt:InvalidAfterConnect>false</tt:InvalidAfterConnect>\
+                    <tt:InvalidAfterReboot>false</tt:InvalidAfterReboot>\
+                    <tt:Timeout>PT0S</tt:Timeout>\
+                </trt:MediaUri></trt:GetStreamUriResponse>",
+                uri = escape(uri.as_str())
+            )
+        }
+        action => return Err(action_not_supported(action)),
+    };
+
+    Ok(envelope(&body))
+}
+
+fn profile_to_xml(profile: &Profile) -> String {
+    format!(
+        "<trt:Profiles token=\"{token}\" fixed=\"true\">\
+            <tt:Name>{name}</tt:Name>\
+            <tt:VideoSourceConfiguration token=\"{token}\">\
+                <tt:Name>{name}</tt:Name>\
+                <tt:UseCount>1</tt:UseCount>\
+                <tt:SourceToken>{token}</tt:SourceToken>\
+                <tt:Bounds x=\"0\" y=\"0\" width=\"{width}\" height=\"{height}\"/>\
+            </tt:VideoSourceConfiguration>\
+            <tt:VideoEncoderConfiguration token=\"{token}\">\
+                <tt:Name>{name}</tt:Name>\
+                <tt:UseCount>1</tt:UseCount>\
+                <tt:Encoding>{encoding}</tt:Encoding>\
+                <tt:Resolution><tt:Width>{width}</tt:Width><tt:Height>{height}</tt:Height></tt:Resolution>\
+                <tt:Quality>5</tt:Quality>\
+                <tt:RateControl>\
+                    <tt:FrameRateLimit>{framerate}</tt:FrameRateLimit>\
+                    <tt:EncodingInterval>1</tt:EncodingInterval>\
+                    <tt:BitrateLimit>0</tt:BitrateLimit>\
+                </tt:RateControl>\
+                <tt:SessionTimeout>PT60S</tt:SessionTimeout>\
+            </tt:VideoEncoderConfiguration>\
+        </trt:Profiles>",
+        token = profile.token,
+        name = escape(&profile.name),
+        encoding = profile.encoding,
+        width = profile.width,
+        height = profile.height,
+        framerate = profile.framerate,
+    )
+}
+
+// The action is the first element inside the SOAP body, e.g: <trt:GetProfiles/>
+fn action(request: &str) -> Option<String> {
+    let regex = Regex::new(r"<(?:\w+:)?Body[^>]*>\s*<(?:\w+:)?(?P<action>\w+)").unwrap();
+    regex
+        .captures(request)
+        .map(|captures| captures["action"].to_string())
+}
+
+fn profile_token(request: &str) -> Option<String> {
+    let regex = Regex::new(r"<(?:\w+:)?ProfileToken>\s*(?P<token>[^<\s]+)\s*<").unwrap();
+    regex
+        .captures(request)
+        .map(|captures| captures["token"].to_string())
+}
+
+fn envelope(body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+        <s:Envelope \
+            xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\" \
+            xmlns:tds=\"http://www.onvif.org/ver10/device/wsdl\" \
+            xmlns:trt=\"http://www.onvif.org/ver10/media/wsdl\" \
+            xmlns:tt=\"http://www.onvif.org/ver10/schema\">\
+            <s:Body>{body}</s:Body>\
+        </s:Envelope>"
+    )
+}
+
+fn fault(reason: &str) -> String {
+    envelope(&format!(
+        "<s:Fault>\
+            <s:Code><s:Value>s:Sender</s:Value></s:Code>\
+            <s:Reason><s:Text xml:lang=\"en\">{}</s:Text></s:Reason>\
+        </s:Fault>",
+        escape(reason)
+    ))
+}
+
+fn action_not_supported(action: Option<&str>) -> String {
+    fault(&format!(
+        "Action not supported: {}",
+        action.unwrap_or("unknown")
+    ))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soap_request_decode() {
+        let request = r#"<?xml version="1.0" encoding="utf-8"?>
+            <s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope">
+                <s:Header/>
+                <s:Body xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+                    <GetStreamUri xmlns="http://www.onvif.org/ver10/media/wsdl">
+                        <StreamSetup/>
+                        <ProfileToken>profile_1</ProfileToken>
+                    </GetStreamUri>
+                </s:Body>
+            </s:Envelope>"#;
+
+        assert_eq!(action(request), Some("GetStreamUri".into()));
+        assert_eq!(profile_token(request), Some("profile_1".into()));
+
+        let request = "<s:Envelope><s:Body><tds:GetDeviceInformation/></s:Body></s:Envelope>";
+        assert_eq!(action(request), Some("GetDeviceInformation".into()));
+        assert_eq!(profile_token(request), None);
+    }
+}
@@ -16,6 +16,59 @@ fn json_error_handler(error: JsonPayloadError, _: &HttpRequest) -> actix_web::Er
     error.into()
 }
 
+#[cfg(feature = "onvif")]
+fn onvif_routes(config: &mut web::ServiceConfig) {
+    config
+        .route(
+            "/onvif/device_service",
+            web::post().to(pages::onvif_device_service),
+        )
+        .route(
+            "/onvif/media_service",
+            web::post().to(pages::onvif_media_service),
+        );
+}
+
+#[cfg(not(feature = "onvif"))]
+fn onvif_routes(_config: &mut web::ServiceConfig) {}
+
+#[cfg(feature = "mavlink")]
+fn mavlink_routes(config: &mut web::ServiceConfig) {
+    config
+        .route("/mavlink/ids", web::get().to(pages::mavlink_ids))
+        .route("/mavlink/ids", web::post().to(pages::mavlink_ids_post))
+        .route("/mavlink/endpoint", web::get().to(pages::mavlink_endpoint))
+        .route(
+            "/mavlink/endpoint",
+            web::post().to(pages::mavlink_endpoint_post),
+        )
+        .route("/mavlink/status", web::get().to(pages::mavlink_status))
+        .route("/mavlink/tracking", web::get().to(pages::mavlink_tracking));
+}
+
+#[cfg(not(feature = "mavlink"))]
+fn mavlink_routes(_config: &mut web::ServiceConfig) {}
+
+// Captures are only taken by the GCS or recorded by request
+#[cfg(any(feature = "mavlink", feature = "recording"))]
+fn captures_routes(config: &mut web::ServiceConfig) {
+    config.route("/captures/{file}", web::get().to(pages::capture));
+}
+
+#[cfg(not(any(feature = "mavlink", feature = "recording")))]
+fn captures_routes(_config: &mut web::ServiceConfig) {}
+
+#[cfg(feature = "recording")]
+fn recording_routes(config: &mut web::ServiceConfig) {
+    config.route(
+        "/streams/recording",
+        web::post().to(pages::stream_recording),
+    );
+}
+
+#[cfg(not(feature = "recording"))]
+fn recording_routes(_config: &mut web::ServiceConfig) {}
+
 // Start REST API server with the desired address
 pub async fn run(server_address: &str) -> Result<(), std::io::Error> {
     let server_address = server_address.to_string();
@@ -52,16 +105,35 @@ pub async fn run(server_address: &str) -> Result<(), std::io::Error> {
                 r"/{filename:.*(\.html|\.js|\.css)}",
                 web::get().to(pages::root),
             )
+            .configure(captures_routes)
             .route("/delete_stream", web::delete().to(pages::remove_stream))
+            .route("/events", web::get().to(pages::events))
+            .route("/gst_sources", web::post().to(pages::gst_source_post))
+            .route("/gst_sources", web::delete().to(pages::gst_source_remove))
+            .configure(mavlink_routes)
+            .route("/mjpeg/{stream}", web::get().to(pages::mjpeg))
+            .route("/onvif", web::get().to(pages::onvif_cameras))
+            .configure(onvif_routes)
             .route("/reset_settings", web::post().to(pages::reset_settings))
             .route("/streams", web::get().to(pages::streams))
             .route("/streams", web::post().to(pages::streams_post))
+            .route("/streams/osd", web::post().to(pages::stream_osd))
+            .route("/streams/fov", web::post().to(pages::stream_fov))
+            .configure(recording_routes)
+            .route(
+                "/streams/streaming",
+                web::post().to(pages::stream_streaming),
+            )
+            .route("/streams/validate", web::post().to(pages::streams_validate))
+            .route("/stats", web::get().to(pages::stats))
             .route("/v4l", web::get().to(pages::v4l))
             .route("/v4l", web::post().to(pages::v4l_post))
+            .route("/v4l/save", web::post().to(pages::v4l_save_post))
             .route(
                 "/camera/reset_controls",
                 web::post().to(pages::camera_reset_controls),
             )
+            .route("/ws/video/{stream}", web::get().to(pages::ws_video))
             .route("/xml", web::get().to(pages::xml))
             .build()
     })
@@ -1,19 +1,33 @@
+#[cfg(any(feature = "onvif", feature = "mavlink"))]
+use crate::cli;
+use crate::event::manager::{self as event_manager, Event};
+use crate::network::onvif_discovery::{self, OnvifCamera};
+#[cfg(feature = "onvif")]
+use crate::onvif;
 use crate::settings;
+#[cfg(any(feature = "mavlink", feature = "recording"))]
+use crate::stream::captures;
+#[cfg(feature = "recording")]
+use crate::stream::recording;
 use crate::stream::{
-    manager as stream_manager,
-    types::{StreamInformation, StreamStatus},
+    fmp4, manager as stream_manager, mjpeg,
+    types::{FieldOfView, StreamInformation, StreamStatistics, StreamStatus},
 };
 use crate::video::{
     types::{Control, Format, VideoSourceType},
     video_source,
     video_source::VideoSource,
-    xml,
+    video_source_gst, xml,
 };
 use crate::video_stream::types::VideoAndStreamInformation;
 use actix_web::{
     web::{self, Json},
     HttpRequest, HttpResponse,
 };
+use futures::{
+    future::{self, Either},
+    StreamExt,
+};
 use paperclip::actix::{api_v2_operation, Apiv2Schema};
 use serde::{Deserialize, Serialize};
 use simple_error::SimpleError;
@@ -33,7 +47,12 @@ pub struct ApiVideoSource {
 pub struct V4lControl {
     device: String,
     v4l_id: u64,
+    #[serde(default)]
     value: i64,
+    // Value of string controls, used instead of `value`
+    string: Option<String>,
+    // Maximum change rate, in control units per second, for zoom, focus, pan and tilt controls
+    ramp_rate: Option<u32>,
 }
 
 #[derive(Apiv2Schema, Debug, Deserialize, Serialize)]
@@ -48,6 +67,59 @@ pub struct RemoveStream {
     name: String,
 }
 
+#[derive(Apiv2Schema, Debug, Deserialize)]
+pub struct PostGstSource {
+    name: String,
+    // GStreamer description providing raw frames, e.g: "aravissrc camera-name=Basler-21234567"
+    description: String,
+}
+
+#[derive(Apiv2Schema, Debug, Deserialize)]
+pub struct RemoveGstSource {
+    name: String,
+}
+
+#[derive(Apiv2Schema, Debug, Deserialize)]
+pub struct StreamOsd {
+    name: String,
+    enabled: bool,
+}
+
+#[derive(Apiv2Schema, Debug, Deserialize)]
+pub struct StreamFov {
+    name: String,
+    // Removed when not defined
+    fov: Option<FieldOfView>,
+}
+
+#[derive(Apiv2Schema, Debug, Deserialize)]
+pub struct StreamStreaming {
+    name: String,
+    enabled: bool,
+}
+
+#[cfg(feature = "recording")]
+#[derive(Apiv2Schema, Debug, Deserialize)]
+pub struct StreamRecording {
+    name: String,
+    enabled: bool,
+}
+
+#[cfg(feature = "mavlink")]
+#[derive(Apiv2Schema, Debug, Deserialize, Serialize)]
+pub struct MavlinkIds {
+    system_id: u8,
+    // Component of the first camera, up to six cameras use the following ones
+    component_id: u8,
+}
+
+#[cfg(feature = "mavlink")]
+#[derive(Apiv2Schema, Debug, Deserialize, Serialize)]
+pub struct MavlinkEndpoint {
+    // Connection string, like the --mavlink argument, e.g: "udpout:192.168.2.1:14550"
+    endpoint: String,
+}
+
 #[derive(Apiv2Schema, Debug, Deserialize)]
 pub struct ResetSettings {
     all: Option<bool>,
@@ -58,9 +130,16 @@ pub struct ResetCameraControls {
     device: String,
 }
 
+#[derive(Apiv2Schema, Debug, Deserialize)]
+pub struct EventsRequest {
+    since: Option<u64>,
+}
+
 #[derive(Apiv2Schema, Debug, Deserialize)]
 pub struct XmlFileRequest {
     file: String,
+    // Adds the parameters of the stream, like its bitrate
+    stream: Option<String>,
 }
 
 use std::{ffi::OsStr, path::Path};
@@ -111,6 +190,7 @@ pub async fn v4l() -> Json<Vec<ApiVideoSource>> {
     let cameras: Vec<ApiVideoSource> = cameras
         .iter()
         .map(|cam| match cam {
+            #[cfg(target_os = "linux")]
             VideoSourceType::Local(cam) => ApiVideoSource {
                 name: cam.name().clone(),
                 source: cam.source_string().to_string(),
@@ -129,6 +209,30 @@ pub async fn v4l() -> Json<Vec<ApiVideoSource>> {
                 formats: redirect.formats(),
                 controls: redirect.controls(),
             },
+            VideoSourceType::Audio(audio) => ApiVideoSource {
+                name: audio.name().clone(),
+                source: audio.source_string().to_string(),
+                formats: audio.formats(),
+                controls: audio.controls(),
+            },
+            VideoSourceType::Ip(ip) => ApiVideoSource {
+                name: ip.name().clone(),
+                source: ip.source_string().to_string(),
+                formats: ip.formats(),
+                controls: ip.controls(),
+            },
+            VideoSourceType::File(file) => ApiVideoSource {
+                name: file.name().clone(),
+                source: file.source_string().to_string(),
+                formats: file.formats(),
+                controls: file.controls(),
+            },
+            VideoSourceType::Screen(screen) => ApiVideoSource {
+                name: screen.name().clone(),
+                source: screen.source_string().to_string(),
+                formats: screen.formats(),
+                controls: screen.controls(),
+            },
         })
         .collect();
 
@@ -136,11 +240,21 @@ pub async fn v4l() -> Json<Vec<ApiVideoSource>> {
 }
 
 #[api_v2_operation]
-/// Change video control for a specific source
+/// Change video control for a specific source, the value is applied again when the camera reconnects or the service restarts
 pub fn v4l_post(json: web::Json<V4lControl>) -> HttpResponse {
-    let control = json.into_inner();
-    let answer = video_source::set_control(&control.device, control.v4l_id, control.value);
+    set_v4l_control(json.into_inner())
+}
+
+#[api_v2_operation]
+/// Change the V4L2 control value and save it, same as POST /v4l, kept for compatibility
+pub fn v4l_save_post(json: web::Json<V4lControl>) -> HttpResponse {
+    set_v4l_control(json.into_inner())
+}
+
+fn set_v4l_control(control: V4lControl) -> HttpResponse {
+    let answer = apply_v4l_control(&control);
     if answer.is_ok() {
+        store_v4l_control(&control);
         return HttpResponse::Ok().finish();
     };
 
@@ -149,6 +263,34 @@ pub fn v4l_post(json: web::Json<V4lControl>) -> HttpResponse {
         .body(format!("{:#?}", answer.err().unwrap()));
 }
 
+// Numeric controls are stored per camera, by USB bus path when possible
+fn store_v4l_control(control: &V4lControl) {
+    if control.string.is_some() {
+        return;
+    }
+
+    let bus = video_source::usb_bus(&control.device);
+    settings::manager::set_control_default(
+        &control.device,
+        bus.as_deref(),
+        control.v4l_id,
+        control.value,
+    );
+}
+
+fn apply_v4l_control(control: &V4lControl) -> std::io::Result<()> {
+    if let Some(string) = &control.string {
+        return video_source::set_control_string(&control.device, control.v4l_id, string);
+    }
+
+    match control.ramp_rate {
+        Some(ramp_rate) => {
+            video_source::ramp_control(&control.device, control.v4l_id, control.value, ramp_rate)
+        }
+        None => video_source::set_control(&control.device, control.v4l_id, control.value),
+    }
+}
+
 #[api_v2_operation]
 /// Reset service settings
 pub async fn reset_settings(query: web::Query<ResetSettings>) -> HttpResponse {
@@ -163,6 +305,19 @@ pub async fn reset_settings(query: web::Query<ResetSettings>) -> HttpResponse {
         .body("Missing argument for reset_settings.");
 }
 
+#[api_v2_operation]
+/// Provide the ONVIF cameras found in the vehicle network, with the stream URIs of their profiles
+pub async fn onvif_cameras() -> Json<Vec<OnvifCamera>> {
+    // Discovery waits for the cameras to answer, keep it out of the server workers
+    let cameras = web::block(onvif_discovery::discover)
+        .await
+        .unwrap_or_else(|error| {
+            error!("Failed to discover ONVIF cameras: {error:#?}");
+            vec![]
+        });
+    Json(cameras)
+}
+
 #[api_v2_operation]
 /// Provide a list of all streams configured
 pub async fn streams() -> Json<Vec<StreamStatus>> {
@@ -170,6 +325,18 @@ pub async fn streams() -> Json<Vec<StreamStatus>> {
     Json(streams)
 }
 
+#[api_v2_operation]
+/// Provide the latest internal events (stream transitions, hotplug, MAVLink commands and errors), optionally only the ones after a given event id
+pub async fn events(query: web::Query<EventsRequest>) -> Json<Vec<Event>> {
+    Json(event_manager::events(query.since))
+}
+
+#[api_v2_operation]
+/// Provide the delivery statistics of all streams, like packets sent and dropped per UDP client
+pub async fn stats() -> Json<Vec<StreamStatistics>> {
+    Json(stream_manager::statistics())
+}
+
 #[api_v2_operation]
 /// Create a video stream
 pub fn streams_post(json: web::Json<PostStream>) -> HttpResponse {
@@ -200,6 +367,40 @@ pub fn streams_post(json: web::Json<PostStream>) -> HttpResponse {
     }
 }
 
+#[api_v2_operation]
+/// Check a video stream configuration and build its pipeline, without creating the stream
+pub fn streams_validate(json: web::Json<PostStream>) -> HttpResponse {
+    let json = json.into_inner();
+
+    let video_source = match video_source::get_video_source(&json.source) {
+        Ok(video_source) => video_source,
+        Err(error) => {
+            return HttpResponse::NotAcceptable()
+                .content_type("text/plain")
+                .body(format!("{:#?}", SimpleError::from(error).to_string()));
+        }
+    };
+
+    match stream_manager::validate_stream(&VideoAndStreamInformation {
+        name: json.name,
+        stream_information: json.stream_information,
+        video_source,
+    }) {
+        // The configuration is fine, but GStreamer can't create the pipeline in this host
+        Ok(validation) if validation.error.is_some() => HttpResponse::NotAcceptable()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&validation).unwrap()),
+        Ok(validation) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&validation).unwrap()),
+        Err(error) => {
+            return HttpResponse::NotAcceptable()
+                .content_type("text/plain")
+                .body(format!("{:#?}", error.to_string()));
+        }
+    }
+}
+
 #[api_v2_operation]
 /// Remove a desired stream
 pub fn remove_stream(query: web::Query<RemoveStream>) -> HttpResponse {
@@ -215,9 +416,179 @@ pub fn remove_stream(query: web::Query<RemoveStream>) -> HttpResponse {
     }
 }
 
+#[api_v2_operation]
+/// Register a custom GStreamer source, available as a camera for the streams
+pub fn gst_source_post(json: web::Json<PostGstSource>) -> HttpResponse {
+    match video_source_gst::add_custom_source(&json.name, &json.description) {
+        Ok(_) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&settings::manager::gst_sources()).unwrap()),
+        Err(error) => {
+            return HttpResponse::NotAcceptable()
+                .content_type("text/plain")
+                .body(format!("{:#?}", error.to_string()));
+        }
+    }
+}
+
+#[api_v2_operation]
+/// Remove a custom GStreamer source
+pub fn gst_source_remove(query: web::Query<RemoveGstSource>) -> HttpResponse {
+    match video_source_gst::remove_custom_source(&query.name) {
+        Ok(_) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&settings::manager::gst_sources()).unwrap()),
+        Err(error) => {
+            return HttpResponse::NotAcceptable()
+                .content_type("text/plain")
+                .body(format!("{:#?}", error.to_string()));
+        }
+    }
+}
+
+#[api_v2_operation]
+/// Show or hide the on-screen display of a stream, the OSD should be configured in its extended configuration
+pub fn stream_osd(json: web::Json<StreamOsd>) -> HttpResponse {
+    match stream_manager::set_osd_enabled(&json.name, json.enabled) {
+        Ok(_) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&stream_manager::streams()).unwrap()),
+        Err(error) => {
+            return HttpResponse::NotAcceptable()
+                .content_type("text/plain")
+                .body(format!("{:#?}", error.to_string()));
+        }
+    }
+}
+
+#[api_v2_operation]
+/// Change the horizontal and vertical field of view of a stream, in degrees, as reported to the GCS
+pub fn stream_fov(json: web::Json<StreamFov>) -> HttpResponse {
+    let json = json.into_inner();
+    match stream_manager::set_stream_fov(&json.name, json.fov) {
+        Ok(_) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&stream_manager::streams()).unwrap()),
+        Err(error) => {
+            return HttpResponse::NotAcceptable()
+                .content_type("text/plain")
+                .body(format!("{:#?}", error.to_string()));
+        }
+    }
+}
+
+#[api_v2_operation]
+/// Resume or pause a stream, paused streams keep their configuration and are running again after a restart
+pub fn stream_streaming(json: web::Json<StreamStreaming>) -> HttpResponse {
+    match stream_manager::set_stream_paused(&json.name, !json.enabled) {
+        Ok(_) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&stream_manager::streams()).unwrap()),
+        Err(error) => {
+            return HttpResponse::NotAcceptable()
+                .content_type("text/plain")
+                .body(format!("{:#?}", error.to_string()));
+        }
+    }
+}
+
+#[cfg(feature = "recording")]
+#[api_v2_operation]
+/// Start or stop recording a stream to an MP4 file, available in /captures while and after it is recorded
+pub fn stream_recording(json: web::Json<StreamRecording>) -> HttpResponse {
+    let result = match json.enabled {
+        true => recording::start(&json.name),
+        false => recording::stop(&json.name),
+    };
+    match result {
+        Ok(_) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&stream_manager::streams()).unwrap()),
+        Err(error) => {
+            return HttpResponse::NotAcceptable()
+                .content_type("text/plain")
+                .body(format!("{:#?}", error.to_string()));
+        }
+    }
+}
+
+#[cfg(feature = "mavlink")]
+#[api_v2_operation]
+/// Provides the MAVLink system ID and the component ID of the first camera
+pub async fn mavlink_ids() -> Json<MavlinkIds> {
+    let ids = settings::manager::mavlink_ids();
+    Json(MavlinkIds {
+        system_id: ids.system_id,
+        component_id: ids.component_id,
+    })
+}
+
+#[cfg(feature = "mavlink")]
+#[api_v2_operation]
+/// Provides the main MAVLink endpoint used by the cameras
+pub async fn mavlink_endpoint() -> Json<Option<MavlinkEndpoint>> {
+    Json(settings::manager::mavlink_endpoint().map(|endpoint| MavlinkEndpoint { endpoint }))
+}
+
+#[cfg(feature = "mavlink")]
+#[api_v2_operation]
+/// Change the main MAVLink endpoint, keeping the extra ones, and reconnect the MAVLink cameras
+pub fn mavlink_endpoint_post(json: web::Json<MavlinkEndpoint>) -> HttpResponse {
+    if let Err(error) = cli::manager::mavlink_connection_string_validator(json.endpoint.clone()) {
+        return HttpResponse::NotAcceptable()
+            .content_type("text/plain")
+            .body(error);
+    }
+
+    settings::manager::set_mavlink_endpoint(&json.endpoint);
+    stream_manager::restart_mavlink_cameras();
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(serde_json::to_string_pretty(&json.into_inner()).unwrap())
+}
+
+#[cfg(feature = "mavlink")]
+#[api_v2_operation]
+/// Provides the state of the MAVLink connections of each camera, one for each endpoint, with their last heartbeats and message counters
+pub async fn mavlink_status() -> Json<Vec<crate::mavlink::mavlink_camera::MavlinkCameraStatus>> {
+    Json(stream_manager::mavlink_status())
+}
+
+#[cfg(feature = "mavlink")]
+#[api_v2_operation]
+/// Provides the region of the image that the GCS asked each camera to track, with normalized coordinates from the top left corner
+pub async fn mavlink_tracking() -> Json<Vec<crate::mavlink::mavlink_camera::MavlinkCameraTracking>>
+{
+    Json(stream_manager::mavlink_tracking())
+}
+
+#[cfg(feature = "mavlink")]
+#[api_v2_operation]
+/// Change the MAVLink system ID and the component ID of the first camera, restarting the MAVLink cameras
+pub fn mavlink_ids_post(json: web::Json<MavlinkIds>) -> HttpResponse {
+    if json.system_id == 0 || !(1..=250).contains(&json.component_id) {
+        return HttpResponse::NotAcceptable()
+            .content_type("text/plain")
+            .body("The system ID should be from 1 to 255, and the component ID from 1 to 250.");
+    }
+
+    settings::manager::set_mavlink_ids(&settings::manager::MavlinkIds {
+        system_id: json.system_id,
+        component_id: json.component_id,
+    });
+    stream_manager::restart_mavlink_cameras();
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(serde_json::to_string_pretty(&json.into_inner()).unwrap())
+}
+
 #[api_v2_operation]
 /// Reset controls from a given camera source
 pub fn camera_reset_controls(json: web::Json<ResetCameraControls>) -> HttpResponse {
+    // Otherwise the stored values would be applied again when the camera reconnects
+    let bus = video_source::usb_bus(&json.device);
+    settings::manager::remove_control_defaults(&json.device, bus.as_deref());
+
     match video_source::reset_controls(&json.device) {
         Ok(_) => HttpResponse::Ok()
             .content_type("application/json")
@@ -239,6 +610,132 @@ pub fn camera_reset_controls(json: web::Json<ResetCameraControls>) -> HttpRespon
     }
 }
 
+#[cfg(feature = "onvif")]
+#[api_v2_operation]
+/// ONVIF Device service (SOAP), available when the service runs with --onvif
+pub fn onvif_device_service(req: HttpRequest, body: web::Bytes) -> HttpResponse {
+    onvif_response(&req, &body, onvif::manager::device_service)
+}
+
+#[cfg(feature = "onvif")]
+#[api_v2_operation]
+/// ONVIF Media service (SOAP), available when the service runs with --onvif
+pub fn onvif_media_service(req: HttpRequest, body: web::Bytes) -> HttpResponse {
+    onvif_response(&req, &body, onvif::manager::media_service)
+}
+
+#[cfg(feature = "onvif")]
+fn onvif_response(
+    req: &HttpRequest,
+    body: &[u8],
+    service: fn(&str, &str) -> Result<String, String>,
+) -> HttpResponse {
+    if !cli::manager::is_onvif_enabled() {
+        return HttpResponse::NotFound()
+            .content_type("text/plain")
+            .body("ONVIF services are disabled.");
+    }
+
+    let request = String::from_utf8_lossy(body);
+    let connection_info = req.connection_info();
+    match service(&request, connection_info.host()) {
+        Ok(response) => HttpResponse::Ok()
+            .content_type("application/soap+xml; charset=utf-8")
+            .body(response),
+        Err(fault) => HttpResponse::BadRequest()
+            .content_type("application/soap+xml; charset=utf-8")
+            .body(fault),
+    }
+}
+
+#[cfg(any(feature = "mavlink", feature = "recording"))]
+#[api_v2_operation]
+/// Provides a file captured by request of the GCS, like the photos and videos taken over MAVLink
+pub fn capture(req: HttpRequest) -> HttpResponse {
+    let file_name = req.match_info().query("file");
+    let content = captures::file(file_name).and_then(|path| std::fs::read(path).ok());
+    match content {
+        Some(content) => {
+            let extension = Path::new(file_name)
+                .extension()
+                .and_then(OsStr::to_str)
+                .unwrap_or("");
+            HttpResponse::Ok()
+                .content_type(actix_files::file_extension_to_mime(extension).to_string())
+                .body(content)
+        }
+        None => HttpResponse::NotFound()
+            .content_type("text/plain")
+            .body(format!("Capture does not exist: {file_name}")),
+    }
+}
+
+#[api_v2_operation]
+/// Provides the video of a running stream as MJPEG (multipart/x-mixed-replace), viewable by browsers
+pub fn mjpeg(req: HttpRequest) -> HttpResponse {
+    let stream_name = req.match_info().query("stream");
+    match mjpeg::new(stream_name) {
+        Ok(stream) => HttpResponse::Ok()
+            .content_type(format!(
+                "multipart/x-mixed-replace; boundary={}",
+                mjpeg::BOUNDARY
+            ))
+            .streaming(stream.map(Ok::<_, actix_web::Error>)),
+        Err(error) => HttpResponse::NotFound()
+            .content_type("text/plain")
+            .body(error.to_string()),
+    }
+}
+
+#[api_v2_operation]
+/// Provides the video of a running H264 stream as fragmented MP4 over WebSocket, to be played with MSE
+pub fn ws_video(req: HttpRequest, body: web::Payload) -> HttpResponse {
+    let stream_name = req.match_info().query("stream");
+    let mut stream = match fmp4::new(stream_name) {
+        Ok(stream) => stream,
+        Err(error) => {
+            return HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body(error.to_string())
+        }
+    };
+
+    let (response, mut session, mut messages) = match actix_ws::handle(&req, body) {
+        Ok(handle) => handle,
+        Err(error) => {
+            return HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body(format!("Invalid WebSocket handshake: {error}"))
+        }
+    };
+
+    // Each chunk is sent as a binary message, until the stream ends or the client leaves
+    actix_web::rt::spawn(async move {
+        let reason = loop {
+            match future::select(stream.next(), messages.next()).await {
+                Either::Left((Some(chunk), _)) => {
+                    if session.binary(chunk).await.is_err() {
+                        return;
+                    }
+                }
+                Either::Left((None, _)) => break None,
+                Either::Right((Some(Ok(actix_ws::Message::Ping(bytes))), _)) => {
+                    if session.pong(&bytes).await.is_err() {
+                        return;
+                    }
+                }
+                Either::Right((Some(Ok(actix_ws::Message::Close(reason))), _)) => break reason,
+                // The client only receives the video
+                Either::Right((Some(Ok(_)), _)) => {}
+                Either::Right((Some(Err(_)), _)) | Either::Right((None, _)) => break None,
+            }
+        };
+        let _ = session.close(reason).await;
+    });
+
+    response
+}
+
 #[api_v2_operation]
 /// Provides a xml description file that contains information for a specific device, based on: https://mavlink.io/en/services/camera_def.html
 pub fn xml(xml_file_request: web::Query<XmlFileRequest>) -> HttpResponse {
@@ -248,10 +745,15 @@ pub fn xml(xml_file_request: web::Query<XmlFileRequest>) -> HttpResponse {
         .iter()
         .find(|source| source.inner().source_string() == xml_file_request.file);
 
+    let bitrate_kbps = xml_file_request
+        .stream
+        .as_deref()
+        .and_then(stream_manager::stream_bitrate_kbps);
+
     if let Some(camera) = camera {
         return HttpResponse::Ok()
             .content_type("text/xml")
-            .body(xml::from_video_source(camera.inner()));
+            .body(xml::from_video_source(camera, bitrate_kbps));
     }
     return HttpResponse::NotFound()
         .content_type("text/plain")
@@ -7,6 +7,7 @@ use tracing::*;
 
 use crate::cli;
 use crate::custom;
+use crate::video::video_source_gst::VideoSourceGst;
 use crate::video_stream::types::VideoAndStreamInformation;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -15,11 +16,82 @@ pub struct HeaderSettingsFile {
     pub version: u32,
 }
 
+// Control value set by the user, applied again when the camera reconnects or the service starts
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ControlDefault {
+    pub source: String,
+    // USB bus path of the camera, used instead of the source since the
+    // device paths may change between boots
+    #[serde(default)]
+    pub bus: Option<String>,
+    pub id: u64,
+    pub value: i64,
+}
+
+impl ControlDefault {
+    fn is_from_camera(&self, source: &str, bus: Option<&str>) -> bool {
+        match (&self.bus, bus) {
+            (Some(our_bus), Some(bus)) => our_bus == bus,
+            _ => self.source == source,
+        }
+    }
+}
+
+// Regular expressions matched with the bus information, name or path of local cameras
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CameraFilter {
+    // Cameras that are never used
+    #[serde(default)]
+    pub blacklist: Vec<String>,
+    // When not empty, only these cameras are used
+    #[serde(default)]
+    pub whitelist: Vec<String>,
+}
+
+// Identification of the cameras in the MAVLink network
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MavlinkIds {
+    pub system_id: u8,
+    // Component of the first camera, the next ones follow it
+    pub component_id: u8,
+}
+
+impl Default for MavlinkIds {
+    fn default() -> Self {
+        Self {
+            system_id: 1,
+            // MAV_COMP_ID_CAMERA
+            component_id: 100,
+        }
+    }
+}
+
+// MAVLink component of the camera of a stream, kept so the GCS sees the same camera after restarts
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MavlinkComponent {
+    pub stream_name: String,
+    pub component_id: u8,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SettingsStruct {
     pub header: HeaderSettingsFile,
     pub mavlink_endpoint: Option<String>, //TODO: Move to URL
+    // Connected in addition to the main endpoint, so the cameras are seen by more GCSs
+    #[serde(default)]
+    pub mavlink_extra_endpoints: Vec<String>,
+    #[serde(default)]
+    pub mavlink_ids: MavlinkIds,
     pub streams: Vec<VideoAndStreamInformation>,
+    #[serde(default)]
+    pub control_defaults: Vec<ControlDefault>,
+    // GStreamer sources registered by the user
+    #[serde(default)]
+    pub gst_sources: Vec<VideoSourceGst>,
+    #[serde(default)]
+    pub camera_filter: CameraFilter,
+    #[serde(default)]
+    pub mavlink_components: Vec<MavlinkComponent>,
 }
 
 #[derive(Debug)]
@@ -43,8 +115,20 @@ impl Default for SettingsStruct {
                 name: "Camera Manager".to_string(),
                 version: 0,
             },
-            mavlink_endpoint: cli::manager::mavlink_connection_string().map(String::from),
+            mavlink_endpoint: cli::manager::mavlink_connection_strings()
+                .first()
+                .map(|endpoint| endpoint.to_string()),
+            mavlink_extra_endpoints: cli::manager::mavlink_connection_strings()
+                .iter()
+                .skip(1)
+                .map(|endpoint| endpoint.to_string())
+                .collect(),
+            mavlink_ids: MavlinkIds::default(),
             streams: custom::create_default_streams(),
+            control_defaults: vec![],
+            gst_sources: vec![],
+            camera_filter: CameraFilter::default(),
+            mavlink_components: vec![],
         }
     }
 }
@@ -154,6 +238,7 @@ pub fn header() -> HeaderSettingsFile {
     return manager.content.as_ref().unwrap().config.header.clone();
 }
 
+#[cfg_attr(not(feature = "mavlink"), allow(dead_code))]
 pub fn mavlink_endpoint() -> Option<String> {
     let manager = MANAGER.lock().unwrap();
     return manager
@@ -175,6 +260,74 @@ pub fn set_mavlink_endpoint(endpoint: &str) {
     save();
 }
 
+// The main endpoint followed by the extra ones, empty when there is no main endpoint
+#[cfg_attr(not(feature = "mavlink"), allow(dead_code))]
+pub fn mavlink_endpoints() -> Vec<String> {
+    let manager = MANAGER.lock().unwrap();
+    let config = &manager.content.as_ref().unwrap().config;
+    match &config.mavlink_endpoint {
+        Some(endpoint) => std::iter::once(endpoint)
+            .chain(config.mavlink_extra_endpoints.iter())
+            .cloned()
+            .collect(),
+        None => vec![],
+    }
+}
+
+pub fn set_mavlink_extra_endpoints(endpoints: &[String]) {
+    {
+        let mut manager = MANAGER.lock().unwrap();
+        manager
+            .content
+            .as_mut()
+            .unwrap()
+            .config
+            .mavlink_extra_endpoints = endpoints.to_vec();
+    }
+    save();
+}
+
+#[cfg_attr(not(feature = "mavlink"), allow(dead_code))]
+pub fn mavlink_ids() -> MavlinkIds {
+    let manager = MANAGER.lock().unwrap();
+    let content = manager.content.as_ref();
+    return content.unwrap().config.mavlink_ids.clone();
+}
+
+pub fn set_mavlink_ids(ids: &MavlinkIds) {
+    // Take care of scope mutex
+    {
+        let mut manager = MANAGER.lock().unwrap();
+        manager.content.as_mut().unwrap().config.mavlink_ids = ids.clone();
+    }
+    save();
+}
+
+#[cfg_attr(not(feature = "mavlink"), allow(dead_code))]
+pub fn mavlink_components() -> Vec<MavlinkComponent> {
+    let manager = MANAGER.lock().unwrap();
+    let content = manager.content.as_ref();
+    return content.unwrap().config.mavlink_components.clone();
+}
+
+// Assign the component to the stream, replacing any previous assignment of either of them
+#[cfg_attr(not(feature = "mavlink"), allow(dead_code))]
+pub fn set_mavlink_component(stream_name: &str, component_id: u8) {
+    // Take care of scope mutex
+    {
+        let mut manager = MANAGER.lock().unwrap();
+        let components = &mut manager.content.as_mut().unwrap().config.mavlink_components;
+        components.retain(|component| {
+            component.stream_name != stream_name && component.component_id != component_id
+        });
+        components.push(MavlinkComponent {
+            stream_name: stream_name.into(),
+            component_id,
+        });
+    }
+    save();
+}
+
 pub fn streams() -> Vec<VideoAndStreamInformation> {
     let manager = MANAGER.lock().unwrap();
     let content = manager.content.as_ref();
@@ -197,6 +350,96 @@ pub fn set_streams(streams: &Vec<VideoAndStreamInformation>) {
     save();
 }
 
+pub fn control_defaults() -> Vec<ControlDefault> {
+    let manager = MANAGER.lock().unwrap();
+    let content = manager.content.as_ref();
+    return content.unwrap().config.control_defaults.clone();
+}
+
+// Store the value of the control, replacing any previous one of the same camera
+pub fn set_control_default(source: &str, bus: Option<&str>, id: u64, value: i64) {
+    // Take care of scope mutex
+    {
+        let mut manager = MANAGER.lock().unwrap();
+        let control_defaults = &mut manager.content.as_mut().unwrap().config.control_defaults;
+        control_defaults
+            .retain(|control| !(control.is_from_camera(source, bus) && control.id == id));
+        control_defaults.push(ControlDefault {
+            source: source.into(),
+            bus: bus.map(String::from),
+            id,
+            value,
+        });
+    }
+    save();
+}
+
+// Forget the stored controls of a camera, e.g: when they are reset to the camera defaults
+pub fn remove_control_defaults(source: &str, bus: Option<&str>) {
+    // Take care of scope mutex
+    {
+        let mut manager = MANAGER.lock().unwrap();
+        let control_defaults = &mut manager.content.as_mut().unwrap().config.control_defaults;
+        control_defaults.retain(|control| !control.is_from_camera(source, bus));
+    }
+    save();
+}
+
+pub fn gst_sources() -> Vec<VideoSourceGst> {
+    let manager = MANAGER.lock().unwrap();
+    let content = manager.content.as_ref();
+    return content.unwrap().config.gst_sources.clone();
+}
+
+// Store the source, replacing any previous one with the same name
+pub fn add_gst_source(source: VideoSourceGst) {
+    // Take care of scope mutex
+    {
+        let mut manager = MANAGER.lock().unwrap();
+        let gst_sources = &mut manager.content.as_mut().unwrap().config.gst_sources;
+        gst_sources.retain(|gst_source| gst_source.name != source.name);
+        gst_sources.push(source);
+    }
+    save();
+}
+
+// Returns false if there is no source with this name
+pub fn remove_gst_source(name: &str) -> bool {
+    // Take care of scope mutex
+    let removed = {
+        let mut manager = MANAGER.lock().unwrap();
+        let gst_sources = &mut manager.content.as_mut().unwrap().config.gst_sources;
+        let length = gst_sources.len();
+        gst_sources.retain(|gst_source| gst_source.name != name);
+        gst_sources.len() != length
+    };
+    if removed {
+        save();
+    }
+    removed
+}
+
+// The patterns from the command line are used together with the stored ones
+#[cfg(target_os = "linux")]
+pub fn camera_filter() -> CameraFilter {
+    let mut camera_filter = {
+        let manager = MANAGER.lock().unwrap();
+        let content = manager.content.as_ref();
+        content.unwrap().config.camera_filter.clone()
+    };
+    camera_filter.blacklist.extend(
+        cli::manager::camera_blacklist()
+            .into_iter()
+            .map(String::from),
+    );
+    camera_filter.whitelist.extend(
+        cli::manager::camera_whitelist()
+            .into_iter()
+            .map(String::from),
+    );
+    camera_filter
+}
+
 pub fn reset() {
     // Take care of scope mutex
     {
@@ -206,7 +449,8 @@ pub fn reset() {
     save();
 }
 
-#[cfg(test)]
+// The streams of the tests are from V4L2 cameras
+#[cfg(all(test, target_os = "linux"))]
 mod tests {
     use super::*;
     use crate::stream::types::{
@@ -251,6 +495,20 @@ mod tests {
         let fake_mavlink_endpoint = "tcp:potatohost:42";
         set_mavlink_endpoint(fake_mavlink_endpoint);
         assert_eq!(mavlink_endpoint(), Some(fake_mavlink_endpoint.into()));
+        let fake_extra_endpoint = "udpout:potatohost:43".to_string();
+        set_mavlink_extra_endpoints(&[fake_extra_endpoint.clone()]);
+        assert_eq!(
+            mavlink_endpoints(),
+            vec![fake_mavlink_endpoint.to_string(), fake_extra_endpoint]
+        );
+
+        assert_eq!(mavlink_ids(), MavlinkIds::default());
+        let fake_mavlink_ids = MavlinkIds {
+            system_id: 42,
+            component_id: 150,
+        };
+        set_mavlink_ids(&fake_mavlink_ids);
+        assert_eq!(mavlink_ids(), fake_mavlink_ids);
 
         let fake_streams = vec![VideoAndStreamInformation {
             name: "PotatoTestStream".into(),
@@ -264,18 +522,63 @@ mod tests {
                         numerator: 17,
                         denominator: 47,
                     },
+                    transcode: None,
+                    bitrate_kbps: None,
+                    keyframe_interval: None,
+                    jpeg_quality: None,
+                    deinterlace: None,
+                    rotation: None,
+                    flip: None,
+                    crop: None,
+                    output_size: None,
                 }),
                 extended_configuration: None,
+                audio: None,
             },
             video_source: VideoSourceType::Local(VideoSourceLocal {
                 name: "Fake Potato Test Video Source Camera".into(),
                 device_path: "/dev/potatovideo".into(),
                 typ: VideoSourceLocalType::Usb("usb-0420:08:47.42-77".into()),
+                usb: None,
             }),
         }];
         set_streams(&mut fake_streams.clone());
         assert_eq!(streams(), fake_streams);
 
+        set_control_default("/dev/potatovideo", None, 42, 1);
+        set_control_default("/dev/potatovideo", None, 42, 7);
+        assert_eq!(
+            control_defaults(),
+            vec![ControlDefault {
+                source: "/dev/potatovideo".into(),
+                bus: None,
+                id: 42,
+                value: 7,
+            }]
+        );
+
+        // The same camera in another path after a reboot
+        let bus = Some("usb-0420:08:47.42-77");
+        set_control_default("/dev/video0", bus, 42, 3);
+        set_control_default("/dev/video2", bus, 42, 5);
+        assert_eq!(control_defaults().len(), 2);
+        assert_eq!(control_defaults()[1].value, 5);
+
+        remove_control_defaults("/dev/video2", bus);
+        assert_eq!(control_defaults().len(), 1);
+
+        // A component is assigned to only one stream
+        set_mavlink_component("PotatoTestStream", 100);
+        set_mavlink_component("PotatoTestStream", 101);
+        set_mavlink_component("OtherStream", 101);
+        assert_eq!(
+            mavlink_components(),
+            vec![MavlinkComponent {
+                stream_name: "OtherStream".into(),
+                component_id: 101,
+            }]
+        );
+
         save();
     }
 }
@@ -0,0 +1,144 @@
+use crate::cli;
+
+use simple_error::{simple_error, SimpleResult};
+#[cfg(feature = "mavlink")]
+use tracing::*;
+
+use std::path::{Path, PathBuf};
+
+// Files captured by request of the GCS, like photos and recordings
+#[cfg(feature = "mavlink")]
+const CAPTURE_EXTENSIONS: [&str; 2] = ["jpg", "mp4"];
+
+// Space of the filesystem of the capture path
+#[cfg(feature = "mavlink")]
+#[derive(Debug)]
+pub struct Storage {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+// Path of a captured file, only files directly inside the capture path are served
+pub fn file(file_name: &str) -> Option<PathBuf> {
+    let name = Path::new(file_name).file_name()?;
+    if name != file_name {
+        return None;
+    }
+
+    let path = Path::new(&cli::manager::capture_path()).join(name);
+    path.is_file().then(|| path)
+}
+
+// Name and path of a new file in the capture path, named after the stream and the current time
+pub fn new_file(stream_name: &str, extension: &str) -> SimpleResult<(String, PathBuf)> {
+    let directory = PathBuf::from(cli::manager::capture_path());
+    std::fs::create_dir_all(&directory).map_err(|error| {
+        simple_error!(format!("Failed to create directory {directory:?}: {error}"))
+    })?;
+
+    let file_name = format!(
+        "{}_{}.{extension}",
+        file_safe_name(stream_name),
+        chrono::Local::now().format("%Y%m%d_%H%M%S%.3f")
+    );
+    let path = directory.join(&file_name);
+    Ok((file_name, path))
+}
+
+#[cfg(all(feature = "mavlink", unix))]
+pub fn storage() -> std::io::Result<Storage> {
+    use std::os::unix::ffi::OsStrExt;
+
+    // The directory is created with the first capture, but its filesystem is known before it
+    let directory = PathBuf::from(cli::manager::capture_path());
+    std::fs::create_dir_all(&directory)?;
+
+    let path = std::ffi::CString::new(directory.as_os_str().as_bytes())
+        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidInput, error))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let block_size = stat.f_frsize as u64;
+    Ok(Storage {
+        total_bytes: stat.f_blocks as u64 * block_size,
+        available_bytes: stat.f_bavail as u64 * block_size,
+    })
+}
+
+// The filesystem is only queried with statvfs
+#[cfg(all(feature = "mavlink", not(unix)))]
+pub fn storage() -> std::io::Result<Storage> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "The capture storage can't be queried on this platform",
+    ))
+}
+
+// Removes the photos and recordings of the capture path, leaving any other file there.
+// Refused while recording, since the file being written would be lost.
+#[cfg(feature = "mavlink")]
+pub fn clear() -> SimpleResult<usize> {
+    #[cfg(feature = "recording")]
+    if super::recording::is_any_running() {
+        return Err(simple_error!(
+            "Captures can't be cleared while a stream is being recorded."
+        ));
+    }
+
+    let directory = PathBuf::from(cli::manager::capture_path());
+    let entries = match std::fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(error) => {
+            return Err(simple_error!(format!(
+                "Failed to read directory {directory:?}: {error}"
+            )))
+        }
+    };
+
+    let mut removed = 0;
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let is_capture = path.is_file()
+            && path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .map_or(false, |extension| CAPTURE_EXTENSIONS.contains(&extension));
+        if !is_capture {
+            continue;
+        }
+
+        match std::fs::remove_file(&path) {
+            Ok(_) => removed += 1,
+            Err(error) => warn!("Failed to remove capture {path:?}: {error}"),
+        }
+    }
+
+    info!("Removed {removed} captures from {directory:?}.");
+    Ok(removed)
+}
+
+fn file_safe_name(name: &str) -> String {
+    name.chars()
+        .map(|character| match character {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => character,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_file_names() {
+        assert_eq!(file_safe_name("Front camera/1"), "Front_camera_1");
+        assert!(file("../settings.json").is_none());
+        assert!(file("/etc/passwd").is_none());
+    }
+}
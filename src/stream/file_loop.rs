@@ -0,0 +1,73 @@
+use gstreamer::glib::WeakRef;
+use gstreamer::prelude::*;
+
+// Name of the decodebin added by the pipeline builder for the video files
+pub const ELEMENT_NAME: &str = "file_source";
+
+// Video files are played in a loop, seeking back to the start when they end.
+// The first end is an EOS, after that the segment seeks only post SEGMENT_DONE,
+// so the next loops don't need to flush the pipeline.
+pub fn track(element: &gstreamer::Element) {
+    if !has_file_source(element) {
+        return;
+    }
+
+    let pipeline = top_pipeline(element);
+    let bus = match pipeline.bus() {
+        Some(bus) => bus,
+        None => return,
+    };
+
+    let pipeline_weak = pipeline.downgrade();
+    bus.set_sync_handler(move |_bus, message| seek_on_end(&pipeline_weak, message));
+}
+
+pub fn has_file_source(element: &gstreamer::Element) -> bool {
+    element
+        .downcast_ref::<gstreamer::Bin>()
+        .and_then(|bin| bin.by_name(ELEMENT_NAME))
+        .is_some()
+}
+
+// The RTSP server gives us the bin of the media, the seeks are done in its pipeline
+pub fn top_pipeline(element: &gstreamer::Element) -> gstreamer::Element {
+    let mut pipeline = element.clone();
+    while let Some(parent) = pipeline
+        .parent()
+        .and_then(|parent| parent.downcast::<gstreamer::Element>().ok())
+    {
+        pipeline = parent;
+    }
+    pipeline
+}
+
+// Bus sync handler of the pipelines with a file source, there can only be one for each bus
+pub fn seek_on_end(
+    pipeline_weak: &WeakRef<gstreamer::Element>,
+    message: &gstreamer::Message,
+) -> gstreamer::BusSyncReply {
+    let flags = match message.view() {
+        gstreamer::MessageView::Eos(_) => {
+            gstreamer::SeekFlags::FLUSH | gstreamer::SeekFlags::SEGMENT
+        }
+        gstreamer::MessageView::SegmentDone(_) => gstreamer::SeekFlags::SEGMENT,
+        _ => return gstreamer::BusSyncReply::Pass,
+    };
+
+    let pipeline = match pipeline_weak.upgrade() {
+        Some(pipeline) => pipeline,
+        None => return gstreamer::BusSyncReply::Pass,
+    };
+    // The message comes from a streaming thread, which can't do the seek.
+    // When it fails, the error restarts the pipeline instead.
+    std::thread::spawn(move || {
+        if let Err(error) = pipeline.seek_simple(flags, gstreamer::ClockTime::ZERO) {
+            gstreamer::element_error!(
+                pipeline,
+                gstreamer::CoreError::Seek,
+                ["Failed to play the video file again: {}", error]
+            );
+        }
+    });
+    gstreamer::BusSyncReply::Drop
+}
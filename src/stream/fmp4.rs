@@ -0,0 +1,37 @@
+use super::manager;
+use super::tap::{StreamTap, TapStream};
+use super::types::CaptureConfiguration;
+use crate::video::types::VideoEncodeType;
+
+use actix_web::web::Bytes;
+use simple_error::{simple_error, SimpleResult};
+
+// Fragmented MP4 (H264 in CMAF fragments) of a running stream, playable by
+// browsers with Media Source Extensions. The first chunk is the init segment.
+pub type Fmp4Stream = TapStream<Bytes>;
+
+pub fn new(stream_name: &str) -> SimpleResult<Fmp4Stream> {
+    let is_h264 = manager::streams().iter().any(|stream| {
+        let information = &stream.video_and_stream;
+        information.name == stream_name
+            && matches!(
+                &information.stream_information.configuration,
+                CaptureConfiguration::VIDEO(configuration) if *configuration.stream_encode() == VideoEncodeType::H264
+            )
+    });
+    if !is_h264 {
+        return Err(simple_error!(format!(
+            "Stream {stream_name:?} does not exist or is not H264."
+        )));
+    }
+
+    let tap = StreamTap::new(
+        stream_name,
+        " ! parsebin ! h264parse ! video/x-h264,stream-format=avc,alignment=au ! mp4mux fragment-duration=500 streamable=true ! appsink name=fmp4_sink emit-signals=true sync=false",
+    )?;
+
+    // A missing fragment would break the playback, so late clients are disconnected
+    tap.into_stream("fmp4_sink", 64, false, |_sample, chunk| {
+        Bytes::copy_from_slice(chunk)
+    })
+}
@@ -0,0 +1,48 @@
+use super::tap::{StreamTap, TapStream};
+
+use actix_web::web::Bytes;
+use simple_error::SimpleResult;
+
+// Decoded frame of a running stream
+#[derive(Clone, Debug)]
+#[cfg_attr(not(feature = "mavlink"), allow(dead_code))]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    // RGBA pixels, row by row, without padding
+    pub data: Bytes,
+    // Presentation timestamp, in nanoseconds
+    pub pts: Option<u64>,
+}
+
+// Receives the frames while it exists, the tap is stopped when it is dropped
+pub type FrameTap = TapStream<Frame>;
+
+// Subscribe to the decoded frames of a running stream, e.g: for snapshots or
+// computer vision. Frames are dropped if the subscriber can't keep up with the stream.
+#[cfg_attr(not(feature = "mavlink"), allow(dead_code))]
+pub fn subscribe(stream_name: &str) -> SimpleResult<FrameTap> {
+    let tap = StreamTap::new(
+        stream_name,
+        " ! decodebin ! videoconvert ! video/x-raw,format=RGBA ! appsink name=frames_sink emit-signals=true max-buffers=1 drop=true sync=false",
+    )?;
+
+    tap.into_stream("frames_sink", 1, true, |sample, data| {
+        let structure = sample.caps().and_then(|caps| caps.structure(0));
+        let dimension = |name| {
+            structure
+                .and_then(|structure| structure.get::<i32>(name).ok())
+                .unwrap_or_default() as u32
+        };
+
+        Frame {
+            width: dimension("width"),
+            height: dimension("height"),
+            data: Bytes::copy_from_slice(data),
+            pts: sample
+                .buffer()
+                .and_then(|buffer| buffer.pts())
+                .map(|pts| pts.nseconds()),
+        }
+    })
+}
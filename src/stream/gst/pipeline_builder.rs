@@ -1,32 +1,319 @@
+use super::utils;
+#[cfg(target_os = "linux")]
+use crate::video::{quirks, video_source_local::VideoSourceLocalType};
 use crate::{
-    stream::types::VideoCaptureConfiguration,
+    stream::manager as stream_manager,
+    stream::thermal,
+    stream::types::{
+        AudioCaptureConfiguration, AudioEncodeType, CaptureConfiguration, EncoderPreference,
+        LatencyProfileSettings, OsdConfiguration, QueueConfiguration, QueueLeakiness,
+        SrtpConfiguration, StreamProfileSettings, TcpContainer, ThermalPalette,
+        VideoCaptureConfiguration,
+    },
+    stream::{file_loop, osd},
     video::{
         types::{VideoEncodeType, VideoSourceType},
-        video_source_gst::VideoSourceGstType,
-        video_source_local::VideoSourceLocalType,
+        video_source::VideoSource,
+        video_source_gst::{FakeSettings, VideoSourceGstType},
+        video_source_screen::ScreenCapture,
     },
     video_stream::types::VideoAndStreamInformation,
 };
+
 use simple_error::{simple_error, SimpleResult};
 use tracing::*;
 
 #[derive(Clone, Debug, Default)]
 pub struct Pipeline {
     pub description: String,
+    // Stream name and configuration of the on-screen display, if the pipeline has one
+    pub osd: Option<(String, OsdConfiguration)>,
 }
 
 impl Pipeline {
     pub fn new(video_and_stream_information: &VideoAndStreamInformation) -> SimpleResult<Self> {
+        if let CaptureConfiguration::AUDIO(configuration) = &video_and_stream_information
+            .stream_information
+            .configuration
+        {
+            return Pipeline::new_audio(video_and_stream_information, configuration);
+        }
+
+        if let Some(description) = Pipeline::build_custom_pipeline(video_and_stream_information) {
+            info!(
+                "New custom pipeline: {:#?}",
+                utils::redact_secrets(&description)
+            );
+            return Ok(Pipeline {
+                description,
+                osd: None,
+            });
+        }
+
+        let source = Pipeline::build_pipeline_source(video_and_stream_information)?;
+        let sink = Pipeline::build_pipeline_sink(video_and_stream_information)?;
+
+        let scheme = video_and_stream_information
+            .stream_information
+            .endpoints
+            .first()
+            .map_or("", |endpoint| endpoint.scheme());
+        let description = match scheme {
+            // NDI carries raw video, so instead of payloading, everything is decoded
+            "ndi" => {
+                let decode = Pipeline::build_pipeline_ndi_decode(video_and_stream_information)?;
+                format!("{source}{decode}{sink}")
+            }
+            // Containers carry the encoded video as it is, without RTP
+            "srt" | "rtmp" | "mpegts" | "tcp" | "file" => {
+                let transcode = Pipeline::build_pipeline_transcode(video_and_stream_information)?;
+                let mux = Pipeline::build_pipeline_mux(video_and_stream_information)?;
+                format!("{source}{transcode}{mux}{sink}")
+            }
+            _ => {
+                let transcode = Pipeline::build_pipeline_transcode(video_and_stream_information)?;
+                let payload = Pipeline::build_pipeline_payload(video_and_stream_information)?;
+                format!("{source}{transcode}{payload}{sink}")
+            }
+        };
+        let description = match Pipeline::build_audio_branch(video_and_stream_information)? {
+            Some(audio) => format!("{description} {audio}"),
+            None => description,
+        };
+
+        info!(
+            "New pipeline built: {:#?}",
+            utils::redact_secrets(&description)
+        );
+
+        Ok(Pipeline {
+            description,
+            osd: Pipeline::get_osd(video_and_stream_information),
+        })
+    }
+
+    // A single source feeding endpoints with different schemes. The RTP payload is
+    // shared by UDP and RTSP endpoints, RTSP being served from a loopback UDP port
+    // (see `build_rtsp_loopback_source`), and each container scheme has its own muxer.
+    pub fn new_tee(
+        video_and_stream_information: &VideoAndStreamInformation,
+        rtsp_loopback_port: Option<u16>,
+    ) -> SimpleResult<Self> {
         let source = Pipeline::build_pipeline_source(video_and_stream_information)?;
         let transcode = Pipeline::build_pipeline_transcode(video_and_stream_information)?;
-        let payload = Pipeline::build_pipeline_payload(video_and_stream_information)?;
+
+        let mut rtp_sinks = vec![];
+        let mut branches = vec![];
+        for information in video_and_stream_information.split_by_scheme() {
+            match information.stream_information.endpoints[0].scheme() {
+                "udp" => rtp_sinks.push(Pipeline::build_pipeline_sink(&information)?),
+                "rtsp" | "rtsps" => {
+                    let port = rtsp_loopback_port
+                        .ok_or_else(|| simple_error!("RTSP endpoints require a loopback port."))?;
+                    rtp_sinks.push(format!(
+                        " ! udpsink host=127.0.0.1 port={port} sync=false async=false"
+                    ));
+                }
+                "srt" | "rtmp" | "mpegts" | "tcp" | "file" => {
+                    let mux = Pipeline::build_pipeline_mux(&information)?;
+                    let sink = Pipeline::build_pipeline_sink(&information)?;
+                    branches.push(format!("{mux}{sink}"));
+                }
+                scheme => {
+                    return Err(simple_error!(format!(
+                        "Endpoints with {scheme} scheme can't be used with other schemes."
+                    )))
+                }
+            }
+        }
+
+        if !rtp_sinks.is_empty() {
+            let payload = Pipeline::build_pipeline_payload(video_and_stream_information)?;
+            let rtp_branch = match rtp_sinks.as_slice() {
+                [sink] => format!("{payload}{sink}"),
+                sinks => {
+                    let sinks: String = sinks
+                        .iter()
+                        .map(|sink| format!(" rtp_tee. ! queue{sink}"))
+                        .collect();
+                    format!("{payload} ! tee name=rtp_tee{sinks}")
+                }
+            };
+            branches.insert(0, rtp_branch);
+        }
+
+        let branches: String = branches
+            .iter()
+            .map(|branch| format!(" video_tee. ! queue{branch}"))
+            .collect();
+        let description = format!("{source}{transcode} ! tee name=video_tee{branches}");
+
+        info!(
+            "New tee pipeline built: {:#?}",
+            utils::redact_secrets(&description)
+        );
+
+        Ok(Pipeline {
+            description,
+            osd: Pipeline::get_osd(video_and_stream_information),
+        })
+    }
+
+    // RTSP media receiving the RTP sent by a tee pipeline to the loopback port
+    #[cfg(feature = "rtsp")]
+    pub fn build_rtsp_loopback_source(
+        video_and_stream_information: &VideoAndStreamInformation,
+        port: u16,
+    ) -> SimpleResult<String> {
+        let configuration =
+            Pipeline::get_video_capture_configuration(video_and_stream_information)?;
+
+        let encoding = match configuration.stream_encode() {
+            VideoEncodeType::H264 => "H264",
+            VideoEncodeType::H265 => "H265",
+            VideoEncodeType::MJPG => "JPEG",
+            VideoEncodeType::VP8 => "VP8",
+            VideoEncodeType::VP9 => "VP9",
+            VideoEncodeType::AV1 => "AV1",
+            video_encode_type => {
+                return Err(simple_error!(format!(
+                "Unsupported VideoEncodeType for RTSP with other schemes: {video_encode_type:#?}",
+            )))
+            }
+        };
+
+        let (pt, _mtu) = Pipeline::get_rtp_settings(video_and_stream_information)?;
+
+        // The RTSP server uses the element named pay0 as the source of the RTP packets
+        Ok(format!("udpsrc name=pay0 address=127.0.0.1 port={port} caps=\"application/x-rtp,media=video,clock-rate=90000,encoding-name={encoding},payload={pt}\""))
+    }
+
+    fn new_audio(
+        video_and_stream_information: &VideoAndStreamInformation,
+        configuration: &AudioCaptureConfiguration,
+    ) -> SimpleResult<Self> {
+        let source = match &video_and_stream_information.video_source {
+            VideoSourceType::Audio(audio_source) => Pipeline::build_audio_source(
+                audio_source.source_string(),
+                configuration.sample_rate,
+                configuration.channels,
+            ),
+            video_source_type => {
+                return Err(simple_error!(format!(
+                    "Unsupported VideoSourceType for audio: {video_source_type:#?}.",
+                )));
+            }
+        };
+
+        let payload = Pipeline::build_audio_payload(&configuration.encode, "pay0", 97);
+
         let sink = Pipeline::build_pipeline_sink(video_and_stream_information)?;
 
-        let description = format!("{source}{transcode}{payload}{sink}");
+        let description = format!("{source}{payload}{sink}");
+
+        info!(
+            "New audio pipeline built: {:#?}",
+            utils::redact_secrets(&description)
+        );
+
+        Ok(Pipeline {
+            description,
+            osd: None,
+        })
+    }
+
+    fn build_audio_source(device: &str, sample_rate: u32, channels: u32) -> String {
+        format!(
+            "alsasrc device=\"{}\" ! audio/x-raw,rate={sample_rate},channels={channels}",
+            device.replace('"', "")
+        )
+    }
+
+    fn build_audio_payload(encode: &AudioEncodeType, name: &str, pt: u8) -> String {
+        match encode {
+            AudioEncodeType::OPUS => format!(
+                " ! audioconvert ! audioresample ! opusenc ! rtpopuspay name={name} pt={pt}"
+            ),
+            // rtpL16pay expects big endian samples
+            AudioEncodeType::L16 => format!(
+                " ! audioconvert ! audio/x-raw,format=S16BE ! rtpL16pay name={name} pt={pt}"
+            ),
+        }
+    }
+
+    // Audio captured alongside the video, as a second RTSP payloader (pay1),
+    // or linked to the muxer of the recordings
+    fn build_audio_branch(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> SimpleResult<Option<String>> {
+        let audio = match &video_and_stream_information.stream_information.audio {
+            Some(audio) => audio,
+            None => return Ok(None),
+        };
 
-        info!("New pipeline built: {description:#?}");
+        let source = Pipeline::build_audio_source(&audio.device, audio.sample_rate, audio.channels);
+        let scheme = video_and_stream_information.stream_information.endpoints[0].scheme();
+        let branch = match scheme {
+            "rtsp" | "rtsps" => {
+                // The payload type should not clash with the video one
+                let (video_pt, _mtu) = Pipeline::get_rtp_settings(video_and_stream_information)?;
+                let pt = if video_pt == 97 { 98 } else { 97 };
+                let payload = Pipeline::build_audio_payload(&audio.encode, "pay1", pt);
+                format!("{source}{payload}")
+            }
+            // Matroska carries the samples as little endian
+            "file" => {
+                let encoder = match audio.encode {
+                    AudioEncodeType::OPUS => " ! audioconvert ! audioresample ! opusenc",
+                    AudioEncodeType::L16 => " ! audioconvert ! audio/x-raw,format=S16LE",
+                };
+                format!("{source}{encoder} ! queue ! mux.")
+            }
+            _ => {
+                return Err(simple_error!(format!(
+                "Audio can't be used with the {scheme} scheme, only with RTSP and file endpoints."
+            )))
+            }
+        };
 
-        Ok(Pipeline { description })
+        Ok(Some(branch))
+    }
+
+    // The pipeline provided by the user, with its placeholders replaced
+    fn build_custom_pipeline(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> Option<String> {
+        let custom_pipeline = video_and_stream_information
+            .stream_information
+            .extended_configuration
+            .as_ref()?
+            .custom_pipeline
+            .as_ref()?;
+
+        let endpoint = video_and_stream_information
+            .stream_information
+            .endpoints
+            .first()?;
+
+        Some(
+            custom_pipeline
+                .replace(
+                    "{device}",
+                    video_and_stream_information
+                        .video_source
+                        .inner()
+                        .source_string(),
+                )
+                .replace("{endpoint}", endpoint.as_str())
+                .replace("{host}", endpoint.host_str().unwrap_or_default())
+                .replace(
+                    "{port}",
+                    &endpoint
+                        .port()
+                        .map(|port| port.to_string())
+                        .unwrap_or_default(),
+                ),
+        )
     }
 
     fn build_capability_string(
@@ -35,6 +322,7 @@ impl Pipeline {
         let configuration =
             Pipeline::get_video_capture_configuration(&video_and_stream_information)?;
 
+        let bayer_format;
         let format = match video_and_stream_information.video_source {
             // Fakes (videotestsrc) are only "video/x-raw" or "video/x-bayer",
             // and to be able to encode it, we need to define an available
@@ -42,10 +330,18 @@ impl Pipeline {
             // We are choosing "UYVY" because it is compatible by the
             // application-rtp template capabilities.
             // For more information: https://gstreamer.freedesktop.org/documentation/additional/design/mediatype-video-raw.html?gi-language=c#formats
-            VideoSourceType::Gst(_) => "video/x-raw,format=UYVY",
+            VideoSourceType::Gst(_) | VideoSourceType::File(_) | VideoSourceType::Screen(_) => {
+                "video/x-raw,format=UYVY"
+            }
             _ => match &configuration.encode {
                 VideoEncodeType::H264 => "video/x-h264",
+                VideoEncodeType::H265 => "video/x-h265",
                 VideoEncodeType::YUYV => "video/x-raw,format=YUY2",
+                VideoEncodeType::Y16 => "video/x-raw,format=GRAY16_LE",
+                VideoEncodeType::Bayer(format) => {
+                    bayer_format = format!("video/x-bayer,format={}", format.gst_format());
+                    &bayer_format
+                }
                 VideoEncodeType::MJPG => "image/jpeg",
                 video_encode_type => {
                     return Err(simple_error!(format!(
@@ -54,7 +350,8 @@ impl Pipeline {
                 }
             },
         };
-        let pipeline_capability = format!(
+        #[cfg_attr(not(target_os = "linux"), allow(unused_mut))]
+        let mut pipeline_capability = format!(
             concat!(
                 "{format},width={width},height={height},framerate={interval_denominator}/{interval_numerator}",
             ),
@@ -64,6 +361,17 @@ impl Pipeline {
             interval_denominator = configuration.frame_interval.denominator,
             interval_numerator = configuration.frame_interval.numerator,
         );
+
+        // Some cameras only stream with the H264 profile that they really encode
+        #[cfg(target_os = "linux")]
+        if let VideoSourceType::Local(local_device) = &video_and_stream_information.video_source {
+            if configuration.encode == VideoEncodeType::H264 {
+                if let Some(profile) = quirks::for_camera(local_device).h264_profile {
+                    pipeline_capability.push_str(&format!(",profile={profile}"));
+                }
+            }
+        }
+
         Ok(pipeline_capability)
     }
 
@@ -72,15 +380,153 @@ impl Pipeline {
     ) -> SimpleResult<String> {
         let pipeline_source = match &video_and_stream_information.video_source {
             VideoSourceType::Gst(gst_source) => match &gst_source.source {
-                VideoSourceGstType::Fake(pattern) => format!("videotestsrc pattern={pattern}"),
-                VideoSourceGstType::Local(_) => {
-                    return Err(simple_error!(format!(
-                        "Unsupported GST source endpoint: {gst_source:#?}",
-                    )));
+                VideoSourceGstType::Fake(description) => {
+                    let settings = match FakeSettings::from_str(description) {
+                        Some(settings) => settings,
+                        None => {
+                            return Err(simple_error!(format!(
+                                "Invalid fake source: {description:?}"
+                            )))
+                        }
+                    };
+                    let overlay = match settings.timestamp {
+                        true => {
+                            " ! timeoverlay halignment=left valignment=top font-desc=\"Sans, 24\""
+                        }
+                        false => "",
+                    };
+                    let text = match &settings.text {
+                        Some(text) => format!(
+                            " ! textoverlay text=\"{}\" halignment=center valignment=center font-desc=\"Sans, 32\"",
+                            text.replace('"', "")
+                        ),
+                        None => "".into(),
+                    };
+                    format!("videotestsrc pattern={}{overlay}{text}", settings.pattern)
+                }
+                // The camera negotiates its own format, converted to the one we expect
+                VideoSourceGstType::Libcamera(camera_name) => {
+                    let configuration =
+                        Pipeline::get_video_capture_configuration(video_and_stream_information)?;
+                    format!(
+                        "libcamerasrc camera-name=\"{camera_name}\" ! video/x-raw,width={width},height={height},framerate={denominator}/{numerator} ! videoconvert",
+                        camera_name = camera_name.replace('"', ""),
+                        width = configuration.width,
+                        height = configuration.height,
+                        denominator = configuration.frame_interval.denominator,
+                        numerator = configuration.frame_interval.numerator,
+                    )
+                }
+                // Same as libcamera, the camera negotiates its own format
+                VideoSourceGstType::MediaFoundation(device_path) => {
+                    let configuration =
+                        Pipeline::get_video_capture_configuration(video_and_stream_information)?;
+                    format!(
+                        "mfvideosrc device-path=\"{device_path}\" ! video/x-raw,width={width},height={height},framerate={denominator}/{numerator} ! videoconvert",
+                        device_path = device_path.replace('\\', "\\\\").replace('"', ""),
+                        width = configuration.width,
+                        height = configuration.height,
+                        denominator = configuration.frame_interval.denominator,
+                        numerator = configuration.frame_interval.numerator,
+                    )
+                }
+                VideoSourceGstType::AVFoundation(device_index) => {
+                    let configuration =
+                        Pipeline::get_video_capture_configuration(video_and_stream_information)?;
+                    let device_index = device_index.parse::<i32>().map_err(|_| {
+                        simple_error!(format!("Invalid AVFoundation device: {device_index:?}"))
+                    })?;
+                    format!(
+                        "avfvideosrc device-index={device_index} ! video/x-raw,width={width},height={height},framerate={denominator}/{numerator} ! videoconvert",
+                        width = configuration.width,
+                        height = configuration.height,
+                        denominator = configuration.frame_interval.denominator,
+                        numerator = configuration.frame_interval.numerator,
+                    )
+                }
+                // The source defines its own format, converted to the one we expect
+                VideoSourceGstType::Local(description) => {
+                    format!("{description} ! videoconvert ! videoscale ! videorate")
                 }
             },
+            // The windows are captured with their decorations, as seen on the screen
+            VideoSourceType::Screen(screen_source) => match &screen_source.capture {
+                ScreenCapture::X11 { display, window } => format!(
+                    "ximagesrc display-name={display} use-damage=false{window} ! videoconvert ! videoscale ! videorate",
+                    display = display.replace(' ', ""),
+                    window = window
+                        .as_ref()
+                        .map(|window| format!(" xname=\"{}\"", window.replace('"', "")))
+                        .unwrap_or_default(),
+                ),
+                ScreenCapture::PipeWire => {
+                    "pipewiresrc ! videoconvert ! videoscale ! videorate".to_string()
+                }
+            },
+            // The file is decoded and played again when it ends
+            VideoSourceType::File(file_source) => {
+                let path = match file_source.path() {
+                    Some(path) => path,
+                    None => {
+                        return Err(simple_error!(format!(
+                            "Invalid file source: {}",
+                            file_source.url
+                        )))
+                    }
+                };
+                // Played again from the start when it ends, by seeking the pipeline
+                format!(
+                    "filesrc location=\"{}\" ! decodebin name={} ! videoconvert ! videoscale ! videorate",
+                    path.to_string_lossy().replace('"', ""),
+                    file_loop::ELEMENT_NAME,
+                )
+            }
+            // The camera defines the size and frame rate, the frames are only demuxed
+            VideoSourceType::Ip(ip_source) if ip_source.is_http() => {
+                return Ok(format!(
+                    "souphttpsrc location=\"{}\" is-live=true do-timestamp=true ! multipartdemux ! image/jpeg",
+                    ip_source.url.replace('"', "")
+                ));
+            }
+            // The camera defines the size and frame rate, the video is only depayloaded
+            VideoSourceType::Ip(ip_source) => {
+                let configuration =
+                    Pipeline::get_video_capture_configuration(video_and_stream_information)?;
+                let depay = match &configuration.encode {
+                    VideoEncodeType::H264 => "rtph264depay",
+                    VideoEncodeType::H265 => "rtph265depay",
+                    VideoEncodeType::MJPG => "rtpjpegdepay",
+                    video_encode_type => {
+                        return Err(simple_error!(format!(
+                            "Unsupported VideoEncodeType for IP cameras: {video_encode_type:#?}",
+                        )))
+                    }
+                };
+                // 200 ms is the rtspsrc default
+                let latency = Pipeline::get_latency_settings(video_and_stream_information)
+                    .map_or(200, |latency| latency.jitterbuffer_latency_ms);
+                return Ok(format!(
+                    "rtspsrc location=\"{}\" latency={latency} ! {depay}",
+                    ip_source.url.replace('"', "")
+                ));
+            }
+            #[cfg(target_os = "linux")]
             VideoSourceType::Local(local_device) => match &local_device.typ {
-                VideoSourceLocalType::Usb(_) | VideoSourceLocalType::LegacyRpiCam(_) => {
+                // The viewfinder pad is not used, but it needs to be linked
+                VideoSourceLocalType::Usb(_)
+                    if Pipeline::get_video_capture_configuration(video_and_stream_information)?
+                        .encode
+                        == VideoEncodeType::H264
+                        && local_device.uses_h264_extension_unit() =>
+                {
+                    format!(
+                        "uvch264src device={} auto-start=true name=uvch264 uvch264.vfsrc ! queue ! fakesink uvch264.vidsrc",
+                        &local_device.device_path
+                    )
+                }
+                VideoSourceLocalType::Usb(_)
+                | VideoSourceLocalType::LegacyRpiCam(_)
+                | VideoSourceLocalType::Hdmi(_) => {
                     format!("v4l2src device={}", &local_device.device_path)
                 }
                 typ => {
@@ -106,32 +552,398 @@ impl Pipeline {
         let configuration =
             Pipeline::get_video_capture_configuration(video_and_stream_information)?;
 
+        let filters = Pipeline::build_video_filters(video_and_stream_information)?;
+
         let pipeline_transcode = match &video_and_stream_information.video_source {
-            VideoSourceType::Gst(_) => match configuration.encode {
-                // Fake sources are video/x-raw, so we need to encode it to
-                // have h264 or mjpg.
-                VideoEncodeType::H264 => concat!(
-                    " ! videoconvert",
-                    " ! x264enc bitrate=5000",
-                    " ! video/x-h264,profile=baseline",
-                ),
-                VideoEncodeType::MJPG => concat!(" ! jpegenc",),
-                _ => "",
-            },
-            VideoSourceType::Local(_) => match configuration.encode {
-                // Because application-rtp templates doesn't accept "YUY2", we
-                // need to transcode it. We are arbitrarily chosing the closest
-                // format available ("UYVY").
-                VideoEncodeType::YUYV => concat!(" ! videoconvert", " ! video/x-raw,format=UYVY",),
-                _ => "",
-            },
+            VideoSourceType::Gst(_) | VideoSourceType::File(_) | VideoSourceType::Screen(_) => {
+                match configuration.stream_encode() {
+                    // Fake, file and screen sources are video/x-raw, so we need to encode it to
+                    // have h264 or mjpg.
+                    VideoEncodeType::H264 => format!(
+                        "{filters}{}",
+                        Pipeline::build_h264_encoder(video_and_stream_information)
+                    ),
+                    VideoEncodeType::MJPG => format!(
+                        "{filters}{}",
+                        Pipeline::build_jpeg_encoder(video_and_stream_information)
+                    ),
+                    _ => filters,
+                }
+            }
+            video_source if video_source.is_camera() => {
+                match (&configuration.encode, &configuration.transcode) {
+                    // Cameras that only provide raw or JPEG frames are encoded by us
+                    (VideoEncodeType::YUYV, Some(transcode)) => format!(
+                        "{filters}{}",
+                        Pipeline::build_encoder(video_and_stream_information, transcode)?
+                    ),
+                    (VideoEncodeType::MJPG, Some(transcode)) => format!(
+                        " ! jpegdec{filters}{}",
+                        Pipeline::build_encoder(video_and_stream_information, transcode)?
+                    ),
+                    (VideoEncodeType::Bayer(_), Some(transcode)) => format!(
+                        " ! bayer2rgb ! videoconvert{filters}{}",
+                        Pipeline::build_encoder(video_and_stream_information, transcode)?
+                    ),
+                    (VideoEncodeType::Y16, Some(transcode)) => format!(
+                        "{}{filters}{}",
+                        Pipeline::build_thermal_colorization(video_and_stream_information),
+                        Pipeline::build_encoder(video_and_stream_information, transcode)?
+                    ),
+                    // Because application-rtp templates doesn't accept "YUY2", we
+                    // need to transcode it. We are arbitrarily chosing the closest
+                    // format available ("UYVY").
+                    (VideoEncodeType::YUYV, _) => format!(
+                        concat!("{filters}", " ! videoconvert", " ! video/x-raw,format=UYVY"),
+                        filters = filters
+                    ),
+                    _ => "".to_string(),
+                }
+            }
             video_source_type => {
                 return Err(simple_error!(format!(
                     "Unsupported VideoSourceType: {video_source_type:#?}.",
                 )));
             }
         };
-        Ok(pipeline_transcode.to_string())
+        Ok(pipeline_transcode)
+    }
+
+    // The 16-bit frames are normalized by the thermal element, then converted to 8 bits and colorized
+    fn build_thermal_colorization(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> String {
+        let palette = video_and_stream_information
+            .stream_information
+            .extended_configuration
+            .as_ref()
+            .and_then(|configuration| configuration.thermal_palette.clone())
+            .unwrap_or(ThermalPalette::Heat);
+
+        let preset = match palette {
+            ThermalPalette::Grayscale => None,
+            ThermalPalette::Heat => Some("heat"),
+            ThermalPalette::YellowBlue => Some("yellowblue"),
+            ThermalPalette::Xray => Some("xray"),
+        };
+
+        format!(
+            " ! identity name={name} ! videoconvert ! video/x-raw,format=GRAY8{colorization}",
+            name = thermal::ELEMENT_NAME,
+            colorization = preset
+                .map(|preset| format!(" ! videoconvert ! coloreffects preset={preset}"))
+                .unwrap_or_default(),
+        )
+    }
+
+    // Elements applied to the raw frames, before they are encoded
+    fn build_video_filters(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> SimpleResult<String> {
+        let configuration =
+            Pipeline::get_video_capture_configuration(video_and_stream_information)?;
+
+        let mut filters = String::new();
+
+        // Interlaced frames look combed when streamed as they are
+        let deinterlace = configuration.deinterlace.unwrap_or_else(|| {
+            match &video_and_stream_information.video_source {
+                #[cfg(target_os = "linux")]
+                VideoSourceType::Local(source) => source.is_interlaced(),
+                _ => false,
+            }
+        });
+        if deinterlace {
+            filters += " ! deinterlace";
+        }
+
+        if let Some(crop) = &configuration.crop {
+            filters += &format!(
+                " ! videocrop left={left} top={top} right={right} bottom={bottom}",
+                left = crop.left,
+                top = crop.top,
+                right = configuration.width - crop.left - crop.width,
+                bottom = configuration.height - crop.top - crop.height,
+            );
+        }
+
+        // Scaling is done before rotating, to rotate fewer pixels
+        if configuration.output_size.is_some() {
+            let (width, height) = configuration.output_resolution();
+            let (width, height) = match configuration.flips() {
+                Some(_) => (width, height),
+                None => (height, width),
+            };
+            filters += &format!(" ! videoscale ! video/x-raw,width={width},height={height}");
+        }
+
+        // Cameras with flip controls do it by themselves
+        if let Some(video_direction) = configuration.video_direction() {
+            if stream_manager::camera_flips(video_and_stream_information).is_none() {
+                filters += &format!(" ! videoflip video-direction={video_direction}");
+            }
+        }
+
+        // The text is updated while the pipeline runs
+        if Pipeline::get_osd(video_and_stream_information).is_some() {
+            filters += &format!(
+                concat!(
+                    " ! textoverlay name={name} text=\"\" valignment=top halignment=left",
+                    " shaded-background=true font-desc=\"Sans, 10\"",
+                ),
+                name = osd::ELEMENT_NAME,
+            );
+        }
+
+        Ok(filters)
+    }
+
+    fn build_encoder(
+        video_and_stream_information: &VideoAndStreamInformation,
+        encode: &VideoEncodeType,
+    ) -> SimpleResult<String> {
+        let encoder = match encode {
+            VideoEncodeType::H264 => Pipeline::build_h264_encoder(video_and_stream_information),
+            VideoEncodeType::H265 => Pipeline::build_h265_encoder(video_and_stream_information),
+            VideoEncodeType::MJPG => Pipeline::build_jpeg_encoder(video_and_stream_information),
+            VideoEncodeType::VP8 | VideoEncodeType::VP9 => {
+                Pipeline::build_vpx_encoder(video_and_stream_information, encode)
+            }
+            VideoEncodeType::AV1 => Pipeline::build_av1_encoder(video_and_stream_information)?,
+            video_encode_type => {
+                return Err(simple_error!(format!(
+                    "Unsupported VideoEncodeType for encoding: {video_encode_type:#?}",
+                )))
+            }
+        };
+        Ok(encoder)
+    }
+
+    // Hardware encoders to be tried, in order, before falling back to software
+    fn hardware_encoders(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> Vec<EncoderPreference> {
+        let preference = video_and_stream_information
+            .stream_information
+            .extended_configuration
+            .as_ref()
+            .and_then(|configuration| configuration.encoder_preference.clone())
+            .unwrap_or(EncoderPreference::Auto);
+
+        match preference {
+            EncoderPreference::Auto => vec![
+                EncoderPreference::Nvidia,
+                EncoderPreference::V4l2,
+                EncoderPreference::Vaapi,
+            ],
+            EncoderPreference::Software => vec![],
+            preference => vec![preference],
+        }
+    }
+
+    // Encodes raw video to H264, preferring the hardware encoders when available
+    fn build_h264_encoder(video_and_stream_information: &VideoAndStreamInformation) -> String {
+        let profile = Pipeline::get_profile_settings(video_and_stream_information);
+        let (bitrate_kbps, key_int_max) =
+            Pipeline::get_encoder_settings(video_and_stream_information);
+
+        for encoder in Pipeline::hardware_encoders(video_and_stream_information) {
+            match encoder {
+                // Jetson (Xavier/Orin) encoders work on NVMM memory, converted by the VIC
+                EncoderPreference::Nvidia
+                    if utils::is_hardware_encoder_available("nvv4l2h264enc") =>
+                {
+                    return format!(
+                        concat!(
+                            " ! nvvidconv",
+                            " ! \"video/x-raw(memory:NVMM),format=I420\"",
+                            " ! nvv4l2h264enc bitrate={bitrate} iframeinterval={key_int_max} insert-sps-pps=true",
+                            " ! video/x-h264,stream-format=byte-stream",
+                        ),
+                        bitrate = bitrate_kbps * 1000,
+                        key_int_max = key_int_max,
+                    );
+                }
+                // The Raspberry Pi hardware encoder (bcm2835-codec) is exposed as a V4L2
+                // memory-to-memory device, the element only exists when there is one.
+                // The SPS/PPS are repeated with each IDR, so receivers can join at any time.
+                EncoderPreference::V4l2 if utils::is_hardware_encoder_available("v4l2h264enc") => {
+                    return format!(
+                        concat!(
+                            " ! videoconvert",
+                            " ! v4l2h264enc extra-controls=\"controls,video_bitrate={bitrate},h264_i_frame_period={key_int_max},repeat_sequence_header=1\"",
+                            " ! video/x-h264,profile=baseline,level=(string)4",
+                        ),
+                        bitrate = bitrate_kbps * 1000,
+                        key_int_max = key_int_max,
+                    );
+                }
+                // Intel and AMD GPUs, through VA-API
+                EncoderPreference::Vaapi
+                    if utils::is_hardware_encoder_available("vaapih264enc") =>
+                {
+                    return format!(
+                        concat!(
+                            " ! videoconvert",
+                            " ! vaapih264enc rate-control=cbr bitrate={bitrate_kbps} keyframe-period={key_int_max}",
+                            " ! video/x-h264,profile=constrained-baseline",
+                        ),
+                        bitrate_kbps = bitrate_kbps,
+                        key_int_max = key_int_max,
+                    );
+                }
+                encoder => debug!("H264 encoder {encoder:?} is not available."),
+            }
+        }
+
+        match profile {
+            Some(profile) => format!(
+                concat!(
+                    " ! videoconvert",
+                    " ! x264enc bitrate={bitrate} tune={tune} speed-preset={speed_preset} key-int-max={key_int_max}",
+                    " ! video/x-h264,profile=baseline",
+                ),
+                bitrate = bitrate_kbps,
+                tune = profile.x264_tune,
+                speed_preset = profile.x264_speed_preset,
+                key_int_max = key_int_max,
+            ),
+            None => format!(
+                concat!(
+                    " ! videoconvert",
+                    " ! x264enc bitrate={bitrate} tune=zerolatency key-int-max={key_int_max}",
+                    " ! video/x-h264,profile=baseline",
+                ),
+                bitrate = bitrate_kbps,
+                key_int_max = key_int_max,
+            ),
+        }
+    }
+
+    // Encodes raw video to H265, falling back to x265enc without hardware encoders
+    fn build_h265_encoder(video_and_stream_information: &VideoAndStreamInformation) -> String {
+        let (bitrate_kbps, key_int_max) =
+            Pipeline::get_encoder_settings(video_and_stream_information);
+
+        for encoder in Pipeline::hardware_encoders(video_and_stream_information) {
+            match encoder {
+                EncoderPreference::Nvidia
+                    if utils::is_hardware_encoder_available("nvv4l2h265enc") =>
+                {
+                    return format!(
+                        concat!(
+                            " ! nvvidconv",
+                            " ! \"video/x-raw(memory:NVMM),format=I420\"",
+                            " ! nvv4l2h265enc bitrate={bitrate} iframeinterval={key_int_max} insert-sps-pps=true",
+                            " ! video/x-h265,stream-format=byte-stream",
+                        ),
+                        bitrate = bitrate_kbps * 1000,
+                        key_int_max = key_int_max,
+                    );
+                }
+                EncoderPreference::V4l2 if utils::is_hardware_encoder_available("v4l2h265enc") => {
+                    return format!(
+                        concat!(
+                            " ! videoconvert",
+                            " ! v4l2h265enc extra-controls=\"controls,video_bitrate={bitrate},video_gop_size={key_int_max},repeat_sequence_header=1\"",
+                            " ! video/x-h265",
+                        ),
+                        bitrate = bitrate_kbps * 1000,
+                        key_int_max = key_int_max,
+                    );
+                }
+                EncoderPreference::Vaapi
+                    if utils::is_hardware_encoder_available("vaapih265enc") =>
+                {
+                    return format!(
+                        concat!(
+                            " ! videoconvert",
+                            " ! vaapih265enc rate-control=cbr bitrate={bitrate_kbps} keyframe-period={key_int_max}",
+                            " ! video/x-h265",
+                        ),
+                        bitrate_kbps = bitrate_kbps,
+                        key_int_max = key_int_max,
+                    );
+                }
+                encoder => debug!("H265 encoder {encoder:?} is not available."),
+            }
+        }
+
+        // HEVC is expensive to encode in software, so the fastest preset is used
+        info!("No hardware H265 encoder available, falling back to x265enc.");
+        format!(
+            concat!(
+                " ! videoconvert",
+                " ! x265enc bitrate={bitrate} speed-preset=ultrafast tune=zerolatency key-int-max={key_int_max}",
+                " ! video/x-h265,profile=main",
+            ),
+            bitrate = bitrate_kbps,
+            key_int_max = key_int_max,
+        )
+    }
+
+    // VP8 and VP9 are royalty free, being supported by all browsers
+    fn build_vpx_encoder(
+        video_and_stream_information: &VideoAndStreamInformation,
+        encode: &VideoEncodeType,
+    ) -> String {
+        let (bitrate_kbps, key_int_max) =
+            Pipeline::get_encoder_settings(video_and_stream_information);
+        let encoder = match encode {
+            VideoEncodeType::VP9 => "vp9enc",
+            _ => "vp8enc",
+        };
+
+        // With deadline=1 (realtime) the encoder does its best to keep up with the source
+        format!(
+            " ! videoconvert ! {encoder} deadline=1 target-bitrate={bitrate} keyframe-max-dist={key_int_max}",
+            bitrate = bitrate_kbps * 1000,
+        )
+    }
+
+    // AV1 gets a better quality for very low bitrates, at the cost of CPU
+    fn build_av1_encoder(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> SimpleResult<String> {
+        // The encoder and parser come from gst-plugins-bad and the payloader
+        // from gst-plugins-rs, which are missing from most distributions
+        let is_available = utils::is_gstreamer_plugin_available("av1enc", "1.14.0")
+            && utils::is_gstreamer_plugin_available("av1parse", "1.20.0")
+            && utils::is_gstreamer_plugin_available("rtpav1pay", "0.8.0");
+        if !is_available {
+            return Err(simple_error!(
+                "AV1 requires the av1enc, av1parse and rtpav1pay GStreamer elements."
+            ));
+        }
+
+        let (bitrate_kbps, key_int_max) =
+            Pipeline::get_encoder_settings(video_and_stream_information);
+
+        // cpu-used=8 is the fastest preset, the only one able to encode in real time
+        Ok(format!(
+            " ! videoconvert ! av1enc cpu-used=8 end-usage=cbr target-bitrate={bitrate_kbps} keyframe-max-dist={key_int_max}"
+        ))
+    }
+
+    fn build_jpeg_encoder(video_and_stream_information: &VideoAndStreamInformation) -> String {
+        let quality = match &video_and_stream_information
+            .stream_information
+            .configuration
+        {
+            CaptureConfiguration::VIDEO(VideoCaptureConfiguration {
+                jpeg_quality: Some(jpeg_quality),
+                ..
+            }) => format!(" quality={jpeg_quality}"),
+            _ => "".to_string(),
+        };
+
+        let has_vaapi = Pipeline::hardware_encoders(video_and_stream_information)
+            .contains(&EncoderPreference::Vaapi);
+        if has_vaapi && utils::is_hardware_encoder_available("vaapijpegenc") {
+            return format!(" ! videoconvert ! vaapijpegenc{quality}");
+        }
+
+        format!(" ! jpegenc{quality}")
     }
 
     fn build_pipeline_payload(
@@ -140,38 +952,233 @@ impl Pipeline {
         let configuration =
             Pipeline::get_video_capture_configuration(&video_and_stream_information)?;
 
-        let pipeline_payload = match &configuration.encode {
+        let queue = Pipeline::build_queue(video_and_stream_information);
+        // With an explicit keyframe interval, SPS/PPS are sent with every IDR frame,
+        // so the clients can start decoding at the first keyframe they receive
+        let config_interval = match configuration.keyframe_interval {
+            Some(_) => -1,
+            None => Pipeline::get_profile_settings(video_and_stream_information)
+                .map_or(10, |profile| profile.config_interval),
+        };
+
+        let (pt, mtu) = Pipeline::get_rtp_settings(video_and_stream_information)?;
+        let mtu = mtu.map(|mtu| format!(" mtu={mtu}")).unwrap_or_default();
+
+        let pipeline_payload = match configuration.stream_encode() {
             // Here we are naming the payloader as pay0 because the rtsp server
             // expects this specific name, and having a name doesn't hurt any
             // other endpoint type.
-            VideoEncodeType::H264 => concat!(
-                " ! h264parse",
-                " ! queue",
-                " ! rtph264pay name=pay0 config-interval=10 pt=96",
+            VideoEncodeType::H264 => format!(
+                concat!(
+                    " ! h264parse",
+                    " ! {queue}",
+                    " ! rtph264pay name=pay0 config-interval={config_interval} pt={pt}{mtu}",
+                ),
+                queue = queue.unwrap_or_else(|| "queue".to_string()),
+                config_interval = config_interval,
+                pt = pt,
+                mtu = mtu,
+            ),
+            VideoEncodeType::H265 => format!(
+                concat!(
+                    " ! h265parse",
+                    " ! {queue}",
+                    " ! rtph265pay name=pay0 config-interval={config_interval} pt={pt}{mtu}",
+                ),
+                queue = queue.unwrap_or_else(|| "queue".to_string()),
+                config_interval = config_interval,
+                pt = pt,
+                mtu = mtu,
+            ),
+            VideoEncodeType::YUYV => format!(
+                concat!(
+                    "{queue}",
+                    " ! rtpvrawpay name=pay0{mtu}",
+                    // Again, as we are always using the "UYVY" format for raw
+                    // application/rtp payloads, "YCbCr-4:2:2" will always be
+                    // the right one to pick.
+                    " ! application/x-rtp,payload={pt},sampling=YCbCr-4:2:2",
+                ),
+                pt = pt,
+                mtu = mtu,
+                queue = queue.map(|queue| format!(" ! {queue}")).unwrap_or_default(),
+            ),
+            VideoEncodeType::MJPG => format!(
+                "{queue} ! rtpjpegpay name=pay0 pt={pt}{mtu}",
+                queue = queue.map(|queue| format!(" ! {queue}")).unwrap_or_default(),
+                pt = pt,
+                mtu = mtu,
+            ),
+            VideoEncodeType::VP8 => format!(
+                "{queue} ! rtpvp8pay name=pay0 pt={pt}{mtu}",
+                queue = queue.map(|queue| format!(" ! {queue}")).unwrap_or_default(),
+                pt = pt,
+                mtu = mtu,
             ),
-            VideoEncodeType::YUYV => concat!(
-                " ! rtpvrawpay name=pay0",
-                // Again, as we are always using the "UYVY" format for raw
-                // application/rtp payloads, "YCbCr-4:2:2" will always be
-                // the right one to pick.
-                " ! application/x-rtp,payload=96,sampling=YCbCr-4:2:2",
+            VideoEncodeType::VP9 => format!(
+                "{queue} ! rtpvp9pay name=pay0 pt={pt}{mtu}",
+                queue = queue.map(|queue| format!(" ! {queue}")).unwrap_or_default(),
+                pt = pt,
+                mtu = mtu,
+            ),
+            VideoEncodeType::AV1 => format!(
+                " ! av1parse{queue} ! rtpav1pay name=pay0 pt={pt}{mtu}",
+                queue = queue.map(|queue| format!(" ! {queue}")).unwrap_or_default(),
+                pt = pt,
+                mtu = mtu,
             ),
-            VideoEncodeType::MJPG => " ! rtpjpegpay name=pay0 pt=96",
             video_encode_type => {
                 return Err(simple_error!(format!(
                     "Unsupported VideoEncodeType: {video_encode_type:#?}"
                 )))
             }
         };
-        Ok(pipeline_payload.to_string())
+        Ok(pipeline_payload)
+    }
+
+    // SRT, RTMP, MPEG-TS, TCP and file endpoints receive the H264 video in a container
+    fn build_pipeline_mux(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> SimpleResult<String> {
+        let configuration =
+            Pipeline::get_video_capture_configuration(&video_and_stream_information)?;
+
+        if *configuration.stream_encode() != VideoEncodeType::H264 {
+            return Err(simple_error!(format!(
+                "Only H264 can be muxed, encode: {:#?}",
+                configuration.stream_encode()
+            )));
+        }
+
+        let queue = Pipeline::build_queue(video_and_stream_information)
+            .unwrap_or_else(|| "queue".to_string());
+        let scheme = video_and_stream_information.stream_information.endpoints[0].scheme();
+
+        let mux = match scheme {
+            // MPEG-TS packets are grouped by 7 to fill a 1316 bytes SRT/UDP payload
+            "srt" | "mpegts" => "mpegtsmux alignment=7",
+            "rtmp" => "flvmux streamable=true",
+            "tcp" => {
+                let container = video_and_stream_information
+                    .stream_information
+                    .extended_configuration
+                    .as_ref()
+                    .and_then(|configuration| configuration.tcp_container.clone())
+                    .unwrap_or(TcpContainer::MpegTs);
+                match container {
+                    TcpContainer::MpegTs => "mpegtsmux",
+                    TcpContainer::Matroska => "matroskamux streamable=true",
+                }
+            }
+            // Named, so the audio branch can be linked to it
+            "file"
+                if video_and_stream_information
+                    .stream_information
+                    .audio
+                    .is_some() =>
+            {
+                "matroskamux name=mux"
+            }
+            "file" => "matroskamux",
+            _ => {
+                return Err(simple_error!(format!(
+                    "There is no container for the {scheme} scheme."
+                )))
+            }
+        };
+
+        Ok(format!(" ! h264parse config-interval=-1 ! {queue} ! {mux}"))
+    }
+
+    fn build_pipeline_ndi_decode(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> SimpleResult<String> {
+        let configuration =
+            Pipeline::get_video_capture_configuration(&video_and_stream_information)?;
+
+        let queue = Pipeline::build_queue(video_and_stream_information)
+            .unwrap_or_else(|| "queue".to_string());
+
+        let decode = match &video_and_stream_information.video_source {
+            // Fake, file and screen sources are already raw "UYVY", one of the formats accepted by ndisink
+            VideoSourceType::Gst(_) | VideoSourceType::File(_) | VideoSourceType::Screen(_) => {
+                "".to_string()
+            }
+            _ => match &configuration.encode {
+                VideoEncodeType::H264 => concat!(" ! h264parse", " ! avdec_h264").to_string(),
+                VideoEncodeType::MJPG => " ! jpegdec".to_string(),
+                VideoEncodeType::YUYV => "".to_string(),
+                VideoEncodeType::Y16 => {
+                    Pipeline::build_thermal_colorization(video_and_stream_information)
+                }
+                VideoEncodeType::Bayer(_) => " ! bayer2rgb".to_string(),
+                video_encode_type => {
+                    return Err(simple_error!(format!(
+                        "Unsupported VideoEncodeType for NDI: {video_encode_type:#?}",
+                    )))
+                }
+            },
+        };
+
+        Ok(format!(
+            "{decode} ! videoconvert ! video/x-raw,format=UYVY ! {queue}"
+        ))
+    }
+
+    // Only streams with a queue configuration (explicit or from a profile)
+    // get a customized queue, so the default pipelines stay the same as before.
+    fn build_queue(video_and_stream_information: &VideoAndStreamInformation) -> Option<String> {
+        let extended_configuration = video_and_stream_information
+            .stream_information
+            .extended_configuration
+            .as_ref()?;
+
+        // An explicit queue configuration takes precedence over the profiles'
+        let QueueConfiguration {
+            leakiness,
+            max_size_buffers,
+            max_size_time_ms,
+        } = match (
+            &extended_configuration.queue,
+            Pipeline::get_latency_settings(video_and_stream_information),
+        ) {
+            (Some(queue), _) => queue.clone(),
+            (None, Some(latency)) => latency.queue,
+            (None, None) => Pipeline::get_profile_settings(video_and_stream_information)?.queue?,
+        };
+
+        let mut queue = match leakiness {
+            // When leaky, the queue drops the old buffers to keep the latest frames flowing
+            QueueLeakiness::Leaky => "queue leaky=downstream".to_string(),
+            QueueLeakiness::Blocking => "queue leaky=no".to_string(),
+        };
+        if let Some(max_size_buffers) = max_size_buffers {
+            queue.push_str(&format!(" max-size-buffers={max_size_buffers}"));
+        }
+        if let Some(max_size_time_ms) = max_size_time_ms {
+            // GStreamer expects the time in nanoseconds
+            queue.push_str(&format!(
+                " max-size-time={max_size_time}",
+                max_size_time = max_size_time_ms * 1_000_000
+            ));
+        }
+
+        Some(queue)
     }
 
     fn build_pipeline_sink(
         video_and_stream_information: &VideoAndStreamInformation,
     ) -> SimpleResult<String> {
         let endpoints = &video_and_stream_information.stream_information.endpoints;
+        let latency = Pipeline::get_latency_settings(video_and_stream_information);
+        // Sinks keep their default synchronization without a latency profile
+        let sync = latency
+            .as_ref()
+            .map(|latency| format!(" sync={}", latency.sync))
+            .unwrap_or_default();
         let pipeline_sink = match endpoints[0].scheme() {
-            "udp" => {
+            "udp" | "udp265" | "mpegts" => {
+                let is_rtp = endpoints[0].scheme() != "mpegts";
                 let clients = endpoints
                     .iter()
                     .map(|endpoint| {
@@ -179,13 +1186,317 @@ impl Pipeline {
                     })
                     .collect::<Vec<String>>()
                     .join(",");
-                format!(" ! multiudpsink clients={clients}")
+
+                let srtp = video_and_stream_information
+                    .stream_information
+                    .extended_configuration
+                    .as_ref()
+                    .and_then(|configuration| configuration.srtp.as_ref())
+                    // MPEG-TS is not carried by RTP, so there is nothing to be encrypted by SRTP
+                    .filter(|_| is_rtp);
+
+                let mut sink = String::new();
+                if is_rtp {
+                    sink.push_str(
+                        " ! rtpbin.send_rtp_sink_0 rtpbin name=rtpbin rtpbin.send_rtp_src_0",
+                    );
+                }
+                if let Some(srtp) = srtp {
+                    sink.push_str(&Pipeline::build_srtp_encoder(srtp)?);
+                }
+                sink.push_str(&format!(" ! multiudpsink name=udp_sink clients={clients}"));
+
+                let udp_sink = video_and_stream_information
+                    .stream_information
+                    .extended_configuration
+                    .as_ref()
+                    .and_then(|configuration| configuration.udp_sink.as_ref());
+
+                let is_multicast = endpoints.iter().any(|endpoint| {
+                    endpoint
+                        .host_str()
+                        .and_then(|host| host.parse::<std::net::IpAddr>().ok())
+                        .map_or(false, |address| address.is_multicast())
+                });
+                let mut multicast_options = String::new();
+                if is_multicast {
+                    // Join the multicast groups, so receivers in this host get the packets as well
+                    multicast_options.push_str(" auto-multicast=true");
+                    if let Some(udp_sink) = udp_sink {
+                        if let Some(ttl) = udp_sink.multicast_ttl {
+                            multicast_options.push_str(&format!(" ttl-mc={ttl}"));
+                        }
+                        if let Some(iface) = &udp_sink.multicast_iface {
+                            multicast_options.push_str(&format!(
+                                " multicast-iface=\"{}\"",
+                                iface.replace('"', "")
+                            ));
+                        }
+                    }
+                }
+                sink.push_str(&multicast_options);
+
+                if let Some(buffer_size) = udp_sink.and_then(|udp_sink| udp_sink.buffer_size) {
+                    sink.push_str(&format!(" buffer-size={buffer_size}"));
+                }
+                match udp_sink.and_then(|udp_sink| udp_sink.sync) {
+                    Some(sync) => sink.push_str(&format!(" sync={sync}")),
+                    None => sink.push_str(&sync),
+                }
+                if let Some(is_async) = udp_sink.and_then(|udp_sink| udp_sink.is_async) {
+                    sink.push_str(&format!(" async={is_async}"));
+                }
+
+                if is_rtp {
+                    sink.push_str(&Pipeline::build_rtcp(
+                        endpoints,
+                        srtp.is_some(),
+                        &multicast_options,
+                        udp_sink.and_then(|udp_sink| udp_sink.rtcp_port),
+                    )?);
+                }
+                sink
+            }
+            "ndi" => {
+                // The NDI source name can be defined by the endpoint host
+                // (e.g: "ndi://FrontCamera"), otherwise the stream name is used
+                let endpoint = &endpoints[0];
+                let name = match endpoint.host_str() {
+                    Some(host) if !host.is_empty() => host.to_string(),
+                    _ => video_and_stream_information.name.clone(),
+                };
+                format!(" ! ndisink ndi-name=\"{}\"", name.replace('"', ""))
+            }
+            "srt" => {
+                // Without a specific host (e.g: "srt://0.0.0.0:9000") we wait
+                // for the receivers to connect, otherwise we connect to the host
+                let endpoint = &endpoints[0];
+                let host = endpoint.host_str().unwrap_or_default();
+                let port = endpoint.port().unwrap();
+                let (host, mode) = match host {
+                    "" | "0.0.0.0" => ("", "listener"),
+                    host => (host, "caller"),
+                };
+
+                let mut sink = format!(" ! srtsink uri=\"srt://{host}:{port}?mode={mode}\"{sync}");
+                if let Some(srt) = video_and_stream_information
+                    .stream_information
+                    .extended_configuration
+                    .as_ref()
+                    .and_then(|configuration| configuration.srt.as_ref())
+                {
+                    if let Some(latency_ms) = srt.latency_ms {
+                        sink.push_str(&format!(" latency={latency_ms}"));
+                    }
+                    if let Some(passphrase) = &srt.passphrase {
+                        sink.push_str(&format!(" passphrase=\"{}\"", passphrase.replace('"', "")));
+                    }
+                }
+                sink
+            }
+            "rtmp" => {
+                // The endpoint is the full publishing URL, including the
+                // stream key, e.g: "rtmp://a.rtmp.youtube.com/live2/<key>"
+                format!(
+                    " ! rtmpsink location=\"{} live=1\"{sync}",
+                    endpoints[0].as_str().replace('"', "")
+                )
+            }
+            "tcp" => {
+                // Clients (e.g: ffmpeg or VLC) connect to "tcp://<host>:<port>"
+                let endpoint = &endpoints[0];
+                format!(
+                    " ! tcpserversink host={} port={} recover-policy=keyframe sync-method=latest-keyframe{sync}",
+                    endpoint.host_str().unwrap(),
+                    endpoint.port().unwrap()
+                )
+            }
+            "file" => {
+                // Named, so each start (e.g: after losing the camera) writes a new file,
+                // muxers seek back to finish the file and can't append to an old one
+                format!(
+                    " ! filesink name=file_sink location=\"{}\"",
+                    endpoints[0].path().replace('"', "")
+                )
             }
             _ => "".to_string(),
         };
         Ok(pipeline_sink)
     }
 
+    // RTCP follows the RTP convention, being sent to the port after the RTP one.
+    // Multicast receivers send their reports to the same group and port, joined by us as well,
+    // while unicast ones are only received in the configured local port, since the port after
+    // the client one may be in use by the client itself, e.g: when it is in this host.
+    fn build_rtcp(
+        endpoints: &[url::Url],
+        is_srtp: bool,
+        multicast_options: &str,
+        local_rtcp_port: Option<u16>,
+    ) -> SimpleResult<String> {
+        let rtcp_port = |endpoint: &url::Url| {
+            let port = endpoint.port().unwrap();
+            port.checked_add(1).ok_or_else(|| {
+                simple_error!(format!("There is no port left for RTCP after {port}."))
+            })
+        };
+
+        let clients = endpoints
+            .iter()
+            .map(|endpoint| {
+                Ok(format!(
+                    "{}:{}",
+                    endpoint.host().unwrap(),
+                    rtcp_port(endpoint)?
+                ))
+            })
+            .collect::<SimpleResult<Vec<String>>>()?
+            .join(",");
+
+        if is_srtp {
+            // Sent as SRTCP, the receiver reports are ignored since they would need to be decrypted
+            return Ok(format!(" rtpbin.send_rtcp_src_0 ! srtpenc.rtcp_sink_0 srtpenc.rtcp_src_0 ! multiudpsink name=rtcp_sink clients={clients} sync=false async=false{multicast_options}"));
+        }
+
+        let endpoint = &endpoints[0];
+        let receiver = match endpoint.host_str() {
+            Some(host)
+                if host
+                    .parse::<std::net::IpAddr>()
+                    .map_or(false, |address| address.is_multicast()) =>
+            {
+                Some(format!(
+                    " udpsrc address={host} port={}",
+                    rtcp_port(endpoint)?
+                ))
+            }
+            _ => local_rtcp_port.map(|port| format!(" udpsrc port={port}")),
+        };
+        let receiver = receiver
+            .map(|receiver| format!("{receiver} ! rtpbin.recv_rtcp_sink_0"))
+            .unwrap_or_default();
+        Ok(format!(
+            " rtpbin.send_rtcp_src_0 ! multiudpsink name=rtcp_sink clients={clients} sync=false async=false{multicast_options}{receiver}"
+        ))
+    }
+
+    // Master key and salt bytes of the hexadecimal key
+    pub fn parse_srtp_key(srtp: &SrtpConfiguration) -> SimpleResult<Vec<u8>> {
+        let key_length = srtp.cipher.key_length();
+        let is_hex = srtp
+            .key
+            .chars()
+            .all(|character| character.is_ascii_hexdigit());
+        if !is_hex || srtp.key.len() != key_length * 2 {
+            return Err(simple_error!(format!(
+                "SRTP key for {} should be a hexadecimal string with {key_length} bytes.",
+                srtp.cipher.as_str()
+            )));
+        }
+
+        Ok((0..srtp.key.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&srtp.key[index..index + 2], 16).unwrap())
+            .collect())
+    }
+
+    fn build_srtp_encoder(srtp: &SrtpConfiguration) -> SimpleResult<String> {
+        Pipeline::parse_srtp_key(srtp)?;
+
+        Ok(format!(
+            " ! srtpenc name=srtpenc key={key} rtp-cipher={cipher} rtp-auth=hmac-sha1-80 rtcp-cipher={cipher} rtcp-auth=hmac-sha1-80",
+            key = srtp.key.to_uppercase(),
+            cipher = srtp.cipher.as_str(),
+        ))
+    }
+
+    // Bitrate (kbps) and keyframe interval used by our encoders,
+    // explicit values take precedence over the profile's
+    pub fn get_encoder_settings(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> (u32, u32) {
+        let profile = Pipeline::get_profile_settings(video_and_stream_information);
+        let configuration =
+            Pipeline::get_video_capture_configuration(video_and_stream_information).ok();
+        let bitrate_kbps = configuration
+            .and_then(|configuration| configuration.bitrate_kbps)
+            .or_else(|| profile.as_ref().map(|profile| profile.bitrate_kbps))
+            .unwrap_or(5000);
+        let key_int_max = configuration
+            .and_then(|configuration| configuration.keyframe_interval)
+            .or_else(|| profile.as_ref().map(|profile| profile.key_int_max))
+            .unwrap_or(30);
+
+        (bitrate_kbps, key_int_max)
+    }
+
+    // RTP payload type and MTU of the payloaders
+    fn get_rtp_settings(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> SimpleResult<(u8, Option<u32>)> {
+        let extended_configuration = video_and_stream_information
+            .stream_information
+            .extended_configuration
+            .as_ref();
+
+        let pt = extended_configuration
+            .and_then(|configuration| configuration.rtp_payload_type)
+            .unwrap_or(96);
+        if !(96..=127).contains(&pt) {
+            return Err(simple_error!(format!(
+                "RTP payload type should be a dynamic one, from 96 to 127, got: {pt}"
+            )));
+        }
+
+        let mtu = extended_configuration.and_then(|configuration| configuration.mtu);
+        if let Some(mtu) = mtu {
+            // Minimum accepted by the GStreamer payloaders
+            if mtu < 28 {
+                return Err(simple_error!(format!(
+                    "MTU should be at least 28 bytes, got: {mtu}"
+                )));
+            }
+        }
+
+        Ok((pt, mtu))
+    }
+
+    fn get_osd(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> Option<(String, OsdConfiguration)> {
+        let osd = video_and_stream_information
+            .stream_information
+            .extended_configuration
+            .as_ref()?
+            .osd
+            .clone()?;
+        Some((video_and_stream_information.name.clone(), osd))
+    }
+
+    fn get_profile_settings(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> Option<StreamProfileSettings> {
+        video_and_stream_information
+            .stream_information
+            .extended_configuration
+            .as_ref()?
+            .profile
+            .as_ref()
+            .map(|profile| profile.settings())
+    }
+
+    fn get_latency_settings(
+        video_and_stream_information: &VideoAndStreamInformation,
+    ) -> Option<LatencyProfileSettings> {
+        video_and_stream_information
+            .stream_information
+            .extended_configuration
+            .as_ref()?
+            .latency_profile
+            .as_ref()
+            .map(|latency_profile| latency_profile.settings())
+    }
+
     fn get_video_capture_configuration(
         video_and_stream_information: &VideoAndStreamInformation,
     ) -> SimpleResult<&VideoCaptureConfiguration> {
@@ -193,12 +1504,17 @@ impl Pipeline {
             .stream_information
             .configuration
         {
-            crate::stream::types::CaptureConfiguration::VIDEO(configuration) => configuration,
-            crate::stream::types::CaptureConfiguration::REDIRECT(_) => {
+            CaptureConfiguration::VIDEO(configuration) => configuration,
+            CaptureConfiguration::REDIRECT(_) => {
                 return Err(simple_error!(
                     "Error: Cannot create a pipeline from a REDIRECT source!"
                 ))
             }
+            CaptureConfiguration::AUDIO(_) => {
+                return Err(simple_error!(
+                    "Error: Cannot create a video pipeline from an AUDIO configuration!"
+                ))
+            }
         };
         Ok(configuration)
     }
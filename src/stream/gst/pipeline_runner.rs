@@ -1,21 +1,56 @@
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use gstreamer::prelude::*;
 use gstreamer::{self, MessageView};
 
 use tracing::debug;
 
+use crate::event::{self, manager::EventKind};
 use crate::stream::stream_backend::StreamBackend;
+use crate::stream::types::{PipelineMessage, PipelineMessageKind, UdpClientStatistics};
+use crate::stream::{file_loop, osd, thermal};
 
 use super::pipeline_builder::Pipeline;
 
+// Number of bus messages kept for each pipeline
+const MAX_PIPELINE_MESSAGES: usize = 10;
+
+// The delay between restarts doubles after each failure, up to the maximum,
+// and goes back to the minimum once the pipeline runs for a healthy duration
+const RESTART_DELAY_MIN: Duration = Duration::from_secs(1);
+const RESTART_DELAY_MAX: Duration = Duration::from_secs(30);
+const HEALTHY_DURATION: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Default)]
 pub struct PipelineRunnerState {
     // move run kill restart logic to enum as states
     pipeline: Pipeline,
     run: bool,
     kill: bool,
+    usb_bandwidth_exhausted: bool,
+    udp_sink: Option<gstreamer::Element>,
+    // Sends that failed in the multiudpsink (e.g: to unreachable hosts), posted as warnings
+    udp_sink_send_errors: u64,
+    // Sends RTCP to the same clients of the udp_sink
+    rtcp_sink: Option<gstreamer::Element>,
+    // Latest errors, warnings and EOS, the oldest first
+    messages: VecDeque<PipelineMessage>,
+}
+
+impl PipelineRunnerState {
+    fn push_message(&mut self, kind: PipelineMessageKind, message: String) {
+        if self.messages.len() >= MAX_PIPELINE_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(PipelineMessage {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            kind,
+            message,
+        });
+    }
 }
 
 #[derive(Debug)]
@@ -49,6 +84,51 @@ impl PipelineRunner {
     }
 }
 
+impl PipelineRunner {
+    // Add a client to the multiudpsink while the pipeline is running, without RTCP
+    pub fn add_udp_client(&self, host: &str, port: u16) -> bool {
+        let state = self.state.lock().unwrap();
+        let sink = match &state.udp_sink {
+            Some(sink) => sink,
+            None => return false,
+        };
+
+        sink.emit_by_name::<()>("add", &[&host, &(port as i32)]);
+        true
+    }
+
+    pub fn remove_udp_client(&self, host: &str, port: u16) {
+        if let Some(sink) = &self.state.lock().unwrap().udp_sink {
+            sink.emit_by_name::<()>("remove", &[&host, &(port as i32)]);
+        }
+    }
+
+    // Replace the clients of the multiudpsink while the pipeline is running
+    pub fn set_udp_clients(&self, clients: &[(String, u16)]) -> bool {
+        let state = self.state.lock().unwrap();
+        let sink = match &state.udp_sink {
+            Some(sink) => sink,
+            None => return false,
+        };
+
+        sink.emit_by_name::<()>("clear", &[]);
+        for (host, port) in clients {
+            sink.emit_by_name::<()>("add", &[host, &(*port as i32)]);
+        }
+
+        if let Some(rtcp_sink) = &state.rtcp_sink {
+            rtcp_sink.emit_by_name::<()>("clear", &[]);
+            // Clients without a port after the RTP one don't receive RTCP, as when building the pipeline
+            for (host, port) in clients {
+                if let Some(rtcp_port) = port.checked_add(1) {
+                    rtcp_sink.emit_by_name::<()>("add", &[host, &(rtcp_port as i32)]);
+                }
+            }
+        }
+        true
+    }
+}
+
 impl StreamBackend for PipelineRunner {
     fn pipeline(&self) -> String {
         let string = self.state.lock().unwrap().pipeline.description.clone();
@@ -77,6 +157,49 @@ impl StreamBackend for PipelineRunner {
     fn allow_same_endpoints(&self) -> bool {
         false
     }
+
+    fn usb_bandwidth_exhausted(&self) -> bool {
+        self.state.lock().unwrap().usb_bandwidth_exhausted
+    }
+
+    fn udp_clients_statistics(&self) -> Vec<UdpClientStatistics> {
+        let state = self.state.lock().unwrap();
+        let sink = match &state.udp_sink {
+            Some(sink) => sink,
+            None => return vec![],
+        };
+
+        let clients = sink.property::<String>("clients");
+        clients
+            .split(',')
+            .filter_map(|client| {
+                let (host, port) = client.rsplit_once(':')?;
+                let port = port.parse::<i32>().ok()?;
+                let stats = sink.emit_by_name::<gstreamer::Structure>("get-stats", &[&host, &port]);
+                let bytes_sent = stats.get::<u64>("bytes-sent").unwrap_or_default();
+                let packets_sent = stats.get::<u64>("packets-sent").unwrap_or_default();
+                Some(UdpClientStatistics {
+                    client: client.to_string(),
+                    bytes_sent,
+                    packets_sent,
+                })
+            })
+            .collect()
+    }
+
+    fn udp_send_errors(&self) -> u64 {
+        self.state.lock().unwrap().udp_sink_send_errors
+    }
+
+    fn pipeline_messages(&self) -> Vec<PipelineMessage> {
+        self.state
+            .lock()
+            .unwrap()
+            .messages
+            .iter()
+            .cloned()
+            .collect()
+    }
 }
 
 impl Drop for PipelineRunner {
@@ -101,15 +224,22 @@ fn pipeline_runner(
     }
 
     let mut pipeline: Option<gstreamer::Element> = None;
+    let mut restart_delay = RESTART_DELAY_MIN;
     //TODO: move to while not kill
     'externalLoop: loop {
-        std::thread::sleep(std::time::Duration::from_secs(1));
+        if restart_delay > RESTART_DELAY_MIN {
+            debug!("Restarting pipeline in {restart_delay:?}");
+        }
+        sleep_unless_killed(&state, restart_delay);
         if state.lock().unwrap().kill {
             break 'externalLoop;
         }
         if !state.lock().unwrap().run {
+            restart_delay = RESTART_DELAY_MIN;
             continue;
         }
+        // If this attempt fails, the next one will wait longer
+        restart_delay = std::cmp::min(restart_delay * 2, RESTART_DELAY_MAX);
 
         let pipeline_description = state.lock().unwrap().pipeline.description.clone();
 
@@ -122,34 +252,52 @@ fn pipeline_runner(
         ) {
             Ok(pipeline) => Some(pipeline),
             Err(error) => {
-                if let Some(gstreamer::ParseError::NoSuchElement) =
+                let message = if let Some(gstreamer::ParseError::NoSuchElement) =
                     error.kind::<gstreamer::ParseError>()
                 {
-                    let _ = channel_tx.send(format!(
+                    format!(
                         "GStreamer error: Missing element(s): {:?}",
                         context.missing_elements()
-                    ));
+                    )
                 } else {
-                    let _ = channel_tx.send(format!(
-                        "GStreamer error: Failed to parse pipeline: {}",
-                        error
-                    ));
-                }
+                    format!("GStreamer error: Failed to parse pipeline: {}", error)
+                };
+                state
+                    .lock()
+                    .unwrap()
+                    .push_message(PipelineMessageKind::Error, message.clone());
+                let _ = channel_tx.send(message);
                 continue;
             }
         };
 
         let bus = pipeline.as_ref().unwrap().bus().unwrap();
 
+        track_udp_sink(pipeline.as_ref().unwrap(), &state);
+        set_file_sink_location(pipeline.as_ref().unwrap());
+
+        if let Some((stream_name, configuration)) = &state.lock().unwrap().pipeline.osd {
+            osd::track(stream_name, configuration, pipeline.as_ref().unwrap());
+        }
+        thermal::track(pipeline.as_ref().unwrap());
+        file_loop::track(pipeline.as_ref().unwrap());
+
         if let Err(error) = pipeline
             .as_ref()
             .unwrap()
             .set_state(gstreamer::State::Playing)
         {
-            let _ = channel_tx.send(format!(
+            let message = format!(
                 "GStreamer error: Unable to set the pipeline to the `Playing` state (check the bus for error messages): {}",
                 error
-            ));
+            );
+            state
+                .lock()
+                .unwrap()
+                .push_message(PipelineMessageKind::Error, message.clone());
+            let _ = channel_tx.send(message);
+            // Bring the pipeline down, releasing the device for the next attempt
+            let _ = pipeline.as_ref().unwrap().set_state(gstreamer::State::Null);
             continue;
         }
 
@@ -171,6 +319,7 @@ fn pipeline_runner(
         let mut previous_position: Option<gstreamer::ClockTime> = None;
         let mut lost_timestamps: usize = 0;
         let max_lost_timestamps: usize = 10;
+        let started = Instant::now();
 
         'innerLoop: loop {
             if state.lock().unwrap().kill {
@@ -205,6 +354,10 @@ fn pipeline_runner(
                             }
 
                             if lost_timestamps > max_lost_timestamps {
+                                state.lock().unwrap().push_message(
+                                    PipelineMessageKind::Error,
+                                    "Lost camera communication".into(),
+                                );
                                 break 'innerLoop;
                             }
 
@@ -220,6 +373,11 @@ fn pipeline_runner(
                 match msg.view() {
                     MessageView::Eos(eos) => {
                         let message = format!("GStreamer error: EOS received: {:#?}", eos);
+                        state
+                            .lock()
+                            .unwrap()
+                            .push_message(PipelineMessageKind::Eos, message.clone());
+                        event::manager::push(EventKind::Error, message.clone());
                         let _ = channel_tx.send(message);
                         break 'innerLoop;
                     }
@@ -230,23 +388,65 @@ fn pipeline_runner(
                             error.error(),
                             error.debug()
                         );
+                        {
+                            let mut state = state.lock().unwrap();
+                            state.push_message(PipelineMessageKind::Error, message.clone());
+                            if is_usb_bandwidth_error(&error) {
+                                // Restarting would fail the same way, so we stop
+                                // here and let the stream manager fall back to a
+                                // lighter configuration.
+                                state.usb_bandwidth_exhausted = true;
+                                state.run = false;
+                            }
+                        }
+                        event::manager::push(EventKind::Error, message.clone());
                         let _ = channel_tx.send(message);
                         break 'innerLoop;
                     }
+                    MessageView::Warning(warning) => {
+                        let message = format!(
+                            "GStreamer warning: Warning from {:?}: {} ({:?})",
+                            warning.src().map(|s| s.path_string()),
+                            warning.error(),
+                            warning.debug()
+                        );
+                        {
+                            let mut state = state.lock().unwrap();
+                            state.push_message(PipelineMessageKind::Warning, message.clone());
+                            // The multiudpsink keeps running when a send fails, only warning about it
+                            let is_udp_sink = match (&state.udp_sink, warning.src()) {
+                                (Some(sink), Some(src)) => {
+                                    sink.upcast_ref::<gstreamer::Object>() == &src
+                                }
+                                _ => false,
+                            };
+                            if is_udp_sink
+                                && warning.error().matches(gstreamer::ResourceError::Write)
+                            {
+                                state.udp_sink_send_errors += 1;
+                            }
+                        }
+                        let _ = channel_tx.send(message);
+                    }
                     _ => (),
                 }
             }
         }
 
+        {
+            let mut state = state.lock().unwrap();
+            state.udp_sink = None;
+            state.rtcp_sink = None;
+            if !state.run || started.elapsed() >= HEALTHY_DURATION {
+                restart_delay = RESTART_DELAY_MIN;
+            }
+        }
         if let Err(error) = pipeline.as_ref().unwrap().set_state(gstreamer::State::Null) {
             let _ = channel_tx.send(format!(
                 "GStreamer error: Unable to set the pipeline to the `Null` state: {:#?}",
                 error
             ));
         }
-
-        // The loop will restart, add delay to avoid high cpu usage
-        std::thread::sleep(std::time::Duration::from_millis(500));
     }
 
     if pipeline.as_ref().is_some() {
@@ -258,3 +458,74 @@ fn pipeline_runner(
         }
     }
 }
+
+// Sleep in small steps, so dropping the runner is not held by the restart delay
+fn sleep_unless_killed(state: &Arc<Mutex<PipelineRunnerState>>, duration: Duration) {
+    let step = Duration::from_millis(100);
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        if state.lock().unwrap().kill {
+            return;
+        }
+        std::thread::sleep(step);
+    }
+}
+
+// Keep a reference of the multiudpsink (if any),
+// allowing the statistics of each client to be queried
+fn track_udp_sink(pipeline: &gstreamer::Element, state: &Arc<Mutex<PipelineRunnerState>>) {
+    // The sinks are named by the pipeline builder
+    let bin = match pipeline.downcast_ref::<gstreamer::Bin>() {
+        Some(bin) => bin,
+        None => return,
+    };
+    let sink = match bin.by_name("udp_sink") {
+        Some(sink) => sink,
+        None => return,
+    };
+
+    let mut state = state.lock().unwrap();
+    state.udp_sink = Some(sink);
+    state.rtcp_sink = bin.by_name("rtcp_sink");
+}
+
+// Each start writes to a new file, named after the endpoint path and the current time
+fn set_file_sink_location(pipeline: &gstreamer::Element) {
+    // The sink is named by the pipeline builder
+    let sink = match pipeline
+        .downcast_ref::<gstreamer::Bin>()
+        .and_then(|bin| bin.by_name("file_sink"))
+    {
+        Some(sink) => sink,
+        None => return,
+    };
+
+    let location = sink
+        .property::<Option<String>>("location")
+        .unwrap_or_default();
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    sink.set_property(
+        "location",
+        super::utils::timestamped_path(&location, &timestamp),
+    );
+}
+
+// When multiple cameras share the same USB bus, v4l2src fails to start
+// streaming with ENOSPC (or EBUSY on some drivers) because the bus
+// bandwidth was already reserved by the other cameras.
+// Other elements may fail with the same errors for unrelated reasons, e.g: a full disk.
+pub fn is_usb_bandwidth_error(error: &gstreamer::message::Error) -> bool {
+    let is_v4l2src = error
+        .src()
+        .and_then(|src| src.downcast::<gstreamer::Element>().ok())
+        .and_then(|element| element.factory())
+        .map(|factory| factory.name() == "v4l2src")
+        .unwrap_or(false);
+    if !is_v4l2src {
+        return false;
+    }
+
+    let description = format!("{} {:?}", error.error(), error.debug());
+    description.contains("No space left on device")
+        || description.contains("Device or resource busy")
+}
@@ -1,5 +1,48 @@
 use gstreamer::prelude::*;
 use simple_error::{simple_error, SimpleResult};
+use tracing::*;
+
+use crate::stream::types::REDACTED;
+
+// Hardware encoders that can be used by the pipeline builder
+const HARDWARE_ENCODERS: &[&str] = &[
+    "nvv4l2h264enc",
+    "nvv4l2h265enc",
+    "v4l2h264enc",
+    "v4l2h265enc",
+    "vaapih264enc",
+    "vaapih265enc",
+    "vaapijpegenc",
+];
+
+// Video encoders, hardware and software, that can be used by the pipeline builder
+const VIDEO_ENCODERS: &[&str] = &[
+    "nvv4l2h264enc",
+    "nvv4l2h265enc",
+    "v4l2h264enc",
+    "v4l2h265enc",
+    "vaapih264enc",
+    "vaapih265enc",
+    "vaapijpegenc",
+    "x264enc",
+    "x265enc",
+    "jpegenc",
+    "vp8enc",
+    "vp9enc",
+    "av1enc",
+];
+
+lazy_static! {
+    // Properties of the elements holding secrets, e.g: the SRTP key and the SRT passphrase
+    static ref SECRET_PROPERTIES: regex::Regex =
+        regex::Regex::new(r#"(^|\s)(key|passphrase|user-pw)=("[^"]*"|\S+)"#).unwrap();
+    // The registry is only checked once, the encoders don't come and go
+    static ref AVAILABLE_HARDWARE_ENCODERS: Vec<&'static str> = HARDWARE_ENCODERS
+        .iter()
+        .copied()
+        .filter(|encoder| is_gstreamer_plugin_available(encoder, "1.14.0"))
+        .collect();
+}
 
 #[derive(Debug)]
 pub struct PluginRankConfig {
@@ -23,6 +66,50 @@ pub fn is_gstreamer_plugin_available(plugin_name: &str, min_version: &str) -> bo
     );
 }
 
+pub fn is_hardware_encoder_available(encoder: &str) -> bool {
+    AVAILABLE_HARDWARE_ENCODERS.contains(&encoder)
+}
+
+// Video encoder of a pipeline, when the video is encoded by us
+pub fn video_encoder(description: &str) -> Option<String> {
+    description
+        .split('!')
+        .filter_map(|element| element.split_whitespace().next())
+        .find(|element| VIDEO_ENCODERS.contains(element))
+        .map(|element| element.to_string())
+}
+
+// Pipeline description that can be logged or shown, without the secrets of the elements
+pub fn redact_secrets(description: &str) -> String {
+    SECRET_PROPERTIES
+        .replace_all(description, format!("${{1}}${{2}}={REDACTED}"))
+        .to_string()
+}
+
+// Probe the hardware encoders at startup, instead of when creating the first pipeline
+pub fn probe_hardware_encoders() {
+    info!(
+        "Hardware encoders available: {:?}",
+        *AVAILABLE_HARDWARE_ENCODERS
+    );
+}
+
+// Create the pipeline without starting it, to know if it can run in this host.
+// On failure, the elements that are not available are returned with the error.
+pub fn dry_run(description: &str) -> Result<(), (String, Vec<String>)> {
+    gstreamer::init().map_err(|error| (error.to_string(), vec![]))?;
+
+    let mut context = gstreamer::ParseContext::new();
+    match gstreamer::parse_launch_full(
+        description,
+        Some(&mut context),
+        gstreamer::ParseFlags::empty(),
+    ) {
+        Ok(_pipeline) => Ok(()),
+        Err(error) => Err((error.to_string(), context.missing_elements())),
+    }
+}
+
 pub fn set_plugin_rank(plugin_name: &str, rank: gstreamer::Rank) -> SimpleResult<()> {
     if let Err(error) = gstreamer::init() {
         tracing::error!("Error! {error}");
@@ -38,3 +125,55 @@ pub fn set_plugin_rank(plugin_name: &str, rank: gstreamer::Rank) -> SimpleResult
 
     Ok(())
 }
+
+// Adds the timestamp to the file name, before its extension: "/tmp/video.mkv" -> "/tmp/video_<timestamp>.mkv"
+pub fn timestamped_path(path: &str, timestamp: &str) -> String {
+    let path = std::path::Path::new(path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{stem}_{timestamp}.{}", extension.to_string_lossy()),
+        None => format!("{stem}_{timestamp}"),
+    };
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_secrets() {
+        assert_eq!(
+            redact_secrets("videotestsrc ! srtpenc name=srtpenc key=00112233 rtp-cipher=aes-128-icm ! fakesink"),
+            "videotestsrc ! srtpenc name=srtpenc key=<redacted> rtp-cipher=aes-128-icm ! fakesink"
+        );
+        assert_eq!(
+            redact_secrets("videotestsrc ! srtsink uri=\"srt://:9000?mode=listener\" passphrase=\"my secret passphrase\" latency=100"),
+            "videotestsrc ! srtsink uri=\"srt://:9000?mode=listener\" passphrase=<redacted> latency=100"
+        );
+        assert_eq!(
+            redact_secrets("rtspsrc location=rtsp://127.0.0.1:8554/test user-id=\"admin\" user-pw=\"1234\" ! fakesink"),
+            "rtspsrc location=rtsp://127.0.0.1:8554/test user-id=\"admin\" user-pw=<redacted> ! fakesink"
+        );
+        // Other properties with "key" in their names are kept
+        assert_eq!(
+            redact_secrets("x264enc key-int-max=30 ! fakesink"),
+            "x264enc key-int-max=30 ! fakesink"
+        );
+    }
+
+    #[test]
+    fn test_timestamped_path() {
+        assert_eq!(
+            timestamped_path("/tmp/video.mkv", "20220101_120000"),
+            "/tmp/video_20220101_120000.mkv"
+        );
+        assert_eq!(
+            timestamped_path("/tmp/video", "20220101_120000"),
+            "/tmp/video_20220101_120000"
+        );
+    }
+}
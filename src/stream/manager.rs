@@ -1,8 +1,19 @@
+use super::gst::pipeline_builder::Pipeline;
+#[cfg(feature = "recording")]
+use super::recording;
 use super::types::*;
-use super::{stream_backend, stream_backend::StreamBackend};
-use crate::mavlink::mavlink_camera::MavlinkCameraHandle;
+use super::{osd, stream_backend, stream_backend::StreamBackend};
+use crate::event::{self, manager::EventKind};
+#[cfg(feature = "mavlink")]
+use crate::mavlink::mavlink_camera::{
+    MavlinkCameraHandle, MavlinkCameraStatus, MavlinkCameraTracking,
+};
 use crate::settings;
-use crate::video::types::VideoSourceType;
+use crate::video::types::{Format, VideoEncodeType, VideoSourceType};
+use crate::video::video_source::{self, VideoSource};
+use crate::video::video_source_gst::{VideoSourceGst, VideoSourceGstType};
+#[cfg(target_os = "linux")]
+use crate::video::{dv_timings, quirks, video_source_local::VideoSourceLocalType};
 use crate::video_stream::types::VideoAndStreamInformation;
 use simple_error::{simple_error, SimpleResult};
 use std::sync::{Arc, Mutex};
@@ -12,12 +23,78 @@ use tracing::*;
 struct Stream {
     stream_type: StreamType,
     video_and_stream_information: VideoAndStreamInformation,
+    #[cfg(feature = "mavlink")]
     mavlink_camera: Option<MavlinkCameraHandle>,
+    // Configuration in use when the original one could not run
+    downgrade: Option<StreamDowngrade>,
+    udp_failover: UdpFailover,
+    // Showing the "camera lost" slate, until the camera is connected again
+    camera_lost: bool,
+    // Stopped on request, e.g: by the GCS. Not kept in the settings.
+    paused: bool,
 }
 
+// Tracks if the UDP clients are still receiving the packets, to switch
+// between the main and the fallback endpoints
+#[derive(Debug, Default)]
+struct UdpFailover {
+    using_fallback: bool,
+    packets_sent: u64,
+    send_errors: u64,
+    stalled_seconds: u32,
+}
+
+// How long the clients can fail to receive packets before switching endpoints
+const UDP_FAILOVER_TIMEOUT_SECONDS: u32 = 3;
+
+impl UdpFailover {
+    // Called every second with the packets sent to all clients and the send errors of the sink,
+    // returns true when nothing was sent but the sends failed for long enough to switch
+    fn update(&mut self, packets_sent: u64, send_errors: u64) -> bool {
+        let is_stalled = packets_sent == self.packets_sent && send_errors > self.send_errors;
+        self.packets_sent = packets_sent;
+        self.send_errors = send_errors;
+
+        if !is_stalled {
+            self.stalled_seconds = 0;
+            return false;
+        }
+
+        self.stalled_seconds += 1;
+        self.stalled_seconds >= UDP_FAILOVER_TIMEOUT_SECONDS
+    }
+
+    // The statistics are per client, so they start over, unlike the errors of the sink
+    fn switch(&mut self) {
+        *self = UdpFailover {
+            using_fallback: !self.using_fallback,
+            send_errors: self.send_errors,
+            ..Default::default()
+        };
+    }
+}
+
+// Fake source streamed in place of cameras that were disconnected
+const CAMERA_LOST_SLATE: &str = "black?text=Camera+lost";
+
+// V4L2 ID of the encoder bitrate control, exposed by some UVC cameras with H264.
+// For more information: https://www.kernel.org/doc/html/v5.5/media/uapi/v4l/ext-ctrls-codec.html
+const V4L2_CID_MPEG_VIDEO_BITRATE: u64 = 0x009909cf;
+
+// V4L2 ID of the JPEG compression quality control, from 1 to 100 for most cameras.
+// For more information: https://www.kernel.org/doc/html/v5.5/media/uapi/v4l/ext-ctrls-jpeg.html
+const V4L2_CID_JPEG_COMPRESSION_QUALITY: u64 = 0x009d0903;
+
+// V4L2 IDs of the image flip controls, used to rotate or flip the video in the camera itself.
+// For more information: https://www.kernel.org/doc/html/v5.5/media/uapi/v4l/control.html
+const V4L2_CID_HFLIP: u64 = 0x00980914;
+const V4L2_CID_VFLIP: u64 = 0x00980915;
+
 #[derive(Default)]
 struct Manager {
     pub streams: Vec<Stream>,
+    // Streams of cameras that are not connected, started when they come back
+    pub waiting: Vec<VideoAndStreamInformation>,
 }
 
 lazy_static! {
@@ -28,6 +105,525 @@ pub fn init() {
     debug!("Starting video stream service.");
 
     config_gstreamer_plugins();
+    crate::stream::gst::utils::probe_hardware_encoders();
+
+    std::thread::Builder::new()
+        .name("stream_watcher".into())
+        .spawn(stream_watcher_loop)
+        .unwrap();
+}
+
+fn stream_watcher_loop() {
+    loop {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        let fallbacks = {
+            let mut manager = MANAGER.as_ref().lock().unwrap();
+            let mut fallbacks = vec![];
+            for stream in manager.streams.iter_mut() {
+                if stream.paused {
+                    continue;
+                }
+
+                #[cfg(target_os = "linux")]
+                check_camera_lost(stream);
+                if stream.camera_lost {
+                    continue;
+                }
+
+                check_udp_failover(stream);
+                #[cfg(target_os = "linux")]
+                check_dv_timings(stream);
+
+                if let Some(fallback_information) = check_usb_bandwidth(stream) {
+                    fallbacks.push(fallback_information);
+                }
+            }
+            fallbacks
+        };
+
+        // Creating the pipelines may take a while, so the manager is not locked meanwhile
+        for fallback_information in fallbacks {
+            let fallback_stream = match stream_backend::new(&fallback_information) {
+                Ok(fallback_stream) => fallback_stream,
+                Err(error) => {
+                    let message = format!(
+                        "Failed to create fallback for stream {:#?}. Reason: {error}",
+                        fallback_information.name
+                    );
+                    error!(message);
+                    event::manager::push(EventKind::Error, message);
+                    continue;
+                }
+            };
+            switch_to_fallback(fallback_information, fallback_stream);
+        }
+    }
+}
+
+// Stops the streams without enough USB bandwidth, returning the lighter configuration to run instead
+fn check_usb_bandwidth(stream: &mut Stream) -> Option<VideoAndStreamInformation> {
+    if !stream.stream_type.inner().usb_bandwidth_exhausted() {
+        return None;
+    }
+
+    let mut current_information = stream.video_and_stream_information.clone();
+    if let Some(downgrade) = &stream.downgrade {
+        current_information.stream_information.configuration = downgrade.configuration.clone();
+    }
+
+    // The previous stream should be stopped before creating the new
+    // one, otherwise the device and the endpoints would still be in use
+    stream.stream_type.mut_inner().stop();
+
+    let fallback_information = usb_bandwidth_fallback(&current_information);
+    if fallback_information.is_none() {
+        let message = format!(
+            "Stream {:#?} failed due to lack of USB bandwidth and there is no lighter configuration to fall back to.",
+            stream.video_and_stream_information.name
+        );
+        error!(message);
+        event::manager::push(EventKind::Error, message);
+    }
+    fallback_information
+}
+
+fn switch_to_fallback(
+    fallback_information: VideoAndStreamInformation,
+    fallback_stream: StreamType,
+) {
+    let mut manager = MANAGER.as_ref().lock().unwrap();
+    // The stream may have been removed or replaced while the fallback was created
+    let stream = match manager.streams.iter_mut().find(|stream| {
+        stream.video_and_stream_information.name == fallback_information.name
+            && stream.stream_type.inner().usb_bandwidth_exhausted()
+    }) {
+        Some(stream) => stream,
+        None => return,
+    };
+
+    #[cfg(feature = "mavlink")]
+    if let Some(mavlink_camera) = &stream.mavlink_camera {
+        mavlink_camera.update_video_parameters(&fallback_information);
+    }
+
+    let downgrade = StreamDowngrade {
+        reason: "Not enough USB bandwidth for the requested configuration".into(),
+        configuration: fallback_information.stream_information.configuration,
+    };
+    let message = format!(
+        "Stream {:#?} ran out of USB bandwidth, downgrading it to: {:#?}",
+        stream.video_and_stream_information.name, downgrade.configuration
+    );
+    warn!(message);
+    event::manager::push(EventKind::Stream, message);
+
+    stream.stream_type = fallback_stream;
+    stream.stream_type.mut_inner().start();
+    stream.downgrade = Some(downgrade);
+    stream.udp_failover = UdpFailover::default();
+}
+
+// Cameras may disappear without a hotplug event, e.g: when they crash and the pipeline fails
+#[cfg(target_os = "linux")]
+fn check_camera_lost(stream: &mut Stream) {
+    if stream.camera_lost {
+        return;
+    }
+
+    let device_path = match &stream.video_and_stream_information.video_source {
+        VideoSourceType::Local(source) => source.device_path.clone(),
+        _ => return,
+    };
+    if std::path::Path::new(&device_path).exists() {
+        return;
+    }
+
+    if switch_to_slate(stream) {
+        let message = format!(
+            "Stream {:#?} lost its camera {device_path:#?}, streaming a slate until it is connected again",
+            stream.video_and_stream_information.name
+        );
+        warn!(message);
+        event::manager::push(EventKind::Hotplug, message);
+    }
+}
+
+// Same stream, with the camera replaced by a slate telling that it was lost
+fn slate_information(information: &VideoAndStreamInformation) -> VideoAndStreamInformation {
+    VideoAndStreamInformation {
+        video_source: VideoSourceType::Gst(VideoSourceGst {
+            name: information.video_source.inner().name().clone(),
+            source: VideoSourceGstType::Fake(CAMERA_LOST_SLATE.into()),
+        }),
+        ..information.clone()
+    }
+}
+
+// The endpoints keep receiving video, so the clients don't need to reconnect
+// when the camera comes back
+#[cfg(target_os = "linux")]
+fn switch_to_slate(stream: &mut Stream) -> bool {
+    // The previous stream should be stopped before creating the slate,
+    // otherwise the endpoints would still be in use
+    stream.stream_type.mut_inner().stop();
+
+    let slate = match stream_backend::new(&slate_information(&stream.video_and_stream_information))
+    {
+        Ok(slate) => slate,
+        Err(error) => {
+            warn!(
+                "Failed to create the camera lost slate of stream {:#?}. Reason: {error}",
+                stream.video_and_stream_information.name
+            );
+            return false;
+        }
+    };
+
+    stream.stream_type = slate;
+    if !stream.paused {
+        stream.stream_type.mut_inner().start();
+    }
+    stream.downgrade = None;
+    stream.udp_failover = UdpFailover::default();
+    stream.camera_lost = true;
+    true
+}
+
+// Sending to an unreachable host fails (e.g: EHOSTUNREACH after ARP timeouts) and the
+// multiudpsink warns about it, so when the sends fail and none succeed, the endpoints are switched.
+fn check_udp_failover(stream: &mut Stream) {
+    let fallback_endpoints = match &stream
+        .video_and_stream_information
+        .stream_information
+        .extended_configuration
+    {
+        Some(ExtendedConfiguration {
+            fallback_endpoints: Some(fallback_endpoints),
+            ..
+        }) if !fallback_endpoints.is_empty() => fallback_endpoints.clone(),
+        _ => return,
+    };
+
+    let udp_stream = match &stream.stream_type {
+        StreamType::UDP(udp_stream) => udp_stream,
+        _ => return,
+    };
+
+    let statistics = udp_stream.udp_clients_statistics();
+    let packets_sent = statistics.iter().map(|client| client.packets_sent).sum();
+    let send_errors = udp_stream.udp_send_errors();
+
+    let failover = &mut stream.udp_failover;
+    if !failover.update(packets_sent, send_errors) {
+        return;
+    }
+
+    let endpoints = if failover.using_fallback {
+        stream
+            .video_and_stream_information
+            .stream_information
+            .endpoints
+            .clone()
+    } else {
+        fallback_endpoints
+    };
+    let clients: Vec<(String, u16)> = endpoints
+        .iter()
+        .filter_map(|endpoint| Some((endpoint.host_str()?.to_string(), endpoint.port()?)))
+        .collect();
+
+    if !udp_stream.set_clients(&clients) {
+        return;
+    }
+
+    failover.switch();
+
+    let message = format!(
+        "Stream {:#?} endpoints are unreachable, switching to the {} endpoints: {:?}",
+        stream.video_and_stream_information.name,
+        if failover.using_fallback {
+            "fallback"
+        } else {
+            "main"
+        },
+        endpoints
+            .iter()
+            .map(|endpoint| endpoint.as_str())
+            .collect::<Vec<&str>>()
+    );
+    warn!(message);
+    event::manager::push(EventKind::Stream, message);
+}
+
+// HDMI sources may change their mode at any time, e.g: when the video output of the OSD
+// is reconfigured, and the receiver only captures the new one after it is set again
+#[cfg(target_os = "linux")]
+fn check_dv_timings(stream: &mut Stream) {
+    let device_path = match &stream.video_and_stream_information.video_source {
+        VideoSourceType::Local(source) if matches!(source.typ, VideoSourceLocalType::Hdmi(_)) => {
+            source.device_path.clone()
+        }
+        _ => return,
+    };
+
+    // Without signal there is nothing to change to
+    let timings = match dv_timings::query(&device_path) {
+        Ok(timings) => timings,
+        Err(_) => return,
+    };
+    if matches_dv_timings(&stream.video_and_stream_information, &timings) {
+        return;
+    }
+
+    let message = format!(
+        "Stream {:#?} HDMI source changed its mode to {}x{} at {}/{} fps",
+        stream.video_and_stream_information.name,
+        timings.width,
+        timings.height,
+        timings.frame_interval.denominator,
+        timings.frame_interval.numerator,
+    );
+    info!(message);
+    event::manager::push(EventKind::Stream, message);
+
+    // The receiver can't be changed while it is capturing
+    stream.stream_type.mut_inner().stop();
+
+    let mut information = stream.video_and_stream_information.clone();
+    apply_dv_timings(&mut information);
+    match stream_backend::new(&information) {
+        Ok(new_stream) => {
+            stream.stream_type = new_stream;
+            stream.stream_type.mut_inner().start();
+            #[cfg(feature = "mavlink")]
+            if let Some(mavlink_camera) = &stream.mavlink_camera {
+                mavlink_camera.update_video_parameters(&information);
+            }
+            stream.video_and_stream_information = information;
+        }
+        Err(error) => {
+            let message = format!(
+                "Failed to create stream {:#?} for the new HDMI mode. Reason: {error}",
+                information.name
+            );
+            error!(message);
+            event::manager::push(EventKind::Error, message);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn matches_dv_timings(
+    video_and_stream_information: &VideoAndStreamInformation,
+    timings: &dv_timings::DvTimings,
+) -> bool {
+    match &video_and_stream_information
+        .stream_information
+        .configuration
+    {
+        CaptureConfiguration::VIDEO(configuration) => {
+            configuration.width == timings.width
+                && configuration.height == timings.height
+                && configuration.frame_interval == timings.frame_interval
+        }
+        _ => true,
+    }
+}
+
+// Some cameras fail, or send broken frames, when captured right after being opened
+fn wait_camera_warmup(video_and_stream_information: &VideoAndStreamInformation) {
+    let warmup_ms = match &video_and_stream_information.video_source {
+        #[cfg(target_os = "linux")]
+        VideoSourceType::Local(source) => quirks::for_camera(source).warmup_ms,
+        _ => None,
+    };
+    if let Some(warmup_ms) = warmup_ms {
+        debug!(
+            "Waiting {warmup_ms} ms for the camera of stream {:#?} to warm up.",
+            video_and_stream_information.name
+        );
+        std::thread::sleep(std::time::Duration::from_millis(warmup_ms));
+    }
+}
+
+// HDMI receivers capture the mode chosen by the HDMI source, used instead of the configured one
+#[cfg(target_os = "linux")]
+fn apply_dv_timings(video_and_stream_information: &mut VideoAndStreamInformation) {
+    let device_path = match &video_and_stream_information.video_source {
+        VideoSourceType::Local(source) if matches!(source.typ, VideoSourceLocalType::Hdmi(_)) => {
+            source.device_path.clone()
+        }
+        _ => return,
+    };
+
+    let timings = match dv_timings::prepare(&device_path) {
+        Ok(timings) => timings,
+        Err(error) => {
+            warn!(
+                "Failed to get the HDMI mode of {device_path}, using the configured one: {error}"
+            );
+            return;
+        }
+    };
+    if matches_dv_timings(video_and_stream_information, &timings) {
+        return;
+    }
+
+    if let CaptureConfiguration::VIDEO(configuration) = &mut video_and_stream_information
+        .stream_information
+        .configuration
+    {
+        info!(
+            "Stream {:#?} configured as {}x{}, using the HDMI mode {}x{} instead.",
+            video_and_stream_information.name,
+            configuration.width,
+            configuration.height,
+            timings.width,
+            timings.height
+        );
+        configuration.width = timings.width;
+        configuration.height = timings.height;
+        configuration.frame_interval = timings.frame_interval;
+    }
+}
+
+// Streams of the same camera capture from different slots (capture nodes), using
+// the first one that is free and provides the configured format
+fn assign_stream_slot(
+    manager: &Manager,
+    video_and_stream_information: &mut VideoAndStreamInformation,
+) {
+    if video_and_stream_information
+        .video_source
+        .inner()
+        .is_shareable()
+    {
+        return;
+    }
+
+    let configuration = match &video_and_stream_information
+        .stream_information
+        .configuration
+    {
+        CaptureConfiguration::VIDEO(configuration) => configuration,
+        _ => return,
+    };
+
+    let slots = video_source::stream_slots(&video_and_stream_information.video_source);
+    if slots.len() < 2 {
+        return;
+    }
+
+    let is_used = |slot: &VideoSourceType| {
+        manager.streams.iter().any(|stream| {
+            stream
+                .video_and_stream_information
+                .video_source
+                .inner()
+                .source_string()
+                == slot.inner().source_string()
+        })
+    };
+    let provides_format = |formats: &Vec<Format>| {
+        formats.iter().any(|format| {
+            format.encode == configuration.encode
+                && format.sizes.iter().any(|size| {
+                    size.width == configuration.width && size.height == configuration.height
+                })
+        })
+    };
+
+    let source_string = video_and_stream_information
+        .video_source
+        .inner()
+        .source_string()
+        .to_string();
+    let slot = slots
+        .iter()
+        .filter(|(slot, formats)| !is_used(slot) && provides_format(formats))
+        .min_by_key(|(slot, _)| slot.inner().source_string() != source_string);
+
+    if let Some((slot, _)) = slot {
+        if slot.inner().source_string() != source_string {
+            info!(
+                "Stream {:#?} will capture from {}, another node of the same camera.",
+                video_and_stream_information.name,
+                slot.inner().source_string()
+            );
+            video_and_stream_information.video_source = slot.clone();
+        }
+    }
+}
+
+// Looks for a lighter configuration for the same video source: raw formats
+// are changed to MJPG (compressed by the camera itself), otherwise, the
+// next smaller resolution with the same encode is used.
+fn usb_bandwidth_fallback(
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> Option<VideoAndStreamInformation> {
+    let configuration = match &video_and_stream_information
+        .stream_information
+        .configuration
+    {
+        CaptureConfiguration::VIDEO(configuration) => configuration,
+        CaptureConfiguration::REDIRECT(_) | CaptureConfiguration::AUDIO(_) => return None,
+    };
+
+    let formats = video_and_stream_information.video_source.inner().formats();
+
+    let same_size =
+        |width: u32, height: u32| configuration.width == width && configuration.height == height;
+
+    let mjpg_fallback = match configuration.encode {
+        VideoEncodeType::YUYV => formats
+            .iter()
+            .filter(|format| format.encode == VideoEncodeType::MJPG)
+            .flat_map(|format| format.sizes.iter())
+            .find(|size| same_size(size.width, size.height))
+            .map(|size| (VideoEncodeType::MJPG, size)),
+        _ => None,
+    };
+
+    let smaller_size_fallback = || {
+        formats
+            .iter()
+            .filter(|format| format.encode == configuration.encode)
+            .flat_map(|format| format.sizes.iter())
+            .filter(|size| size.width * size.height < configuration.width * configuration.height)
+            .max_by_key(|size| size.width * size.height)
+            .map(|size| (configuration.encode.clone(), size))
+    };
+
+    let (encode, size) = mjpg_fallback.or_else(smaller_size_fallback)?;
+
+    // Keep the same frame rate if possible
+    let frame_interval = size
+        .intervals
+        .iter()
+        .find(|&interval| *interval == configuration.frame_interval)
+        .or_else(|| size.intervals.first())?
+        .clone();
+
+    let mut fallback_information = video_and_stream_information.clone();
+    fallback_information.stream_information.configuration =
+        CaptureConfiguration::VIDEO(VideoCaptureConfiguration {
+            encode,
+            height: size.height,
+            width: size.width,
+            frame_interval,
+            transcode: configuration.transcode.clone(),
+            bitrate_kbps: configuration.bitrate_kbps,
+            keyframe_interval: configuration.keyframe_interval,
+            jpeg_quality: configuration.jpeg_quality,
+            deinterlace: configuration.deinterlace,
+            rotation: configuration.rotation,
+            flip: configuration.flip.clone(),
+            crop: configuration.crop.clone(),
+            output_size: configuration.output_size.clone(),
+        });
+
+    Some(fallback_information)
 }
 
 fn config_gstreamer_plugins() {
@@ -46,23 +642,42 @@ fn config_gstreamer_plugins() {
 }
 
 pub fn start_default() {
-    MANAGER.as_ref().lock().unwrap().streams.clear();
+    let mut manager = MANAGER.as_ref().lock().unwrap();
+    manager.streams.clear();
+    manager.waiting.clear();
+    drop(manager);
 
+    #[cfg_attr(not(target_os = "linux"), allow(unused_mut))]
     let mut streams = settings::manager::streams();
 
     // Update all local video sources to make sure that is available
+    #[cfg(target_os = "linux")]
     streams.iter_mut().for_each(|stream| {
         if let VideoSourceType::Local(source) = &mut stream.video_source {
             if !source.update_device() {
                 error!("Source appears to be invalid or not found: {source:#?}");
+                event::manager::push(
+                    EventKind::Hotplug,
+                    format!(
+                        "Source {:#?} appears to be invalid or not found",
+                        source.name()
+                    ),
+                );
             }
         }
     });
 
-    // Remove all invalid video_sources
-    let streams: Vec<VideoAndStreamInformation> = streams
+    // Streams of disconnected cameras wait for them, other invalid video_sources are removed
+    let (streams, invalid_streams): (Vec<VideoAndStreamInformation>, _) = streams
         .into_iter()
-        .filter(|stream| stream.video_source.inner().is_valid())
+        .partition(|stream| stream.video_source.inner().is_valid());
+    MANAGER.as_ref().lock().unwrap().waiting = invalid_streams
+        .into_iter()
+        .filter(|stream| match stream.video_source {
+            #[cfg(target_os = "linux")]
+            VideoSourceType::Local(_) => true,
+            _ => false,
+        })
         .collect();
 
     debug!("streams: {streams:#?}");
@@ -70,6 +685,10 @@ pub fn start_default() {
     for stream in streams {
         add_stream_and_start(stream).unwrap_or_else(|error| {
             error!("Not possible to start stream: {error}");
+            event::manager::push(
+                EventKind::Error,
+                format!("Not possible to start stream: {error}"),
+            );
         });
     }
 }
@@ -83,9 +702,16 @@ pub fn start() {
             StreamType::UDP(stream) => {
                 stream.start();
             }
+            #[cfg(feature = "rtsp")]
             StreamType::RTSP(stream) => {
                 stream.start();
             }
+            StreamType::PIPELINE(stream) => {
+                stream.start();
+            }
+            StreamType::TEE(stream) => {
+                stream.start();
+            }
             StreamType::REDIRECT(_) => (),
         }
     }
@@ -98,67 +724,786 @@ pub fn streams() -> Vec<StreamStatus> {
         .iter()
         .map(|stream| StreamStatus {
             running: stream.stream_type.inner().is_running(),
-            video_and_stream: stream.video_and_stream_information.clone(),
+            video_and_stream: redacted(&stream.video_and_stream_information),
+            downgrade: stream.downgrade.clone(),
+            pipeline_messages: stream.stream_type.inner().pipeline_messages(),
+            encoder: crate::stream::gst::utils::video_encoder(
+                &stream.stream_type.inner().pipeline(),
+            ),
+            redirect_health: match &stream.stream_type {
+                StreamType::REDIRECT(redirect) => Some(redirect.health()),
+                _ => None,
+            },
+            camera_lost: stream.camera_lost,
+            paused: stream.paused,
+            #[cfg(feature = "recording")]
+            recording: recording::file_name(&stream.video_and_stream_information.name),
+            #[cfg(not(feature = "recording"))]
+            recording: None,
         })
+        .chain(manager.waiting.iter().map(|information| StreamStatus {
+            running: false,
+            video_and_stream: redacted(information),
+            downgrade: None,
+            pipeline_messages: vec![],
+            encoder: None,
+            redirect_health: None,
+            camera_lost: false,
+            paused: false,
+            recording: None,
+        }))
         .collect();
 
     return status;
 }
 
-pub fn add_stream_and_start(
-    video_and_stream_information: VideoAndStreamInformation,
-) -> SimpleResult<()> {
-    //TODO: Check if stream can handle caps
-    let mut manager = MANAGER.as_ref().lock().unwrap();
+// The secrets are only kept in the settings, not shown by the API
+fn redacted(video_and_stream_information: &VideoAndStreamInformation) -> VideoAndStreamInformation {
+    let mut video_and_stream_information = video_and_stream_information.clone();
+    let extended_configuration = &mut video_and_stream_information
+        .stream_information
+        .extended_configuration;
+    *extended_configuration = extended_configuration
+        .as_ref()
+        .map(|configuration| configuration.redacted());
+    video_and_stream_information
+}
+
+pub fn statistics() -> Vec<StreamStatistics> {
+    let manager = MANAGER.as_ref().lock().unwrap();
+    manager
+        .streams
+        .iter()
+        .map(|stream| StreamStatistics {
+            name: stream.video_and_stream_information.name.clone(),
+            udp_clients: stream.stream_type.inner().udp_clients_statistics(),
+            udp_send_errors: stream.stream_type.inner().udp_send_errors(),
+        })
+        .collect()
+}
+
+// Return a GStreamer source that receives the running stream in this host,
+// and the port added as a client when the stream is sent over UDP
+pub fn tap_source(stream_name: &str) -> SimpleResult<(String, Option<u16>)> {
+    let manager = MANAGER.as_ref().lock().unwrap();
+    let stream = manager
+        .streams
+        .iter()
+        .find(|stream| stream.video_and_stream_information.name == stream_name)
+        .ok_or_else(|| simple_error!(format!("Stream {stream_name:?} does not exist.")))?;
+
+    let information = &stream.video_and_stream_information.stream_information;
+    let encode = match &information.configuration {
+        CaptureConfiguration::VIDEO(configuration) => configuration.stream_encode().clone(),
+        _ => {
+            return Err(simple_error!(format!(
+                "Stream {stream_name:?} does not have video."
+            )))
+        }
+    };
+    let not_running = || simple_error!(format!("Stream {stream_name:?} is not running."));
+    let jitterbuffer_latency_ms = information
+        .extended_configuration
+        .as_ref()
+        .and_then(|configuration| configuration.latency_profile.as_ref())
+        .map(|latency_profile| latency_profile.settings().jitterbuffer_latency_ms);
+
+    match &stream.stream_type {
+        StreamType::UDP(udp) => {
+            if information
+                .extended_configuration
+                .as_ref()
+                .and_then(|configuration| configuration.srtp.as_ref())
+                .is_some()
+            {
+                return Err(simple_error!(format!(
+                    "Stream {stream_name:?} is encrypted with SRTP and can't be received locally."
+                )));
+            }
+
+            let (encoding, depay) = match encode {
+                VideoEncodeType::H264 => ("H264", "rtph264depay"),
+                VideoEncodeType::H265 => ("H265", "rtph265depay"),
+                VideoEncodeType::MJPG => ("JPEG", "rtpjpegdepay"),
+                VideoEncodeType::VP8 => ("VP8", "rtpvp8depay"),
+                VideoEncodeType::VP9 => ("VP9", "rtpvp9depay"),
+                VideoEncodeType::AV1 => ("AV1", "rtpav1depay"),
+                encode => {
+                    return Err(simple_error!(format!(
+                        "Stream {stream_name:?} with {encode:?} encode can't be received locally."
+                    )))
+                }
+            };
+            let port = loopback_port()?;
+            if !udp.add_client("127.0.0.1", port) {
+                return Err(not_running());
+            }
+            let latency = jitterbuffer_latency_ms
+                .map(|latency| format!(" latency={latency}"))
+                .unwrap_or_default();
+            Ok((
+                format!("udpsrc address=127.0.0.1 port={port} caps=\"application/x-rtp,media=video,clock-rate=90000,encoding-name={encoding}\" ! rtpjitterbuffer{latency} ! {depay}"),
+                Some(port),
+            ))
+        }
+        StreamType::PIPELINE(mpegts) if mpegts.scheme == "mpegts" => {
+            let port = loopback_port()?;
+            if !mpegts.add_client("127.0.0.1", port) {
+                return Err(not_running());
+            }
+            Ok((
+                format!("udpsrc address=127.0.0.1 port={port} caps=\"video/mpegts,systemstream=true\" ! tsdemux"),
+                Some(port),
+            ))
+        }
+        #[cfg(feature = "rtsp")]
+        StreamType::RTSP(_) => {
+            let mut endpoint = information.endpoints[0].clone();
+            let _ = endpoint.set_host(Some("127.0.0.1"));
+            let mut source = format!(
+                "rtspsrc location=\"{endpoint}\" latency={}",
+                jitterbuffer_latency_ms.unwrap_or(0)
+            );
+            if let Some(credentials) = information
+                .extended_configuration
+                .as_ref()
+                .and_then(|configuration| configuration.rtsp_auth.as_ref())
+            {
+                source.push_str(&format!(
+                    " user-id=\"{}\" user-pw=\"{}\"",
+                    credentials.username.replace('"', ""),
+                    credentials.password.replace('"', "")
+                ));
+            }
+            if endpoint.scheme() == "rtsps" {
+                // The certificate is usually self-signed and not valid for 127.0.0.1
+                source.push_str(" tls-validation-flags=0");
+            }
+            Ok((source, None))
+        }
+        _ => Err(simple_error!(format!(
+            "Stream {stream_name:?} can't be received locally, only UDP, MPEG-TS and RTSP streams are supported."
+        ))),
+    }
+}
+
+pub fn remove_tap_client(stream_name: &str, port: u16) {
+    let manager = MANAGER.as_ref().lock().unwrap();
+    let stream = match manager
+        .streams
+        .iter()
+        .find(|stream| stream.video_and_stream_information.name == stream_name)
+    {
+        Some(stream) => stream,
+        None => return,
+    };
+
+    match &stream.stream_type {
+        StreamType::UDP(udp) => udp.remove_client("127.0.0.1", port),
+        StreamType::PIPELINE(mpegts) if mpegts.scheme == "mpegts" => {
+            mpegts.remove_client("127.0.0.1", port)
+        }
+        _ => (),
+    }
+}
+
+pub fn loopback_port() -> SimpleResult<u16> {
+    std::net::UdpSocket::bind("127.0.0.1:0")
+        .and_then(|socket| socket.local_addr())
+        .map(|address| address.port())
+        .map_err(|error| simple_error!(format!("Failed to find a free local port: {error}")))
+}
+
+// The camera controls are only set through V4L2
+fn v4l2_camera(
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> Option<&dyn VideoSource> {
+    match &video_and_stream_information.video_source {
+        #[cfg(target_os = "linux")]
+        VideoSourceType::Local(source) => Some(source),
+        _ => None,
+    }
+}
+
+// Cameras with an H264 encoder may allow changing its bitrate,
+// for transcoded streams it is done by our encoder instead
+fn apply_bitrate_control(video_and_stream_information: &VideoAndStreamInformation) {
+    let bitrate_kbps = match &video_and_stream_information
+        .stream_information
+        .configuration
+    {
+        CaptureConfiguration::VIDEO(VideoCaptureConfiguration {
+            bitrate_kbps: Some(bitrate_kbps),
+            transcode: None,
+            ..
+        }) => *bitrate_kbps,
+        _ => return,
+    };
+
+    let source = match v4l2_camera(video_and_stream_information) {
+        Some(source) => source,
+        None => return,
+    };
+
+    let has_control = source
+        .controls()
+        .iter()
+        .any(|control| control.id == V4L2_CID_MPEG_VIDEO_BITRATE);
+    if !has_control {
+        debug!(
+            "Camera {:?} has no bitrate control, bitrate not applied.",
+            source.name()
+        );
+        return;
+    }
+
+    // V4L2 expects the bitrate in bits per second
+    if let Err(error) =
+        source.set_control_by_id(V4L2_CID_MPEG_VIDEO_BITRATE, bitrate_kbps as i64 * 1000)
+    {
+        warn!(
+            "Failed to set camera {:?} bitrate to {bitrate_kbps} kbps: {error}",
+            source.name()
+        );
+    }
+}
+
+fn apply_jpeg_quality_control(video_and_stream_information: &VideoAndStreamInformation) {
+    // When transcoding, the quality is applied by our encoder
+    let jpeg_quality = match &video_and_stream_information
+        .stream_information
+        .configuration
+    {
+        CaptureConfiguration::VIDEO(VideoCaptureConfiguration {
+            encode: VideoEncodeType::MJPG,
+            jpeg_quality: Some(jpeg_quality),
+            transcode: None,
+            ..
+        }) => *jpeg_quality,
+        _ => return,
+    };
+
+    let source = match v4l2_camera(video_and_stream_information) {
+        Some(source) => source,
+        None => return,
+    };
+
+    let has_control = source
+        .controls()
+        .iter()
+        .any(|control| control.id == V4L2_CID_JPEG_COMPRESSION_QUALITY);
+    if !has_control {
+        debug!(
+            "Camera {:?} has no JPEG quality control, quality not applied.",
+            source.name()
+        );
+        return;
+    }
+
+    if let Err(error) =
+        source.set_control_by_id(V4L2_CID_JPEG_COMPRESSION_QUALITY, jpeg_quality as i64)
+    {
+        warn!(
+            "Failed to set camera {:?} JPEG quality to {jpeg_quality}: {error}",
+            source.name()
+        );
+    }
+}
+
+// Horizontal and vertical flips to be set in the camera, when its controls
+// can do the configured rotation and flip, so the pipeline doesn't need to
+pub fn camera_flips(
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> Option<(bool, bool)> {
+    let configuration = match &video_and_stream_information
+        .stream_information
+        .configuration
+    {
+        CaptureConfiguration::VIDEO(configuration) => configuration,
+        _ => return None,
+    };
+    configuration.video_direction()?;
+    let flips = configuration.flips()?;
+
+    let source = v4l2_camera(video_and_stream_information)?;
+
+    let controls = source.controls();
+    let has_control = |id| controls.iter().any(|control| control.id == id);
+    if !has_control(V4L2_CID_HFLIP) || !has_control(V4L2_CID_VFLIP) {
+        return None;
+    }
 
+    Some(flips)
+}
+
+fn apply_flip_controls(video_and_stream_information: &VideoAndStreamInformation) {
+    let (hflip, vflip) = match camera_flips(video_and_stream_information) {
+        Some(flips) => flips,
+        None => return,
+    };
+
+    let source = match v4l2_camera(video_and_stream_information) {
+        Some(source) => source,
+        None => return,
+    };
+
+    for (id, value) in [(V4L2_CID_HFLIP, hflip), (V4L2_CID_VFLIP, vflip)] {
+        if let Err(error) = source.set_control_by_id(id, value as i64) {
+            warn!(
+                "Failed to set camera {:?} flip control {id} to {value}: {error}",
+                source.name()
+            );
+        }
+    }
+}
+
+// Name, source and endpoints should not be used by the other streams
+fn check_conflicts(
+    manager: &Manager,
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> SimpleResult<()> {
     for stream in manager.streams.iter() {
         if !stream.stream_type.inner().allow_same_endpoints() {
             stream
                 .video_and_stream_information
-                .conflicts_with(&video_and_stream_information)?
+                .conflicts_with(video_and_stream_information)?
         }
     }
+    Ok(())
+}
+
+// Check if a stream could be added, without starting it
+pub fn validate_stream(
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> SimpleResult<StreamValidation> {
+    check_conflicts(
+        &MANAGER.as_ref().lock().unwrap(),
+        video_and_stream_information,
+    )?;
+
+    let pipeline = match stream_backend::validate(video_and_stream_information)? {
+        Some(pipeline) => pipeline.description,
+        None => {
+            return Ok(StreamValidation {
+                pipeline: "".into(),
+                missing_elements: vec![],
+                error: None,
+            })
+        }
+    };
+
+    let (error, missing_elements) = match crate::stream::gst::utils::dry_run(&pipeline) {
+        Ok(_) => (None, vec![]),
+        Err((error, missing_elements)) => (Some(error), missing_elements),
+    };
+
+    Ok(StreamValidation {
+        pipeline: crate::stream::gst::utils::redact_secrets(&pipeline),
+        missing_elements,
+        error,
+    })
+}
+
+pub fn add_stream_and_start(
+    mut video_and_stream_information: VideoAndStreamInformation,
+) -> SimpleResult<()> {
+    //TODO: Check if stream can handle caps
+    let manager = MANAGER.as_ref().lock().unwrap();
+
+    #[cfg(target_os = "linux")]
+    apply_dv_timings(&mut video_and_stream_information);
+    assign_stream_slot(&manager, &mut video_and_stream_information);
+    check_conflicts(&manager, &video_and_stream_information)?;
 
     let mut stream = stream_backend::new(&video_and_stream_information)?;
 
+    // The camera is set up without holding the manager, since its warmup blocks for a while
+    drop(manager);
+    apply_bitrate_control(&video_and_stream_information);
+    apply_jpeg_quality_control(&video_and_stream_information);
+    apply_flip_controls(&video_and_stream_information);
+    wait_camera_warmup(&video_and_stream_information);
+
+    // Other streams may have been added meanwhile
+    let mut manager = MANAGER.as_ref().lock().unwrap();
+    check_conflicts(&manager, &video_and_stream_information)?;
+
+    #[cfg(feature = "mavlink")]
     let mavlink_camera = MavlinkCameraHandle::try_new(&video_and_stream_information, &stream);
 
     stream.mut_inner().start();
     manager.streams.push(Stream {
         stream_type: stream,
         video_and_stream_information: video_and_stream_information.clone(),
+        #[cfg(feature = "mavlink")]
         mavlink_camera,
+        downgrade: None,
+        udp_failover: UdpFailover::default(),
+        camera_lost: false,
+        paused: false,
     });
 
-    //TODO: Create function to update settings
-    let video_and_stream_informations = manager
+    save_settings(&manager);
+    event::manager::push(
+        EventKind::Stream,
+        format!("Stream {:#?} started", video_and_stream_information.name),
+    );
+    return Ok(());
+}
+
+#[cfg(feature = "mavlink")]
+pub fn mavlink_status() -> Vec<MavlinkCameraStatus> {
+    let manager = MANAGER.as_ref().lock().unwrap();
+    manager
         .streams
         .iter()
-        .map(|stream| stream.video_and_stream_information.clone())
-        .collect();
-    settings::manager::set_streams(&video_and_stream_informations);
-    return Ok(());
+        .filter_map(|stream| stream.mavlink_camera.as_ref())
+        .flat_map(|mavlink_camera| mavlink_camera.status())
+        .collect()
+}
+
+#[cfg(feature = "mavlink")]
+pub fn mavlink_tracking() -> Vec<MavlinkCameraTracking> {
+    let manager = MANAGER.as_ref().lock().unwrap();
+    manager
+        .streams
+        .iter()
+        .filter_map(|stream| stream.mavlink_camera.as_ref())
+        .map(|mavlink_camera| mavlink_camera.tracking())
+        .collect()
+}
+
+// Recreates the MAVLink cameras of the streams, e.g: when their IDs change
+#[cfg(feature = "mavlink")]
+pub fn restart_mavlink_cameras() {
+    let mut manager = MANAGER.as_ref().lock().unwrap();
+    // All of them are dropped first, releasing their components to be assigned again
+    for stream in manager.streams.iter_mut() {
+        stream.mavlink_camera = None;
+    }
+    for stream in manager.streams.iter_mut() {
+        stream.mavlink_camera =
+            MavlinkCameraHandle::try_new(&stream.video_and_stream_information, &stream.stream_type);
+        if let Some(mavlink_camera) = &stream.mavlink_camera {
+            mavlink_camera.set_paused(stream.paused);
+        }
+    }
+    event::manager::push(EventKind::Stream, "MAVLink cameras restarted".to_string());
+}
+
+// Show or hide the on-screen display of a stream, keeping it in the settings
+pub fn set_osd_enabled(stream_name: &str, enabled: bool) -> SimpleResult<()> {
+    let mut manager = MANAGER.as_ref().lock().unwrap();
+    let stream = manager
+        .streams
+        .iter_mut()
+        .find(|stream| stream.video_and_stream_information.name == *stream_name)
+        .ok_or_else(|| simple_error!("Identification does not match any stream."))?;
+
+    let osd = stream
+        .video_and_stream_information
+        .stream_information
+        .extended_configuration
+        .as_mut()
+        .and_then(|configuration| configuration.osd.as_mut())
+        .ok_or_else(|| simple_error!(format!("Stream {stream_name:#?} has no OSD configured.")))?;
+    osd.enabled = enabled;
+
+    osd::set_enabled(stream_name, enabled);
+
+    save_settings(&manager);
+    Ok(())
+}
+
+// Change the field of view of a stream, keeping it in the settings
+pub fn set_stream_fov(stream_name: &str, fov: Option<FieldOfView>) -> SimpleResult<()> {
+    if let Some(fov) = &fov {
+        let valid = |angle: f32| angle > 0.0 && angle < 360.0;
+        if !valid(fov.horizontal) || !valid(fov.vertical) {
+            return Err(simple_error!(format!(
+                "The field of view should be from 0 to 360 degrees, got {fov:?}."
+            )));
+        }
+    }
+
+    let mut manager = MANAGER.as_ref().lock().unwrap();
+    let stream = manager
+        .streams
+        .iter_mut()
+        .find(|stream| stream.video_and_stream_information.name == *stream_name)
+        .ok_or_else(|| simple_error!("Identification does not match any stream."))?;
+
+    stream
+        .video_and_stream_information
+        .stream_information
+        .extended_configuration
+        .get_or_insert_with(Default::default)
+        .fov = fov;
+
+    #[cfg(feature = "mavlink")]
+    if let Some(mavlink_camera) = &stream.mavlink_camera {
+        mavlink_camera.update_video_parameters(&stream.video_and_stream_information);
+    }
+
+    save_settings(&manager);
+    Ok(())
+}
+
+// Bitrate of the video encoded by us, or by the camera, in kbps. Not known for redirected streams.
+pub fn stream_bitrate_kbps(stream_name: &str) -> Option<u32> {
+    let manager = MANAGER.as_ref().lock().unwrap();
+    let stream = manager
+        .streams
+        .iter()
+        .find(|stream| stream.video_and_stream_information.name == *stream_name)?;
+    match &stream
+        .video_and_stream_information
+        .stream_information
+        .configuration
+    {
+        CaptureConfiguration::VIDEO(_) => {
+            Some(Pipeline::get_encoder_settings(&stream.video_and_stream_information).0)
+        }
+        _ => None,
+    }
+}
+
+// Changes the bitrate of a stream, keeping it in the settings. The pipeline is
+// recreated, since the encoders can't change it while running.
+#[cfg(feature = "mavlink")]
+pub fn set_stream_bitrate(stream_name: &str, bitrate_kbps: u32) -> SimpleResult<()> {
+    let mut manager = MANAGER.as_ref().lock().unwrap();
+    let stream = manager
+        .streams
+        .iter_mut()
+        .find(|stream| stream.video_and_stream_information.name == *stream_name)
+        .ok_or_else(|| simple_error!("Identification does not match any stream."))?;
+
+    let mut information = stream.video_and_stream_information.clone();
+    match &mut information.stream_information.configuration {
+        CaptureConfiguration::VIDEO(configuration) => {
+            configuration.bitrate_kbps = Some(bitrate_kbps)
+        }
+        _ => {
+            return Err(simple_error!(format!(
+                "Stream {stream_name:#?} has no video configuration."
+            )))
+        }
+    }
+
+    // The slate keeps running, the new bitrate is used when the camera comes back
+    if !stream.camera_lost {
+        stream.stream_type.mut_inner().stop();
+        let new_stream = stream_backend::new(&information)?;
+        apply_bitrate_control(&information);
+        stream.stream_type = new_stream;
+        if !stream.paused {
+            stream.stream_type.mut_inner().start();
+        }
+        stream.downgrade = None;
+        stream.udp_failover = UdpFailover::default();
+    }
+    #[cfg(feature = "mavlink")]
+    if let Some(mavlink_camera) = &stream.mavlink_camera {
+        mavlink_camera.update_video_parameters(&information);
+    }
+    stream.video_and_stream_information = information;
+
+    save_settings(&manager);
+    event::manager::push(
+        EventKind::Stream,
+        format!("Stream {stream_name:#?} bitrate changed to {bitrate_kbps} kbps"),
+    );
+    Ok(())
+}
+
+// Stops a stream without removing it, or starts it again. A paused stream that loses
+// its camera is started again when the camera is connected.
+pub fn set_stream_paused(stream_name: &str, paused: bool) -> SimpleResult<()> {
+    // The recording would stall, it's stopped before the manager is locked since it is a client of the stream
+    #[cfg(feature = "recording")]
+    if paused && recording::elapsed(stream_name).is_some() {
+        recording::stop(stream_name)?;
+    }
+
+    let mut manager = MANAGER.as_ref().lock().unwrap();
+    let stream = manager
+        .streams
+        .iter_mut()
+        .find(|stream| stream.video_and_stream_information.name == *stream_name)
+        .ok_or_else(|| simple_error!("Identification does not match any stream."))?;
+
+    if let StreamType::REDIRECT(_) = stream.stream_type {
+        return Err(simple_error!(format!(
+            "Stream {stream_name:#?} is redirected, it can only be paused by its source."
+        )));
+    }
+    if stream.paused == paused {
+        return Ok(());
+    }
+
+    if paused {
+        stream.stream_type.mut_inner().stop();
+    } else {
+        // The stream is created again since not all of them can be restarted, with the
+        // same configuration in use before it was paused
+        let mut information = match stream.camera_lost {
+            true => slate_information(&stream.video_and_stream_information),
+            false => stream.video_and_stream_information.clone(),
+        };
+        if let Some(downgrade) = &stream.downgrade {
+            information.stream_information.configuration = downgrade.configuration.clone();
+        }
+
+        let new_stream = stream_backend::new(&information)?;
+        stream.stream_type = new_stream;
+        stream.stream_type.mut_inner().start();
+        stream.udp_failover = UdpFailover::default();
+    }
+    stream.paused = paused;
+
+    #[cfg(feature = "mavlink")]
+    if let Some(mavlink_camera) = &stream.mavlink_camera {
+        mavlink_camera.set_paused(paused);
+    }
+
+    event::manager::push(
+        EventKind::Stream,
+        format!(
+            "Stream {stream_name:#?} {}",
+            match paused {
+                true => "paused",
+                false => "resumed",
+            }
+        ),
+    );
+    Ok(())
 }
 
 pub fn remove_stream(stream_name: &str) -> SimpleResult<()> {
     let find_stream = |stream: &Stream| stream.video_and_stream_information.name == *stream_name;
 
+    // Finished while the stream is still running, and before the manager is locked, since the recording is a client of it
+    #[cfg(feature = "recording")]
+    if recording::elapsed(stream_name).is_some() {
+        if let Err(error) = recording::stop(stream_name) {
+            warn!("Failed to stop recording of stream {stream_name:?}: {error}");
+        }
+    }
+
     let mut manager = MANAGER.as_ref().lock().unwrap();
-    match manager.streams.iter().position(find_stream) {
-        Some(index) => {
-            manager.streams.remove(index);
-            let video_and_stream_informations = manager
-                .streams
-                .iter()
-                .map(|stream| stream.video_and_stream_information.clone())
-                .collect();
-            settings::manager::set_streams(&video_and_stream_informations);
-            Ok(())
+    if let Some(index) = manager.streams.iter().position(find_stream) {
+        manager.streams.remove(index);
+    } else if let Some(index) = manager
+        .waiting
+        .iter()
+        .position(|information| information.name == *stream_name)
+    {
+        manager.waiting.remove(index);
+    } else {
+        return Err(simple_error!("Identification does not match any stream."));
+    }
+
+    save_settings(&manager);
+    event::manager::push(
+        EventKind::Stream,
+        format!("Stream {stream_name:#?} removed"),
+    );
+    Ok(())
+}
+
+// Called when cameras are connected or disconnected: the streams of missing cameras
+// wait for them, and the ones of cameras that came back (maybe in another path) are restarted
+#[cfg(target_os = "linux")]
+pub fn update_devices() {
+    let mut manager = MANAGER.as_ref().lock().unwrap();
+    let mut restart = vec![];
+
+    for mut stream in std::mem::take(&mut manager.streams) {
+        let mut information = stream.video_and_stream_information.clone();
+        let source = match &mut information.video_source {
+            VideoSourceType::Local(source) => source,
+            _ => {
+                manager.streams.push(stream);
+                continue;
+            }
+        };
+
+        let previous_path = source.device_path.clone();
+        if !source.update_device() {
+            if stream.camera_lost {
+                manager.streams.push(stream);
+            } else if switch_to_slate(&mut stream) {
+                let message = format!(
+                    "Stream {:#?} lost its camera, streaming a slate until it is connected again",
+                    information.name
+                );
+                warn!(message);
+                event::manager::push(EventKind::Hotplug, message);
+                stream.video_and_stream_information = information;
+                manager.streams.push(stream);
+            } else {
+                let message = format!(
+                    "Stream {:#?} stopped, waiting for its camera to be connected",
+                    information.name
+                );
+                warn!(message);
+                event::manager::push(EventKind::Hotplug, message);
+                // The stream is dropped, stopping its pipeline
+                manager.waiting.push(information);
+            }
+        } else if stream.camera_lost || source.device_path != previous_path {
+            // The slate is dropped, releasing the endpoints for the camera stream
+            restart.push(information);
+        } else {
+            manager.streams.push(stream);
+        }
+    }
+
+    for mut information in std::mem::take(&mut manager.waiting) {
+        if let VideoSourceType::Local(source) = &mut information.video_source {
+            if source.update_device() {
+                restart.push(information);
+                continue;
+            }
+        }
+        manager.waiting.push(information);
+    }
+
+    save_settings(&manager);
+    drop(manager);
+
+    for information in restart {
+        info!(
+            "Restarting stream {:#?} for its reconnected camera.",
+            information.name
+        );
+        if let Err(error) = add_stream_and_start(information.clone()) {
+            let message = format!(
+                "Failed to restart stream {:#?} after its camera was connected: {error}",
+                information.name
+            );
+            error!(message);
+            event::manager::push(EventKind::Error, message);
+
+            let mut manager = MANAGER.as_ref().lock().unwrap();
+            manager.waiting.push(information);
+            save_settings(&manager);
         }
-        None => Err(simple_error!("Identification does not match any stream.")),
     }
 }
 
+// Streams waiting for their cameras are kept in the settings
+fn save_settings(manager: &Manager) {
+    let video_and_stream_informations = manager
+        .streams
+        .iter()
+        .map(|stream| stream.video_and_stream_information.clone())
+        .chain(manager.waiting.iter().cloned())
+        .collect();
+    settings::manager::set_streams(&video_and_stream_informations);
+}
+
 //TODO: rework to use UML definition
 // Add a new pipeline string to run
 /*
@@ -169,3 +1514,175 @@ pub fn add(description: &'static str) {
     manager.streams.push(StreamType::UDP(stream));
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::types::{StreamInformation, VideoCaptureConfiguration};
+    use crate::video::types::FrameInterval;
+    use url::Url;
+
+    #[test]
+    fn test_udp_failover() {
+        let mut failover = UdpFailover::default();
+
+        // Sending, even with some errors
+        assert!(!failover.update(100, 0));
+        assert!(!failover.update(200, 1));
+
+        // Every send fails, the endpoints are switched after the timeout
+        for _ in 1..UDP_FAILOVER_TIMEOUT_SECONDS {
+            assert!(!failover.update(200, failover.send_errors + 10));
+        }
+        assert!(failover.update(200, failover.send_errors + 10));
+
+        failover.switch();
+        assert!(failover.using_fallback);
+        assert_eq!(failover.packets_sent, 0);
+        assert_eq!(
+            failover.send_errors,
+            1 + 10 * UDP_FAILOVER_TIMEOUT_SECONDS as u64
+        );
+
+        // The fallback clients receive the packets, the old errors don't count
+        assert!(!failover.update(0, failover.send_errors));
+        assert!(!failover.update(50, failover.send_errors));
+
+        // A stream without packets is not stalled, e.g: when the camera is paused
+        for _ in 0..=UDP_FAILOVER_TIMEOUT_SECONDS {
+            assert!(!failover.update(50, failover.send_errors));
+        }
+        assert!(failover.using_fallback);
+    }
+
+    #[test]
+    fn test_unsupported_tap_source() {
+        let srtp = ExtendedConfiguration {
+            srtp: Some(SrtpConfiguration {
+                cipher: SrtpCipher::Aes128Icm,
+                key: "00".repeat(SrtpCipher::Aes128Icm.key_length()),
+            }),
+            ..Default::default()
+        };
+        let streams = vec![
+            ("Encrypted tap", VideoEncodeType::H264, Some(srtp), "SRTP"),
+            ("Raw tap", VideoEncodeType::YUYV, None, "YUYV"),
+        ];
+
+        for (name, encode, extended_configuration, reason) in streams {
+            let video_and_stream_information = VideoAndStreamInformation {
+                name: name.into(),
+                stream_information: StreamInformation {
+                    endpoints: vec![Url::parse("udp://192.168.0.1:5600").unwrap()],
+                    configuration: CaptureConfiguration::VIDEO(VideoCaptureConfiguration {
+                        encode,
+                        height: 480,
+                        width: 640,
+                        frame_interval: FrameInterval {
+                            numerator: 1,
+                            denominator: 30,
+                        },
+                        transcode: None,
+                        bitrate_kbps: None,
+                        keyframe_interval: None,
+                        jpeg_quality: None,
+                        deinterlace: None,
+                        rotation: None,
+                        flip: None,
+                        crop: None,
+                        output_size: None,
+                    }),
+                    extended_configuration,
+                    audio: None,
+                },
+                video_source: VideoSourceType::Gst(VideoSourceGst {
+                    name: "Test camera".into(),
+                    source: VideoSourceGstType::Fake("ball".into()),
+                }),
+            };
+            let stream = Stream {
+                stream_type: stream_backend::new(&video_and_stream_information).unwrap(),
+                video_and_stream_information,
+                #[cfg(feature = "mavlink")]
+                mavlink_camera: None,
+                downgrade: None,
+                udp_failover: UdpFailover::default(),
+                camera_lost: false,
+                paused: false,
+            };
+            MANAGER.as_ref().lock().unwrap().streams.push(stream);
+
+            let error = tap_source(name).unwrap_err();
+            assert!(error.to_string().contains(reason), "{name}: {error}");
+
+            MANAGER
+                .as_ref()
+                .lock()
+                .unwrap()
+                .streams
+                .retain(|stream| stream.video_and_stream_information.name != name);
+        }
+    }
+
+    #[cfg(all(feature = "rtsp", target_os = "linux"))]
+    #[test]
+    fn test_rtsp_slate() {
+        let video_and_stream_information = VideoAndStreamInformation {
+            name: "Slate".into(),
+            stream_information: StreamInformation {
+                endpoints: vec![Url::parse("rtsp://0.0.0.0:8554/slate").unwrap()],
+                configuration: CaptureConfiguration::VIDEO(VideoCaptureConfiguration {
+                    encode: VideoEncodeType::YUYV,
+                    height: 480,
+                    width: 640,
+                    frame_interval: FrameInterval {
+                        numerator: 1,
+                        denominator: 30,
+                    },
+                    transcode: None,
+                    bitrate_kbps: None,
+                    keyframe_interval: None,
+                    jpeg_quality: None,
+                    deinterlace: None,
+                    rotation: None,
+                    flip: None,
+                    crop: None,
+                    output_size: None,
+                }),
+                extended_configuration: None,
+                audio: None,
+            },
+            video_source: VideoSourceType::Gst(VideoSourceGst {
+                name: "Test camera".into(),
+                source: VideoSourceGstType::Fake("ball".into()),
+            }),
+        };
+        let mut stream = Stream {
+            stream_type: stream_backend::new(&video_and_stream_information).unwrap(),
+            video_and_stream_information,
+            #[cfg(feature = "mavlink")]
+            mavlink_camera: None,
+            downgrade: None,
+            udp_failover: UdpFailover::default(),
+            camera_lost: false,
+            paused: false,
+        };
+        assert!(stream.stream_type.mut_inner().start());
+
+        // The slate takes the path of the previous stream, which releases it when dropped
+        assert!(switch_to_slate(&mut stream));
+        assert!(stream.camera_lost);
+        assert!(stream
+            .stream_type
+            .inner()
+            .pipeline()
+            .contains("Camera lost"));
+        assert!(stream.stream_type.mut_inner().start());
+        let key = (8554, "/slate".to_string());
+        assert!(crate::stream::rtsp_server::RTSP_SERVER
+            .lock()
+            .unwrap()
+            .path_to_factory
+            .contains_key(&key));
+    }
+}
@@ -0,0 +1,28 @@
+use super::tap::{StreamTap, TapStream};
+
+use actix_web::web::Bytes;
+use simple_error::SimpleResult;
+
+pub const BOUNDARY: &str = "frame";
+
+// JPEG frames of a running stream, as the parts of a multipart/x-mixed-replace body
+pub type MjpegStream = TapStream<Bytes>;
+
+pub fn new(stream_name: &str) -> SimpleResult<MjpegStream> {
+    let tap = StreamTap::new(
+        stream_name,
+        " ! decodebin ! videoconvert ! jpegenc ! appsink name=mjpeg_sink emit-signals=true max-buffers=1 drop=true sync=false",
+    )?;
+
+    // Slow clients lose frames instead of accumulating them
+    tap.into_stream("mjpeg_sink", 1, true, |_sample, jpeg| {
+        let mut part = format!(
+            "--{BOUNDARY}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            jpeg.len()
+        )
+        .into_bytes();
+        part.extend_from_slice(jpeg);
+        part.extend_from_slice(b"\r\n");
+        Bytes::from(part)
+    })
+}
@@ -1,9 +1,27 @@
+#[cfg(any(feature = "mavlink", feature = "recording"))]
+pub mod captures;
+pub mod file_loop;
+pub mod fmp4;
+pub mod frames;
 pub mod gst;
 pub mod manager;
+pub mod mjpeg;
+pub mod osd;
+#[cfg(feature = "recording")]
+pub mod recording;
+#[cfg(feature = "rtsp")]
 pub mod rtsp_server;
+#[cfg(feature = "mavlink")]
+pub mod snapshot;
 pub mod stream_backend;
+pub mod tap;
+pub mod thermal;
 pub mod types;
+pub mod video_stream_pipeline;
 pub mod video_stream_redirect;
+#[cfg(feature = "rtsp")]
 pub mod video_stream_rtsp;
+pub mod video_stream_tee;
 pub mod video_stream_udp;
+#[cfg(feature = "webrtc")]
 pub mod webrtc;
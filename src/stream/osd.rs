@@ -0,0 +1,168 @@
+use super::types::{OsdConfiguration, OsdItem};
+
+use gstreamer::glib::WeakRef;
+use gstreamer::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::*;
+
+// Name of the textoverlay element added by the pipeline builder
+pub const ELEMENT_NAME: &str = "osd";
+
+// How often the overlays are updated
+const UPDATE_PERIOD: std::time::Duration = std::time::Duration::from_millis(200);
+
+// Vehicle telemetry shown in the overlays, updated from the MAVLink messages
+#[derive(Clone, Debug, Default)]
+pub struct Telemetry {
+    // Meters, negative when underwater
+    pub altitude: Option<f32>,
+    // Degrees, from 0 (north) to 359
+    pub heading: Option<i16>,
+    pub battery_voltage: Option<f32>,
+    // Percentage
+    pub battery_remaining: Option<i8>,
+}
+
+#[derive(Debug)]
+struct Overlay {
+    configuration: OsdConfiguration,
+    elements: Vec<WeakRef<gstreamer::Element>>,
+}
+
+lazy_static! {
+    static ref TELEMETRY: Arc<Mutex<Telemetry>> = Arc::new(Mutex::new(Telemetry::default()));
+    // Overlays of the running pipelines, indexed by stream name,
+    // updated by their own thread
+    static ref OVERLAYS: Arc<Mutex<HashMap<String, Overlay>>> = {
+        std::thread::Builder::new()
+            .name("osd".into())
+            .spawn(update_loop)
+            .unwrap();
+        Arc::new(Mutex::new(HashMap::new()))
+    };
+}
+
+#[cfg_attr(not(feature = "mavlink"), allow(dead_code))]
+pub fn update_telemetry(update: impl FnOnce(&mut Telemetry)) {
+    update(&mut TELEMETRY.lock().unwrap());
+}
+
+// Keep the overlay of a pipeline (if any) updated with the telemetry
+pub fn track(stream_name: &str, configuration: &OsdConfiguration, pipeline: &gstreamer::Element) {
+    let element = match pipeline
+        .downcast_ref::<gstreamer::Bin>()
+        .and_then(|bin| bin.by_name(ELEMENT_NAME))
+    {
+        Some(element) => element,
+        None => return,
+    };
+
+    element.set_property("silent", !configuration.enabled);
+
+    let mut overlays = OVERLAYS.lock().unwrap();
+    let overlay = overlays
+        .entry(stream_name.to_string())
+        .or_insert_with(|| Overlay {
+            configuration: configuration.clone(),
+            elements: vec![],
+        });
+    overlay.configuration = configuration.clone();
+    overlay.elements.push(element.downgrade());
+}
+
+// Show or hide the overlays of a running stream
+pub fn set_enabled(stream_name: &str, enabled: bool) {
+    let mut overlays = OVERLAYS.lock().unwrap();
+    let overlay = match overlays.get_mut(stream_name) {
+        Some(overlay) => overlay,
+        None => return,
+    };
+
+    overlay.configuration.enabled = enabled;
+    for element in overlay.elements.iter().filter_map(WeakRef::upgrade) {
+        element.set_property("silent", !enabled);
+    }
+}
+
+fn update_loop() {
+    loop {
+        std::thread::sleep(UPDATE_PERIOD);
+
+        let telemetry = TELEMETRY.lock().unwrap().clone();
+        let mut overlays = OVERLAYS.lock().unwrap();
+
+        // Pipelines that were stopped have their elements dropped
+        overlays.retain(|_, overlay| {
+            overlay
+                .elements
+                .retain(|element| element.upgrade().is_some());
+            !overlay.elements.is_empty()
+        });
+
+        for (stream_name, overlay) in overlays.iter() {
+            if !overlay.configuration.enabled {
+                continue;
+            }
+
+            let text = text(&overlay.configuration.items, &telemetry);
+            trace!("Updating OSD of stream {stream_name:?}: {text:?}");
+            for element in overlay.elements.iter().filter_map(WeakRef::upgrade) {
+                element.set_property("text", &text);
+            }
+        }
+    }
+}
+
+// One line per item, with "--" for the telemetry not received yet
+fn text(items: &[OsdItem], telemetry: &Telemetry) -> String {
+    items
+        .iter()
+        .map(|item| match item {
+            OsdItem::Timestamp => chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            OsdItem::Depth => match telemetry.altitude {
+                Some(altitude) if altitude < 0.0 => format!("Depth: {:.1} m", -altitude),
+                Some(altitude) => format!("Altitude: {altitude:.1} m"),
+                None => "Depth: --".to_string(),
+            },
+            OsdItem::Heading => match telemetry.heading {
+                Some(heading) => format!("Heading: {heading}°"),
+                None => "Heading: --".to_string(),
+            },
+            OsdItem::Battery => match (telemetry.battery_voltage, telemetry.battery_remaining) {
+                (Some(voltage), Some(remaining)) => {
+                    format!("Battery: {voltage:.1} V ({remaining}%)")
+                }
+                (Some(voltage), None) => format!("Battery: {voltage:.1} V"),
+                _ => "Battery: --".to_string(),
+            },
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn telemetry_text() {
+        let items = vec![OsdItem::Depth, OsdItem::Heading, OsdItem::Battery];
+
+        assert_eq!(
+            text(&items, &Telemetry::default()),
+            "Depth: --\nHeading: --\nBattery: --"
+        );
+
+        let telemetry = Telemetry {
+            altitude: Some(-12.34),
+            heading: Some(270),
+            battery_voltage: Some(15.27),
+            battery_remaining: Some(80),
+        };
+        assert_eq!(
+            text(&items, &telemetry),
+            "Depth: 12.3 m\nHeading: 270°\nBattery: 15.3 V (80%)"
+        );
+    }
+}
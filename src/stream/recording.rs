@@ -0,0 +1,108 @@
+use super::captures;
+use super::tap::StreamTap;
+
+use simple_error::{simple_error, SimpleResult};
+use tracing::*;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Time for the muxer to write the end of the file when a recording stops
+const FINISH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// MP4 recording of a running stream, made from a local copy of its video,
+// so the stream doesn't need to be restarted
+#[derive(Debug)]
+struct Recording {
+    tap: StreamTap,
+    file_name: String,
+    started: Instant,
+}
+
+lazy_static! {
+    // Indexed by stream name, a stream is recorded to one file at a time
+    static ref RECORDINGS: Mutex<HashMap<String, Recording>> = Mutex::new(HashMap::new());
+}
+
+// Starts recording the stream to the capture path, returning the name of the file
+pub fn start(stream_name: &str) -> SimpleResult<String> {
+    if let Some(file_name) = file_name(stream_name) {
+        return Err(simple_error!(format!(
+            "Stream {stream_name:?} is already being recorded to {file_name:?}."
+        )));
+    }
+
+    let (file_name, path) = captures::new_file(stream_name, "mp4")?;
+    // Fragmented, so the file can still be played if the recording is not finished properly
+    let tap = StreamTap::new(
+        stream_name,
+        &format!(
+            " ! parsebin ! mp4mux fragment-duration=1000 ! filesink location=\"{}\"",
+            path.display()
+        ),
+    )?;
+    tap.start()?;
+
+    // The recordings are not locked while the tap is created, since the stream manager
+    // is locked to create it, and the recordings are locked by the manager
+    let recording = Recording {
+        tap,
+        file_name: file_name.clone(),
+        started: Instant::now(),
+    };
+    let replaced = RECORDINGS
+        .lock()
+        .unwrap()
+        .insert(stream_name.to_string(), recording);
+    if let Some(recording) = replaced {
+        warn!(
+            "Recording of stream {stream_name:?} to {:?} was replaced.",
+            recording.file_name
+        );
+        recording.tap.finish(FINISH_TIMEOUT);
+    }
+
+    info!("Started recording of stream {stream_name:?} to {path:?}.");
+    Ok(file_name)
+}
+
+// Stops recording the stream, returning the name of the file
+pub fn stop(stream_name: &str) -> SimpleResult<String> {
+    let recording = RECORDINGS
+        .lock()
+        .unwrap()
+        .remove(stream_name)
+        .ok_or_else(|| simple_error!(format!("Stream {stream_name:?} is not being recorded.")))?;
+
+    recording.tap.finish(FINISH_TIMEOUT);
+    info!(
+        "Stopped recording of stream {stream_name:?} to {:?} after {:?}.",
+        recording.file_name,
+        recording.started.elapsed()
+    );
+    Ok(recording.file_name)
+}
+
+// Name of the file the stream is being recorded to
+pub fn file_name(stream_name: &str) -> Option<String> {
+    RECORDINGS
+        .lock()
+        .unwrap()
+        .get(stream_name)
+        .map(|recording| recording.file_name.clone())
+}
+
+#[cfg(feature = "mavlink")]
+pub fn is_any_running() -> bool {
+    !RECORDINGS.lock().unwrap().is_empty()
+}
+
+// Time since the recording of the stream started, none if it is not being recorded
+pub fn elapsed(stream_name: &str) -> Option<Duration> {
+    RECORDINGS
+        .lock()
+        .unwrap()
+        .get(stream_name)
+        .map(|recording| recording.started.elapsed())
+}
@@ -1,26 +1,41 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 use std::collections::HashMap;
 
 use glib;
+use gstreamer::prelude::*;
 use gstreamer_rtsp_server;
 use gstreamer_rtsp_server::prelude::{
-    RTSPMediaFactoryExt, RTSPMountPointsExt, RTSPServerExt, RTSPServerExtManual,
+    RTSPAddressPoolExt, RTSPAuthExt, RTSPAuthExtManual, RTSPMediaExt, RTSPMediaFactoryExt,
+    RTSPMediaFactoryExtManual, RTSPMountPointsExt, RTSPServerExt, RTSPServerExtManual,
+    RTSPStreamExt,
 };
 use simple_error::{simple_error, SimpleResult};
+use tracing::*;
+
+use super::gst::{pipeline_builder::Pipeline, pipeline_runner};
+use super::types::ExtendedConfiguration;
+use super::{file_loop, osd, thermal};
 
 #[allow(dead_code)]
 pub struct RTSPServer {
-    pub server: gstreamer_rtsp_server::RTSPServer,
+    // One server for each port, sharing the same authentication
+    pub servers: HashMap<u16, gstreamer_rtsp_server::RTSPServer>,
     host: String,
-    port: u16,
     run: bool,
-    pub path_to_factory: HashMap<String, gstreamer_rtsp_server::RTSPMediaFactory>,
+    auth: gstreamer_rtsp_server::RTSPAuth,
+    // Basic authentication credentials required by each port and path
+    path_to_basic: HashMap<(u16, String), String>,
+    pub path_to_factory: HashMap<(u16, String), gstreamer_rtsp_server::RTSPMediaFactory>,
     main_loop_thread: Option<std::thread::JoinHandle<()>>,
     main_loop_thread_rx_channel: std::sync::mpsc::Receiver<String>,
 }
 
+// Role given to clients by the server authentication
+const ANONYMOUS_ROLE: &str = "anonymous";
+
 lazy_static! {
     pub static ref RTSP_SERVER: Arc<Mutex<RTSPServer>> =
         Arc::new(Mutex::new(RTSPServer::default()));
@@ -36,17 +51,48 @@ impl RTSPServer {
         let is_running = false;
         let (sender, receiver) = std::sync::mpsc::channel::<String>();
 
+        let auth = gstreamer_rtsp_server::RTSPAuth::new();
+        // Clients without credentials receive the anonymous role, allowed in each
+        // factory without credentials
+        let mut token = gstreamer_rtsp_server::RTSPToken::new(&[(
+            *gstreamer_rtsp_server::RTSP_TOKEN_MEDIA_FACTORY_ROLE,
+            &ANONYMOUS_ROLE,
+        )]);
+        auth.set_default_token(Some(&mut token));
+
+        if let Err(error) = RTSPServer::configure_tls(&auth) {
+            error!("Failed to configure RTSP server TLS: {error}");
+        }
+
         RTSPServer {
-            server: gstreamer_rtsp_server::RTSPServer::new(),
+            servers: HashMap::new(),
             host: "0.0.0.0".into(),
-            port: 8554,
             run: is_running,
+            auth,
+            path_to_basic: HashMap::new(),
             path_to_factory: HashMap::new(),
             main_loop_thread: Some(thread::spawn(move || RTSPServer::run_main_loop(sender))),
             main_loop_thread_rx_channel: receiver,
         }
     }
 
+    // Loads the certificate defined by the command line, all connections will use TLS
+    fn configure_tls(auth: &gstreamer_rtsp_server::RTSPAuth) -> SimpleResult<()> {
+        let (certificate_path, key_path) = match crate::cli::manager::rtsp_tls_files() {
+            Some(files) => files,
+            None => return Ok(()),
+        };
+
+        let certificate =
+            gstreamer_rtsp_server::gio::TlsCertificate::from_files(certificate_path, key_path)
+                .map_err(|error| {
+                    simple_error!(format!("Failed to load {certificate_path:?}: {error}"))
+                })?;
+        auth.set_tls_certificate(Some(&certificate));
+
+        Ok(())
+    }
+
     pub fn is_running() -> bool {
         RTSP_SERVER.as_ref().lock().unwrap().run
     }
@@ -63,80 +109,210 @@ impl RTSPServer {
                 continue;
             }
 
-            let mut rtsp_server = RTSP_SERVER.as_ref().lock().unwrap();
-
-            // Attach the server to our main context.
-            // A main context is the thing where other stuff is registering itself for its
-            // events (e.g. sockets, GStreamer bus, ...) and the main loop is something that
-            // polls the main context for its events and dispatches them to whoever is
-            // interested in them. In this example, we only do have one, so we can
-            // leave the context parameter empty, it will automatically select
-            // the default one.
-            let id = rtsp_server.server.attach(None).unwrap();
-
-            // Start the mainloop. From this point on, the server will start to serve
+            // The servers are attached to the default main context when created, see `server`.
+            // Start the mainloop. From this point on, the servers will start to serve
             // our quality content to connecting clients.
             let main_loop = glib::MainLoop::new(None, false);
-            rtsp_server.run = true;
-            drop(rtsp_server);
-
             main_loop.run();
 
             let mut rtsp_server = RTSP_SERVER.as_ref().lock().unwrap();
             rtsp_server.run = false;
+        }
+    }
 
-            id.remove();
+    // Return the server for the given port, creating it if needed
+    fn server(&mut self, port: u16) -> SimpleResult<gstreamer_rtsp_server::RTSPServer> {
+        if let Some(server) = self.servers.get(&port) {
+            return Ok(server.clone());
         }
+
+        let server = gstreamer_rtsp_server::RTSPServer::new();
+        server.set_address(&self.host);
+        server.set_service(&port.to_string());
+        server.set_auth(Some(&self.auth));
+
+        // Attach the server to our main context.
+        // A main context is the thing where other stuff is registering itself for its
+        // events (e.g. sockets, GStreamer bus, ...) and the main loop is something that
+        // polls the main context for its events and dispatches them to whoever is
+        // interested in them. In this example, we only do have one, so we can
+        // leave the context parameter empty, it will automatically select
+        // the default one.
+        server.attach(None).map_err(|error| {
+            simple_error!(format!(
+                "Failed to start RTSP server on port {port}: {error}"
+            ))
+        })?;
+
+        self.servers.insert(port, server.clone());
+        Ok(server)
     }
 
-    pub fn add_pipeline(pipeline_description: &str, path: &str) -> SimpleResult<()> {
+    // Returns the flag set when the camera of the media runs out of USB bandwidth
+    pub fn add_pipeline(
+        pipeline: &Pipeline,
+        port: u16,
+        path: &str,
+        extended_configuration: Option<&ExtendedConfiguration>,
+    ) -> SimpleResult<Arc<AtomicBool>> {
         // Initialize the singleton before calling gstreamer factory
         let mut rtsp_server = RTSP_SERVER.as_ref().lock().unwrap();
 
         let factory = gstreamer_rtsp_server::RTSPMediaFactory::new();
-        factory.set_launch(&pipeline_description);
+        factory.set_launch(&pipeline.description);
         factory.set_shared(true);
 
-        match rtsp_server
-            .path_to_factory
-            .insert(path.to_string(), factory)
+        if let Some(latency_profile) =
+            extended_configuration.and_then(|configuration| configuration.latency_profile.as_ref())
         {
-            Some(server) => Err(simple_error!(format!("Error: required path already exists! The older was updated with the new configurations: {server:#?}"))),
-            None => Ok(())
+            factory.set_latency(latency_profile.settings().jitterbuffer_latency_ms);
         }
-    }
 
-    fn configure(host: &str, port: u16) {
-        let mut rtsp_server = RTSP_SERVER.as_ref().lock().unwrap();
-        if rtsp_server.run {
-            return;
+        if let Some((stream_name, configuration)) = pipeline.osd.clone() {
+            factory.connect_media_configure(move |_factory, media| {
+                if let Some(element) = media.element() {
+                    osd::track(&stream_name, &configuration, &element);
+                }
+            });
         }
-        rtsp_server.host = host.to_string();
-        rtsp_server.port = port;
 
-        rtsp_server.server.set_address(&rtsp_server.host);
+        let usb_bandwidth_exhausted = Arc::new(AtomicBool::new(false));
+        let media_usb_bandwidth_exhausted = usb_bandwidth_exhausted.clone();
+        factory.connect_media_configure(move |_factory, media| {
+            if let Some(element) = media.element() {
+                thermal::track(&element);
+                track_bus(&element, media_usb_bandwidth_exhausted.clone());
+            }
+        });
+
+        let multicast =
+            extended_configuration.and_then(|configuration| configuration.rtsp_multicast.as_ref());
+        if let Some(multicast) = multicast {
+            let address: std::net::IpAddr = multicast.address.parse().map_err(|error| {
+                simple_error!(format!(
+                    "Invalid multicast address {:#?}: {error}",
+                    multicast.address
+                ))
+            })?;
+            if !address.is_multicast() {
+                return Err(simple_error!(format!(
+                    "Address {address} is not a multicast address."
+                )));
+            }
+            if multicast.port_min > multicast.port_max {
+                return Err(simple_error!(format!(
+                    "Invalid multicast port range: {} to {}.",
+                    multicast.port_min, multicast.port_max
+                )));
+            }
 
-        rtsp_server
-            .server
-            .set_service(&rtsp_server.port.to_string());
-    }
+            let pool = gstreamer_rtsp_server::RTSPAddressPool::new();
+            pool.add_range(
+                &multicast.address,
+                &multicast.address,
+                multicast.port_min,
+                multicast.port_max,
+                multicast.ttl,
+            )
+            .map_err(|error| simple_error!(format!("Failed to create address pool: {error}")))?;
+            factory.set_address_pool(Some(&pool));
+            // Only multicast is allowed, otherwise clients would negotiate unicast by default
+            factory.set_protocols(gstreamer_rtsp_server::gst_rtsp::RTSPLowerTrans::UDP_MCAST);
+        }
+
+        if let Some(srtp) =
+            extended_configuration.and_then(|configuration| configuration.srtp.as_ref())
+        {
+            let key = Pipeline::parse_srtp_key(srtp)?;
+            let cipher = srtp.cipher.as_str();
+            // Only secure profiles are offered, the key is sent to each client in the SDP (MIKEY)
+            factory.set_profiles(
+                gstreamer_rtsp_server::gst_rtsp::RTSPProfile::SAVP
+                    | gstreamer_rtsp_server::gst_rtsp::RTSPProfile::SAVPF,
+            );
+            factory.connect_media_configure(move |_factory, media| {
+                for index in 0..media.n_streams() {
+                    let stream = match media.stream(index) {
+                        Some(stream) => stream,
+                        None => continue,
+                    };
+                    let key = key.clone();
+                    // The server creates its encoders with a random key, replaced by the configured one
+                    stream.connect_new_rtp_encoder(move |_stream, encoder| {
+                        encoder.set_property("random-key", false);
+                        encoder.set_property("key", gstreamer::Buffer::from_slice(key.clone()));
+                        encoder.set_property_from_str("rtp-cipher", cipher);
+                        encoder.set_property_from_str("rtcp-cipher", cipher);
+                    });
+                }
+            });
+        }
 
-    pub fn start_pipeline(path: &str) {
-        RTSPServer::configure("0.0.0.0".into(), 8554);
+        // With the server authentication, each factory needs explicit permissions
+        let credentials =
+            extended_configuration.and_then(|configuration| configuration.rtsp_auth.as_ref());
+        let role = match credentials {
+            Some(credentials) => {
+                if credentials.username.is_empty() || credentials.username.contains(':') {
+                    return Err(simple_error!(
+                        "RTSP username should not be empty or contain \":\"."
+                    ));
+                }
+
+                let basic = gstreamer_rtsp_server::RTSPAuth::make_basic(
+                    &credentials.username,
+                    &credentials.password,
+                )
+                .to_string();
+                // Each pair of credentials has its own role
+                let role = format!("user_{basic}");
+                let token = gstreamer_rtsp_server::RTSPToken::new(&[(
+                    *gstreamer_rtsp_server::RTSP_TOKEN_MEDIA_FACTORY_ROLE,
+                    &role,
+                )]);
+                rtsp_server.auth.add_basic(&basic, &token);
+                rtsp_server
+                    .path_to_basic
+                    .insert((port, path.to_string()), basic);
+                role
+            }
+            None => ANONYMOUS_ROLE.to_string(),
+        };
+        factory.add_role_from_structure(
+            &gstreamer::Structure::builder(&role)
+                .field(*gstreamer_rtsp_server::RTSP_PERM_MEDIA_FACTORY_ACCESS, true)
+                .field(
+                    *gstreamer_rtsp_server::RTSP_PERM_MEDIA_FACTORY_CONSTRUCT,
+                    true,
+                )
+                .build(),
+        );
 
+        match rtsp_server
+            .path_to_factory
+            .insert((port, path.to_string()), factory)
+        {
+            Some(server) => Err(simple_error!(format!("Error: required path already exists! The older was updated with the new configurations: {server:#?}"))),
+            None => Ok(usb_bandwidth_exhausted)
+        }
+    }
+
+    pub fn start_pipeline(port: u16, path: &str) -> SimpleResult<()> {
         let mut rtsp_server = RTSP_SERVER.as_ref().lock().unwrap();
+        let server = rtsp_server.server(port)?;
 
         // Much like HTTP servers, RTSP servers have multiple endpoints that
         // provide different streams. Here, we ask our server to give
         // us a reference to his list of endpoints, so we can add our
         // test endpoint, providing the pipeline from the cli.
-        let mounts = rtsp_server
-            .server
+        let mounts = server
             .mount_points()
-            .ok_or("Could not get mount points")
-            .unwrap();
+            .ok_or_else(|| simple_error!("Could not get mount points"))?;
 
-        let factory = rtsp_server.path_to_factory.get(path).unwrap();
+        let factory = rtsp_server
+            .path_to_factory
+            .get(&(port, path.to_string()))
+            .ok_or_else(|| simple_error!(format!("No pipeline for path {path:?}")))?;
 
         // Now we add a new mount-point and tell the RTSP server to serve the content
         // provided by the factory we configured above, when a client connects to
@@ -144,25 +320,59 @@ impl RTSPServer {
         mounts.add_factory(path, factory);
 
         rtsp_server.run = true; // start the main loop thread
+        Ok(())
     }
 
-    pub fn stop_pipeline(path: &str) {
+    pub fn stop_pipeline(port: u16, path: &str) {
         let mut rtsp_server = RTSP_SERVER.as_ref().lock().unwrap();
 
-        // Much like HTTP servers, RTSP servers have multiple endpoints that
-        // provide different streams. Here, we ask our server to give
-        // us a reference to his list of endpoints, so we can add our
-        // test endpoint, providing the pipeline from the cli.
-        let mounts = rtsp_server
-            .server
-            .mount_points()
-            .ok_or("Could not get mount points")
-            .unwrap();
-
-        mounts.remove_factory(path);
+        if let Some(mounts) = rtsp_server
+            .servers
+            .get(&port)
+            .and_then(|server| server.mount_points())
+        {
+            mounts.remove_factory(path);
+        }
 
-        rtsp_server.path_to_factory.remove(path);
+        let key = (port, path.to_string());
+        rtsp_server.path_to_factory.remove(&key);
+
+        if let Some(basic) = rtsp_server.path_to_basic.remove(&key) {
+            // The same credentials may be used by other paths
+            if !rtsp_server
+                .path_to_basic
+                .values()
+                .any(|other| other == &basic)
+            {
+                rtsp_server.auth.remove_basic(&basic);
+            }
+        }
         // TODO: call mainloop.quit() to stop the server if there is no endpoints
         // if rtsp_server.path_to_factory.is_empty() {...}
     }
 }
+
+// The media pipelines are run by the server, so their errors are only seen by a sync handler of their bus.
+// Video files are looped by the same handler, since each bus can only have one.
+fn track_bus(element: &gstreamer::Element, usb_bandwidth_exhausted: Arc<AtomicBool>) {
+    let pipeline = file_loop::top_pipeline(element);
+    let bus = match pipeline.bus() {
+        Some(bus) => bus,
+        None => return,
+    };
+
+    let has_file_source = file_loop::has_file_source(element);
+    let pipeline_weak = pipeline.downgrade();
+    bus.set_sync_handler(move |_bus, message| {
+        if let gstreamer::MessageView::Error(error) = message.view() {
+            if pipeline_runner::is_usb_bandwidth_error(&error) {
+                usb_bandwidth_exhausted.store(true, Ordering::Relaxed);
+            }
+        }
+
+        if has_file_source {
+            return file_loop::seek_on_end(&pipeline_weak, message);
+        }
+        gstreamer::BusSyncReply::Pass
+    });
+}
@@ -0,0 +1,89 @@
+use super::captures;
+use super::frames::{self, Frame};
+
+use futures::{FutureExt, StreamExt};
+use gstreamer::prelude::*;
+use simple_error::{simple_error, SimpleResult};
+use tracing::*;
+
+use std::time::{Duration, Instant};
+
+// Time to receive a frame, the tap needs a keyframe before it can decode the stream
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Encodes the next frame of a running stream as JPEG
+pub fn capture_jpeg(stream_name: &str) -> SimpleResult<Vec<u8>> {
+    let mut frames = frames::subscribe(stream_name)?;
+
+    // Called from threads without an async runtime, like the MAVLink ones
+    let deadline = Instant::now() + CAPTURE_TIMEOUT;
+    while Instant::now() < deadline {
+        match frames.next().now_or_never() {
+            Some(Some(frame)) => return encode_jpeg(frame),
+            Some(None) => break,
+            None => std::thread::sleep(Duration::from_millis(20)),
+        }
+    }
+
+    Err(simple_error!(format!(
+        "Failed to receive a frame from stream {stream_name:?}."
+    )))
+}
+
+// The frame is pushed to a pipeline of its own, since the tap doesn't encode it
+fn encode_jpeg(frame: Frame) -> SimpleResult<Vec<u8>> {
+    let description = format!(
+        "appsrc name=frame_source caps=video/x-raw,format=RGBA,width={},height={},framerate=0/1 ! videoconvert ! jpegenc ! appsink name=jpeg_sink sync=false",
+        frame.width, frame.height
+    );
+    let pipeline = gstreamer::parse_launch(&description)
+        .map_err(|error| simple_error!(format!("Failed to create JPEG pipeline: {error}")))?;
+    let bin = pipeline
+        .downcast_ref::<gstreamer::Bin>()
+        .ok_or_else(|| simple_error!("JPEG pipeline is not a bin."))?;
+    let (source, sink) = match (bin.by_name("frame_source"), bin.by_name("jpeg_sink")) {
+        (Some(source), Some(sink)) => (source, sink),
+        _ => return Err(simple_error!("Failed to find the JPEG pipeline elements.")),
+    };
+
+    pipeline
+        .set_state(gstreamer::State::Playing)
+        .map_err(|error| simple_error!(format!("Failed to start JPEG pipeline: {error}")))?;
+
+    let mut buffer = gstreamer::Buffer::from_slice(frame.data);
+    if let Some(buffer) = buffer.get_mut() {
+        buffer.set_pts(frame.pts.map(gstreamer::ClockTime::from_nseconds));
+    }
+    let pushed = source.emit_by_name::<gstreamer::FlowReturn>("push-buffer", &[&buffer]);
+    let _ = source.emit_by_name::<gstreamer::FlowReturn>("end-of-stream", &[]);
+
+    let timeout = CAPTURE_TIMEOUT.as_nanos() as u64;
+    let sample = match pushed {
+        gstreamer::FlowReturn::Ok => {
+            sink.emit_by_name::<Option<gstreamer::Sample>>("try-pull-sample", &[&timeout])
+        }
+        _ => None,
+    };
+    let _ = pipeline.set_state(gstreamer::State::Null);
+
+    let sample = sample.ok_or_else(|| simple_error!("Failed to encode the frame as JPEG."))?;
+    let buffer = sample
+        .buffer()
+        .ok_or_else(|| simple_error!("JPEG sample has no buffer."))?;
+    let map = buffer
+        .map_readable()
+        .map_err(|error| simple_error!(format!("Failed to read JPEG buffer: {error}")))?;
+    Ok(map.to_vec())
+}
+
+// Captures a JPEG of the stream to the capture path, returning the name of the file
+pub fn save_jpeg(stream_name: &str) -> SimpleResult<String> {
+    let jpeg = capture_jpeg(stream_name)?;
+
+    let (file_name, path) = captures::new_file(stream_name, "jpg")?;
+    std::fs::write(&path, jpeg)
+        .map_err(|error| simple_error!(format!("Failed to write {path:?}: {error}")))?;
+
+    info!("Saved snapshot of stream {stream_name:?} to {path:?}.");
+    Ok(file_name)
+}
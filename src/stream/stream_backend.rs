@@ -1,6 +1,11 @@
+use super::gst::pipeline_builder::Pipeline;
+use super::manager as stream_manager;
 use super::types::*;
+use super::video_stream_pipeline::VideoStreamPipeline;
 use super::video_stream_redirect::VideoStreamRedirect;
+#[cfg(feature = "rtsp")]
 use super::video_stream_rtsp::VideoStreamRtsp;
+use super::video_stream_tee::VideoStreamTee;
 use super::video_stream_udp::VideoStreamUdp;
 use crate::video::types::{VideoEncodeType, VideoSourceType};
 use crate::video_stream::types::VideoAndStreamInformation;
@@ -16,15 +21,58 @@ where
     fn restart(&mut self);
     fn pipeline(&self) -> String;
     fn allow_same_endpoints(&self) -> bool;
+    fn usb_bandwidth_exhausted(&self) -> bool;
+    fn udp_clients_statistics(&self) -> Vec<UdpClientStatistics>;
+    fn udp_send_errors(&self) -> u64;
+    // Latest errors, warnings and EOS posted by the pipeline
+    fn pipeline_messages(&self) -> Vec<PipelineMessage>;
 }
 
 pub fn new(
     video_and_stream_information: &VideoAndStreamInformation,
 ) -> Result<StreamType, SimpleError> {
+    check(video_and_stream_information)?;
+    return create_stream(video_and_stream_information);
+}
+
+// Run the same checks of "new" and build the pipeline, without creating the stream.
+// Redirect streams don't have a pipeline.
+pub fn validate(
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> Result<Option<Pipeline>, SimpleError> {
+    check(video_and_stream_information)?;
+
+    if let VideoSourceType::Redirect(_) = video_and_stream_information.video_source {
+        return Ok(None);
+    }
+
+    if video_and_stream_information.split_by_scheme().len() > 1 {
+        let has_rtsp = video_and_stream_information
+            .stream_information
+            .endpoints
+            .iter()
+            .any(|endpoint| matches!(endpoint.scheme(), "rtsp" | "rtsps"));
+        let rtsp_loopback_port = match has_rtsp {
+            true => Some(stream_manager::loopback_port()?),
+            false => None,
+        };
+        return Pipeline::new_tee(video_and_stream_information, rtsp_loopback_port).map(Some);
+    }
+
+    Pipeline::new(video_and_stream_information).map(Some)
+}
+
+fn check(video_and_stream_information: &VideoAndStreamInformation) -> Result<(), SimpleError> {
     check_endpoints(video_and_stream_information)?;
     check_encode(video_and_stream_information)?;
-    check_scheme(video_and_stream_information)?;
-    return create_stream(video_and_stream_information);
+    check_custom_pipeline(video_and_stream_information)?;
+    check_audio(video_and_stream_information)?;
+    // Each scheme has its own requirements, even when sharing the pipeline
+    for information in video_and_stream_information.split_by_scheme() {
+        check_features(&information)?;
+        check_scheme(&information)?;
+    }
+    return Ok(());
 }
 
 fn check_endpoints(
@@ -40,41 +88,362 @@ fn check_endpoints(
         .windows(2)
         .any(|win| win[0].scheme() != win[1].scheme());
 
-    // We don't allow different schemes
+    // Different schemes are served by the same pipeline, split by a tee
     if endpoints_have_different_schemes {
+        let is_redirect = matches!(
+            video_and_stream_information.video_source,
+            VideoSourceType::Redirect(_)
+        );
+        let all_supported = endpoints.iter().all(|endpoint| {
+            matches!(
+                endpoint.scheme(),
+                "udp" | "rtsp" | "rtsps" | "srt" | "rtmp" | "mpegts" | "tcp" | "file"
+            )
+        });
+        if is_redirect || !all_supported {
+            return Err(simple_error!(format!(
+                "Endpoints with different schemes should be \"udp\", \"rtsp\", \"rtsps\", \"srt\", \"rtmp\", \"mpegts\", \"tcp\" or \"file\", and can't be used by REDIRECT sources: {endpoints:#?}"
+            )));
+        }
+
+        let has_scheme = |scheme| endpoints.iter().any(|endpoint| endpoint.scheme() == scheme);
+        if has_scheme("udp") && has_scheme("mpegts") {
+            return Err(simple_error!(
+                "Endpoints with udp and mpegts schemes can't be used by the same stream."
+            ));
+        }
+    }
+
+    if let Some(fallback_endpoints) = video_and_stream_information
+        .stream_information
+        .extended_configuration
+        .as_ref()
+        .and_then(|configuration| configuration.fallback_endpoints.as_ref())
+    {
+        let all_udp = endpoints
+            .iter()
+            .chain(fallback_endpoints.iter())
+            .all(|endpoint| {
+                endpoint.scheme() == "udp" && endpoint.host().is_some() && endpoint.port().is_some()
+            });
+        if !all_udp {
+            return Err(simple_error!(format!(
+                "Fallback endpoints are only supported by UDP streams, and should contain host and port: {fallback_endpoints:#?}"
+            )));
+        }
+    }
+
+    return Ok(());
+}
+
+// Custom pipelines are only accepted if GStreamer can create them
+fn check_custom_pipeline(
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> Result<(), SimpleError> {
+    let has_custom_pipeline = video_and_stream_information
+        .stream_information
+        .extended_configuration
+        .as_ref()
+        .and_then(|configuration| configuration.custom_pipeline.as_ref())
+        .is_some();
+    if !has_custom_pipeline {
+        return Ok(());
+    }
+
+    if video_and_stream_information.split_by_scheme().len() > 1 {
+        return Err(simple_error!(
+            "Custom pipelines can't be used by endpoints with different schemes."
+        ));
+    }
+
+    if let VideoSourceType::Redirect(_) = video_and_stream_information.video_source {
+        return Err(simple_error!(
+            "Custom pipelines can't be used by REDIRECT sources."
+        ));
+    }
+
+    let pipeline = Pipeline::new(video_and_stream_information)?;
+
+    super::gst::utils::dry_run(&pipeline.description).map_err(|(error, missing_elements)| {
+        simple_error!(format!(
+            "Invalid custom pipeline {:#?}: {error}, missing elements: {missing_elements:?}",
+            pipeline.description
+        ))
+    })?;
+
+    return Ok(());
+}
+
+fn check_audio(
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> Result<(), SimpleError> {
+    let audio = match &video_and_stream_information.stream_information.audio {
+        Some(audio) => audio,
+        None => return Ok(()),
+    };
+
+    if video_and_stream_information.split_by_scheme().len() > 1 {
+        return Err(simple_error!(
+            "Audio can't be used by endpoints with different schemes."
+        ));
+    }
+
+    let scheme = video_and_stream_information.stream_information.endpoints[0].scheme();
+    if !matches!(scheme, "rtsp" | "rtsps" | "file") {
         return Err(simple_error!(format!(
-            "Endpoints scheme are not the same: {endpoints:#?}"
+            "Audio can't be used with the {scheme} scheme, only with RTSP and file endpoints."
+        )));
+    }
+
+    if !matches!(
+        video_and_stream_information
+            .stream_information
+            .configuration,
+        CaptureConfiguration::VIDEO(_)
+    ) {
+        return Err(simple_error!(
+            "Audio can only be added to video configurations."
+        ));
+    }
+
+    if audio.device.is_empty() || audio.sample_rate == 0 || audio.channels == 0 {
+        return Err(simple_error!(format!(
+            "Invalid audio configuration: {audio:?}"
         )));
     }
 
     return Ok(());
 }
 
+fn check_features(
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> Result<(), SimpleError> {
+    // Redirect streams only advertise the endpoints, nothing is served by us
+    if let VideoSourceType::Redirect(_) = video_and_stream_information.video_source {
+        return Ok(());
+    }
+
+    let scheme = video_and_stream_information
+        .stream_information
+        .endpoints
+        .first()
+        .unwrap()
+        .scheme();
+
+    let missing_feature = match scheme {
+        "rtsp" | "rtsps" if !cfg!(feature = "rtsp") => Some("rtsp"),
+        "ndi" if !cfg!(feature = "ndi") => Some("ndi"),
+        "file" if !cfg!(feature = "recording") => Some("recording"),
+        "webrtc" if !cfg!(feature = "webrtc") => Some("webrtc"),
+        _ => None,
+    };
+
+    if let Some(feature) = missing_feature {
+        return Err(simple_error!(format!(
+            "Endpoint with {scheme} scheme is not available: built without feature {feature:?}."
+        )));
+    }
+
+    // The field of view is only reported to the GCS
+    let has_fov = video_and_stream_information
+        .stream_information
+        .extended_configuration
+        .as_ref()
+        .map_or(false, |configuration| configuration.fov.is_some());
+    if has_fov && !cfg!(feature = "mavlink") {
+        return Err(simple_error!(
+            "Field of view is not available: built without feature \"mavlink\"."
+        ));
+    }
+
+    return Ok(());
+}
+
 fn check_encode(
     video_and_stream_information: &VideoAndStreamInformation,
 ) -> Result<(), SimpleError> {
-    let encode = match &video_and_stream_information
+    let is_audio_source = matches!(
+        video_and_stream_information.video_source,
+        VideoSourceType::Audio(_)
+    );
+
+    let configuration = match &video_and_stream_information
         .stream_information
         .configuration
     {
-        CaptureConfiguration::VIDEO(configuration) => configuration.encode.clone(),
+        CaptureConfiguration::VIDEO(configuration) => configuration,
         CaptureConfiguration::REDIRECT(_) => return Ok(()),
+        CaptureConfiguration::AUDIO(_) => {
+            if !is_audio_source {
+                return Err(simple_error!(
+                    "Audio configuration can only be used with audio sources"
+                ));
+            }
+            return Ok(());
+        }
     };
 
+    if is_audio_source {
+        return Err(simple_error!(
+            "Audio sources can only be used with audio configurations"
+        ));
+    }
+
+    let encode = configuration.encode.clone();
+    let transcode = configuration.transcode.clone();
+
     match &encode {
         VideoEncodeType::UNKNOWN(name) => {
             return Err(simple_error!(format!(
                 "Encode is not supported and also unknown: {name}",
             )))
         }
-        VideoEncodeType::H264 | VideoEncodeType::YUYV | VideoEncodeType::MJPG => (),
-        _ => {
+        VideoEncodeType::H264
+        | VideoEncodeType::H265
+        | VideoEncodeType::YUYV
+        | VideoEncodeType::MJPG => (),
+        // Thermal and Bayer frames are converted to colors and encoded by us
+        VideoEncodeType::Y16 | VideoEncodeType::Bayer(_) => {
+            if transcode.is_none() {
+                return Err(simple_error!(format!(
+                    "{encode:?} captures should be transcoded, e.g: to H264."
+                )));
+            }
+        }
+        VideoEncodeType::VP8 | VideoEncodeType::VP9 | VideoEncodeType::AV1 => {
             return Err(simple_error!(format!(
-                "Only H264, YUYV and MJPG encodes are supported now, used: {encode:?}",
-            )));
+                "{encode:?} can only be used as transcode, from YUYV or MJPG captures."
+            )))
         }
     };
 
+    // IP cameras are received as RTP, without raw payloads
+    if let VideoSourceType::Ip(ip_source) = &video_and_stream_information.video_source {
+        if ip_source.is_http() && encode != VideoEncodeType::MJPG {
+            return Err(simple_error!(format!(
+                "HTTP cameras only provide MJPG, not {encode:?}."
+            )));
+        }
+        if matches!(
+            encode,
+            VideoEncodeType::YUYV | VideoEncodeType::Y16 | VideoEncodeType::Bayer(_)
+        ) {
+            return Err(simple_error!(format!(
+                "IP cameras should provide H264, H265 or MJPG, not {encode:?}."
+            )));
+        }
+    }
+
+    if let Some(rotation) = configuration.rotation {
+        if ![0, 90, 180, 270].contains(&rotation) {
+            return Err(simple_error!(format!(
+                "Rotation should be 0, 90, 180 or 270 degrees, got: {rotation}"
+            )));
+        }
+    }
+
+    if let Some(jpeg_quality) = configuration.jpeg_quality {
+        if !(1..=100).contains(&jpeg_quality) {
+            return Err(simple_error!(format!(
+                "JPEG quality should be from 1 to 100, got: {jpeg_quality}"
+            )));
+        }
+    }
+
+    // Encoded frames can't be rotated or flipped, unless the camera does it by itself
+    let has_raw_frames = !video_and_stream_information.video_source.is_camera()
+        || matches!(
+            (&encode, &transcode),
+            (VideoEncodeType::YUYV, _)
+                | (VideoEncodeType::Y16, _)
+                | (VideoEncodeType::Bayer(_), _)
+                | (VideoEncodeType::MJPG, Some(_))
+        );
+    if let Some(crop) = &configuration.crop {
+        // Overflowing sums are outside of any frame
+        let is_inside = crop
+            .left
+            .checked_add(crop.width)
+            .map_or(false, |right| right <= configuration.width)
+            && crop
+                .top
+                .checked_add(crop.height)
+                .map_or(false, |bottom| bottom <= configuration.height);
+        if crop.width == 0 || crop.height == 0 || !is_inside {
+            return Err(simple_error!(format!(
+                "Crop {crop:?} should be inside the captured {}x{} frames.",
+                configuration.width, configuration.height
+            )));
+        }
+    }
+
+    if let Some(size) = &configuration.output_size {
+        if size.width == 0 || size.height == 0 {
+            return Err(simple_error!(format!(
+                "Output size should not be empty, got: {size:?}"
+            )));
+        }
+    }
+
+    let has_osd = video_and_stream_information
+        .stream_information
+        .extended_configuration
+        .as_ref()
+        .and_then(|configuration| configuration.osd.as_ref())
+        .is_some();
+    if has_osd && !has_raw_frames {
+        return Err(simple_error!(format!(
+            "OSD over {encode:?} captures requires a transcode."
+        )));
+    }
+
+    if configuration.deinterlace == Some(true) && !has_raw_frames {
+        return Err(simple_error!(format!(
+            "Deinterlacing {encode:?} captures requires a transcode."
+        )));
+    }
+
+    if (configuration.crop.is_some() || configuration.output_size.is_some()) && !has_raw_frames {
+        return Err(simple_error!(format!(
+            "Crop and output size of {encode:?} captures requires a transcode."
+        )));
+    }
+
+    if configuration.video_direction().is_some()
+        && !has_raw_frames
+        && stream_manager::camera_flips(video_and_stream_information).is_none()
+    {
+        return Err(simple_error!(format!(
+            "Rotation and flip of {encode:?} captures requires a transcode, or a camera with flip controls."
+        )));
+    }
+
+    if let Some(transcode) = transcode {
+        // Only uncompressed or JPEG frames are cheap enough to be decoded and encoded again.
+        // MJPG can be encoded again as MJPG, to lower its quality.
+        let is_transcodable = matches!(
+            encode,
+            VideoEncodeType::YUYV
+                | VideoEncodeType::Y16
+                | VideoEncodeType::Bayer(_)
+                | VideoEncodeType::MJPG
+        );
+        let is_encodable = matches!(
+            transcode,
+            VideoEncodeType::H264
+                | VideoEncodeType::H265
+                | VideoEncodeType::MJPG
+                | VideoEncodeType::VP8
+                | VideoEncodeType::VP9
+                | VideoEncodeType::AV1
+        );
+        if !is_transcodable || !is_encodable {
+            return Err(simple_error!(format!(
+                "Only YUYV, Y16, Bayer and MJPG can be transcoded, and only to H264, H265, MJPG, VP8, VP9 or AV1. Encode: {encode:?}, transcode: {transcode:?}"
+            )));
+        }
+    }
+
     return Ok(());
 }
 
@@ -86,32 +455,49 @@ fn check_scheme(
         .stream_information
         .configuration
     {
-        CaptureConfiguration::VIDEO(configuration) => configuration.encode.clone(),
-        CaptureConfiguration::REDIRECT(_) => VideoEncodeType::UNKNOWN("".into()),
+        CaptureConfiguration::VIDEO(configuration) => configuration.stream_encode().clone(),
+        CaptureConfiguration::REDIRECT(_) | CaptureConfiguration::AUDIO(_) => {
+            VideoEncodeType::UNKNOWN("".into())
+        }
     };
     let scheme = endpoints.first().unwrap().scheme();
 
     if let VideoSourceType::Redirect(_) = video_and_stream_information.video_source {
         match scheme {
-            "udp" | "udp265"| "rtsp" | "mpegts" | "tcp" => scheme.to_string(),
+            "udp" | "udp265"| "rtsp" | "rtsps" | "mpegts" | "tcp" => scheme.to_string(),
             _ => return Err(simple_error!(format!(
-                "The URL's scheme for REDIRECT endpoints should be \"udp\", \"udp265\", \"rtsp\", \"mpegts\" or \"tcp\", but was: {scheme:?}",
+                "The URL's scheme for REDIRECT endpoints should be \"udp\", \"udp265\", \"rtsp\", \"rtsps\", \"mpegts\" or \"tcp\", but was: {scheme:?}",
             )))
         };
     } else {
         match scheme {
-            "rtsp" => {
+            "rtsp" | "rtsps" => {
                 if endpoints.len() > 1 {
                     return Err(simple_error!(format!(
                         "Multiple RTSP endpoints are not acceptable: {endpoints:#?}"
                     )));
                 }
+
+                // The same RTSP server serves all streams, with or without TLS
+                let is_tls_enabled = crate::cli::manager::rtsp_tls_files().is_some();
+                if is_tls_enabled && scheme == "rtsp" {
+                    return Err(simple_error!(
+                        "RTSP server is using TLS, endpoints should use the rtsps scheme."
+                    ));
+                }
+                if !is_tls_enabled && scheme == "rtsps" {
+                    return Err(simple_error!("Endpoint with rtsps scheme requires the RTSP server TLS certificate and key, see --rtsp-tls-certificate and --rtsp-tls-key."));
+                }
             }
-            "udp" => {
-                if VideoEncodeType::H265 == encode {
+            "udp" | "udp265" => {
+                if scheme == "udp" && VideoEncodeType::H265 == encode {
                     return Err(simple_error!("Endpoint with udp scheme only supports H264, encode type is H265, the scheme should be udp265."));
                 }
 
+                if scheme == "udp265" && VideoEncodeType::H265 != encode {
+                    return Err(simple_error!(format!("Endpoint with udp265 scheme only supports H265 encode. Encode: {encode:?}, Endpoints: {endpoints:#?}")));
+                }
+
                 //UDP endpoints should contain both host and port
                 let no_host_or_port = endpoints
                     .iter()
@@ -119,13 +505,134 @@ fn check_scheme(
 
                 if no_host_or_port {
                     return Err(simple_error!(format!(
-                        "Endpoint with udp scheme should contain host and port. Endpoints: {endpoints:#?}"
+                        "Endpoint with {scheme} scheme should contain host and port. Endpoints: {endpoints:#?}"
                     )));
                 }
             }
-            "udp265" => {
-                if VideoEncodeType::H265 != encode {
-                    return Err(simple_error!(format!("Endpoint with udp265 scheme only supports H265 encode. Encode: {encode:?}, Endpoints: {endpoints:#?}")));
+            "ndi" => {
+                if endpoints.len() > 1 {
+                    return Err(simple_error!(format!(
+                        "Multiple NDI endpoints are not acceptable: {endpoints:#?}"
+                    )));
+                }
+
+                if let CaptureConfiguration::AUDIO(_) = &video_and_stream_information
+                    .stream_information
+                    .configuration
+                {
+                    return Err(simple_error!(
+                        "Endpoint with ndi scheme only supports video."
+                    ));
+                }
+            }
+            "srt" => {
+                if endpoints.len() > 1 {
+                    return Err(simple_error!(format!(
+                        "Multiple SRT endpoints are not acceptable: {endpoints:#?}"
+                    )));
+                }
+
+                if endpoints[0].port().is_none() {
+                    return Err(simple_error!(format!(
+                        "Endpoint with srt scheme should contain a port. Endpoints: {endpoints:#?}"
+                    )));
+                }
+
+                if VideoEncodeType::H264 != encode {
+                    return Err(simple_error!(format!(
+                        "Endpoint with srt scheme only supports H264 encode. Encode: {encode:?}"
+                    )));
+                }
+
+                let passphrase = video_and_stream_information
+                    .stream_information
+                    .extended_configuration
+                    .as_ref()
+                    .and_then(|configuration| configuration.srt.as_ref())
+                    .and_then(|srt| srt.passphrase.as_ref());
+                if let Some(passphrase) = passphrase {
+                    if !(10..=79).contains(&passphrase.len()) {
+                        return Err(simple_error!(
+                            "SRT passphrase should have from 10 to 79 characters."
+                        ));
+                    }
+                }
+            }
+            "mpegts" => {
+                let no_host_or_port = endpoints
+                    .iter()
+                    .any(|endpoint| endpoint.host().is_none() || endpoint.port().is_none());
+
+                if no_host_or_port {
+                    return Err(simple_error!(format!(
+                        "Endpoint with mpegts scheme should contain host and port. Endpoints: {endpoints:#?}"
+                    )));
+                }
+
+                if VideoEncodeType::H264 != encode {
+                    return Err(simple_error!(format!(
+                        "Endpoint with mpegts scheme only supports H264 encode. Encode: {encode:?}"
+                    )));
+                }
+            }
+            "tcp" => {
+                if endpoints.len() > 1 {
+                    return Err(simple_error!(format!(
+                        "Multiple TCP endpoints are not acceptable: {endpoints:#?}"
+                    )));
+                }
+
+                if endpoints[0].host().is_none() || endpoints[0].port().is_none() {
+                    return Err(simple_error!(format!(
+                        "Endpoint with tcp scheme should contain host and port. Endpoints: {endpoints:#?}"
+                    )));
+                }
+
+                if VideoEncodeType::H264 != encode {
+                    return Err(simple_error!(format!(
+                        "Endpoint with tcp scheme only supports H264 encode. Encode: {encode:?}"
+                    )));
+                }
+            }
+            "file" => {
+                if endpoints.len() > 1 {
+                    return Err(simple_error!(format!(
+                        "Multiple file endpoints are not acceptable: {endpoints:#?}"
+                    )));
+                }
+
+                // The path should be absolute, e.g: "file:///home/pi/video.mkv"
+                let path = std::path::Path::new(endpoints[0].path());
+                let has_directory = path.parent().map_or(false, |directory| directory.is_dir());
+                if endpoints[0].has_host() || !has_directory || path.file_name().is_none() {
+                    return Err(simple_error!(format!(
+                        "Endpoint with file scheme should contain the path of a file in an existing directory. Endpoints: {endpoints:#?}"
+                    )));
+                }
+
+                if VideoEncodeType::H264 != encode {
+                    return Err(simple_error!(format!(
+                        "Endpoint with file scheme only supports H264 encode. Encode: {encode:?}"
+                    )));
+                }
+            }
+            "rtmp" => {
+                if endpoints.len() > 1 {
+                    return Err(simple_error!(format!(
+                        "Multiple RTMP endpoints are not acceptable: {endpoints:#?}"
+                    )));
+                }
+
+                if endpoints[0].host().is_none() {
+                    return Err(simple_error!(format!(
+                        "Endpoint with rtmp scheme should contain the server host. Endpoints: {endpoints:#?}"
+                    )));
+                }
+
+                if VideoEncodeType::H264 != encode {
+                    return Err(simple_error!(format!(
+                        "Endpoint with rtmp scheme only supports H264 encode. Encode: {encode:?}"
+                    )));
                 }
             }
             _ => {
@@ -147,33 +654,68 @@ fn create_udp_stream(
     )?))
 }
 
+#[cfg(feature = "rtsp")]
 fn create_rtsp_stream(
     video_and_stream_information: &VideoAndStreamInformation,
 ) -> Result<StreamType, SimpleError> {
     let endpoints = &video_and_stream_information.stream_information.endpoints;
-    let endpoint = &endpoints[0];
-    if endpoint.scheme() != "rtsp" {
+    let (port, path) = rtsp_port_and_path(&endpoints[0])?;
+
+    Ok(StreamType::RTSP(VideoStreamRtsp::new(
+        video_and_stream_information,
+        port,
+        path,
+    )?))
+}
+
+fn rtsp_port_and_path(endpoint: &url::Url) -> Result<(u16, String), SimpleError> {
+    if !matches!(endpoint.scheme(), "rtsp" | "rtsps") {
         return Err(simple_error!(format!(
-            "The URL's scheme for RTSP endpoints should be \"rtsp\", but was: {:?}",
+            "The URL's scheme for RTSP endpoints should be \"rtsp\" or \"rtsps\", but was: {:?}",
             endpoint.scheme()
         )));
     }
-    if endpoint.port() != Some(8554) {
+    let port = endpoint.port().ok_or_else(|| {
+        simple_error!(format!(
+            "The URL for RTSP endpoints should contain a port, but was: {:?}",
+            endpoint.as_str()
+        ))
+    })?;
+    let has_empty_segment = endpoint.path_segments().map_or(true, |mut segments| {
+        segments.any(|segment| segment.is_empty())
+    });
+    if has_empty_segment {
         return Err(simple_error!(format!(
-            "The URL's port for RTSP endpoints should be \"8554\", but was: {:?}",
-            endpoint.port()
-        )));
-    }
-    if endpoint.path_segments().iter().count() != 1 {
-        return Err(simple_error!(format!(
-            "The URL's path for RTSP endpoints must have one segment (e.g.: \"segmentA\" and not \"segmentA/segmentB\"), but was: {:?}",
+            "The URL's path for RTSP endpoints must have non-empty segments (e.g.: \"segmentA\" or \"segmentA/segmentB\"), but was: {:?}",
             endpoint.path()
         )));
     }
 
-    Ok(StreamType::RTSP(VideoStreamRtsp::new(
+    Ok((port, endpoint.path().to_string()))
+}
+
+fn create_pipeline_stream(
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> Result<StreamType, SimpleError> {
+    Ok(StreamType::PIPELINE(VideoStreamPipeline::new(
+        video_and_stream_information,
+    )?))
+}
+
+fn create_tee_stream(
+    video_and_stream_information: &VideoAndStreamInformation,
+) -> Result<StreamType, SimpleError> {
+    let rtsp_endpoint = video_and_stream_information
+        .stream_information
+        .endpoints
+        .iter()
+        .find(|endpoint| matches!(endpoint.scheme(), "rtsp" | "rtsps"))
+        .map(rtsp_port_and_path)
+        .transpose()?;
+
+    Ok(StreamType::TEE(VideoStreamTee::new(
         video_and_stream_information,
-        endpoint.path().to_string(),
+        rtsp_endpoint,
     )?))
 }
 
@@ -183,7 +725,7 @@ fn create_redirect_stream(
     let endpoint = &video_and_stream_information.stream_information.endpoints[0];
 
     Ok(StreamType::REDIRECT(VideoStreamRedirect::new(
-        endpoint.scheme().to_string(),
+        endpoint.clone(),
     )?))
 }
 
@@ -193,6 +735,8 @@ fn create_stream(
     // The scheme was validated by "new" function
     if let VideoSourceType::Redirect(_) = video_and_stream_information.video_source {
         create_redirect_stream(video_and_stream_information)
+    } else if video_and_stream_information.split_by_scheme().len() > 1 {
+        create_tee_stream(video_and_stream_information)
     } else {
         let endpoint = &video_and_stream_information
             .stream_information
@@ -201,29 +745,42 @@ fn create_stream(
             .next()
             .unwrap();
         match endpoint.scheme() {
-            "udp" => create_udp_stream(video_and_stream_information),
-            "rtsp" => create_rtsp_stream(video_and_stream_information),
+            "udp" | "udp265" => create_udp_stream(video_and_stream_information),
+            #[cfg(feature = "rtsp")]
+            "rtsp" | "rtsps" => create_rtsp_stream(video_and_stream_information),
+            #[cfg(feature = "ndi")]
+            "ndi" => create_pipeline_stream(video_and_stream_information),
+            #[cfg(feature = "recording")]
+            "file" => create_pipeline_stream(video_and_stream_information),
+            "srt" | "rtmp" | "mpegts" | "tcp" => {
+                create_pipeline_stream(video_and_stream_information)
+            }
             something => Err(simple_error!(format!("Unsupported scheme: {something}"))),
         }
     }
 }
 
-#[cfg(test)]
+// The streams of the tests are from V4L2 cameras
+#[cfg(all(test, target_os = "linux"))]
 mod tests {
     use super::*;
     use crate::stream::types::CaptureConfiguration;
     use crate::video::{
         types::FrameInterval,
+        video_source_file::VideoSourceFile,
+        video_source_gst::{VideoSourceGst, VideoSourceGstType},
+        video_source_ip::VideoSourceIp,
         video_source_local::{VideoSourceLocal, VideoSourceLocalType},
     };
 
     use url::Url;
 
-    fn stream_type_fabricator(
+    fn video_and_stream_information_fabricator(
         stream_endpoints: &Vec<Url>,
         video_encode_type: &VideoEncodeType,
-    ) -> StreamType {
-        let stream = create_stream(&VideoAndStreamInformation {
+        extended_configuration: Option<ExtendedConfiguration>,
+    ) -> VideoAndStreamInformation {
+        VideoAndStreamInformation {
             name: "Test".into(),
             stream_information: StreamInformation {
                 endpoints: stream_endpoints.clone(),
@@ -235,15 +792,38 @@ mod tests {
                         numerator: 1,
                         denominator: 30,
                     },
+                    transcode: None,
+                    bitrate_kbps: None,
+                    keyframe_interval: None,
+                    jpeg_quality: None,
+                    deinterlace: None,
+                    rotation: None,
+                    flip: None,
+                    crop: None,
+                    output_size: None,
                 }),
-                extended_configuration: None,
+                extended_configuration,
+                audio: None,
             },
             video_source: VideoSourceType::Local(VideoSourceLocal {
                 name: "PotatoCam".into(),
                 device_path: "/dev/video42".into(),
                 typ: VideoSourceLocalType::Usb("TestPotatoCam".into()),
+                usb: None,
             }),
-        });
+        }
+    }
+
+    fn stream_type_fabricator(
+        stream_endpoints: &Vec<Url>,
+        video_encode_type: &VideoEncodeType,
+        extended_configuration: Option<ExtendedConfiguration>,
+    ) -> StreamType {
+        let stream = create_stream(&video_and_stream_information_fabricator(
+            stream_endpoints,
+            video_encode_type,
+            extended_configuration,
+        ));
 
         assert!(stream.is_ok());
         stream.unwrap()
@@ -252,15 +832,16 @@ mod tests {
     #[test]
     fn test_udp() {
         let pipeline_testing = vec![
-            (VideoEncodeType::H264, "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse ! queue ! rtph264pay name=pay0 config-interval=10 pt=96 ! multiudpsink clients=192.168.0.1:42"),
-            (VideoEncodeType::YUYV, "v4l2src device=/dev/video42 ! video/x-raw,format=YUY2,width=1280,height=720,framerate=30/1 ! videoconvert ! video/x-raw,format=UYVY ! rtpvrawpay name=pay0 ! application/x-rtp,payload=96,sampling=YCbCr-4:2:2 ! multiudpsink clients=192.168.0.1:42"),
-            (VideoEncodeType::MJPG, "v4l2src device=/dev/video42 ! image/jpeg,width=1280,height=720,framerate=30/1 ! rtpjpegpay name=pay0 pt=96 ! multiudpsink clients=192.168.0.1:42"),
+            (VideoEncodeType::H264, "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse ! queue ! rtph264pay name=pay0 config-interval=10 pt=96 ! rtpbin.send_rtp_sink_0 rtpbin name=rtpbin rtpbin.send_rtp_src_0 ! multiudpsink name=udp_sink clients=192.168.0.1:42 rtpbin.send_rtcp_src_0 ! multiudpsink name=rtcp_sink clients=192.168.0.1:43 sync=false async=false"),
+            (VideoEncodeType::YUYV, "v4l2src device=/dev/video42 ! video/x-raw,format=YUY2,width=1280,height=720,framerate=30/1 ! videoconvert ! video/x-raw,format=UYVY ! rtpvrawpay name=pay0 ! application/x-rtp,payload=96,sampling=YCbCr-4:2:2 ! rtpbin.send_rtp_sink_0 rtpbin name=rtpbin rtpbin.send_rtp_src_0 ! multiudpsink name=udp_sink clients=192.168.0.1:42 rtpbin.send_rtcp_src_0 ! multiudpsink name=rtcp_sink clients=192.168.0.1:43 sync=false async=false"),
+            (VideoEncodeType::MJPG, "v4l2src device=/dev/video42 ! image/jpeg,width=1280,height=720,framerate=30/1 ! rtpjpegpay name=pay0 pt=96 ! rtpbin.send_rtp_sink_0 rtpbin name=rtpbin rtpbin.send_rtp_src_0 ! multiudpsink name=udp_sink clients=192.168.0.1:42 rtpbin.send_rtcp_src_0 ! multiudpsink name=rtcp_sink clients=192.168.0.1:43 sync=false async=false"),
         ];
 
         for (encode_type, expected_pipeline) in pipeline_testing.iter() {
             let stream = stream_type_fabricator(
                 &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
                 encode_type,
+                None,
             );
             let pipeline = match &stream {
                 StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
@@ -270,10 +851,330 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_udp265() {
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("udp265://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::H265,
+            None,
+        );
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert_eq!(
+            &pipeline,
+            "v4l2src device=/dev/video42 ! video/x-h265,width=1280,height=720,framerate=30/1 ! h265parse ! queue ! rtph265pay name=pay0 config-interval=10 pt=96 ! rtpbin.send_rtp_sink_0 rtpbin name=rtpbin rtpbin.send_rtp_src_0 ! multiudpsink name=udp_sink clients=192.168.0.1:42 rtpbin.send_rtcp_src_0 ! multiudpsink name=rtcp_sink clients=192.168.0.1:43 sync=false async=false"
+        );
+    }
+
+    #[test]
+    fn test_transcode() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::MJPG,
+            // Hardware encoders depend on the host running the tests
+            Some(ExtendedConfiguration {
+                encoder_preference: Some(EncoderPreference::Software),
+                ..Default::default()
+            }),
+        );
+        if let CaptureConfiguration::VIDEO(configuration) = &mut video_and_stream_information
+            .stream_information
+            .configuration
+        {
+            configuration.transcode = Some(VideoEncodeType::H264);
+        }
+
+        let stream = create_stream(&video_and_stream_information).unwrap();
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "v4l2src device=/dev/video42 ! image/jpeg,width=1280,height=720,framerate=30/1 ! jpegdec ! videoconvert ! x264enc bitrate=5000 tune=zerolatency key-int-max=30 ! video/x-h264,profile=baseline ! h264parse ! queue ! rtph264pay name=pay0"
+        ));
+    }
+
+    #[test]
+    fn test_ip_source() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::H264,
+            None,
+        );
+        video_and_stream_information.video_source =
+            VideoSourceType::Ip(VideoSourceIp::from_url("rtsp://192.168.2.10:554/stream").unwrap());
+
+        let stream = create_stream(&video_and_stream_information).unwrap();
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "rtspsrc location=\"rtsp://192.168.2.10:554/stream\" latency=200 ! rtph264depay ! h264parse ! queue ! rtph264pay name=pay0 config-interval=10 pt=96"
+        ));
+    }
+
+    #[test]
+    fn test_http_mjpeg_source() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::MJPG,
+            // Hardware encoders depend on the host running the tests
+            Some(ExtendedConfiguration {
+                encoder_preference: Some(EncoderPreference::Software),
+                ..Default::default()
+            }),
+        );
+        video_and_stream_information.video_source =
+            VideoSourceType::Ip(VideoSourceIp::from_url("http://192.168.2.11:8080/video").unwrap());
+        if let CaptureConfiguration::VIDEO(configuration) = &mut video_and_stream_information
+            .stream_information
+            .configuration
+        {
+            configuration.transcode = Some(VideoEncodeType::H264);
+        }
+
+        let stream = create_stream(&video_and_stream_information).unwrap();
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "souphttpsrc location=\"http://192.168.2.11:8080/video\" is-live=true do-timestamp=true ! multipartdemux ! image/jpeg ! jpegdec ! videoconvert ! x264enc bitrate=5000 tune=zerolatency key-int-max=30 ! video/x-h264,profile=baseline ! h264parse ! queue ! rtph264pay name=pay0"
+        ));
+    }
+
+    #[test]
+    fn test_custom_gst_source() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::YUYV,
+            None,
+        );
+        video_and_stream_information.video_source = VideoSourceType::Gst(VideoSourceGst {
+            name: "GigE camera".into(),
+            source: VideoSourceGstType::Local("aravissrc camera-name=Basler-21234567".into()),
+        });
+
+        let stream = create_stream(&video_and_stream_information).unwrap();
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "aravissrc camera-name=Basler-21234567 ! videoconvert ! videoscale ! videorate ! video/x-raw,format=UYVY,width=1280,height=720,framerate=30/1"
+        ));
+    }
+
+    #[test]
+    fn test_file_source() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::YUYV,
+            None,
+        );
+        video_and_stream_information.video_source = VideoSourceType::File(
+            VideoSourceFile::from_url("file:///home/pi/videos/dive.mp4").unwrap(),
+        );
+
+        let stream = create_stream(&video_and_stream_information).unwrap();
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "filesrc location=\"/home/pi/videos/dive.mp4\" ! decodebin name=file_source ! videoconvert ! videoscale ! videorate ! video/x-raw,format=UYVY,width=1280,height=720,framerate=30/1"
+        ));
+    }
+
+    #[test]
+    fn test_thermal() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::Y16,
+            Some(ExtendedConfiguration {
+                encoder_preference: Some(EncoderPreference::Software),
+                thermal_palette: Some(ThermalPalette::YellowBlue),
+                ..Default::default()
+            }),
+        );
+        if let CaptureConfiguration::VIDEO(configuration) = &mut video_and_stream_information
+            .stream_information
+            .configuration
+        {
+            configuration.transcode = Some(VideoEncodeType::H264);
+        }
+
+        let stream = create_stream(&video_and_stream_information).unwrap();
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "v4l2src device=/dev/video42 ! video/x-raw,format=GRAY16_LE,width=1280,height=720,framerate=30/1 ! identity name=thermal ! videoconvert ! video/x-raw,format=GRAY8 ! videoconvert ! coloreffects preset=yellowblue ! videoconvert ! x264enc"
+        ));
+    }
+
+    #[test]
+    fn test_h265_transcode() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("udp265://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::YUYV,
+            // Hardware encoders depend on the host running the tests
+            Some(ExtendedConfiguration {
+                encoder_preference: Some(EncoderPreference::Software),
+                ..Default::default()
+            }),
+        );
+        if let CaptureConfiguration::VIDEO(configuration) = &mut video_and_stream_information
+            .stream_information
+            .configuration
+        {
+            configuration.transcode = Some(VideoEncodeType::H265);
+        }
+
+        let stream = create_stream(&video_and_stream_information).unwrap();
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "v4l2src device=/dev/video42 ! video/x-raw,format=YUY2,width=1280,height=720,framerate=30/1 ! videoconvert ! x265enc bitrate=5000 speed-preset=ultrafast tune=zerolatency key-int-max=30 ! video/x-h265,profile=main ! h265parse ! queue ! rtph265pay name=pay0"
+        ));
+        assert_eq!(
+            crate::stream::gst::utils::video_encoder(&pipeline),
+            Some("x265enc".to_string())
+        );
+    }
+
+    #[test]
+    fn test_jpeg_quality() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::MJPG,
+            // Hardware encoders depend on the host running the tests
+            Some(ExtendedConfiguration {
+                encoder_preference: Some(EncoderPreference::Software),
+                ..Default::default()
+            }),
+        );
+        if let CaptureConfiguration::VIDEO(configuration) = &mut video_and_stream_information
+            .stream_information
+            .configuration
+        {
+            configuration.transcode = Some(VideoEncodeType::MJPG);
+            configuration.jpeg_quality = Some(50);
+        }
+
+        let stream = create_stream(&video_and_stream_information).unwrap();
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "v4l2src device=/dev/video42 ! image/jpeg,width=1280,height=720,framerate=30/1 ! jpegdec ! jpegenc quality=50 ! rtpjpegpay name=pay0 pt=96"
+        ));
+    }
+
+    #[test]
+    fn test_rotation() {
+        let video_and_stream_information = |encode, flip| {
+            let mut video_and_stream_information = video_and_stream_information_fabricator(
+                &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+                &encode,
+                None,
+            );
+            if let CaptureConfiguration::VIDEO(configuration) = &mut video_and_stream_information
+                .stream_information
+                .configuration
+            {
+                configuration.rotation = Some(90);
+                configuration.flip = flip;
+            }
+            video_and_stream_information
+        };
+
+        let stream = create_stream(&video_and_stream_information(
+            VideoEncodeType::YUYV,
+            Some(VideoFlip::Vertical),
+        ))
+        .unwrap();
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "v4l2src device=/dev/video42 ! video/x-raw,format=YUY2,width=1280,height=720,framerate=30/1 ! videoflip video-direction=ul-lr ! videoconvert ! video/x-raw,format=UYVY ! rtpvrawpay name=pay0"
+        ));
+
+        // Encoded captures can't be rotated without a transcode
+        assert!(check_encode(&video_and_stream_information(VideoEncodeType::H264, None)).is_err());
+    }
+
+    #[test]
+    fn test_crop_and_scale() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::YUYV,
+            None,
+        );
+        if let CaptureConfiguration::VIDEO(configuration) = &mut video_and_stream_information
+            .stream_information
+            .configuration
+        {
+            configuration.crop = Some(VideoCrop {
+                left: 160,
+                top: 90,
+                width: 960,
+                height: 540,
+            });
+            configuration.output_size = Some(VideoSize {
+                width: 640,
+                height: 360,
+            });
+        }
+
+        let stream = create_stream(&video_and_stream_information).unwrap();
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "v4l2src device=/dev/video42 ! video/x-raw,format=YUY2,width=1280,height=720,framerate=30/1 ! videocrop left=160 top=90 right=160 bottom=90 ! videoscale ! video/x-raw,width=640,height=360 ! videoconvert ! video/x-raw,format=UYVY ! rtpvrawpay name=pay0"
+        ));
+    }
+
+    #[test]
+    fn test_crop_overflow() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::YUYV,
+            None,
+        );
+        if let CaptureConfiguration::VIDEO(configuration) = &mut video_and_stream_information
+            .stream_information
+            .configuration
+        {
+            // Wrapping sums would end up inside of the frame
+            configuration.crop = Some(VideoCrop {
+                left: u32::MAX,
+                top: u32::MAX,
+                width: 2,
+                height: 2,
+            });
+        }
+
+        assert!(create_stream(&video_and_stream_information).is_err());
+    }
+
+    #[cfg(feature = "rtsp")]
     #[test]
     fn test_rtsp() {
         let pipeline_testing = vec![
             (VideoEncodeType::H264, "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse ! queue ! rtph264pay name=pay0 config-interval=10 pt=96"),
+            (VideoEncodeType::H265, "v4l2src device=/dev/video42 ! video/x-h265,width=1280,height=720,framerate=30/1 ! h265parse ! queue ! rtph265pay name=pay0 config-interval=10 pt=96"),
             (VideoEncodeType::YUYV, "v4l2src device=/dev/video42 ! video/x-raw,format=YUY2,width=1280,height=720,framerate=30/1 ! videoconvert ! video/x-raw,format=UYVY ! rtpvrawpay name=pay0 ! application/x-rtp,payload=96,sampling=YCbCr-4:2:2"),
             (VideoEncodeType::MJPG, "v4l2src device=/dev/video42 ! image/jpeg,width=1280,height=720,framerate=30/1 ! rtpjpegpay name=pay0 pt=96"),
         ];
@@ -282,6 +1183,7 @@ mod tests {
             let stream = stream_type_fabricator(
                 &vec![Url::parse("rtsp://0.0.0.0:8554/test").unwrap()],
                 encode_type,
+                None,
             );
             let pipeline = match &stream {
                 StreamType::RTSP(video_stream_rtsp) => video_stream_rtsp.pipeline(),
@@ -290,4 +1192,491 @@ mod tests {
             assert_eq!(&pipeline, expected_pipeline);
         }
     }
+
+    #[cfg(feature = "rtsp")]
+    #[test]
+    fn test_rtsp_path() {
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("rtsp://0.0.0.0:8554/vehicle1/front").unwrap()],
+            &VideoEncodeType::H264,
+            None,
+        );
+        assert!(matches!(stream, StreamType::RTSP(_)));
+    }
+
+    #[test]
+    fn test_queue_configuration() {
+        let pipeline_testing = vec![
+            (VideoEncodeType::H264, "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse ! queue leaky=downstream max-size-buffers=1 ! rtph264pay name=pay0 config-interval=10 pt=96 ! rtpbin.send_rtp_sink_0 rtpbin name=rtpbin rtpbin.send_rtp_src_0 ! multiudpsink name=udp_sink clients=192.168.0.1:42 rtpbin.send_rtcp_src_0 ! multiudpsink name=rtcp_sink clients=192.168.0.1:43 sync=false async=false"),
+            (VideoEncodeType::MJPG, "v4l2src device=/dev/video42 ! image/jpeg,width=1280,height=720,framerate=30/1 ! queue leaky=downstream max-size-buffers=1 ! rtpjpegpay name=pay0 pt=96 ! rtpbin.send_rtp_sink_0 rtpbin name=rtpbin rtpbin.send_rtp_src_0 ! multiudpsink name=udp_sink clients=192.168.0.1:42 rtpbin.send_rtcp_src_0 ! multiudpsink name=rtcp_sink clients=192.168.0.1:43 sync=false async=false"),
+        ];
+
+        for (encode_type, expected_pipeline) in pipeline_testing.iter() {
+            let stream = stream_type_fabricator(
+                &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+                encode_type,
+                Some(ExtendedConfiguration {
+                    queue: Some(QueueConfiguration {
+                        leakiness: QueueLeakiness::Leaky,
+                        max_size_buffers: Some(1),
+                        max_size_time_ms: None,
+                    }),
+                    ..Default::default()
+                }),
+            );
+            let pipeline = match &stream {
+                StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+                _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+            };
+            assert_eq!(&pipeline, expected_pipeline);
+        }
+    }
+
+    #[test]
+    fn test_stream_profile() {
+        let pipeline_testing = vec![
+            (StreamProfile::LowLatency, "v4l2src device=/dev/video42 ! image/jpeg,width=1280,height=720,framerate=30/1 ! jpegdec ! videoconvert ! x264enc bitrate=2000 tune=zerolatency speed-preset=ultrafast key-int-max=15 ! video/x-h264,profile=baseline ! h264parse ! queue leaky=downstream max-size-buffers=1 ! rtph264pay name=pay0 config-interval=-1 pt=96"),
+            (StreamProfile::RecordMaster, "v4l2src device=/dev/video42 ! image/jpeg,width=1280,height=720,framerate=30/1 ! jpegdec ! videoconvert ! x264enc bitrate=20000 tune=film speed-preset=faster key-int-max=120 ! video/x-h264,profile=baseline ! h264parse ! queue leaky=no max-size-buffers=0 max-size-time=5000000000 ! rtph264pay name=pay0 config-interval=1 pt=96"),
+        ];
+
+        for (profile, expected_pipeline) in pipeline_testing.iter() {
+            let mut video_and_stream_information = video_and_stream_information_fabricator(
+                &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+                &VideoEncodeType::MJPG,
+                // Hardware encoders depend on the host running the tests
+                Some(ExtendedConfiguration {
+                    profile: Some(profile.clone()),
+                    encoder_preference: Some(EncoderPreference::Software),
+                    ..Default::default()
+                }),
+            );
+            if let CaptureConfiguration::VIDEO(configuration) = &mut video_and_stream_information
+                .stream_information
+                .configuration
+            {
+                configuration.transcode = Some(VideoEncodeType::H264);
+            }
+
+            let stream = create_stream(&video_and_stream_information).unwrap();
+            let pipeline = match &stream {
+                StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+                _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+            };
+            assert!(
+                pipeline.starts_with(expected_pipeline),
+                "{profile:?}: {pipeline}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_latency_profile() {
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::H264,
+            Some(ExtendedConfiguration {
+                latency_profile: Some(LatencyProfile::LowestLatency),
+                ..Default::default()
+            }),
+        );
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.starts_with(
+            "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse ! queue leaky=downstream max-size-buffers=1 ! rtph264pay name=pay0 config-interval=10 pt=96 ! rtpbin.send_rtp_sink_0 rtpbin name=rtpbin rtpbin.send_rtp_src_0 ! multiudpsink name=udp_sink clients=192.168.0.1:42 sync=false rtpbin.send_rtcp_src_0"
+        ));
+
+        // The explicit queue configuration takes precedence
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::H264,
+            Some(ExtendedConfiguration {
+                latency_profile: Some(LatencyProfile::Robust),
+                queue: Some(QueueConfiguration {
+                    leakiness: QueueLeakiness::Leaky,
+                    max_size_buffers: Some(2),
+                    max_size_time_ms: None,
+                }),
+                ..Default::default()
+            }),
+        );
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.contains(" ! queue leaky=downstream max-size-buffers=2 ! "));
+        assert!(
+            pipeline.contains(" ! multiudpsink name=udp_sink clients=192.168.0.1:42 sync=true ")
+        );
+    }
+
+    #[test]
+    fn test_rtp_configuration() {
+        let pipeline_testing = vec![
+            (VideoEncodeType::H264, "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse ! queue ! rtph264pay name=pay0 config-interval=10 pt=100 mtu=1200"),
+            (VideoEncodeType::YUYV, "v4l2src device=/dev/video42 ! video/x-raw,format=YUY2,width=1280,height=720,framerate=30/1 ! videoconvert ! video/x-raw,format=UYVY ! rtpvrawpay name=pay0 mtu=1200 ! application/x-rtp,payload=100,sampling=YCbCr-4:2:2"),
+            (VideoEncodeType::MJPG, "v4l2src device=/dev/video42 ! image/jpeg,width=1280,height=720,framerate=30/1 ! rtpjpegpay name=pay0 pt=100 mtu=1200"),
+        ];
+
+        for (encode_type, expected_pipeline) in pipeline_testing.iter() {
+            let stream = stream_type_fabricator(
+                &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+                encode_type,
+                Some(ExtendedConfiguration {
+                    rtp_payload_type: Some(100),
+                    mtu: Some(1200),
+                    ..Default::default()
+                }),
+            );
+            let pipeline = match &stream {
+                StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+                _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+            };
+            assert!(pipeline.starts_with(expected_pipeline));
+        }
+    }
+
+    #[test]
+    fn test_custom_pipeline() {
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::H264,
+            Some(ExtendedConfiguration {
+                custom_pipeline: Some(
+                    "v4l2src device={device} ! h264parse ! rtph264pay ! udpsink host={host} port={port}"
+                        .into(),
+                ),
+                ..Default::default()
+            }),
+        );
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert_eq!(
+            &pipeline,
+            "v4l2src device=/dev/video42 ! h264parse ! rtph264pay ! udpsink host=192.168.0.1 port=42"
+        );
+    }
+
+    #[test]
+    fn test_udp_sink_configuration() {
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::H264,
+            Some(ExtendedConfiguration {
+                udp_sink: Some(UdpSinkConfiguration {
+                    buffer_size: Some(212992),
+                    sync: Some(false),
+                    is_async: None,
+                    multicast_ttl: Some(4),
+                    multicast_iface: None,
+                    rtcp_port: Some(5700),
+                }),
+                ..Default::default()
+            }),
+        );
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        // Multicast options are only used with multicast endpoints
+        assert!(pipeline.contains(
+            " ! multiudpsink name=udp_sink clients=192.168.0.1:42 buffer-size=212992 sync=false rtpbin.send_rtcp_src_0"
+        ));
+        assert!(pipeline.ends_with(" udpsrc port=5700 ! rtpbin.recv_rtcp_sink_0"));
+    }
+
+    #[test]
+    fn test_udp_multicast() {
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("udp://239.0.0.1:5600").unwrap()],
+            &VideoEncodeType::H264,
+            Some(ExtendedConfiguration {
+                udp_sink: Some(UdpSinkConfiguration {
+                    buffer_size: None,
+                    sync: None,
+                    is_async: None,
+                    multicast_ttl: Some(4),
+                    multicast_iface: Some("eth0".into()),
+                    rtcp_port: None,
+                }),
+                ..Default::default()
+            }),
+        );
+        let pipeline = match &stream {
+            StreamType::UDP(video_stream_udp) => video_stream_udp.pipeline(),
+            _any_other_stream_type => panic!("Failed to create UDP stream: {stream:?}."),
+        };
+        assert!(pipeline.ends_with(
+            " ! multiudpsink name=udp_sink clients=239.0.0.1:5600 auto-multicast=true ttl-mc=4 multicast-iface=\"eth0\" rtpbin.send_rtcp_src_0 ! multiudpsink name=rtcp_sink clients=239.0.0.1:5601 sync=false async=false auto-multicast=true ttl-mc=4 multicast-iface=\"eth0\" udpsrc address=239.0.0.1 port=5601 ! rtpbin.recv_rtcp_sink_0"
+        ));
+    }
+
+    #[cfg(feature = "ndi")]
+    #[test]
+    fn test_ndi() {
+        let pipeline_testing = vec![
+            (VideoEncodeType::H264, "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse ! avdec_h264 ! videoconvert ! video/x-raw,format=UYVY ! queue ! ndisink ndi-name=\"FrontCamera\""),
+            (VideoEncodeType::MJPG, "v4l2src device=/dev/video42 ! image/jpeg,width=1280,height=720,framerate=30/1 ! jpegdec ! videoconvert ! video/x-raw,format=UYVY ! queue ! ndisink ndi-name=\"FrontCamera\""),
+        ];
+
+        for (encode_type, expected_pipeline) in pipeline_testing.iter() {
+            let stream = stream_type_fabricator(
+                &vec![Url::parse("ndi://FrontCamera").unwrap()],
+                encode_type,
+                None,
+            );
+            let pipeline = match &stream {
+                StreamType::PIPELINE(video_stream_pipeline) => video_stream_pipeline.pipeline(),
+                _any_other_stream_type => panic!("Failed to create NDI stream: {stream:?}."),
+            };
+            assert_eq!(&pipeline, expected_pipeline);
+        }
+    }
+
+    #[test]
+    fn test_srt() {
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("srt://0.0.0.0:9000").unwrap()],
+            &VideoEncodeType::H264,
+            Some(ExtendedConfiguration {
+                srt: Some(SrtConfiguration {
+                    latency_ms: Some(200),
+                    passphrase: None,
+                }),
+                ..Default::default()
+            }),
+        );
+        let pipeline = match &stream {
+            StreamType::PIPELINE(video_stream_pipeline) => video_stream_pipeline.pipeline(),
+            _any_other_stream_type => panic!("Failed to create SRT stream: {stream:?}."),
+        };
+        assert_eq!(
+            &pipeline,
+            "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse config-interval=-1 ! queue ! mpegtsmux alignment=7 ! srtsink uri=\"srt://:9000?mode=listener\" latency=200"
+        );
+    }
+
+    #[test]
+    fn test_rtmp() {
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("rtmp://a.rtmp.youtube.com/live2/key").unwrap()],
+            &VideoEncodeType::H264,
+            None,
+        );
+        let pipeline = match &stream {
+            StreamType::PIPELINE(video_stream_pipeline) => video_stream_pipeline.pipeline(),
+            _any_other_stream_type => panic!("Failed to create RTMP stream: {stream:?}."),
+        };
+        assert_eq!(
+            &pipeline,
+            "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse config-interval=-1 ! queue ! flvmux streamable=true ! rtmpsink location=\"rtmp://a.rtmp.youtube.com/live2/key live=1\""
+        );
+    }
+
+    #[test]
+    fn test_mpegts() {
+        let stream = stream_type_fabricator(
+            &vec![
+                Url::parse("mpegts://192.168.0.1:5600").unwrap(),
+                Url::parse("mpegts://192.168.0.2:5600").unwrap(),
+            ],
+            &VideoEncodeType::H264,
+            None,
+        );
+        let pipeline = match &stream {
+            StreamType::PIPELINE(video_stream_pipeline) => video_stream_pipeline.pipeline(),
+            _any_other_stream_type => panic!("Failed to create MPEG-TS stream: {stream:?}."),
+        };
+        assert_eq!(
+            &pipeline,
+            "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse config-interval=-1 ! queue ! mpegtsmux alignment=7 ! multiudpsink name=udp_sink clients=192.168.0.1:5600,192.168.0.2:5600"
+        );
+    }
+
+    #[test]
+    fn test_udp_and_mpegts() {
+        // Both branches of the tee would have a multiudpsink named udp_sink
+        let error = check_endpoints(&video_and_stream_information_fabricator(
+            &vec![
+                Url::parse("udp://192.168.0.1:5600").unwrap(),
+                Url::parse("mpegts://192.168.0.1:5601").unwrap(),
+            ],
+            &VideoEncodeType::H264,
+            None,
+        ))
+        .unwrap_err();
+        assert_eq!(
+            error.as_str(),
+            "Endpoints with udp and mpegts schemes can't be used by the same stream."
+        );
+    }
+
+    #[test]
+    fn test_check_features() {
+        let endpoints = vec![
+            ("rtsp://0.0.0.0:8554/test", "rtsp", cfg!(feature = "rtsp")),
+            ("rtsps://0.0.0.0:8554/test", "rtsp", cfg!(feature = "rtsp")),
+            ("ndi://FrontCamera", "ndi", cfg!(feature = "ndi")),
+            (
+                "file:///tmp/video.mkv",
+                "recording",
+                cfg!(feature = "recording"),
+            ),
+            ("webrtc://0.0.0.0:6021", "webrtc", cfg!(feature = "webrtc")),
+            ("udp://192.168.0.1:42", "", true),
+        ];
+
+        for (endpoint, feature, is_available) in endpoints {
+            let endpoint = Url::parse(endpoint).unwrap();
+            let result = check_features(&video_and_stream_information_fabricator(
+                &vec![endpoint.clone()],
+                &VideoEncodeType::H264,
+                None,
+            ));
+            match is_available {
+                true => assert!(result.is_ok(), "{endpoint}: {result:?}"),
+                false => assert_eq!(
+                    result.unwrap_err().as_str(),
+                    format!(
+                        "Endpoint with {} scheme is not available: built without feature \"{feature}\".",
+                        endpoint.scheme()
+                    )
+                ),
+            }
+        }
+
+        let result = check_features(&video_and_stream_information_fabricator(
+            &vec![Url::parse("udp://192.168.0.1:42").unwrap()],
+            &VideoEncodeType::H264,
+            Some(ExtendedConfiguration {
+                fov: Some(FieldOfView {
+                    horizontal: 90.0,
+                    vertical: 60.0,
+                }),
+                ..Default::default()
+            }),
+        ));
+        match cfg!(feature = "mavlink") {
+            true => assert!(result.is_ok()),
+            false => assert_eq!(
+                result.unwrap_err().as_str(),
+                "Field of view is not available: built without feature \"mavlink\"."
+            ),
+        }
+    }
+
+    #[test]
+    fn test_tcp() {
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("tcp://0.0.0.0:5000").unwrap()],
+            &VideoEncodeType::H264,
+            Some(ExtendedConfiguration {
+                tcp_container: Some(TcpContainer::Matroska),
+                ..Default::default()
+            }),
+        );
+        let pipeline = match &stream {
+            StreamType::PIPELINE(video_stream_pipeline) => video_stream_pipeline.pipeline(),
+            _any_other_stream_type => panic!("Failed to create TCP stream: {stream:?}."),
+        };
+        assert_eq!(
+            &pipeline,
+            "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse config-interval=-1 ! queue ! matroskamux streamable=true ! tcpserversink host=0.0.0.0 port=5000 recover-policy=keyframe sync-method=latest-keyframe"
+        );
+    }
+
+    #[test]
+    fn test_tee() {
+        let stream = stream_type_fabricator(
+            &vec![
+                Url::parse("udp://192.168.0.1:42").unwrap(),
+                Url::parse("srt://0.0.0.0:9000").unwrap(),
+                Url::parse("udp://192.168.0.2:42").unwrap(),
+            ],
+            &VideoEncodeType::H264,
+            None,
+        );
+        let pipeline = match &stream {
+            StreamType::TEE(video_stream_tee) => video_stream_tee.pipeline(),
+            _any_other_stream_type => panic!("Failed to create tee stream: {stream:?}."),
+        };
+        assert_eq!(
+            &pipeline,
+            "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! tee name=video_tee video_tee. ! queue ! h264parse ! queue ! rtph264pay name=pay0 config-interval=10 pt=96 ! rtpbin.send_rtp_sink_0 rtpbin name=rtpbin rtpbin.send_rtp_src_0 ! multiudpsink name=udp_sink clients=192.168.0.1:42,192.168.0.2:42 rtpbin.send_rtcp_src_0 ! multiudpsink name=rtcp_sink clients=192.168.0.1:43,192.168.0.2:43 sync=false async=false video_tee. ! queue ! h264parse config-interval=-1 ! queue ! mpegtsmux alignment=7 ! srtsink uri=\"srt://:9000?mode=listener\""
+        );
+    }
+
+    #[cfg(feature = "rtsp")]
+    #[test]
+    fn test_tee_rtsp() {
+        let stream = stream_type_fabricator(
+            &vec![
+                Url::parse("rtsp://0.0.0.0:8554/tee").unwrap(),
+                Url::parse("udp://192.168.0.1:42").unwrap(),
+            ],
+            &VideoEncodeType::H264,
+            None,
+        );
+        let pipeline = match &stream {
+            StreamType::TEE(video_stream_tee) => video_stream_tee.pipeline(),
+            _any_other_stream_type => panic!("Failed to create tee stream: {stream:?}."),
+        };
+        // The payload is shared by the UDP clients and the RTSP server
+        assert!(pipeline.contains(
+            " ! rtph264pay name=pay0 config-interval=10 pt=96 ! tee name=rtp_tee rtp_tee. ! queue ! rtpbin.send_rtp_sink_0"
+        ));
+        assert!(pipeline.contains(" rtp_tee. ! queue ! udpsink host=127.0.0.1 port="));
+    }
+
+    #[cfg(feature = "recording")]
+    #[test]
+    fn test_file() {
+        let stream = stream_type_fabricator(
+            &vec![Url::parse("file:///tmp/video.mkv").unwrap()],
+            &VideoEncodeType::H264,
+            None,
+        );
+        let pipeline = match &stream {
+            StreamType::PIPELINE(video_stream_pipeline) => video_stream_pipeline.pipeline(),
+            _any_other_stream_type => panic!("Failed to create file stream: {stream:?}."),
+        };
+        assert_eq!(
+            &pipeline,
+            "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse config-interval=-1 ! queue ! matroskamux ! filesink name=file_sink location=\"/tmp/video.mkv\""
+        );
+    }
+
+    #[cfg(feature = "recording")]
+    #[test]
+    fn test_file_audio() {
+        let mut video_and_stream_information = video_and_stream_information_fabricator(
+            &vec![Url::parse("file:///tmp/video.mkv").unwrap()],
+            &VideoEncodeType::H264,
+            None,
+        );
+        video_and_stream_information.stream_information.audio = Some(StreamAudio {
+            device: "hw:1,0".into(),
+            encode: AudioEncodeType::OPUS,
+            sample_rate: 48000,
+            channels: 1,
+        });
+
+        let stream = create_stream(&video_and_stream_information).unwrap();
+        let pipeline = match &stream {
+            StreamType::PIPELINE(video_stream_pipeline) => video_stream_pipeline.pipeline(),
+            _any_other_stream_type => panic!("Failed to create file stream: {stream:?}."),
+        };
+        assert_eq!(
+            &pipeline,
+            "v4l2src device=/dev/video42 ! video/x-h264,width=1280,height=720,framerate=30/1 ! h264parse config-interval=-1 ! queue ! matroskamux name=mux ! filesink name=file_sink location=\"/tmp/video.mkv\" alsasrc device=\"hw:1,0\" ! audio/x-raw,rate=48000,channels=1 ! audioconvert ! audioresample ! opusenc ! queue ! mux."
+        );
+
+        // Only RTSP and file endpoints carry the audio
+        video_and_stream_information.stream_information.endpoints =
+            vec![Url::parse("udp://192.168.0.1:42").unwrap()];
+        assert!(create_stream(&video_and_stream_information).is_err());
+    }
 }
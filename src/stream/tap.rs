@@ -0,0 +1,178 @@
+use super::manager;
+
+use futures::{channel::mpsc, Stream};
+use gstreamer::prelude::*;
+use simple_error::{simple_error, SimpleResult};
+use tracing::*;
+
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+// Local copy of a running stream, received from its endpoints as any other
+// client, so the video source doesn't need to be opened again.
+// Only UDP (without SRTP, the key is not known by the tap), MPEG-TS and RTSP
+// streams can be received, other streams (e.g: SRT, RTMP, TCP, files, redirects,
+// or with multiple schemes) and raw video return an error, see `manager::tap_source`.
+#[derive(Debug)]
+pub struct StreamTap {
+    stream_name: String,
+    // Port added as a client of the stream, when sent over UDP
+    loopback_port: Option<u16>,
+    pipeline: gstreamer::Pipeline,
+}
+
+impl StreamTap {
+    // The received video is linked to `sink`, e.g: " ! decodebin ! fakesink"
+    pub fn new(stream_name: &str, sink: &str) -> SimpleResult<Self> {
+        let (source, loopback_port) = manager::tap_source(stream_name)?;
+        let description = format!("{source}{sink}");
+
+        let pipeline = gstreamer::parse_launch(&description)
+            .map_err(|error| simple_error!(format!("Failed to create tap pipeline: {error}")))
+            .and_then(|pipeline| {
+                pipeline
+                    .downcast::<gstreamer::Pipeline>()
+                    .map_err(|_| simple_error!("Tap description is not a pipeline."))
+            });
+        let pipeline = match pipeline {
+            Ok(pipeline) => pipeline,
+            Err(error) => {
+                if let Some(port) = loopback_port {
+                    manager::remove_tap_client(stream_name, port);
+                }
+                return Err(error);
+            }
+        };
+
+        debug!(
+            "New tap for stream {stream_name:?}: {:#?}",
+            crate::stream::gst::utils::redact_secrets(&description)
+        );
+        Ok(Self {
+            stream_name: stream_name.to_string(),
+            loopback_port,
+            pipeline,
+        })
+    }
+
+    // Start the tap, forwarding the samples of the appsink `sink_name` as a stream,
+    // converted by `convert` from the sample and its buffer data.
+    // When the receiver is late, the samples are dropped if `drop_late`, otherwise
+    // the stream is finished, since the missing data would corrupt it.
+    pub fn into_stream<T, F>(
+        self,
+        sink_name: &str,
+        capacity: usize,
+        drop_late: bool,
+        convert: F,
+    ) -> SimpleResult<TapStream<T>>
+    where
+        T: Send + 'static,
+        F: Fn(&gstreamer::Sample, &[u8]) -> T + Send + Sync + 'static,
+    {
+        let sink = self
+            .pipeline
+            .by_name(sink_name)
+            .ok_or_else(|| simple_error!(format!("Failed to find tap sink {sink_name:?}.")))?;
+
+        let (sender, receiver) = mpsc::channel::<T>(capacity);
+        let sender = Mutex::new(Some(sender));
+        sink.connect("new-sample", false, move |values| {
+            let sink = values[0].get::<gstreamer::Element>().ok()?;
+            let sample = sink.emit_by_name::<Option<gstreamer::Sample>>("pull-sample", &[])?;
+            let buffer = sample.buffer()?;
+            let map = buffer.map_readable().ok()?;
+
+            let mut sender = sender.lock().unwrap();
+            if let Some(channel) = sender.as_mut() {
+                if let Err(error) = channel.try_send(convert(&sample, &map)) {
+                    if error.is_disconnected() || !drop_late {
+                        *sender = None;
+                    }
+                }
+            }
+
+            Some(gstreamer::FlowReturn::Ok.to_value())
+        });
+
+        self.pipeline
+            .set_state(gstreamer::State::Playing)
+            .map_err(|error| simple_error!(format!("Failed to start tap pipeline: {error}")))?;
+
+        Ok(TapStream {
+            receiver,
+            _tap: self,
+        })
+    }
+}
+
+#[cfg(feature = "recording")]
+impl StreamTap {
+    // Start the tap, for sinks that don't need to be read, e.g: files
+    pub fn start(&self) -> SimpleResult<()> {
+        self.pipeline
+            .set_state(gstreamer::State::Playing)
+            .map(|_| ())
+            .map_err(|error| simple_error!(format!("Failed to start tap pipeline: {error}")))
+    }
+
+    // Stop the tap after the end of the stream reaches its sinks, so muxers can finalize their files
+    pub fn finish(self, timeout: std::time::Duration) {
+        if !self.pipeline.send_event(gstreamer::event::Eos::new()) {
+            warn!(
+                "Failed to send EOS to tap of stream {:?}.",
+                self.stream_name
+            );
+            return;
+        }
+
+        let message = self.pipeline.bus().and_then(|bus| {
+            bus.timed_pop_filtered(
+                gstreamer::ClockTime::from_mseconds(timeout.as_millis() as u64),
+                &[gstreamer::MessageType::Eos, gstreamer::MessageType::Error],
+            )
+        });
+        match message.as_ref().map(|message| message.view()) {
+            Some(gstreamer::MessageView::Eos(_)) => (),
+            Some(gstreamer::MessageView::Error(error)) => warn!(
+                "Tap of stream {:?} failed while finishing: {}",
+                self.stream_name,
+                error.error()
+            ),
+            _ => warn!(
+                "Tap of stream {:?} did not finish in {timeout:?}.",
+                self.stream_name
+            ),
+        }
+    }
+}
+
+impl Drop for StreamTap {
+    fn drop(&mut self) {
+        if let Err(error) = self.pipeline.set_state(gstreamer::State::Null) {
+            warn!(
+                "Failed to stop tap for stream {:?}: {error}",
+                self.stream_name
+            );
+        }
+
+        if let Some(port) = self.loopback_port {
+            manager::remove_tap_client(&self.stream_name, port);
+        }
+    }
+}
+
+// Data produced by a tap, the tap is stopped when this is dropped
+pub struct TapStream<T> {
+    receiver: mpsc::Receiver<T>,
+    _tap: StreamTap,
+}
+
+impl<T> Stream for TapStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Option<T>> {
+        Pin::new(&mut self.receiver).poll_next(context)
+    }
+}
@@ -0,0 +1,73 @@
+use gstreamer::prelude::*;
+
+// Name of the identity element added by the pipeline builder before the colorization
+pub const ELEMENT_NAME: &str = "thermal";
+
+// Thermal sensors (e.g: FLIR Lepton and Boson) provide 16-bit frames using only a small
+// part of the range, so they would look completely gray once converted to 8 bits.
+// The frames are normalized before reaching the colorization.
+pub fn track(pipeline: &gstreamer::Element) {
+    let pad = match pipeline
+        .downcast_ref::<gstreamer::Bin>()
+        .and_then(|bin| bin.by_name(ELEMENT_NAME))
+        .and_then(|element| element.static_pad("sink"))
+    {
+        Some(pad) => pad,
+        None => return,
+    };
+
+    pad.add_probe(gstreamer::PadProbeType::BUFFER, |_pad, info| {
+        if let Some(gstreamer::PadProbeData::Buffer(buffer)) = &mut info.data {
+            if let Ok(mut map) = buffer.make_mut().map_writable() {
+                normalize(map.as_mut_slice());
+            }
+        }
+        gstreamer::PadProbeReturn::Ok
+    });
+}
+
+// Stretch the GRAY16_LE values of the frame to the full 16-bit range
+fn normalize(frame: &mut [u8]) {
+    let values = || {
+        frame
+            .chunks_exact(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    };
+    let (min, max) = match (values().min(), values().max()) {
+        (Some(min), Some(max)) if max > min => (min as u32, max as u32),
+        _ => return,
+    };
+
+    for bytes in frame.chunks_exact_mut(2) {
+        let value = u16::from_le_bytes([bytes[0], bytes[1]]) as u32;
+        let value = ((value - min) * u16::MAX as u32 / (max - min)) as u16;
+        bytes.copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_normalization() {
+        // Kelvin * 100, as sent by radiometric Leptons
+        let mut frame: Vec<u8> = [29500u16, 29750, 30000]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        normalize(&mut frame);
+        assert_eq!(
+            frame,
+            [0u16, 32767, 65535]
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<u8>>()
+        );
+
+        // Uniform frames are kept as they are
+        let mut frame = vec![0x34, 0x12, 0x34, 0x12];
+        normalize(&mut frame);
+        assert_eq!(frame, vec![0x34, 0x12, 0x34, 0x12]);
+    }
+}
@@ -1,6 +1,9 @@
+#[cfg(feature = "rtsp")]
+use super::video_stream_rtsp::VideoStreamRtsp;
 use super::{
-    stream_backend::StreamBackend, video_stream_redirect::VideoStreamRedirect,
-    video_stream_rtsp::VideoStreamRtsp, video_stream_udp::VideoStreamUdp,
+    stream_backend::StreamBackend, video_stream_pipeline::VideoStreamPipeline,
+    video_stream_redirect::VideoStreamRedirect, video_stream_tee::VideoStreamTee,
+    video_stream_udp::VideoStreamUdp,
 };
 use crate::{
     video::types::{FrameInterval, VideoEncodeType},
@@ -15,24 +18,35 @@ use url::Url;
 #[allow(dead_code)]
 pub enum StreamType {
     UDP(VideoStreamUdp),
+    #[cfg(feature = "rtsp")]
     RTSP(VideoStreamRtsp),
     REDIRECT(VideoStreamRedirect),
+    // SRT, RTMP, MPEG-TS, TCP, NDI and file endpoints
+    PIPELINE(VideoStreamPipeline),
+    // Endpoints with different schemes
+    TEE(VideoStreamTee),
 }
 
 impl StreamType {
     pub fn inner(&self) -> &(dyn StreamBackend + '_) {
         match self {
             StreamType::UDP(backend) => backend,
+            #[cfg(feature = "rtsp")]
             StreamType::RTSP(backend) => backend,
             StreamType::REDIRECT(backend) => backend,
+            StreamType::PIPELINE(backend) => backend,
+            StreamType::TEE(backend) => backend,
         }
     }
 
     pub fn mut_inner(&mut self) -> &mut (dyn StreamBackend + '_) {
         match self {
             StreamType::UDP(backend) => backend,
+            #[cfg(feature = "rtsp")]
             StreamType::RTSP(backend) => backend,
             StreamType::REDIRECT(backend) => backend,
+            StreamType::PIPELINE(backend) => backend,
+            StreamType::TEE(backend) => backend,
         }
     }
 }
@@ -43,38 +57,610 @@ pub struct VideoCaptureConfiguration {
     pub height: u32,
     pub width: u32,
     pub frame_interval: FrameInterval,
+    // Encode sent to the endpoints when the captured one should be transcoded,
+    // e.g: YUYV or MJPG cameras streamed as H264
+    pub transcode: Option<VideoEncodeType>,
+    // Used by our encoder, or by the camera encoder when it has a bitrate control
+    pub bitrate_kbps: Option<u32>,
+    // Number of frames between keyframes, shorter intervals recover faster from packet loss
+    pub keyframe_interval: Option<u32>,
+    // From 1 to 100, used by our JPEG encoder, or by the camera when it has a quality control
+    pub jpeg_quality: Option<u32>,
+    // Deinterlace the captured frames, by default only when the camera reports interlaced ones
+    pub deinterlace: Option<bool>,
+    // Clockwise rotation in degrees: 0, 90, 180 or 270
+    pub rotation: Option<u32>,
+    // Applied before the rotation
+    pub flip: Option<VideoFlip>,
+    // Region of the captured frames to be streamed
+    pub crop: Option<VideoCrop>,
+    // Size of the streamed frames, after the rotation
+    pub output_size: Option<VideoSize>,
+}
+
+impl VideoCaptureConfiguration {
+    // Encode of the video sent to the endpoints
+    pub fn stream_encode(&self) -> &VideoEncodeType {
+        self.transcode.as_ref().unwrap_or(&self.encode)
+    }
+
+    // Orientation as clockwise quarter turns of a (possibly) horizontally mirrored
+    // video, a vertical flip being a horizontal one rotated by 180 degrees
+    fn orientation(&self) -> (u32, bool) {
+        let quarter_turns = self.rotation.unwrap_or(0) / 90;
+        match self.flip {
+            None => (quarter_turns % 4, false),
+            Some(VideoFlip::Horizontal) => (quarter_turns % 4, true),
+            Some(VideoFlip::Vertical) => ((quarter_turns + 2) % 4, true),
+        }
+    }
+
+    // Value of the videoflip's video-direction property, if the video needs to be changed
+    pub fn video_direction(&self) -> Option<&'static str> {
+        match self.orientation() {
+            (0, false) => None,
+            (1, false) => Some("90r"),
+            (2, false) => Some("180"),
+            (_, false) => Some("90l"),
+            (0, true) => Some("horiz"),
+            (1, true) => Some("ur-ll"),
+            (2, true) => Some("vert"),
+            (_, true) => Some("ul-lr"),
+        }
+    }
+
+    // Size of the streamed frames, after cropping, scaling and rotating the captured ones
+    pub fn output_resolution(&self) -> (u32, u32) {
+        if let Some(size) = &self.output_size {
+            return (size.width, size.height);
+        }
+
+        let (width, height) = match &self.crop {
+            Some(crop) => (crop.width, crop.height),
+            None => (self.width, self.height),
+        };
+        match self.orientation() {
+            (1 | 3, _) => (height, width),
+            _ => (width, height),
+        }
+    }
+
+    // Horizontal and vertical flips with the same result, not possible for 90 degrees rotations
+    pub fn flips(&self) -> Option<(bool, bool)> {
+        match self.orientation() {
+            (0, mirrored) => Some((mirrored, false)),
+            (2, mirrored) => Some((!mirrored, true)),
+            _ => None,
+        }
+    }
+}
+
+// Rectangle in pixels, starting at the top left corner of the frame
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct VideoCrop {
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct VideoSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoFlip {
+    Horizontal,
+    Vertical,
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct RedirectCaptureConfiguration {}
 
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum AudioEncodeType {
+    OPUS,
+    // Uncompressed 16 bits PCM
+    L16,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AudioCaptureConfiguration {
+    pub encode: AudioEncodeType,
+    pub sample_rate: u32,
+    pub channels: u32,
+}
+
 #[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum CaptureConfiguration {
     VIDEO(VideoCaptureConfiguration),
     REDIRECT(RedirectCaptureConfiguration),
+    AUDIO(AudioCaptureConfiguration),
+}
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueLeakiness {
+    // Blocks upstream when full, so no frame is dropped (prefer completeness)
+    Blocking,
+    // Drops the oldest buffered frames when full (prefer the latest frame)
+    Leaky,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct QueueConfiguration {
+    pub leakiness: QueueLeakiness,
+    pub max_size_buffers: Option<u32>,
+    pub max_size_time_ms: Option<u64>,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StreamProfile {
+    LowLatency,
+    Balanced,
+    HighQuality,
+    RecordMaster,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamProfileSettings {
+    pub queue: Option<QueueConfiguration>,
+    pub bitrate_kbps: u32,
+    pub key_int_max: u32,
+    pub x264_tune: &'static str,
+    pub x264_speed_preset: &'static str,
+    pub config_interval: i32,
+}
+
+impl StreamProfile {
+    pub fn settings(&self) -> StreamProfileSettings {
+        match self {
+            // Drops late frames and sends the SPS/PPS with every IDR, so the
+            // receivers can recover as soon as possible
+            StreamProfile::LowLatency => StreamProfileSettings {
+                queue: Some(QueueConfiguration {
+                    leakiness: QueueLeakiness::Leaky,
+                    max_size_buffers: Some(1),
+                    max_size_time_ms: None,
+                }),
+                bitrate_kbps: 2000,
+                key_int_max: 15,
+                x264_tune: "zerolatency",
+                x264_speed_preset: "ultrafast",
+                config_interval: -1,
+            },
+            StreamProfile::Balanced => StreamProfileSettings {
+                queue: None,
+                bitrate_kbps: 5000,
+                key_int_max: 30,
+                x264_tune: "zerolatency",
+                x264_speed_preset: "superfast",
+                config_interval: 10,
+            },
+            StreamProfile::HighQuality => StreamProfileSettings {
+                queue: Some(QueueConfiguration {
+                    leakiness: QueueLeakiness::Blocking,
+                    max_size_buffers: None,
+                    max_size_time_ms: None,
+                }),
+                bitrate_kbps: 10000,
+                key_int_max: 60,
+                x264_tune: "zerolatency",
+                x264_speed_preset: "veryfast",
+                config_interval: 10,
+            },
+            // Never drops frames, buffering up to a few seconds if the
+            // sink can't keep up
+            StreamProfile::RecordMaster => StreamProfileSettings {
+                queue: Some(QueueConfiguration {
+                    leakiness: QueueLeakiness::Blocking,
+                    max_size_buffers: Some(0),
+                    max_size_time_ms: Some(5000),
+                }),
+                bitrate_kbps: 20000,
+                key_int_max: 120,
+                x264_tune: "film",
+                x264_speed_preset: "faster",
+                config_interval: 1,
+            },
+        }
+    }
+}
+
+// Trade-off between the glass-to-glass delay and the resilience to jitter
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LatencyProfile {
+    LowestLatency,
+    Balanced,
+    Robust,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatencyProfileSettings {
+    pub queue: QueueConfiguration,
+    // Synchronize the sinks with the clock
+    pub sync: bool,
+    // Used by the jitter buffers of the RTP receivers
+    pub jitterbuffer_latency_ms: u32,
+}
+
+impl LatencyProfile {
+    pub fn settings(&self) -> LatencyProfileSettings {
+        match self {
+            // Only the latest frame is kept, and it is sent as soon as it is ready
+            LatencyProfile::LowestLatency => LatencyProfileSettings {
+                queue: QueueConfiguration {
+                    leakiness: QueueLeakiness::Leaky,
+                    max_size_buffers: Some(1),
+                    max_size_time_ms: None,
+                },
+                sync: false,
+                jitterbuffer_latency_ms: 0,
+            },
+            LatencyProfile::Balanced => LatencyProfileSettings {
+                queue: QueueConfiguration {
+                    leakiness: QueueLeakiness::Leaky,
+                    max_size_buffers: Some(5),
+                    max_size_time_ms: None,
+                },
+                sync: false,
+                jitterbuffer_latency_ms: 50,
+            },
+            // Absorbs bursts and network jitter, at the cost of a few hundred milliseconds
+            LatencyProfile::Robust => LatencyProfileSettings {
+                queue: QueueConfiguration {
+                    leakiness: QueueLeakiness::Blocking,
+                    max_size_buffers: Some(0),
+                    max_size_time_ms: Some(1000),
+                },
+                sync: true,
+                jitterbuffer_latency_ms: 500,
+            },
+        }
+    }
+}
+
+// Socket and clock behavior of multiudpsink, used by UDP streams
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct UdpSinkConfiguration {
+    // Socket send buffer size in bytes, the kernel default is used if not set
+    pub buffer_size: Option<u32>,
+    // Synchronize buffers with the clock, dropping the late ones
+    pub sync: Option<bool>,
+    // Wait for the state change to PAUSED/PLAYING asynchronously
+    #[serde(rename = "async")]
+    pub is_async: Option<bool>,
+    // Time to live of the packets sent to multicast endpoints, e.g: "udp://239.0.0.1:5600"
+    pub multicast_ttl: Option<u8>,
+    // Network interface used to send packets to multicast endpoints, e.g: "eth0"
+    pub multicast_iface: Option<String>,
+    // Local port receiving the RTCP receiver reports of unicast endpoints, they are ignored if not set
+    pub rtcp_port: Option<u16>,
+}
+
+// Multicast group used by the RTSP server to deliver the same packets to all
+// clients of a mount, instead of one unicast flow per client
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct RtspMulticastConfiguration {
+    pub address: String,
+    pub port_min: u16,
+    pub port_max: u16,
+    pub ttl: u8,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum SrtpCipher {
+    #[serde(rename = "aes-128-icm")]
+    Aes128Icm,
+    #[serde(rename = "aes-256-icm")]
+    Aes256Icm,
+}
+
+impl SrtpCipher {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SrtpCipher::Aes128Icm => "aes-128-icm",
+            SrtpCipher::Aes256Icm => "aes-256-icm",
+        }
+    }
+
+    // Master key followed by the master salt, in bytes
+    pub fn key_length(&self) -> usize {
+        match self {
+            SrtpCipher::Aes128Icm => 30,
+            SrtpCipher::Aes256Icm => 46,
+        }
+    }
+}
+
+// SRTP encryption, authenticated with HMAC-SHA1-80.
+// UDP receivers should be given the key, while RTSP clients receive it in the SDP (MIKEY).
+#[derive(Apiv2Schema, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SrtpConfiguration {
+    pub cipher: SrtpCipher,
+    // Master key and salt as a hexadecimal string
+    pub key: String,
+}
+
+#[derive(Apiv2Schema, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SrtConfiguration {
+    // Time to recover lost packets, the SRT default is 125 ms
+    pub latency_ms: Option<u32>,
+    // Enables AES encryption, should have from 10 to 79 characters
+    pub passphrase: Option<String>,
+}
+
+// Credentials required by the RTSP clients, using basic authentication.
+// Without rtsps, the credentials are sent as plain text.
+#[derive(Apiv2Schema, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RtspAuthConfiguration {
+    pub username: String,
+    pub password: String,
+}
+
+// Shown in place of the secrets, which are only kept in the settings
+pub const REDACTED: &str = "<redacted>";
+
+impl std::fmt::Debug for SrtpConfiguration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SrtpConfiguration")
+            .field("cipher", &self.cipher)
+            .field("key", &REDACTED)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for SrtConfiguration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SrtConfiguration")
+            .field("latency_ms", &self.latency_ms)
+            .field("passphrase", &self.passphrase.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for RtspAuthConfiguration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RtspAuthConfiguration")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
+
+impl ExtendedConfiguration {
+    // Copy of the configuration to be shown by the API, without the secrets
+    pub fn redacted(&self) -> Self {
+        let mut configuration = self.clone();
+        if let Some(srtp) = &mut configuration.srtp {
+            srtp.key = REDACTED.into();
+        }
+        if let Some(passphrase) = configuration
+            .srt
+            .as_mut()
+            .and_then(|srt| srt.passphrase.as_mut())
+        {
+            *passphrase = REDACTED.into();
+        }
+        if let Some(rtsp_auth) = &mut configuration.rtsp_auth {
+            rtsp_auth.password = REDACTED.into();
+        }
+        configuration
+    }
+}
+
+// Container used to mux TCP streams
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TcpContainer {
+    MpegTs,
+    Matroska,
+}
+
+// False colors of the thermal captures, from the coloreffects presets
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThermalPalette {
+    Grayscale,
+    Heat,
+    YellowBlue,
+    Xray,
+}
+
+// Encoder used when the video needs to be encoded by us
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncoderPreference {
+    // The first hardware encoder available, falling back to software
+    Auto,
+    // V4L2 memory-to-memory encoders, e.g: Raspberry Pi
+    V4l2,
+    // NVIDIA Jetson encoders, with the conversion done by nvvidconv
+    Nvidia,
+    // VA-API encoders, e.g: Intel GPUs
+    Vaapi,
+    Software,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsdItem {
+    Timestamp,
+    // Depth when underwater, altitude otherwise
+    Depth,
+    Heading,
+    Battery,
+}
+
+// On-screen display with the vehicle telemetry, drawn over the video
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct OsdConfiguration {
+    // Can be changed while the stream is running
+    pub enabled: bool,
+    // Shown in this order, one per line
+    pub items: Vec<OsdItem>,
+}
+
+// Angles covered by the camera image, in degrees
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct FieldOfView {
+    pub horizontal: f32,
+    pub vertical: f32,
 }
 
 #[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct ExtendedConfiguration {
     pub thermal: bool,
+    // Colors of the Y16 captures, Heat is used if not defined
+    pub thermal_palette: Option<ThermalPalette>,
+    pub profile: Option<StreamProfile>,
+    // The queue and udp_sink options take precedence over the latency profile ones
+    pub latency_profile: Option<LatencyProfile>,
+    pub queue: Option<QueueConfiguration>,
+    pub udp_sink: Option<UdpSinkConfiguration>,
+    pub rtsp_multicast: Option<RtspMulticastConfiguration>,
+    pub srtp: Option<SrtpConfiguration>,
+    // UDP endpoints used when the main ones become unreachable
+    pub fallback_endpoints: Option<Vec<Url>>,
+    pub srt: Option<SrtConfiguration>,
+    // MPEG-TS is used if not defined
+    pub tcp_container: Option<TcpContainer>,
+    pub rtsp_auth: Option<RtspAuthConfiguration>,
+    // Auto is used if not defined
+    pub encoder_preference: Option<EncoderPreference>,
+    pub osd: Option<OsdConfiguration>,
+    // Dynamic RTP payload type, from 96 to 127, 96 is used if not defined
+    pub rtp_payload_type: Option<u8>,
+    // Maximum size of the RTP packets, smaller ones are better for some radio links
+    pub mtu: Option<u32>,
+    // GStreamer pipeline used instead of the one we build, with the placeholders
+    // {device}, {endpoint}, {host} and {port} replaced by the stream ones.
+    // RTSP streams expect the payloader to be named pay0.
+    pub custom_pipeline: Option<String>,
+    // Reported to the GCS, 90 degrees of horizontal field of view are assumed if not defined
+    pub fov: Option<FieldOfView>,
 }
 
 impl Default for ExtendedConfiguration {
     fn default() -> Self {
-        Self { thermal: false }
+        Self {
+            thermal: false,
+            thermal_palette: None,
+            profile: None,
+            latency_profile: None,
+            queue: None,
+            udp_sink: None,
+            rtsp_multicast: None,
+            srtp: None,
+            fallback_endpoints: None,
+            srt: None,
+            tcp_container: None,
+            rtsp_auth: None,
+            encoder_preference: None,
+            osd: None,
+            rtp_payload_type: None,
+            mtu: None,
+            custom_pipeline: None,
+            fov: None,
+        }
     }
 }
 
+// Audio captured alongside the video, e.g: from a hydrophone or a USB microphone
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct StreamAudio {
+    // ALSA device, as used by alsasrc: "hw:<card>,<device>"
+    pub device: String,
+    pub encode: AudioEncodeType,
+    pub sample_rate: u32,
+    pub channels: u32,
+}
+
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize, Apiv2Schema)]
 pub struct StreamInformation {
     pub endpoints: Vec<Url>,
     pub configuration: CaptureConfiguration,
     pub extended_configuration: Option<ExtendedConfiguration>,
+    // Only served by RTSP and file endpoints
+    pub audio: Option<StreamAudio>,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, Deserialize, Serialize)]
+pub struct StreamDowngrade {
+    pub reason: String,
+    pub configuration: CaptureConfiguration,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, Default, Serialize)]
+pub struct UdpClientStatistics {
+    pub client: String,
+    pub bytes_sent: u64,
+    pub packets_sent: u64,
+}
+
+#[derive(Apiv2Schema, Debug, Serialize)]
+pub struct StreamStatistics {
+    pub name: String,
+    pub udp_clients: Vec<UdpClientStatistics>,
+    // Sends that failed since the stream started, GStreamer doesn't tell to which client
+    pub udp_send_errors: u64,
+}
+
+// Stream configuration checked without starting it
+#[derive(Apiv2Schema, Debug, Deserialize, Serialize)]
+pub struct StreamValidation {
+    // Empty for redirect streams, served by someone else
+    pub pipeline: String,
+    // Elements used by the pipeline that are not available in this host
+    pub missing_elements: Vec<String>,
+    // Why GStreamer failed to create the pipeline
+    pub error: Option<String>,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PipelineMessageKind {
+    Error,
+    Warning,
+    Eos,
+}
+
+// Message posted to the GStreamer bus of a stream pipeline
+#[derive(Apiv2Schema, Clone, Debug, Deserialize, Serialize)]
+pub struct PipelineMessage {
+    pub timestamp: String,
+    pub kind: PipelineMessageKind,
+    pub message: String,
 }
 
 #[derive(Apiv2Schema, Debug, Deserialize, Serialize)]
 pub struct StreamStatus {
     pub running: bool,
     pub video_and_stream: VideoAndStreamInformation,
+    pub downgrade: Option<StreamDowngrade>,
+    pub pipeline_messages: Vec<PipelineMessage>,
+    // Element encoding the video, when it is encoded by us, e.g: "x265enc"
+    pub encoder: Option<String>,
+    // Result of the last check of the redirected endpoint, for redirect streams
+    pub redirect_health: Option<RedirectHealth>,
+    // The camera was disconnected and a slate is streamed in its place
+    pub camera_lost: bool,
+    // Stopped on request, e.g: by the GCS, until it is resumed
+    pub paused: bool,
+    // File the stream is being recorded to, in the capture path
+    pub recording: Option<String>,
+}
+
+#[derive(Apiv2Schema, Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RedirectHealth {
+    // Not set until the endpoint is checked, or when it can't be checked
+    pub available: Option<bool>,
+    pub last_error: Option<String>,
+    pub last_check: Option<String>,
 }
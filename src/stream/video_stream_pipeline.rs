@@ -0,0 +1,92 @@
+use super::{
+    gst::pipeline_builder::Pipeline,
+    gst::pipeline_runner::PipelineRunner,
+    stream_backend::StreamBackend,
+    types::{PipelineMessage, UdpClientStatistics},
+};
+
+// Stream fed by a single pipeline that runs by itself, e.g: SRT, RTMP, TCP, MPEG-TS, NDI and files.
+// The scheme of the endpoints tells which kind of stream it is.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct VideoStreamPipeline {
+    pipeline_runner: PipelineRunner,
+    pub scheme: String,
+}
+
+impl VideoStreamPipeline {
+    pub fn new(
+        video_and_stream_information: &crate::video_stream::types::VideoAndStreamInformation,
+    ) -> Result<Self, simple_error::SimpleError> {
+        let scheme = video_and_stream_information
+            .stream_information
+            .endpoints
+            .first()
+            .map(|endpoint| endpoint.scheme().to_string())
+            .unwrap_or_default();
+
+        Ok(VideoStreamPipeline {
+            pipeline_runner: PipelineRunner::new(Pipeline::new(video_and_stream_information)?),
+            scheme,
+        })
+    }
+}
+
+impl VideoStreamPipeline {
+    // Only MPEG-TS streams are sent by a multiudpsink, where clients can be added
+    pub fn add_client(&self, host: &str, port: u16) -> bool {
+        self.pipeline_runner.add_udp_client(host, port)
+    }
+
+    pub fn remove_client(&self, host: &str, port: u16) {
+        self.pipeline_runner.remove_udp_client(host, port)
+    }
+}
+
+impl Drop for VideoStreamPipeline {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl StreamBackend for VideoStreamPipeline {
+    fn start(&mut self) -> bool {
+        self.pipeline_runner.start()
+    }
+
+    fn stop(&mut self) -> bool {
+        self.pipeline_runner.stop()
+    }
+
+    fn restart(&mut self) {
+        self.pipeline_runner.restart()
+    }
+
+    fn is_running(&self) -> bool {
+        self.pipeline_runner.is_running()
+    }
+
+    fn pipeline(&self) -> String {
+        self.pipeline_runner.pipeline()
+    }
+
+    fn allow_same_endpoints(&self) -> bool {
+        false
+    }
+
+    fn usb_bandwidth_exhausted(&self) -> bool {
+        self.pipeline_runner.usb_bandwidth_exhausted()
+    }
+
+    fn udp_clients_statistics(&self) -> Vec<UdpClientStatistics> {
+        self.pipeline_runner.udp_clients_statistics()
+    }
+
+    fn udp_send_errors(&self) -> u64 {
+        self.pipeline_runner.udp_send_errors()
+    }
+
+    fn pipeline_messages(&self) -> Vec<PipelineMessage> {
+        self.pipeline_runner.pipeline_messages()
+    }
+}
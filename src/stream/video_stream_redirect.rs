@@ -1,28 +1,77 @@
-use simple_error::SimpleError;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use simple_error::{simple_error, SimpleError, SimpleResult};
+use tracing::*;
+use url::Url;
 
 use super::stream_backend::StreamBackend;
+use super::types::{PipelineMessage, RedirectHealth, UdpClientStatistics};
+
+// Time between two checks of the redirected endpoint
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+// How long to wait for the RTSP server answer, or for an UDP packet
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
 
 #[derive(Debug)]
 pub struct VideoStreamRedirect {
     pub scheme: String,
+    endpoint: Url,
+    health: Arc<Mutex<RedirectHealth>>,
+    probing: Arc<AtomicBool>,
 }
 
 impl VideoStreamRedirect {
-    pub fn new(scheme: String) -> Result<Self, SimpleError> {
-        Ok(Self { scheme })
+    pub fn new(endpoint: Url) -> Result<Self, SimpleError> {
+        Ok(Self {
+            scheme: endpoint.scheme().to_string(),
+            endpoint,
+            health: Default::default(),
+            probing: Default::default(),
+        })
+    }
+
+    pub fn health(&self) -> RedirectHealth {
+        self.health.lock().unwrap().clone()
+    }
+
+    // Shared with the MAVLink camera, to report if the stream can be received
+    #[cfg(feature = "mavlink")]
+    pub fn health_handle(&self) -> Arc<Mutex<RedirectHealth>> {
+        self.health.clone()
     }
 }
 
 impl Drop for VideoStreamRedirect {
-    fn drop(&mut self) {}
+    fn drop(&mut self) {
+        self.stop();
+    }
 }
 
 impl StreamBackend for VideoStreamRedirect {
     fn start(&mut self) -> bool {
-        true
+        if self.probing.swap(true, Ordering::SeqCst) {
+            return true;
+        }
+
+        let endpoint = self.endpoint.clone();
+        let health = self.health.clone();
+        let probing = self.probing.clone();
+        std::thread::Builder::new()
+            .name(format!(
+                "redirect_probe_{}",
+                endpoint.port().unwrap_or_default()
+            ))
+            .spawn(move || probe_loop(endpoint, health, probing))
+            .is_ok()
     }
 
     fn stop(&mut self) -> bool {
+        // The thread is not joined, it finishes after the probe in progress
+        self.probing.store(false, Ordering::SeqCst);
         true
     }
 
@@ -41,4 +90,147 @@ impl StreamBackend for VideoStreamRedirect {
     fn allow_same_endpoints(&self) -> bool {
         false
     }
+
+    fn usb_bandwidth_exhausted(&self) -> bool {
+        false
+    }
+
+    fn udp_clients_statistics(&self) -> Vec<UdpClientStatistics> {
+        vec![]
+    }
+
+    fn udp_send_errors(&self) -> u64 {
+        0
+    }
+
+    fn pipeline_messages(&self) -> Vec<PipelineMessage> {
+        vec![]
+    }
+}
+
+fn probe_loop(endpoint: Url, health: Arc<Mutex<RedirectHealth>>, probing: Arc<AtomicBool>) {
+    while probing.load(Ordering::SeqCst) {
+        let result = match endpoint.scheme() {
+            "rtsp" => probe_rtsp(&endpoint),
+            // There is no TLS support here, a connection is all we can check
+            "rtsps" | "tcp" => socket_address(&endpoint).and_then(|address| {
+                TcpStream::connect_timeout(&address, PROBE_TIMEOUT)
+                    .map(|_| ())
+                    .map_err(|error| simple_error!(format!("Failed to connect: {error}")))
+            }),
+            "udp" | "udp265" | "mpegts" => probe_udp(&endpoint),
+            scheme => {
+                debug!("Redirect streams with {scheme:#?} scheme are not probed.");
+                return;
+            }
+        };
+
+        let available = result.is_ok();
+        let mut health = health.lock().unwrap();
+        if health.available != Some(available) {
+            match &result {
+                Ok(_) => info!("Redirected endpoint {endpoint} is available."),
+                Err(error) => warn!("Redirected endpoint {endpoint} is unavailable: {error}"),
+            }
+        }
+        health.available = Some(available);
+        health.last_error = result.err().map(|error| error.to_string());
+        health.last_check = Some(chrono::Local::now().to_rfc3339());
+        drop(health);
+
+        std::thread::sleep(PROBE_INTERVAL);
+    }
+}
+
+fn socket_address(endpoint: &Url) -> SimpleResult<SocketAddr> {
+    let host = endpoint
+        .host_str()
+        .ok_or_else(|| simple_error!(format!("Endpoint {endpoint} has no host.")))?;
+    let port = endpoint.port().unwrap_or(match endpoint.scheme() {
+        "rtsp" => 554,
+        "rtsps" => 322,
+        _ => 5600,
+    });
+    (host, port)
+        .to_socket_addrs()
+        .map_err(|error| simple_error!(format!("Failed to resolve {host:#?}: {error}")))?
+        .next()
+        .ok_or_else(|| simple_error!(format!("Failed to resolve {host:#?}.")))
+}
+
+// The server should describe the stream, otherwise there is nothing to receive
+fn probe_rtsp(endpoint: &Url) -> SimpleResult<()> {
+    let address = socket_address(endpoint)?;
+    let request =
+        format!("DESCRIBE {endpoint} RTSP/1.0\r\nCSeq: 1\r\nAccept: application/sdp\r\n\r\n");
+
+    let mut stream = TcpStream::connect_timeout(&address, PROBE_TIMEOUT)
+        .map_err(|error| simple_error!(format!("Failed to connect to {address}: {error}")))?;
+    stream
+        .set_read_timeout(Some(PROBE_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(PROBE_TIMEOUT)))
+        .and_then(|_| stream.write_all(request.as_bytes()))
+        .map_err(|error| simple_error!(format!("Failed to send request to {address}: {error}")))?;
+
+    // Only the status line is needed, the SDP content may never finish
+    let mut answer = [0u8; 256];
+    let size = stream
+        .read(&mut answer)
+        .map_err(|error| simple_error!(format!("Failed to read answer from {address}: {error}")))?;
+
+    check_rtsp_answer(&String::from_utf8_lossy(&answer[..size]))
+}
+
+fn check_rtsp_answer(answer: &str) -> SimpleResult<()> {
+    let status = answer.lines().next().unwrap_or_default();
+    if !status.starts_with("RTSP/") {
+        return Err(simple_error!("Invalid answer from RTSP server."));
+    }
+    if !status.contains(" 200 ") {
+        return Err(simple_error!(format!("DESCRIBE failed: {status}")));
+    }
+    Ok(())
+}
+
+// Packets can only be seen when they are sent to this host
+fn probe_udp(endpoint: &Url) -> SimpleResult<()> {
+    let port = socket_address(endpoint)?.port();
+    let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], port)))
+        .map_err(|error| simple_error!(format!("Failed to listen on port {port}: {error}")))?;
+    socket
+        .set_read_timeout(Some(PROBE_TIMEOUT))
+        .map_err(|error| simple_error!(format!("Failed to configure socket: {error}")))?;
+
+    let mut buffer = [0u8; 2048];
+    socket
+        .recv(&mut buffer)
+        .map(|_| ())
+        .map_err(|_| simple_error!(format!("No packets received on port {port}.")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn rtsp_describe() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 256];
+            let size = stream.read(&mut request).unwrap();
+            assert!(String::from_utf8_lossy(&request[..size]).starts_with("DESCRIBE rtsp://"));
+            stream
+                .write_all(b"RTSP/1.0 200 OK\r\nCSeq: 1\r\n\r\n")
+                .unwrap();
+        });
+
+        let endpoint = Url::parse(&format!("rtsp://127.0.0.1:{port}/test")).unwrap();
+        assert!(probe_rtsp(&endpoint).is_ok());
+
+        assert!(check_rtsp_answer("RTSP/1.0 404 Not Found\r\n").is_err());
+        assert!(check_rtsp_answer("HTTP/1.1 200 OK\r\n").is_err());
+    }
 }
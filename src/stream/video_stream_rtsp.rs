@@ -1,24 +1,46 @@
 use super::gst::pipeline_builder::Pipeline;
 use super::stream_backend::StreamBackend;
+use super::types::{PipelineMessage, UdpClientStatistics};
 
 use super::rtsp_server::RTSPServer;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tracing::*;
+
 #[derive(Debug)]
 pub struct VideoStreamRtsp {
     pipeline: Pipeline,
+    endpoint_port: u16,
     endpoint_path: String,
+    usb_bandwidth_exhausted: Arc<AtomicBool>,
+    // The path is released when stopped, and may be taken by the stream replacing this one
+    stopped: bool,
 }
 
 impl VideoStreamRtsp {
     pub fn new(
         video_and_stream_information: &crate::video_stream::types::VideoAndStreamInformation,
+        endpoint_port: u16,
         endpoint_path: String,
     ) -> Result<Self, simple_error::SimpleError> {
         let pipeline = Pipeline::new(video_and_stream_information)?;
-        RTSPServer::add_pipeline(&pipeline.description, &endpoint_path)?;
+        let usb_bandwidth_exhausted = RTSPServer::add_pipeline(
+            &pipeline,
+            endpoint_port,
+            &endpoint_path,
+            video_and_stream_information
+                .stream_information
+                .extended_configuration
+                .as_ref(),
+        )?;
         Ok(VideoStreamRtsp {
             pipeline,
+            endpoint_port,
             endpoint_path,
+            usb_bandwidth_exhausted,
+            stopped: false,
         })
     }
 }
@@ -31,12 +53,18 @@ impl Drop for VideoStreamRtsp {
 
 impl StreamBackend for VideoStreamRtsp {
     fn start(&mut self) -> bool {
-        RTSPServer::start_pipeline(&self.endpoint_path);
+        if let Err(error) = RTSPServer::start_pipeline(self.endpoint_port, &self.endpoint_path) {
+            error!("Failed to start RTSP stream: {error}");
+            return false;
+        }
         true
     }
 
     fn stop(&mut self) -> bool {
-        RTSPServer::stop_pipeline(&self.endpoint_path);
+        if !self.stopped {
+            RTSPServer::stop_pipeline(self.endpoint_port, &self.endpoint_path);
+            self.stopped = true;
+        }
         true
     }
 
@@ -55,4 +83,20 @@ impl StreamBackend for VideoStreamRtsp {
     fn allow_same_endpoints(&self) -> bool {
         false
     }
+
+    fn usb_bandwidth_exhausted(&self) -> bool {
+        self.usb_bandwidth_exhausted.load(Ordering::Relaxed)
+    }
+
+    fn udp_clients_statistics(&self) -> Vec<UdpClientStatistics> {
+        vec![]
+    }
+
+    fn udp_send_errors(&self) -> u64 {
+        0
+    }
+
+    fn pipeline_messages(&self) -> Vec<PipelineMessage> {
+        vec![]
+    }
 }
@@ -0,0 +1,154 @@
+use super::{
+    gst::pipeline_builder::Pipeline,
+    gst::pipeline_runner::PipelineRunner,
+    manager,
+    stream_backend::StreamBackend,
+    types::{PipelineMessage, UdpClientStatistics},
+};
+use crate::video_stream::types::VideoAndStreamInformation;
+
+#[cfg(feature = "rtsp")]
+use super::rtsp_server::RTSPServer;
+#[cfg(feature = "rtsp")]
+use tracing::*;
+
+use simple_error::SimpleResult;
+
+// Stream with endpoints of different schemes, all fed by the same pipeline
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct VideoStreamTee {
+    pipeline_runner: PipelineRunner,
+    // RTSP server port and path, if there is an RTSP endpoint
+    rtsp_endpoint: Option<(u16, String)>,
+    // Scheme of the endpoint advertised over MAVLink, if any of them can be received by GCSs
+    pub scheme: Option<String>,
+}
+
+impl VideoStreamTee {
+    pub fn new(
+        video_and_stream_information: &VideoAndStreamInformation,
+        rtsp_endpoint: Option<(u16, String)>,
+    ) -> SimpleResult<Self> {
+        let rtsp_loopback_port = match &rtsp_endpoint {
+            Some(_) => Some(manager::loopback_port()?),
+            None => None,
+        };
+        let pipeline = Pipeline::new_tee(video_and_stream_information, rtsp_loopback_port)?;
+
+        if let (Some((port, path)), Some(loopback_port)) = (&rtsp_endpoint, rtsp_loopback_port) {
+            add_rtsp_pipeline(video_and_stream_information, *port, path, loopback_port)?;
+        }
+
+        let scheme = video_and_stream_information
+            .stream_information
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.scheme())
+            .find(|scheme| matches!(*scheme, "rtsp" | "rtsps" | "udp" | "mpegts" | "tcp"))
+            .map(|scheme| scheme.to_string());
+
+        Ok(VideoStreamTee {
+            pipeline_runner: PipelineRunner::new(pipeline),
+            rtsp_endpoint,
+            scheme,
+        })
+    }
+}
+
+#[cfg(feature = "rtsp")]
+fn add_rtsp_pipeline(
+    video_and_stream_information: &VideoAndStreamInformation,
+    port: u16,
+    path: &str,
+    loopback_port: u16,
+) -> SimpleResult<()> {
+    let description =
+        Pipeline::build_rtsp_loopback_source(video_and_stream_information, loopback_port)?;
+    RTSPServer::add_pipeline(
+        &Pipeline {
+            description,
+            osd: None,
+        },
+        port,
+        path,
+        video_and_stream_information
+            .stream_information
+            .extended_configuration
+            .as_ref(),
+    )?;
+    Ok(())
+}
+
+#[cfg(not(feature = "rtsp"))]
+fn add_rtsp_pipeline(
+    _video_and_stream_information: &VideoAndStreamInformation,
+    _port: u16,
+    _path: &str,
+    _loopback_port: u16,
+) -> SimpleResult<()> {
+    Err(simple_error::simple_error!(
+        "RTSP endpoints are not available: built without feature \"rtsp\"."
+    ))
+}
+
+impl Drop for VideoStreamTee {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl StreamBackend for VideoStreamTee {
+    fn start(&mut self) -> bool {
+        #[cfg(feature = "rtsp")]
+        if let Some((port, path)) = &self.rtsp_endpoint {
+            if let Err(error) = RTSPServer::start_pipeline(*port, path) {
+                error!("Failed to start RTSP endpoint: {error}");
+                return false;
+            }
+        }
+
+        self.pipeline_runner.start()
+    }
+
+    fn stop(&mut self) -> bool {
+        #[cfg(feature = "rtsp")]
+        if let Some((port, path)) = &self.rtsp_endpoint {
+            RTSPServer::stop_pipeline(*port, path);
+        }
+
+        self.pipeline_runner.stop()
+    }
+
+    fn restart(&mut self) {
+        self.pipeline_runner.restart()
+    }
+
+    fn is_running(&self) -> bool {
+        self.pipeline_runner.is_running()
+    }
+
+    fn pipeline(&self) -> String {
+        self.pipeline_runner.pipeline()
+    }
+
+    fn allow_same_endpoints(&self) -> bool {
+        false
+    }
+
+    fn usb_bandwidth_exhausted(&self) -> bool {
+        self.pipeline_runner.usb_bandwidth_exhausted()
+    }
+
+    fn udp_clients_statistics(&self) -> Vec<UdpClientStatistics> {
+        self.pipeline_runner.udp_clients_statistics()
+    }
+
+    fn udp_send_errors(&self) -> u64 {
+        self.pipeline_runner.udp_send_errors()
+    }
+
+    fn pipeline_messages(&self) -> Vec<PipelineMessage> {
+        self.pipeline_runner.pipeline_messages()
+    }
+}
@@ -1,6 +1,8 @@
 use super::{
-    gst::pipeline_builder::Pipeline, gst::pipeline_runner::PipelineRunner,
+    gst::pipeline_builder::Pipeline,
+    gst::pipeline_runner::PipelineRunner,
     stream_backend::StreamBackend,
+    types::{PipelineMessage, UdpClientStatistics},
 };
 
 #[derive(Debug)]
@@ -19,6 +21,20 @@ impl VideoStreamUdp {
     }
 }
 
+impl VideoStreamUdp {
+    pub fn set_clients(&self, clients: &[(String, u16)]) -> bool {
+        self.pipeline_runner.set_udp_clients(clients)
+    }
+
+    pub fn add_client(&self, host: &str, port: u16) -> bool {
+        self.pipeline_runner.add_udp_client(host, port)
+    }
+
+    pub fn remove_client(&self, host: &str, port: u16) {
+        self.pipeline_runner.remove_udp_client(host, port)
+    }
+}
+
 impl Drop for VideoStreamUdp {
     fn drop(&mut self) {
         self.stop();
@@ -49,4 +65,20 @@ impl StreamBackend for VideoStreamUdp {
     fn allow_same_endpoints(&self) -> bool {
         false
     }
+
+    fn usb_bandwidth_exhausted(&self) -> bool {
+        self.pipeline_runner.usb_bandwidth_exhausted()
+    }
+
+    fn udp_clients_statistics(&self) -> Vec<UdpClientStatistics> {
+        self.pipeline_runner.udp_clients_statistics()
+    }
+
+    fn udp_send_errors(&self) -> u64 {
+        self.pipeline_runner.udp_send_errors()
+    }
+
+    fn pipeline_messages(&self) -> Vec<PipelineMessage> {
+        self.pipeline_runner.pipeline_messages()
+    }
 }
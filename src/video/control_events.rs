@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::ext_controls::{
+    self, ioc, ioctl, IOC_READ, IOC_WRITE, V4L2_CTRL_TYPE_INTEGER64, V4L2_CTRL_TYPE_STRING,
+};
+use super::types::*;
+use super::video_source::{self, VideoSource};
+use super::video_source_local::VideoSourceLocal;
+use crate::event::{self, manager::EventKind};
+
+use v4l::prelude::*;
+
+use tracing::*;
+
+// Controls can be changed by other processes or by the camera itself (e.g: auto exposure),
+// the V4L2 control events keep our cached values updated when that happens.
+// For more information: https://www.kernel.org/doc/html/v5.5/media/uapi/v4l/vidioc-dqevent.html
+
+// From linux/videodev2.h
+const V4L2_EVENT_CTRL: u32 = 3;
+const V4L2_EVENT_CTRL_CH_VALUE: u32 = 1 << 0;
+
+const POLL_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[repr(C)]
+struct V4l2EventSubscription {
+    typ: u32,
+    id: u32,
+    flags: u32,
+    reserved: [u32; 5],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+union V4l2EventCtrlValue {
+    value: i32,
+    value64: i64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct V4l2EventCtrl {
+    changes: u32,
+    typ: u32,
+    value: V4l2EventCtrlValue,
+    flags: u32,
+    minimum: i32,
+    maximum: i32,
+    step: i32,
+    default_value: i32,
+}
+
+#[repr(C)]
+union V4l2EventData {
+    ctrl: V4l2EventCtrl,
+    data: [u64; 8],
+}
+
+#[repr(C)]
+struct V4l2Event {
+    typ: u32,
+    u: V4l2EventData,
+    pending: u32,
+    sequence: u32,
+    timestamp: libc::timespec,
+    id: u32,
+    reserved: [u32; 8],
+}
+
+const VIDIOC_DQEVENT: u64 = ioc::<V4l2Event>(IOC_READ, 89);
+const VIDIOC_SUBSCRIBE_EVENT: u64 = ioc::<V4l2EventSubscription>(IOC_WRITE, 90);
+
+lazy_static! {
+    // Device paths with a thread waiting for their events
+    static ref WATCHED_DEVICES: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+// Start watching the controls of the local cameras that are not watched yet,
+// should be called again when cameras are connected
+pub fn watch_cameras() {
+    for camera in video_source::cameras_available() {
+        let camera = match camera {
+            VideoSourceType::Local(camera) => camera,
+            _ => continue,
+        };
+
+        if !WATCHED_DEVICES
+            .lock()
+            .unwrap()
+            .insert(camera.device_path.clone())
+        {
+            continue;
+        }
+
+        std::thread::Builder::new()
+            .name(format!("controls {}", camera.device_path))
+            .spawn(move || {
+                // Fails when the camera is disconnected
+                if let Err(error) = watch(&camera) {
+                    debug!(
+                        "Stopped watching controls of device {}: {error}",
+                        camera.device_path
+                    );
+                }
+                WATCHED_DEVICES.lock().unwrap().remove(&camera.device_path);
+            })
+            .unwrap();
+    }
+}
+
+fn watch(camera: &VideoSourceLocal) -> std::io::Result<()> {
+    let names: HashMap<u32, String> = camera
+        .controls()
+        .into_iter()
+        .map(|control| (control.id as u32, control.name))
+        .collect();
+    // Devices without controls, like metadata nodes, have nothing to watch
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let device = Device::with_path(&camera.device_path)?;
+    for id in names.keys() {
+        let mut subscription = V4l2EventSubscription {
+            typ: V4L2_EVENT_CTRL,
+            id: *id,
+            flags: 0,
+            reserved: [0; 5],
+        };
+        if let Err(error) = ioctl(&device, VIDIOC_SUBSCRIBE_EVENT, &mut subscription) {
+            debug!(
+                "Failed to subscribe to events of control {id} from device {}: {error}",
+                camera.device_path
+            );
+        }
+    }
+    debug!("Watching controls of device {}.", camera.device_path);
+
+    loop {
+        let mut poll = libc::pollfd {
+            fd: device.handle().fd(),
+            events: libc::POLLPRI,
+            revents: 0,
+        };
+        let result = unsafe { libc::poll(&mut poll, 1, POLL_TIMEOUT.as_millis() as libc::c_int) };
+        if result < 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(error);
+        }
+        if poll.revents & libc::POLLPRI == 0 {
+            if poll.revents & (libc::POLLERR | libc::POLLHUP | libc::POLLNVAL) != 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotConnected,
+                    "Device is no longer available.",
+                ));
+            }
+            continue;
+        }
+
+        let mut event: V4l2Event = unsafe { std::mem::zeroed() };
+        ioctl(&device, VIDIOC_DQEVENT, &mut event)?;
+        if event.typ != V4L2_EVENT_CTRL {
+            continue;
+        }
+
+        let ctrl = unsafe { event.u.ctrl };
+        if ctrl.changes & V4L2_EVENT_CTRL_CH_VALUE == 0 {
+            continue;
+        }
+
+        // String values are not sent in the event
+        let value = match ctrl.typ {
+            V4L2_CTRL_TYPE_STRING => ext_controls::control(&device, event.id)?,
+            V4L2_CTRL_TYPE_INTEGER64 => {
+                v4l::control::Control::Value64(unsafe { ctrl.value.value64 })
+            }
+            _ => v4l::control::Control::Value(unsafe { ctrl.value.value }),
+        };
+        update_control(camera, event.id, &names, value);
+    }
+}
+
+fn update_control(
+    camera: &VideoSourceLocal,
+    control_id: u32,
+    names: &HashMap<u32, String>,
+    value: v4l::control::Control,
+) {
+    let value_description = match &value {
+        v4l::control::Control::Value(value) => value.to_string(),
+        v4l::control::Control::Value64(value) => value.to_string(),
+        v4l::control::Control::String(value) => format!("{value:?}"),
+    };
+
+    video_source::update_cached_control(
+        camera.typ.bus_info(),
+        &camera.device_path,
+        control_id as u64,
+        |control| match (&mut control.configuration, value) {
+            (ControlType::Bool(bool), v4l::control::Control::Value(value)) => {
+                bool.value = value as i64
+            }
+            (ControlType::Slider(slider), v4l::control::Control::Value(value)) => {
+                slider.value = value as i64
+            }
+            (ControlType::Slider(slider), v4l::control::Control::Value64(value)) => {
+                slider.value = value
+            }
+            (ControlType::Menu(menu), v4l::control::Control::Value(value)) => {
+                menu.value = value as i64
+            }
+            (ControlType::String(string), v4l::control::Control::String(value)) => {
+                string.value = value
+            }
+            _ => (),
+        },
+    );
+
+    let name = names
+        .get(&control_id)
+        .cloned()
+        .unwrap_or_else(|| control_id.to_string());
+    debug!(
+        "Control {name:?} of device {} changed to {value_description}.",
+        camera.device_path
+    );
+    event::manager::push(
+        EventKind::Control,
+        format!(
+            "Control {name:?} of camera {:#?} changed to {value_description}",
+            camera.name
+        ),
+    );
+}
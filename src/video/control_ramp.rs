@@ -0,0 +1,140 @@
+use super::types::{ControlType, VideoSourceType};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::*;
+
+// V4L2 IDs for the controls that move optics or the camera itself, and that
+// make the video jump when changed at once.
+// For more information: https://www.kernel.org/doc/html/v5.5/media/uapi/v4l/ext-ctrls-camera.html
+const V4L2_CID_PAN_ABSOLUTE: u64 = 0x009a0908;
+const V4L2_CID_TILT_ABSOLUTE: u64 = 0x009a0909;
+const V4L2_CID_FOCUS_ABSOLUTE: u64 = 0x009a090a;
+const V4L2_CID_ZOOM_ABSOLUTE: u64 = 0x009a090d;
+
+// How often the ramp moves the control towards its target
+const RAMP_PERIOD: std::time::Duration = std::time::Duration::from_millis(50);
+
+#[derive(Debug)]
+struct Ramp {
+    target: i64,
+    units_per_second: u32,
+}
+
+lazy_static! {
+    // Ramps being actuated, indexed by source string and control id
+    static ref RAMPS: Arc<Mutex<HashMap<(String, u64), Ramp>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+pub fn is_rampable(control_id: u64) -> bool {
+    matches!(
+        control_id,
+        V4L2_CID_PAN_ABSOLUTE
+            | V4L2_CID_TILT_ABSOLUTE
+            | V4L2_CID_FOCUS_ABSOLUTE
+            | V4L2_CID_ZOOM_ABSOLUTE
+    )
+}
+
+// Moves the control to the target value, limited to `units_per_second`.
+// If there is a ramp running for the same control, its target is updated.
+pub fn ramp_control(
+    camera: VideoSourceType,
+    control_id: u64,
+    target: i64,
+    units_per_second: u32,
+) -> std::io::Result<()> {
+    let control = camera
+        .inner()
+        .controls()
+        .into_iter()
+        .find(|control| control.id == control_id)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Control ID '{control_id}' is not valid."),
+            )
+        })?;
+
+    let slider = match control.configuration {
+        ControlType::Slider(slider) => slider,
+        _ => return camera.inner().set_control_by_id(control_id, target),
+    };
+
+    if units_per_second == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Ramp rate should be greater than zero.",
+        ));
+    }
+
+    let key = (camera.inner().source_string().to_string(), control_id);
+    let ramp = Ramp {
+        target: target.clamp(slider.min, slider.max),
+        units_per_second,
+    };
+
+    let mut ramps = RAMPS.lock().unwrap();
+    if ramps.insert(key.clone(), ramp).is_some() {
+        // The running task will pick the new target
+        return Ok(());
+    }
+    drop(ramps);
+
+    let step = std::cmp::max(slider.step, 1);
+    let min = slider.min;
+    let current = slider.value;
+
+    std::thread::Builder::new()
+        .name(format!("control_ramp_{}_{control_id}", key.0))
+        .spawn(move || ramp_loop(camera, key, current, min, step))?;
+
+    Ok(())
+}
+
+fn ramp_loop(camera: VideoSourceType, key: (String, u64), mut current: i64, min: i64, step: i64) {
+    let (source_string, control_id) = key.clone();
+    debug!("Starting control ramp for {source_string:?} control {control_id}.");
+
+    loop {
+        std::thread::sleep(RAMP_PERIOD);
+
+        let mut ramps = RAMPS.lock().unwrap();
+        let ramp = match ramps.get(&key) {
+            Some(ramp) => ramp,
+            None => break,
+        };
+
+        let max_delta = std::cmp::max(
+            (ramp.units_per_second as f64 * RAMP_PERIOD.as_secs_f64()) as i64,
+            step,
+        );
+        let delta = (ramp.target - current).clamp(-max_delta, max_delta);
+        // Keep the value aligned with the control steps, unless we are done
+        let mut next = current + delta;
+        if next != ramp.target {
+            next = min + ((next - min) / step) * step;
+        }
+        let done = next == ramp.target;
+        if done {
+            ramps.remove(&key);
+        }
+        drop(ramps);
+
+        if next != current {
+            if let Err(error) = camera.inner().set_control_by_id(control_id, next) {
+                error!("Failed to ramp {source_string:?} control {control_id} to {next}. Reason: {error:#?}");
+                RAMPS.lock().unwrap().remove(&key);
+                break;
+            }
+            current = next;
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    debug!("Finished control ramp for {source_string:?} control {control_id}.");
+}
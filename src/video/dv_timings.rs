@@ -0,0 +1,333 @@
+use super::ext_controls::{ioc, ioctl, IOC_READ, IOC_WRITE};
+use super::types::FrameInterval;
+
+use v4l::prelude::*;
+
+use tracing::*;
+
+// HDMI receivers, like the tc358743 HDMI to CSI-2 bridge, don't have frame sizes to choose from,
+// they capture what the HDMI source sends. The source picks its mode from the EDID of the receiver,
+// and the detected mode (DV timings) has to be set in the receiver before capturing.
+// For more information: https://www.kernel.org/doc/html/v5.5/media/uapi/v4l/dv-timings.html
+
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+struct V4l2BtTimings {
+    width: u32,
+    height: u32,
+    interlaced: u32,
+    polarities: u32,
+    pixelclock: u64,
+    hfrontporch: u32,
+    hsync: u32,
+    hbackporch: u32,
+    vfrontporch: u32,
+    vsync: u32,
+    vbackporch: u32,
+    il_vfrontporch: u32,
+    il_vsync: u32,
+    il_vbackporch: u32,
+    standards: u32,
+    flags: u32,
+    picture_aspect: [u32; 2],
+    cea861_vic: u8,
+    hdmi_vic: u8,
+    reserved: [u8; 46],
+}
+
+// The timings are in a union with 32 reserved words, 4 bytes bigger than them
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+struct V4l2DvTimings {
+    typ: u32,
+    bt: V4l2BtTimings,
+    reserved: [u8; 4],
+}
+
+#[repr(C)]
+struct V4l2Edid {
+    pad: u32,
+    start_block: u32,
+    blocks: u32,
+    reserved: [u32; 5],
+    edid: *mut u8,
+}
+
+const VIDIOC_G_EDID: u64 = ioc::<V4l2Edid>(IOC_READ | IOC_WRITE, 40);
+const VIDIOC_S_EDID: u64 = ioc::<V4l2Edid>(IOC_READ | IOC_WRITE, 41);
+const VIDIOC_S_DV_TIMINGS: u64 = ioc::<V4l2DvTimings>(IOC_READ | IOC_WRITE, 87);
+const VIDIOC_G_DV_TIMINGS: u64 = ioc::<V4l2DvTimings>(IOC_READ | IOC_WRITE, 88);
+const VIDIOC_QUERY_DV_TIMINGS: u64 = ioc::<V4l2DvTimings>(IOC_READ, 99);
+
+const EDID_BLOCK_SIZE: usize = 128;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DvTimings {
+    pub width: u32,
+    pub height: u32,
+    pub interlaced: bool,
+    pub frame_interval: FrameInterval,
+}
+
+impl From<&V4l2BtTimings> for DvTimings {
+    fn from(bt: &V4l2BtTimings) -> Self {
+        let interlaced = bt.interlaced != 0;
+        let horizontal_total = bt.width + bt.hfrontporch + bt.hsync + bt.hbackporch;
+        let mut vertical_total = bt.height + bt.vfrontporch + bt.vsync + bt.vbackporch;
+        if interlaced {
+            vertical_total += bt.il_vfrontporch + bt.il_vsync + bt.il_vbackporch;
+        }
+
+        Self {
+            width: bt.width,
+            height: bt.height,
+            interlaced,
+            frame_interval: frame_interval(
+                bt.pixelclock,
+                horizontal_total as u64 * vertical_total as u64,
+            ),
+        }
+    }
+}
+
+// NTSC rates, like 59.94, are kept as fractions of 1001
+fn frame_interval(pixel_clock: u64, pixels_per_frame: u64) -> FrameInterval {
+    if pixel_clock == 0 || pixels_per_frame == 0 {
+        return FrameInterval {
+            numerator: 1,
+            denominator: 0,
+        };
+    }
+
+    let rate = pixel_clock as f64 / pixels_per_frame as f64;
+    if (rate - rate.round()).abs() < 0.05 {
+        return FrameInterval {
+            numerator: 1,
+            denominator: rate.round() as u32,
+        };
+    }
+    FrameInterval {
+        numerator: 1001,
+        denominator: (rate * 1001.0).round() as u32,
+    }
+}
+
+// HDMI receivers report the timings of their input, other capture devices don't
+pub fn is_supported(device_path: &str) -> bool {
+    let device = match Device::with_path(device_path) {
+        Ok(device) => device,
+        Err(_) => return false,
+    };
+    let mut timings: V4l2DvTimings = unsafe { std::mem::zeroed() };
+    ioctl(&device, VIDIOC_G_DV_TIMINGS, &mut timings).is_ok()
+}
+
+// Timings of the HDMI signal, fails without signal or while it is not stable
+pub fn query(device_path: &str) -> std::io::Result<DvTimings> {
+    let device = Device::with_path(device_path)?;
+    let mut timings: V4l2DvTimings = unsafe { std::mem::zeroed() };
+    ioctl(&device, VIDIOC_QUERY_DV_TIMINGS, &mut timings)?;
+    // Fields of packed structures can't be borrowed
+    let bt = timings.bt;
+    Ok(DvTimings::from(&bt))
+}
+
+// Sets our EDID when the receiver has none, and the timings of the current HDMI signal,
+// which should be done before every capture since the HDMI source may have changed its mode
+pub fn prepare(device_path: &str) -> std::io::Result<DvTimings> {
+    let device = Device::with_path(device_path)?;
+
+    match edid_blocks(&device) {
+        Ok(0) => {
+            let mut edid = default_edid();
+            let mut description = V4l2Edid {
+                pad: 0,
+                start_block: 0,
+                blocks: 1,
+                reserved: [0; 5],
+                edid: edid.as_mut_ptr(),
+            };
+            match ioctl(&device, VIDIOC_S_EDID, &mut description) {
+                Ok(_) => info!("EDID set for HDMI receiver {device_path}."),
+                Err(error) => warn!("Failed to set EDID of HDMI receiver {device_path}: {error}"),
+            }
+        }
+        Ok(_) => (),
+        Err(error) => debug!("Failed to get EDID of HDMI receiver {device_path}: {error}"),
+    }
+
+    let mut timings: V4l2DvTimings = unsafe { std::mem::zeroed() };
+    ioctl(&device, VIDIOC_QUERY_DV_TIMINGS, &mut timings)?;
+    ioctl(&device, VIDIOC_S_DV_TIMINGS, &mut timings)?;
+
+    let bt = timings.bt;
+    let timings = DvTimings::from(&bt);
+    debug!("HDMI receiver {device_path} timings: {timings:?}");
+    Ok(timings)
+}
+
+fn edid_blocks(device: &Device) -> std::io::Result<u32> {
+    let mut description = V4l2Edid {
+        pad: 0,
+        start_block: 0,
+        blocks: 0,
+        reserved: [0; 5],
+        edid: std::ptr::null_mut(),
+    };
+    ioctl(device, VIDIOC_G_EDID, &mut description)?;
+    Ok(description.blocks)
+}
+
+struct DetailedTiming {
+    pixel_clock_khz: u32,
+    horizontal_active: u16,
+    horizontal_blank: u16,
+    horizontal_front_porch: u16,
+    horizontal_sync: u16,
+    vertical_active: u16,
+    vertical_blank: u16,
+    vertical_front_porch: u16,
+    vertical_sync: u16,
+}
+
+impl DetailedTiming {
+    // Detailed timing descriptor, from the VESA E-EDID standard
+    fn encode(&self) -> [u8; 18] {
+        let clock = ((self.pixel_clock_khz / 10) as u16).to_le_bytes();
+        let high = |value: u16| (value >> 8) as u8;
+        [
+            clock[0],
+            clock[1],
+            self.horizontal_active as u8,
+            self.horizontal_blank as u8,
+            (high(self.horizontal_active) << 4) | high(self.horizontal_blank),
+            self.vertical_active as u8,
+            self.vertical_blank as u8,
+            (high(self.vertical_active) << 4) | high(self.vertical_blank),
+            self.horizontal_front_porch as u8,
+            self.horizontal_sync as u8,
+            ((self.vertical_front_porch as u8 & 0x0f) << 4) | (self.vertical_sync as u8 & 0x0f),
+            (high(self.horizontal_front_porch) & 0x03) << 6
+                | (high(self.horizontal_sync) & 0x03) << 4
+                | ((self.vertical_front_porch >> 4) as u8 & 0x03) << 2
+                | ((self.vertical_sync >> 4) as u8 & 0x03),
+            // No image size and borders
+            0,
+            0,
+            0,
+            0,
+            0,
+            // Digital separate sync, with positive polarities
+            0x1e,
+        ]
+    }
+}
+
+// EDID of a DVI display preferring 1080p30 and accepting 720p60, modes that fit in the
+// two CSI-2 lanes of most boards. RGB is converted by the receivers to the captured format.
+fn default_edid() -> [u8; EDID_BLOCK_SIZE] {
+    let mut edid = [0u8; EDID_BLOCK_SIZE];
+
+    edid[0..8].copy_from_slice(&[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]);
+    // Manufacturer "MCM", product 1, no serial number, made in 2022
+    edid[8..10].copy_from_slice(&[0x34, 0x6d]);
+    edid[10] = 1;
+    edid[17] = (2022 - 1990) as u8;
+    // EDID 1.3, digital input, gamma of 2.2, RGB with the preferred timing first
+    edid[18] = 1;
+    edid[19] = 3;
+    edid[20] = 0x80;
+    edid[23] = 0x78;
+    edid[24] = 0x0a;
+    // sRGB chromaticity
+    edid[25..35].copy_from_slice(&[0xee, 0x91, 0xa3, 0x54, 0x4c, 0x99, 0x26, 0x0f, 0x50, 0x54]);
+    // 640x480@60, established for every display
+    edid[35] = 0x20;
+    // Unused standard timings
+    edid[38..54].fill(0x01);
+
+    let full_hd_30 = DetailedTiming {
+        pixel_clock_khz: 74250,
+        horizontal_active: 1920,
+        horizontal_blank: 280,
+        horizontal_front_porch: 88,
+        horizontal_sync: 44,
+        vertical_active: 1080,
+        vertical_blank: 45,
+        vertical_front_porch: 4,
+        vertical_sync: 5,
+    };
+    let hd_60 = DetailedTiming {
+        pixel_clock_khz: 74250,
+        horizontal_active: 1280,
+        horizontal_blank: 370,
+        horizontal_front_porch: 110,
+        horizontal_sync: 40,
+        vertical_active: 720,
+        vertical_blank: 30,
+        vertical_front_porch: 5,
+        vertical_sync: 5,
+    };
+    edid[54..72].copy_from_slice(&full_hd_30.encode());
+    edid[72..90].copy_from_slice(&hd_60.encode());
+
+    // Display name, terminated by a line feed and padded with spaces
+    let mut name = [0x20u8; 13];
+    let text = b"CameraManager";
+    name[..text.len()].copy_from_slice(text);
+    edid[90..95].copy_from_slice(&[0x00, 0x00, 0x00, 0xfc, 0x00]);
+    edid[95..108].copy_from_slice(&name);
+
+    // Dummy descriptor
+    edid[108..113].copy_from_slice(&[0x00, 0x00, 0x00, 0x10, 0x00]);
+
+    // All bytes should sum to zero
+    let sum = edid[..127]
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    edid[127] = 0u8.wrapping_sub(sum);
+    edid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timings_frame_interval() {
+        // 1080p60 and 1080p59.94
+        assert_eq!(
+            frame_interval(148_500_000, 2200 * 1125),
+            FrameInterval {
+                numerator: 1,
+                denominator: 60
+            }
+        );
+        assert_eq!(
+            frame_interval(148_351_648, 2200 * 1125),
+            FrameInterval {
+                numerator: 1001,
+                denominator: 60000
+            }
+        );
+        assert_eq!(std::mem::size_of::<V4l2DvTimings>(), 132);
+    }
+
+    #[test]
+    fn edid_block() {
+        let edid = default_edid();
+        assert_eq!(
+            &edid[..8],
+            &[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]
+        );
+        assert_eq!(
+            edid.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)),
+            0
+        );
+        // 74.25 MHz, 1920 + 280 horizontal and 1080 + 45 vertical pixels
+        assert_eq!(
+            &edid[54..62],
+            &[0x01, 0x1d, 0x80, 0x18, 0x71, 0x38, 0x2d, 0x40]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::ffi::CString;
+use std::mem::size_of;
+
+use v4l::control::Control;
+use v4l::prelude::*;
+
+// The v4l crate only handles 32-bit integer controls (VIDIOC_G_CTRL and VIDIOC_S_CTRL),
+// 64-bit integer and string controls are accessed with the extended controls API.
+// For more information: https://www.kernel.org/doc/html/v5.5/media/uapi/v4l/vidioc-g-ext-ctrls.html
+
+// From linux/videodev2.h
+pub(super) const V4L2_CTRL_TYPE_INTEGER64: u32 = 5;
+pub(super) const V4L2_CTRL_TYPE_STRING: u32 = 7;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+union V4l2ExtControlValue {
+    value: i32,
+    value64: i64,
+    string: *mut libc::c_char,
+}
+
+#[repr(C, packed)]
+struct V4l2ExtControl {
+    id: u32,
+    size: u32,
+    reserved2: [u32; 1],
+    value: V4l2ExtControlValue,
+}
+
+#[repr(C)]
+struct V4l2ExtControls {
+    which: u32,
+    count: u32,
+    error_idx: u32,
+    request_fd: i32,
+    reserved: [u32; 1],
+    controls: *mut V4l2ExtControl,
+}
+
+#[repr(C)]
+struct V4l2QueryExtCtrl {
+    id: u32,
+    typ: u32,
+    name: [libc::c_char; 32],
+    minimum: i64,
+    maximum: i64,
+    step: u64,
+    default_value: i64,
+    flags: u32,
+    elem_size: u32,
+    elems: u32,
+    nr_of_dims: u32,
+    dims: [u32; 4],
+    reserved: [u32; 32],
+}
+
+// Directions of the _IOC macro
+pub(super) const IOC_WRITE: u64 = 1;
+pub(super) const IOC_READ: u64 = 2;
+
+// Equivalent to the _IOC macro, with 'V' as the type
+pub(super) const fn ioc<T>(direction: u64, number: u64) -> u64 {
+    (direction << 30) | ((size_of::<T>() as u64) << 16) | ((b'V' as u64) << 8) | number
+}
+
+const VIDIOC_G_EXT_CTRLS: u64 = ioc::<V4l2ExtControls>(IOC_READ | IOC_WRITE, 71);
+const VIDIOC_S_EXT_CTRLS: u64 = ioc::<V4l2ExtControls>(IOC_READ | IOC_WRITE, 72);
+const VIDIOC_QUERY_EXT_CTRL: u64 = ioc::<V4l2QueryExtCtrl>(IOC_READ | IOC_WRITE, 103);
+
+// Description with the 64-bit ranges, the ones from VIDIOC_QUERYCTRL are truncated to 32 bits
+#[derive(Debug)]
+pub struct Description {
+    pub is_integer64: bool,
+    pub is_string: bool,
+    pub minimum: i64,
+    // Maximum length, for string controls
+    pub maximum: i64,
+    pub step: u64,
+    pub default: i64,
+}
+
+pub(super) fn ioctl<T>(device: &Device, request: u64, argument: &mut T) -> std::io::Result<()> {
+    let result = unsafe {
+        libc::ioctl(
+            device.handle().fd(),
+            request as _,
+            argument as *mut T as *mut libc::c_void,
+        )
+    };
+    if result < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+pub fn description(device: &Device, id: u32) -> std::io::Result<Description> {
+    let mut query: V4l2QueryExtCtrl = unsafe { std::mem::zeroed() };
+    query.id = id;
+    ioctl(device, VIDIOC_QUERY_EXT_CTRL, &mut query)?;
+
+    Ok(Description {
+        is_integer64: query.typ == V4L2_CTRL_TYPE_INTEGER64,
+        is_string: query.typ == V4L2_CTRL_TYPE_STRING,
+        minimum: query.minimum,
+        maximum: query.maximum,
+        step: query.step,
+        default: query.default_value,
+    })
+}
+
+// Read the current value, as Value, Value64 or String depending on the control type
+pub fn control(device: &Device, id: u32) -> std::io::Result<Control> {
+    let description = description(device, id)?;
+
+    // String controls are written by the driver in our buffer, with the null terminator
+    let mut buffer: Vec<u8> = match description.is_string {
+        true => vec![0; description.maximum.max(0) as usize + 1],
+        false => vec![],
+    };
+    let mut control = V4l2ExtControl {
+        id,
+        size: buffer.len() as u32,
+        reserved2: [0],
+        value: match description.is_string {
+            true => V4l2ExtControlValue {
+                string: buffer.as_mut_ptr() as *mut libc::c_char,
+            },
+            false => V4l2ExtControlValue { value64: 0 },
+        },
+    };
+    run_ext_controls(device, VIDIOC_G_EXT_CTRLS, &mut control)?;
+
+    let value = control.value;
+    unsafe {
+        if description.is_string {
+            let length = buffer.iter().position(|&byte| byte == 0).unwrap_or(0);
+            return Ok(Control::String(
+                String::from_utf8_lossy(&buffer[..length]).into_owned(),
+            ));
+        }
+        if description.is_integer64 {
+            return Ok(Control::Value64(value.value64));
+        }
+        Ok(Control::Value(value.value))
+    }
+}
+
+pub fn set_control(device: &Device, id: u32, value: Control) -> std::io::Result<()> {
+    // Should live until the ioctl returns
+    let string;
+    let mut control = match value {
+        Control::Value(value) => V4l2ExtControl {
+            id,
+            size: 0,
+            reserved2: [0],
+            value: V4l2ExtControlValue { value },
+        },
+        Control::Value64(value64) => V4l2ExtControl {
+            id,
+            size: 0,
+            reserved2: [0],
+            value: V4l2ExtControlValue { value64 },
+        },
+        Control::String(value) => {
+            string = CString::new(value).map_err(|error| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, error.to_string())
+            })?;
+            V4l2ExtControl {
+                id,
+                size: string.as_bytes_with_nul().len() as u32,
+                reserved2: [0],
+                value: V4l2ExtControlValue {
+                    string: string.as_ptr() as *mut libc::c_char,
+                },
+            }
+        }
+    };
+    run_ext_controls(device, VIDIOC_S_EXT_CTRLS, &mut control)
+}
+
+fn run_ext_controls(
+    device: &Device,
+    request: u64,
+    control: &mut V4l2ExtControl,
+) -> std::io::Result<()> {
+    let mut controls = V4l2ExtControls {
+        // The control class works with kernels older than V4L2_CTRL_WHICH_CUR_VAL
+        which: control.id & 0x0fff0000,
+        count: 1,
+        error_idx: 0,
+        request_fd: 0,
+        reserved: [0],
+        controls: control,
+    };
+    ioctl(device, request, &mut controls)
+}
@@ -0,0 +1,193 @@
+use std::io::Read;
+use std::os::unix::io::FromRawFd;
+use std::time::{Duration, Instant};
+
+use super::video_source;
+use crate::event::{self, manager::EventKind};
+
+use tracing::*;
+
+// Kernel uevents are received before udev creates the device nodes and sets
+// their permissions, so the callback only runs after the devices settle down
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+const READ_TIMEOUT: Duration = Duration::from_millis(250);
+
+// Multicast group of the uevents sent by the kernel
+const KERNEL_UEVENT_GROUP: u32 = 1;
+
+#[derive(Debug, PartialEq)]
+enum HotplugAction {
+    Add,
+    Remove,
+}
+
+#[derive(Debug, PartialEq)]
+struct HotplugEvent {
+    action: HotplugAction,
+    // E.g: "/dev/video0"
+    device: String,
+}
+
+// Watch the V4L2 devices being connected or disconnected, clearing the capability
+// cache and calling `on_change` once the devices settle down after each batch of events
+pub fn start(on_change: impl Fn() + Send + 'static) {
+    std::thread::Builder::new()
+        .name("hotplug".into())
+        .spawn(move || {
+            if let Err(error) = watch(on_change) {
+                error!("Failed to monitor hotplug events, cameras will only be detected when the service starts: {error}");
+            }
+        })
+        .unwrap();
+}
+
+fn watch(on_change: impl Fn()) -> std::io::Result<()> {
+    let mut socket = uevent_socket()?;
+    debug!("Monitoring hotplug events.");
+
+    let mut buffer = [0u8; 8192];
+    let mut last_event: Option<Instant> = None;
+    loop {
+        match socket.read(&mut buffer) {
+            Ok(size) => {
+                if let Some(event) = parse_uevent(&buffer[..size]) {
+                    info!("Hotplug event: {event:?}");
+                    event::manager::push(
+                        EventKind::Hotplug,
+                        match event.action {
+                            HotplugAction::Add => format!("Device {:#?} connected", event.device),
+                            HotplugAction::Remove => {
+                                format!("Device {:#?} disconnected", event.device)
+                            }
+                        },
+                    );
+                    last_event = Some(Instant::now());
+                }
+            }
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) => {}
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+
+        if last_event.map_or(false, |instant| instant.elapsed() >= SETTLE_TIME) {
+            last_event = None;
+            video_source::clear_capability_cache();
+            on_change();
+        }
+    }
+}
+
+// Netlink socket receiving the kernel uevents, read with a timeout
+fn uevent_socket() -> std::io::Result<std::fs::File> {
+    unsafe {
+        let fd = libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            libc::NETLINK_KOBJECT_UEVENT,
+        );
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // The file closes the socket when dropped
+        let socket = std::fs::File::from_raw_fd(fd);
+
+        let mut address: libc::sockaddr_nl = std::mem::zeroed();
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = KERNEL_UEVENT_GROUP;
+        if libc::bind(
+            fd,
+            &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        ) < 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let timeout = libc::timeval {
+            tv_sec: READ_TIMEOUT.as_secs() as libc::time_t,
+            tv_usec: READ_TIMEOUT.subsec_micros() as libc::suseconds_t,
+        };
+        if libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_RCVTIMEO,
+            &timeout as *const libc::timeval as *const libc::c_void,
+            std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+        ) < 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(socket)
+    }
+}
+
+// Kernel uevents are a header followed by null separated KEY=VALUE pairs, e.g:
+// "add@/devices/.../video4linux/video0\0ACTION=add\0SUBSYSTEM=video4linux\0DEVNAME=video0\0"
+fn parse_uevent(message: &[u8]) -> Option<HotplugEvent> {
+    let message = String::from_utf8_lossy(message);
+    let fields = message
+        .split('\0')
+        .skip(1)
+        .filter_map(|field| field.split_once('='));
+
+    let (mut action, mut subsystem, mut device) = (None, None, None);
+    for (key, value) in fields {
+        match key {
+            "ACTION" => action = Some(value),
+            "SUBSYSTEM" => subsystem = Some(value),
+            "DEVNAME" => device = Some(value),
+            _ => (),
+        }
+    }
+
+    if subsystem? != "video4linux" {
+        return None;
+    }
+
+    let action = match action? {
+        "add" => HotplugAction::Add,
+        "remove" => HotplugAction::Remove,
+        _ => return None,
+    };
+
+    let device = device?;
+    let device = match device.starts_with('/') {
+        true => device.to_string(),
+        false => format!("/dev/{device}"),
+    };
+
+    Some(HotplugEvent { action, device })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uevent_decode() {
+        let message = b"add@/devices/platform/scb/fd500000.pcie/usb1/1-1/1-1.4/1-1.4:1.0/video4linux/video2\0\
+            ACTION=add\0\
+            DEVPATH=/devices/platform/scb/fd500000.pcie/usb1/1-1/1-1.4/1-1.4:1.0/video4linux/video2\0\
+            SUBSYSTEM=video4linux\0\
+            DEVNAME=video2\0\
+            SEQNUM=2319\0";
+        assert_eq!(
+            parse_uevent(message),
+            Some(HotplugEvent {
+                action: HotplugAction::Add,
+                device: "/dev/video2".into(),
+            })
+        );
+
+        let message = b"remove@/devices/platform/scb/usb1/1-1/1-1.4\0\
+            ACTION=remove\0\
+            SUBSYSTEM=usb\0\
+            DEVNAME=bus/usb/001/005\0";
+        assert_eq!(parse_uevent(message), None);
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+
+use tracing::*;
+
+// Cameras may expose multiple video nodes, like UVC cameras with a metadata node,
+// all of them are entities of the media device of the camera.
+// For more information: https://www.kernel.org/doc/html/v5.5/media/uapi/mediactl/media-ioc-enum-entities.html
+
+// From linux/media.h
+const MEDIA_ENT_ID_FLAG_NEXT: u32 = 1 << 31;
+const MEDIA_ENT_FL_DEFAULT: u32 = 1 << 0;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct MediaEntityDev {
+    major: u32,
+    minor: u32,
+}
+
+#[repr(C)]
+union MediaEntityData {
+    dev: MediaEntityDev,
+    raw: [u8; 184],
+}
+
+#[repr(C)]
+struct MediaEntityDesc {
+    id: u32,
+    name: [libc::c_char; 32],
+    typ: u32,
+    revision: u32,
+    flags: u32,
+    group_id: u32,
+    pads: u16,
+    links: u16,
+    reserved: [u32; 4],
+    data: MediaEntityData,
+}
+
+// Equivalent to the _IOWR macro, with '|' as the type
+const MEDIA_IOC_ENUM_ENTITIES: u64 =
+    (3 << 30) | ((size_of::<MediaEntityDesc>() as u64) << 16) | ((b'|' as u64) << 8) | 0x01;
+
+#[derive(Debug)]
+struct Entity {
+    id: u32,
+    flags: u32,
+    major: u32,
+    minor: u32,
+}
+
+// Returns the video nodes of each media device, e.g: [["/dev/video0", "/dev/video1"]],
+// with the default node of the device first
+pub fn video_node_groups() -> Vec<Vec<String>> {
+    let video_nodes: HashMap<(u32, u32), String> = device_paths("/dev/video")
+        .into_iter()
+        .filter_map(|path| {
+            let device = std::fs::metadata(&path).ok()?.rdev();
+            let number = (libc::major(device), libc::minor(device));
+            Some((number, path))
+        })
+        .collect();
+
+    device_paths("/dev/media")
+        .iter()
+        .filter_map(|media_path| match entities(media_path) {
+            Ok(entities) => Some(entities),
+            Err(error) => {
+                debug!("Failed to enumerate entities of media device {media_path}: {error}");
+                None
+            }
+        })
+        .map(|mut entities| {
+            entities.sort_by_key(|entity| (entity.flags & MEDIA_ENT_FL_DEFAULT == 0, entity.id));
+            entities
+                .iter()
+                .filter_map(|entity| video_nodes.get(&(entity.major, entity.minor)).cloned())
+                .collect::<Vec<String>>()
+        })
+        .filter(|group| !group.is_empty())
+        .collect()
+}
+
+fn device_paths(prefix: &str) -> Vec<String> {
+    let mut paths: Vec<String> = match std::fs::read_dir("/dev/") {
+        Ok(entries) => entries
+            .filter_map(|entry| Some(entry.ok()?.path().to_str()?.to_string()))
+            .filter(|path| path.starts_with(prefix))
+            .collect(),
+        Err(_) => return vec![],
+    };
+    paths.sort();
+    paths
+}
+
+fn entities(media_path: &str) -> std::io::Result<Vec<Entity>> {
+    let file = std::fs::File::open(media_path)?;
+
+    let mut entities = vec![];
+    let mut id = 0;
+    loop {
+        let mut description: MediaEntityDesc = unsafe { std::mem::zeroed() };
+        description.id = id | MEDIA_ENT_ID_FLAG_NEXT;
+        let result = unsafe {
+            libc::ioctl(
+                file.as_raw_fd(),
+                MEDIA_IOC_ENUM_ENTITIES as _,
+                &mut description as *mut MediaEntityDesc as *mut libc::c_void,
+            )
+        };
+        if result < 0 {
+            let error = std::io::Error::last_os_error();
+            // The last entity was already enumerated
+            if error.raw_os_error() == Some(libc::EINVAL) {
+                return Ok(entities);
+            }
+            return Err(error);
+        }
+
+        id = description.id;
+        let dev = unsafe { description.data.dev };
+        entities.push(Entity {
+            id,
+            flags: description.flags,
+            major: dev.major,
+            minor: dev.minor,
+        });
+    }
+}
@@ -1,7 +1,29 @@
+// V4L2 and the Linux kernel interfaces are only available on Linux
+#[cfg(target_os = "linux")]
+pub mod control_events;
+pub mod control_ramp;
+#[cfg(target_os = "linux")]
+pub mod dv_timings;
+#[cfg(target_os = "linux")]
+pub mod ext_controls;
+#[cfg(target_os = "linux")]
+pub mod hotplug;
+#[cfg(target_os = "linux")]
+pub mod media_controller;
+pub mod platform;
+#[cfg(target_os = "linux")]
+pub mod quirks;
 pub mod types;
+#[cfg(target_os = "linux")]
+pub mod uvc_h264;
 pub mod video_source;
 pub mod xml;
 
+pub mod video_source_audio;
+pub mod video_source_file;
 pub mod video_source_gst;
+pub mod video_source_ip;
+#[cfg(target_os = "linux")]
 pub mod video_source_local;
 pub mod video_source_redirect;
+pub mod video_source_screen;
@@ -0,0 +1,55 @@
+use super::types::VideoSourceType;
+#[cfg(target_os = "linux")]
+use super::video_source::VideoSourceAvailable;
+use super::video_source_gst;
+#[cfg(target_os = "linux")]
+use super::video_source_local::VideoSourceLocal;
+
+// Cameras connected to this computer, enumerated with the API of each operating system
+pub trait PlatformCameras {
+    fn cameras_available() -> Vec<VideoSourceType>;
+}
+
+// V4L2 devices, and the cameras only available with libcamera
+#[cfg(target_os = "linux")]
+pub struct Linux;
+
+#[cfg(target_os = "linux")]
+impl PlatformCameras for Linux {
+    fn cameras_available() -> Vec<VideoSourceType> {
+        [
+            VideoSourceLocal::cameras_available(),
+            video_source_gst::libcamera_cameras(),
+        ]
+        .concat()
+    }
+}
+
+// Media Foundation devices, e.g: webcams and capture cards of topside computers
+#[cfg(target_os = "windows")]
+pub struct Windows;
+
+#[cfg(target_os = "windows")]
+impl PlatformCameras for Windows {
+    fn cameras_available() -> Vec<VideoSourceType> {
+        video_source_gst::media_foundation_cameras()
+    }
+}
+
+// AVFoundation devices, e.g: the built-in camera of laptops
+#[cfg(target_os = "macos")]
+pub struct MacOs;
+
+#[cfg(target_os = "macos")]
+impl PlatformCameras for MacOs {
+    fn cameras_available() -> Vec<VideoSourceType> {
+        video_source_gst::avfoundation_cameras()
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub type Platform = Linux;
+#[cfg(target_os = "windows")]
+pub type Platform = Windows;
+#[cfg(target_os = "macos")]
+pub type Platform = MacOs;
@@ -0,0 +1,171 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::types::{FrameInterval, Size};
+use super::video_source_local::{UsbDescriptor, VideoSourceLocal};
+use crate::cli;
+
+use tracing::*;
+
+// Known problems of specific camera models, and how to work around them.
+// The quirks are loaded from the JSON file passed with --camera-quirks, e.g:
+// [{"usb_id": "046d:082d", "ignored_framerates": [60], "warmup_ms": 500}]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CameraQuirks {
+    // USB vendor and product IDs, in hexadecimal, e.g: "046d:082d"
+    #[serde(default)]
+    pub usb_id: Option<String>,
+    // Regular expression matched with the name of the camera
+    #[serde(default)]
+    pub card_name: Option<String>,
+    // H264 profile of the caps of the camera, e.g: "constrained-baseline",
+    // for cameras that don't deliver the profile that they advertise
+    #[serde(default)]
+    pub h264_profile: Option<String>,
+    // Frame rates that the camera advertises but can't deliver
+    #[serde(default)]
+    pub ignored_framerates: Vec<u32>,
+    // Time to wait before capturing, for cameras that fail or send broken frames after opened
+    #[serde(default)]
+    pub warmup_ms: Option<u64>,
+}
+
+lazy_static! {
+    static ref QUIRKS: Vec<CameraQuirks> = load();
+}
+
+fn load() -> Vec<CameraQuirks> {
+    let path = match cli::manager::camera_quirks_file() {
+        Some(path) => path,
+        None => return vec![],
+    };
+
+    let quirks = std::fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|content| {
+            serde_json::from_str::<Vec<CameraQuirks>>(&content).map_err(|error| error.to_string())
+        });
+    match quirks {
+        Ok(quirks) => {
+            info!("Loaded {} camera quirks from {path:?}.", quirks.len());
+            quirks
+        }
+        Err(error) => {
+            error!("Failed to load camera quirks from {path:?}. Reason: {error}");
+            vec![]
+        }
+    }
+}
+
+impl CameraQuirks {
+    fn matches(&self, usb_id: Option<&str>, card_name: &str) -> bool {
+        if self.usb_id.is_none() && self.card_name.is_none() {
+            return false;
+        }
+
+        if let Some(our_usb_id) = &self.usb_id {
+            if usb_id.map_or(true, |usb_id| !our_usb_id.eq_ignore_ascii_case(usb_id)) {
+                return false;
+            }
+        }
+
+        if let Some(pattern) = &self.card_name {
+            match Regex::new(pattern) {
+                Ok(regex) if regex.is_match(card_name) => (),
+                Ok(_) => return false,
+                Err(error) => {
+                    warn!("Ignoring quirks with invalid card name {pattern:?}: {error}");
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    pub fn filter_sizes(&self, sizes: &mut Vec<Size>) {
+        if self.ignored_framerates.is_empty() {
+            return;
+        }
+
+        for size in sizes.iter_mut() {
+            size.intervals
+                .retain(|interval| !self.ignored_framerates.contains(&framerate(interval)));
+        }
+        sizes.retain(|size| !size.intervals.is_empty());
+    }
+}
+
+fn framerate(interval: &FrameInterval) -> u32 {
+    (interval.denominator as f64 / interval.numerator.max(1) as f64).round() as u32
+}
+
+// Quirks of the first entry matching the camera, none if it has no known problems
+pub fn for_camera(camera: &VideoSourceLocal) -> CameraQuirks {
+    if QUIRKS.is_empty() {
+        return CameraQuirks::default();
+    }
+
+    // Cameras from the settings may have been stored without USB descriptor
+    let usb_id = camera
+        .usb
+        .clone()
+        .or_else(|| UsbDescriptor::from_device(&camera.device_path))
+        .map(|usb| usb.usb_id());
+    let quirks = QUIRKS
+        .iter()
+        .find(|quirks| quirks.matches(usb_id.as_deref(), &camera.name));
+    match quirks {
+        Some(quirks) => {
+            trace!("Camera {} quirks: {quirks:?}", camera.device_path);
+            quirks.clone()
+        }
+        None => CameraQuirks::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quirks_match() {
+        let quirks: CameraQuirks =
+            serde_json::from_str(r#"{"usb_id": "046D:082D", "ignored_framerates": [60]}"#).unwrap();
+        assert!(quirks.matches(Some("046d:082d"), "HD Pro Webcam C920"));
+        assert!(!quirks.matches(Some("046d:0825"), "HD Pro Webcam C920"));
+        assert!(!quirks.matches(None, "HD Pro Webcam C920"));
+
+        let quirks = CameraQuirks {
+            card_name: Some("^USB Capture".into()),
+            ..Default::default()
+        };
+        assert!(quirks.matches(None, "USB Capture HDMI"));
+        assert!(!quirks.matches(None, "Some USB Capture"));
+        assert!(!CameraQuirks::default().matches(None, "USB Capture HDMI"));
+
+        let interval = |denominator| FrameInterval {
+            numerator: 1,
+            denominator,
+        };
+        let mut sizes = vec![
+            Size {
+                width: 1920,
+                height: 1080,
+                intervals: vec![interval(60)],
+            },
+            Size {
+                width: 1280,
+                height: 720,
+                intervals: vec![interval(60), interval(30)],
+            },
+        ];
+        CameraQuirks {
+            ignored_framerates: vec![60],
+            ..Default::default()
+        }
+        .filter_sizes(&mut sizes);
+        assert_eq!(sizes.len(), 1);
+        assert_eq!(sizes[0].intervals, vec![interval(30)]);
+    }
+}
@@ -1,15 +1,26 @@
 use super::video_source::VideoSource;
+use super::video_source_audio::VideoSourceAudio;
+use super::video_source_file::VideoSourceFile;
 use super::video_source_gst::VideoSourceGst;
+use super::video_source_ip::VideoSourceIp;
+#[cfg(target_os = "linux")]
 use super::video_source_local::VideoSourceLocal;
 use super::video_source_redirect::VideoSourceRedirect;
+use super::video_source_screen::VideoSourceScreen;
 use paperclip::actix::Apiv2Schema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Apiv2Schema, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum VideoSourceType {
     Gst(VideoSourceGst),
+    // V4L2 devices
+    #[cfg(target_os = "linux")]
     Local(VideoSourceLocal),
     Redirect(VideoSourceRedirect),
+    Audio(VideoSourceAudio),
+    Ip(VideoSourceIp),
+    File(VideoSourceFile),
+    Screen(VideoSourceScreen),
 }
 
 #[derive(Apiv2Schema, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
@@ -19,6 +30,34 @@ pub enum VideoEncodeType {
     H264,
     MJPG,
     YUYV,
+    // 16-bit grayscale, provided by thermal cameras
+    Y16,
+    // 8-bit raw sensor data, provided by machine vision cameras
+    Bayer(BayerFormat),
+    VP8,
+    VP9,
+    AV1,
+}
+
+// Order of the color filters, named after the first 2x2 block of pixels
+#[derive(Apiv2Schema, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum BayerFormat {
+    BGGR,
+    GBRG,
+    GRBG,
+    RGGB,
+}
+
+impl BayerFormat {
+    // Format of the video/x-bayer caps
+    pub fn gst_format(&self) -> &'static str {
+        match self {
+            BayerFormat::BGGR => "bggr",
+            BayerFormat::GBRG => "gbrg",
+            BayerFormat::GRBG => "grbg",
+            BayerFormat::RGGB => "rggb",
+        }
+    }
 }
 
 #[derive(Apiv2Schema, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
@@ -54,6 +93,7 @@ pub enum ControlType {
     Bool(ControlBool),
     Slider(ControlSlider),
     Menu(ControlMenu),
+    String(ControlString),
 }
 
 #[derive(Apiv2Schema, Clone, Debug, Default, Serialize)]
@@ -70,11 +110,11 @@ pub struct ControlBool {
 
 #[derive(Apiv2Schema, Clone, Debug, Serialize)]
 pub struct ControlSlider {
-    pub default: i32,
+    pub default: i64,
     pub value: i64,
-    pub step: i32,
-    pub max: i32,
-    pub min: i32,
+    pub step: i64,
+    pub max: i64,
+    pub min: i64,
 }
 
 #[derive(Apiv2Schema, Clone, Debug, Serialize)]
@@ -84,6 +124,12 @@ pub struct ControlMenu {
     pub options: Vec<ControlOption>,
 }
 
+#[derive(Apiv2Schema, Clone, Debug, Serialize)]
+pub struct ControlString {
+    pub value: String,
+    pub max_length: u32,
+}
+
 #[derive(Apiv2Schema, Clone, Debug, Serialize)]
 pub struct ControlOption {
     pub name: String,
@@ -93,10 +139,104 @@ pub struct ControlOption {
 impl VideoSourceType {
     pub fn inner(&self) -> &(dyn VideoSource + '_) {
         match self {
+            #[cfg(target_os = "linux")]
             VideoSourceType::Local(local) => local,
             VideoSourceType::Gst(gst) => gst,
             VideoSourceType::Redirect(redirect) => redirect,
+            VideoSourceType::Audio(audio) => audio,
+            VideoSourceType::Ip(ip) => ip,
+            VideoSourceType::File(file) => file,
+            VideoSourceType::Screen(screen) => screen,
+        }
+    }
+
+    // Cameras streaming their frames as they are captured, encoded or not, while the other
+    // sources are decoded to raw frames
+    pub fn is_camera(&self) -> bool {
+        match self {
+            #[cfg(target_os = "linux")]
+            VideoSourceType::Local(_) => true,
+            VideoSourceType::Ip(_) => true,
+            _ => false,
+        }
+    }
+}
+
+// Why a control value was rejected, with the values accepted by the control
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlValueError {
+    OutOfRange { value: i64, min: i64, max: i64 },
+    NotAligned { value: i64, min: i64, step: i64 },
+    InvalidOption { value: i64, options: Vec<i64> },
+}
+
+impl std::fmt::Display for ControlValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ControlValueError::OutOfRange { value, min, max } => {
+                write!(
+                    f,
+                    "Value {value} is out of range, it should be from {min} to {max}."
+                )
+            }
+            ControlValueError::NotAligned { value, min, step } => write!(
+                f,
+                "Value {value} is not a valid step, it should be {min} plus a multiple of {step}."
+            ),
+            ControlValueError::InvalidOption { value, options } => {
+                write!(
+                    f,
+                    "Value {value} is not a valid option, options are: {options:?}."
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ControlValueError {}
+
+impl Control {
+    // Check the value before it is sent to the camera, since drivers
+    // may reject it with a generic error or clamp it silently
+    pub fn validate(&self, value: i64) -> Result<(), ControlValueError> {
+        match &self.configuration {
+            ControlType::Bool(_) => {
+                if !(0..=1).contains(&value) {
+                    return Err(ControlValueError::OutOfRange {
+                        value,
+                        min: 0,
+                        max: 1,
+                    });
+                }
+            }
+            ControlType::Slider(slider) => {
+                if !(slider.min..=slider.max).contains(&value) {
+                    return Err(ControlValueError::OutOfRange {
+                        value,
+                        min: slider.min,
+                        max: slider.max,
+                    });
+                }
+                if slider.step > 1 && (value - slider.min) % slider.step != 0 {
+                    return Err(ControlValueError::NotAligned {
+                        value,
+                        min: slider.min,
+                        step: slider.step,
+                    });
+                }
+            }
+            ControlType::Menu(menu) => {
+                if !menu.options.iter().any(|option| option.value == value) {
+                    return Err(ControlValueError::InvalidOption {
+                        value,
+                        options: menu.options.iter().map(|option| option.value).collect(),
+                    });
+                }
+            }
+            // Strings are checked by their setter
+            ControlType::String(_) => (),
         }
+        Ok(())
     }
 }
 
@@ -105,8 +245,17 @@ impl VideoEncodeType {
     pub fn from_str(fourcc: &str) -> VideoEncodeType {
         return match fourcc {
             "H264" => VideoEncodeType::H264,
+            "HEVC" => VideoEncodeType::H265,
             "MJPG" => VideoEncodeType::MJPG,
             "YUYV" => VideoEncodeType::YUYV,
+            "Y16 " => VideoEncodeType::Y16,
+            "BA81" => VideoEncodeType::Bayer(BayerFormat::BGGR),
+            "GBRG" => VideoEncodeType::Bayer(BayerFormat::GBRG),
+            "GRBG" => VideoEncodeType::Bayer(BayerFormat::GRBG),
+            "RGGB" => VideoEncodeType::Bayer(BayerFormat::RGGB),
+            "VP80" => VideoEncodeType::VP8,
+            "VP90" => VideoEncodeType::VP9,
+            "AV01" => VideoEncodeType::AV1,
             _ => VideoEncodeType::UNKNOWN(fourcc.to_string()),
         };
     }
@@ -121,6 +270,7 @@ impl Default for ControlType {
     }
 }
 
+#[cfg(target_os = "linux")]
 pub static STANDARD_SIZES: &[(u32, u32); 16] = &[
     (7680, 4320),
     (7200, 3060),
@@ -139,3 +289,85 @@ pub static STANDARD_SIZES: &[(u32, u32); 16] = &[
     (320, 240),
     (256, 144),
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        assert_eq!(VideoEncodeType::from_str("YUYV"), VideoEncodeType::YUYV);
+        assert_eq!(VideoEncodeType::from_str("Y16 "), VideoEncodeType::Y16);
+        assert_eq!(
+            VideoEncodeType::from_str("BA81"),
+            VideoEncodeType::Bayer(BayerFormat::BGGR)
+        );
+        assert_eq!(
+            VideoEncodeType::from_str("RGGB"),
+            VideoEncodeType::Bayer(BayerFormat::RGGB)
+        );
+        assert_eq!(
+            VideoEncodeType::from_str("NV12"),
+            VideoEncodeType::UNKNOWN("NV12".into())
+        );
+    }
+
+    #[test]
+    fn control_validation() {
+        let slider = Control {
+            configuration: ControlType::Slider(ControlSlider {
+                default: 0,
+                value: 0,
+                step: 10,
+                max: 100,
+                min: -100,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(slider.validate(-30), Ok(()));
+        assert_eq!(
+            slider.validate(110),
+            Err(ControlValueError::OutOfRange {
+                value: 110,
+                min: -100,
+                max: 100
+            })
+        );
+        assert_eq!(
+            slider.validate(15),
+            Err(ControlValueError::NotAligned {
+                value: 15,
+                min: -100,
+                step: 10
+            })
+        );
+
+        let menu = Control {
+            configuration: ControlType::Menu(ControlMenu {
+                default: 1,
+                value: 1,
+                options: vec![
+                    ControlOption {
+                        name: "Manual Mode".into(),
+                        value: 1,
+                    },
+                    ControlOption {
+                        name: "Aperture Priority Mode".into(),
+                        value: 3,
+                    },
+                ],
+            }),
+            ..Default::default()
+        };
+        assert_eq!(menu.validate(3), Ok(()));
+        assert_eq!(
+            menu.validate(2),
+            Err(ControlValueError::InvalidOption {
+                value: 2,
+                options: vec![1, 3]
+            })
+        );
+
+        assert!(Control::default().validate(2).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use std::mem::size_of;
+use std::os::unix::io::AsRawFd;
+
+use super::video_source_local;
+
+use tracing::*;
+
+// UVC 1.1 cameras (e.g: older Logitech C920 and Sonix based ones) don't have a V4L2 H264 format,
+// the H264 stream is muxed into the MJPG frames and configured with an extension unit.
+// For more information: https://www.usb.org/sites/default/files/USB_Video_Class_1_1_090711.zip
+// and the "H.264 Payload" document of the UVC 1.5 specification.
+
+// GUID of the H264 extension unit, {A29E7641-DE04-47E3-8B2B-F4341AFF003B}, as stored in the descriptors
+const UVC_H264_GUID: [u8; 16] = [
+    0x41, 0x76, 0x9e, 0xa2, 0x04, 0xde, 0xe3, 0x47, 0x8b, 0x2b, 0xf4, 0x34, 0x1a, 0xff, 0x00, 0x3b,
+];
+
+// From the USB and UVC specifications
+const USB_DT_INTERFACE: u8 = 0x04;
+const USB_DT_CS_INTERFACE: u8 = 0x24;
+const USB_CLASS_VIDEO: u8 = 0x0e;
+const UVC_SC_VIDEOCONTROL: u8 = 0x01;
+const UVC_VC_EXTENSION_UNIT: u8 = 0x06;
+
+// From linux/uvcvideo.h and the H264 extension unit selectors
+const UVC_GET_LEN: u8 = 0x85;
+const UVCX_VIDEO_CONFIG_PROBE: u8 = 0x01;
+
+#[repr(C)]
+struct UvcXuControlQuery {
+    unit: u8,
+    selector: u8,
+    query: u8,
+    size: u16,
+    data: *mut u8,
+}
+
+// Equivalent to the _IOWR macro, with 'u' as the type
+const UVCIOC_CTRL_QUERY: u64 =
+    (3 << 30) | ((size_of::<UvcXuControlQuery>() as u64) << 16) | ((b'u' as u64) << 8) | 0x21;
+
+// Returns the ID of the H264 extension unit, when the camera has one that answers to the probe control
+pub fn extension_unit(device_path: &str) -> Option<u8> {
+    let descriptors =
+        std::fs::read(video_source_local::usb_device_path(device_path)?.join("descriptors"))
+            .ok()?;
+    let unit = find_extension_unit(&descriptors, &UVC_H264_GUID)?;
+
+    match probe_control_length(device_path, unit) {
+        Ok(length) if length > 0 => Some(unit),
+        Ok(_) => None,
+        Err(error) => {
+            debug!("H264 extension unit {unit} of {device_path} is not accessible: {error}");
+            None
+        }
+    }
+}
+
+fn find_extension_unit(descriptors: &[u8], guid: &[u8; 16]) -> Option<u8> {
+    let mut is_video_control = false;
+    let mut remaining = descriptors;
+
+    while remaining.len() >= 2 {
+        let length = remaining[0] as usize;
+        if length < 2 || length > remaining.len() {
+            break;
+        }
+        let descriptor = &remaining[..length];
+        remaining = &remaining[length..];
+
+        match descriptor[1] {
+            USB_DT_INTERFACE if length >= 7 => {
+                is_video_control =
+                    descriptor[5] == USB_CLASS_VIDEO && descriptor[6] == UVC_SC_VIDEOCONTROL;
+            }
+            // bUnitID is followed by guidExtensionCode
+            USB_DT_CS_INTERFACE
+                if is_video_control
+                    && length >= 20
+                    && descriptor[2] == UVC_VC_EXTENSION_UNIT
+                    && &descriptor[4..20] == guid =>
+            {
+                return Some(descriptor[3]);
+            }
+            _ => (),
+        }
+    }
+
+    None
+}
+
+fn probe_control_length(device_path: &str, unit: u8) -> std::io::Result<u16> {
+    let file = std::fs::File::open(device_path)?;
+
+    let mut length = [0u8; 2];
+    let mut query = UvcXuControlQuery {
+        unit,
+        selector: UVCX_VIDEO_CONFIG_PROBE,
+        query: UVC_GET_LEN,
+        size: length.len() as u16,
+        data: length.as_mut_ptr(),
+    };
+    let result = unsafe {
+        libc::ioctl(
+            file.as_raw_fd(),
+            UVCIOC_CTRL_QUERY as _,
+            &mut query as *mut UvcXuControlQuery as *mut libc::c_void,
+        )
+    };
+    if result < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(u16::from_le_bytes(length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_unit_descriptor() {
+        let mut extension_unit = vec![28, USB_DT_CS_INTERFACE, UVC_VC_EXTENSION_UNIT, 12];
+        extension_unit.extend_from_slice(&UVC_H264_GUID);
+        extension_unit.extend_from_slice(&[16, 1, 3, 1, 3, 0xff, 0xff, 0]);
+
+        let control_interface = [9, USB_DT_INTERFACE, 0, 0, 1, USB_CLASS_VIDEO, 1, 0, 0];
+        let streaming_interface = [9, USB_DT_INTERFACE, 1, 0, 1, USB_CLASS_VIDEO, 2, 0, 0];
+
+        let descriptors = [&control_interface[..], &extension_unit].concat();
+        assert_eq!(find_extension_unit(&descriptors, &UVC_H264_GUID), Some(12));
+
+        // Streaming interfaces have other descriptors with the same subtype, e.g: VS_FORMAT_MJPEG
+        let descriptors = [&streaming_interface[..], &extension_unit].concat();
+        assert_eq!(find_extension_unit(&descriptors, &UVC_H264_GUID), None);
+
+        assert_eq!(find_extension_unit(&[0, 1, 2], &UVC_H264_GUID), None);
+    }
+}
@@ -1,15 +1,41 @@
+use super::control_ramp;
+use super::platform::{Platform, PlatformCameras};
 use super::types::*;
+use super::video_source_audio::VideoSourceAudio;
+use super::video_source_file::VideoSourceFile;
 use super::video_source_gst::VideoSourceGst;
-use super::video_source_local::VideoSourceLocal;
+use super::video_source_ip::VideoSourceIp;
+#[cfg(target_os = "linux")]
+use super::video_source_local::{VideoSourceLocal, VideoSourceLocalType};
 use super::video_source_redirect::VideoSourceRedirect;
+use super::video_source_screen::VideoSourceScreen;
+use crate::settings;
+#[cfg(target_os = "linux")]
+use std::{collections::HashMap, sync::Mutex};
 use tracing::*;
 
+// Formats and controls of the local cameras, as enumerating them is slow and can
+// disturb running captures. Indexed by the bus information and device path,
+// since the same path may be used by another camera after a hotplug.
+#[cfg(target_os = "linux")]
+#[derive(Default)]
+struct CapabilityCache {
+    formats: HashMap<(String, String), Vec<Format>>,
+    controls: HashMap<(String, String), Vec<Control>>,
+}
+
+#[cfg(target_os = "linux")]
+lazy_static! {
+    static ref CAPABILITY_CACHE: Mutex<CapabilityCache> = Mutex::new(CapabilityCache::default());
+}
+
 pub trait VideoSource {
     fn name(&self) -> &String;
     fn source_string(&self) -> &str;
     fn formats(&self) -> Vec<Format>;
     fn set_control_by_name(&self, control_name: &str, value: i64) -> std::io::Result<()>;
     fn set_control_by_id(&self, control_id: u64, value: i64) -> std::io::Result<()>;
+    fn set_control_string_by_id(&self, control_id: u64, value: &str) -> std::io::Result<()>;
     fn control_value_by_name(&self, control_name: &str) -> std::io::Result<i64>;
     fn control_value_by_id(&self, control_id: u64) -> std::io::Result<i64>;
     fn controls(&self) -> Vec<Control>;
@@ -23,9 +49,13 @@ pub trait VideoSourceAvailable {
 
 pub fn cameras_available() -> Vec<VideoSourceType> {
     return [
-        &VideoSourceLocal::cameras_available()[..],
+        &Platform::cameras_available()[..],
         &VideoSourceGst::cameras_available()[..],
         &VideoSourceRedirect::cameras_available()[..],
+        &VideoSourceAudio::cameras_available()[..],
+        &VideoSourceIp::cameras_available()[..],
+        &VideoSourceFile::cameras_available()[..],
+        &VideoSourceScreen::cameras_available()[..],
     ]
     .concat();
 }
@@ -40,6 +70,22 @@ pub fn get_video_source(source_string: &str) -> Result<VideoSourceType, std::io:
         return Ok(camera.clone());
     }
 
+    if let Some(ip_source) = VideoSourceIp::from_url(source_string) {
+        return Ok(VideoSourceType::Ip(ip_source));
+    }
+
+    if let Some(file_source) = VideoSourceFile::from_url(source_string) {
+        return Ok(VideoSourceType::File(file_source));
+    }
+
+    if let Some(screen_source) = VideoSourceScreen::from_str(source_string) {
+        return Ok(VideoSourceType::Screen(screen_source));
+    }
+
+    if let Some(fake_source) = VideoSourceGst::fake_from_str(source_string) {
+        return Ok(VideoSourceType::Gst(fake_source));
+    }
+
     let sources_available: Vec<String> = cameras
         .iter()
         .map(|source| source.inner().source_string().to_string())
@@ -53,12 +99,204 @@ pub fn get_video_source(source_string: &str) -> Result<VideoSourceType, std::io:
     ));
 }
 
+// Returns the cached formats of the device, enumerating them only when not cached yet
+#[cfg(target_os = "linux")]
+pub fn cached_formats(
+    bus_info: &str,
+    device_path: &str,
+    enumerate: impl FnOnce() -> Vec<Format>,
+) -> Vec<Format> {
+    let key = (bus_info.to_string(), device_path.to_string());
+    if let Some(formats) = CAPABILITY_CACHE.lock().unwrap().formats.get(&key) {
+        return formats.clone();
+    }
+
+    let formats = enumerate();
+    // Devices that fail to be enumerated are tried again in the next call
+    if !formats.is_empty() {
+        CAPABILITY_CACHE
+            .lock()
+            .unwrap()
+            .formats
+            .insert(key, formats.clone());
+    }
+    formats
+}
+
+// Returns the cached controls of the device, enumerating them only when not cached yet.
+// The control values change, so they should be read again by the caller.
+#[cfg(target_os = "linux")]
+pub fn cached_controls(
+    bus_info: &str,
+    device_path: &str,
+    enumerate: impl FnOnce() -> Vec<Control>,
+) -> Vec<Control> {
+    let key = (bus_info.to_string(), device_path.to_string());
+    if let Some(controls) = CAPABILITY_CACHE.lock().unwrap().controls.get(&key) {
+        return controls.clone();
+    }
+
+    let controls = enumerate();
+    if !controls.is_empty() {
+        CAPABILITY_CACHE
+            .lock()
+            .unwrap()
+            .controls
+            .insert(key, controls.clone());
+    }
+    controls
+}
+
+// Updates a control of the cached ones, returning false if it is not cached
+#[cfg(target_os = "linux")]
+pub fn update_cached_control(
+    bus_info: &str,
+    device_path: &str,
+    control_id: u64,
+    update: impl FnOnce(&mut Control),
+) -> bool {
+    let key = (bus_info.to_string(), device_path.to_string());
+    let mut cache = CAPABILITY_CACHE.lock().unwrap();
+    let control = cache
+        .controls
+        .get_mut(&key)
+        .and_then(|controls| controls.iter_mut().find(|control| control.id == control_id));
+    match control {
+        Some(control) => {
+            update(control);
+            true
+        }
+        None => false,
+    }
+}
+
+// Should be called when cameras are connected or disconnected
+#[cfg(target_os = "linux")]
+pub fn clear_capability_cache() {
+    debug!("Clearing camera capability cache.");
+    let mut cache = CAPABILITY_CACHE.lock().unwrap();
+    cache.formats.clear();
+    cache.controls.clear();
+}
+
 pub fn set_control(source_string: &str, control_id: u64, value: i64) -> std::io::Result<()> {
     let camera = get_video_source(source_string)?;
     debug!("Set camera ({source_string}) control ({control_id}) value ({value}).");
     return camera.inner().set_control_by_id(control_id, value);
 }
 
+pub fn set_control_string(
+    source_string: &str,
+    control_id: u64,
+    value: &str,
+) -> std::io::Result<()> {
+    let camera = get_video_source(source_string)?;
+    debug!("Set camera ({source_string}) control ({control_id}) string ({value:?}).");
+    return camera.inner().set_control_string_by_id(control_id, value);
+}
+
+// Changes the control gradually, at a maximum rate of `units_per_second`,
+// for the controls that would make the video jump (zoom, focus, pan and tilt).
+// Any other control is changed at once.
+pub fn ramp_control(
+    source_string: &str,
+    control_id: u64,
+    value: i64,
+    units_per_second: u32,
+) -> std::io::Result<()> {
+    let camera = get_video_source(source_string)?;
+    if !control_ramp::is_rampable(control_id) {
+        debug!("Control ({control_id}) can't be ramped, setting it at once.");
+        return camera.inner().set_control_by_id(control_id, value);
+    }
+
+    debug!("Ramp camera ({source_string}) control ({control_id}) to value ({value}) at {units_per_second} units per second.");
+    return control_ramp::ramp_control(camera, control_id, value, units_per_second);
+}
+
+// Sources of the same camera that can be captured at the same time, each with its formats.
+// Cameras with multiple capture nodes have one slot for each node, other sources have only one.
+pub fn stream_slots(video_source: &VideoSourceType) -> Vec<(VideoSourceType, Vec<Format>)> {
+    match video_source {
+        #[cfg(target_os = "linux")]
+        VideoSourceType::Local(source) => source
+            .capture_nodes()
+            .into_iter()
+            .map(|device_path| {
+                let node = VideoSourceLocal {
+                    device_path,
+                    ..source.clone()
+                };
+                let formats = node.node_formats();
+                (VideoSourceType::Local(node), formats)
+            })
+            .collect(),
+        _ => vec![(video_source.clone(), video_source.inner().formats())],
+    }
+}
+
+// USB bus path of a local camera, which doesn't change when it reconnects
+pub fn usb_bus(source_string: &str) -> Option<String> {
+    match get_video_source(source_string).ok()? {
+        #[cfg(target_os = "linux")]
+        VideoSourceType::Local(VideoSourceLocal {
+            typ: VideoSourceLocalType::Usb(bus),
+            ..
+        }) => Some(bus),
+        _ => None,
+    }
+}
+
+// Applies the control values stored by the user, since UVC cameras lose them
+// when they are power cycled. Should run when the service starts and when cameras reconnect.
+pub fn apply_control_defaults() {
+    let cameras = cameras_available();
+    for control in settings::manager::control_defaults() {
+        #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+        let sources: Vec<&VideoSourceType> = match &control.bus {
+            Some(bus) => cameras
+                .iter()
+                .filter(|camera| match camera {
+                    #[cfg(target_os = "linux")]
+                    VideoSourceType::Local(VideoSourceLocal {
+                        typ: VideoSourceLocalType::Usb(camera_bus),
+                        ..
+                    }) => camera_bus == bus,
+                    _ => false,
+                })
+                .collect(),
+            None => cameras
+                .iter()
+                .filter(|camera| camera.inner().source_string() == control.source)
+                .collect(),
+        };
+        if sources.is_empty() {
+            debug!(
+                "Camera ({}) of control {} is not connected.",
+                control.source, control.id
+            );
+            continue;
+        }
+
+        // Cameras may have more than one device, only one of them has the controls
+        let errors: Vec<std::io::Error> = sources
+            .iter()
+            .map_while(|camera| {
+                camera
+                    .inner()
+                    .set_control_by_id(control.id, control.value)
+                    .err()
+            })
+            .collect();
+        if errors.len() == sources.len() {
+            warn!(
+                "Failed to apply stored value of control {} for camera ({}). Reason: {errors:?}",
+                control.id, control.source
+            );
+        }
+    }
+}
+
 pub fn reset_controls(source_string: &str) -> Result<(), Vec<std::io::Error>> {
     let camera = get_video_source(source_string);
     if let Err(error) = camera {
@@ -75,15 +313,14 @@ pub fn reset_controls(source_string: &str) -> Result<(), Vec<std::io::Error>> {
         }
 
         let default_value = match &control.configuration {
-            ControlType::Bool(bool) => bool.default,
+            ControlType::Bool(bool) => bool.default as i64,
             ControlType::Slider(slider) => slider.default,
-            ControlType::Menu(menu) => menu.default,
+            ControlType::Menu(menu) => menu.default as i64,
+            // V4L2 doesn't provide defaults for string controls
+            ControlType::String(_) => continue,
         };
 
-        if let Err(error) = camera
-            .inner()
-            .set_control_by_id(control.id, default_value as i64)
-        {
+        if let Err(error) = camera.inner().set_control_by_id(control.id, default_value) {
             let error_message = format!(
                 "Error when trying to reset control '{}' (id {}). Error: {}.",
                 control.name,
@@ -0,0 +1,158 @@
+use super::types::*;
+use super::video_source::{VideoSource, VideoSourceAvailable};
+
+use paperclip::actix::Apiv2Schema;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::*;
+
+#[derive(Apiv2Schema, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum VideoSourceAudioType {
+    // ALSA device, as used by alsasrc: "hw:<card>,<device>"
+    Alsa(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VideoSourceAudio {
+    pub name: String,
+    pub source: VideoSourceAudioType,
+}
+
+impl VideoSourceAudio {
+    // Each line of /proc/asound/pcm follows:
+    // <card>-<device>: <id> : <name> : playback <n> : capture <n>
+    // E.g: 01-00: USB Audio : USB Audio : capture 1
+    // Only devices with "capture" are audio sources.
+    fn from_proc_asound_pcm_line(line: &str) -> Option<Self> {
+        let regex = Regex::new(
+            r"^(?P<card>\d+)-(?P<device>\d+): (?P<id>[^:]*) : (?P<name>[^:]*) :.*capture",
+        )
+        .unwrap();
+        let captures = regex.captures(line)?;
+
+        let card = captures["card"].parse::<u32>().ok()?;
+        let device = captures["device"].parse::<u32>().ok()?;
+
+        Some(VideoSourceAudio {
+            name: captures["name"].trim().to_string(),
+            source: VideoSourceAudioType::Alsa(format!("hw:{card},{device}")),
+        })
+    }
+}
+
+impl VideoSource for VideoSourceAudio {
+    fn name(&self) -> &String {
+        return &self.name;
+    }
+
+    fn source_string(&self) -> &str {
+        match &self.source {
+            VideoSourceAudioType::Alsa(device) => &device,
+        }
+    }
+
+    fn formats(&self) -> Vec<Format> {
+        // Audio sources don't have video formats
+        vec![]
+    }
+
+    fn set_control_by_name(&self, _control_name: &str, _value: i64) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Audio source doesn't have controls.",
+        ))
+    }
+
+    fn set_control_by_id(&self, _control_id: u64, _value: i64) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Audio source doesn't have controls.",
+        ))
+    }
+
+    fn set_control_string_by_id(&self, _control_id: u64, _value: &str) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Audio source doesn't have controls.",
+        ))
+    }
+
+    fn control_value_by_name(&self, _control_name: &str) -> std::io::Result<i64> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Audio source doesn't have controls.",
+        ))
+    }
+
+    fn control_value_by_id(&self, _control_id: u64) -> std::io::Result<i64> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Audio source doesn't have controls.",
+        ))
+    }
+
+    fn controls(&self) -> Vec<Control> {
+        vec![]
+    }
+
+    fn is_valid(&self) -> bool {
+        match &self.source {
+            VideoSourceAudioType::Alsa(device) => !device.is_empty(),
+        }
+    }
+
+    fn is_shareable(&self) -> bool {
+        return false;
+    }
+}
+
+impl VideoSourceAvailable for VideoSourceAudio {
+    fn cameras_available() -> Vec<VideoSourceType> {
+        let pcm = match std::fs::read_to_string("/proc/asound/pcm") {
+            Ok(pcm) => pcm,
+            Err(error) => {
+                debug!("Failed to read ALSA devices: {error:#?}");
+                return vec![];
+            }
+        };
+
+        pcm.lines()
+            .filter_map(VideoSourceAudio::from_proc_asound_pcm_line)
+            .map(VideoSourceType::Audio)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proc_asound_pcm_decode() {
+        let lines = vec![
+            (
+                Some(VideoSourceAudio {
+                    name: "USB Audio".into(),
+                    source: VideoSourceAudioType::Alsa("hw:1,0".into()),
+                }),
+                "01-00: USB Audio : USB Audio : capture 1",
+            ),
+            (
+                Some(VideoSourceAudio {
+                    name: "ALC1220 Analog".into(),
+                    source: VideoSourceAudioType::Alsa("hw:0,0".into()),
+                }),
+                "00-00: ALC1220 Analog : ALC1220 Analog : playback 1 : capture 1",
+            ),
+            (
+                // Playback only devices are not audio sources
+                None,
+                "00-03: HDMI 0 : HDMI 0 : playback 1",
+            ),
+        ];
+
+        for (expected, line) in lines {
+            assert_eq!(expected, VideoSourceAudio::from_proc_asound_pcm_line(line));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use super::types::*;
+use super::video_source::{VideoSource, VideoSourceAvailable};
+
+use serde::{Deserialize, Serialize};
+
+// Video file played in a loop as if it were a camera, e.g: "file:///home/pi/dive.mp4",
+// useful to test the streams and MAVLink without any hardware (e.g: SITL setups).
+// The file is decoded and the frames are encoded again by our streams.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VideoSourceFile {
+    pub name: String,
+    pub url: String,
+}
+
+impl VideoSourceFile {
+    // Files are not discovered, they are created from the source string of the streams
+    pub fn from_url(url: &str) -> Option<Self> {
+        let parsed_url = url::Url::parse(url).ok()?;
+        if parsed_url.scheme() != "file" {
+            return None;
+        }
+
+        let path = parsed_url.to_file_path().ok()?;
+        Some(VideoSourceFile {
+            name: format!("File {}", path.file_name()?.to_string_lossy()),
+            url: url.to_string(),
+        })
+    }
+
+    pub fn path(&self) -> Option<std::path::PathBuf> {
+        url::Url::parse(&self.url).ok()?.to_file_path().ok()
+    }
+}
+
+impl VideoSource for VideoSourceFile {
+    fn name(&self) -> &String {
+        return &self.name;
+    }
+
+    fn source_string(&self) -> &str {
+        return &self.url;
+    }
+
+    fn formats(&self) -> Vec<Format> {
+        // The frames are scaled and their rate changed to the configured ones
+        let intervals: Vec<FrameInterval> = [60, 30, 25, 15]
+            .iter()
+            .map(|&frame_interval| FrameInterval {
+                denominator: frame_interval,
+                numerator: 1,
+            })
+            .collect();
+
+        let sizes: Vec<Size> = [(640, 480), (1280, 720), (1920, 1080)]
+            .iter()
+            .map(|&(width, height)| Size {
+                width,
+                height,
+                intervals: intervals.clone(),
+            })
+            .collect();
+
+        [
+            VideoEncodeType::H264,
+            VideoEncodeType::YUYV,
+            VideoEncodeType::MJPG,
+        ]
+        .into_iter()
+        .map(|encode| Format {
+            encode,
+            sizes: sizes.clone(),
+        })
+        .collect()
+    }
+
+    fn set_control_by_name(&self, _control_name: &str, _value: i64) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "File source doesn't have controls.",
+        ))
+    }
+
+    fn set_control_by_id(&self, _control_id: u64, _value: i64) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "File source doesn't have controls.",
+        ))
+    }
+
+    fn set_control_string_by_id(&self, _control_id: u64, _value: &str) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "File source doesn't have controls.",
+        ))
+    }
+
+    fn control_value_by_name(&self, _control_name: &str) -> std::io::Result<i64> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "File source doesn't have controls.",
+        ))
+    }
+
+    fn control_value_by_id(&self, _control_id: u64) -> std::io::Result<i64> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "File source doesn't have controls.",
+        ))
+    }
+
+    fn controls(&self) -> Vec<Control> {
+        vec![]
+    }
+
+    fn is_valid(&self) -> bool {
+        self.path().map_or(false, |path| path.is_file())
+    }
+
+    fn is_shareable(&self) -> bool {
+        // Each stream reads the file by itself
+        return true;
+    }
+}
+
+impl VideoSourceAvailable for VideoSourceFile {
+    fn cameras_available() -> Vec<VideoSourceType> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_source_from_url() {
+        let source = VideoSourceFile::from_url("file:///home/pi/videos/dive.mp4").unwrap();
+        assert_eq!(source.name, "File dive.mp4");
+        assert_eq!(source.source_string(), "file:///home/pi/videos/dive.mp4");
+        assert_eq!(
+            source.path(),
+            Some(std::path::PathBuf::from("/home/pi/videos/dive.mp4"))
+        );
+
+        assert!(VideoSourceFile::from_url("rtsp://192.168.2.10:554/stream").is_none());
+        assert!(VideoSourceFile::from_url("/home/pi/videos/dive.mp4").is_none());
+    }
+}
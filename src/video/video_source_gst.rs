@@ -1,15 +1,29 @@
 use super::types::*;
 use super::video_source::{VideoSource, VideoSourceAvailable};
-use super::video_source_local::VideoSourceLocal;
+use crate::settings;
 
+use gstreamer::prelude::*;
 use paperclip::actix::Apiv2Schema;
 use serde::{Deserialize, Serialize};
+use simple_error::{simple_error, SimpleResult};
+use tracing::*;
 
 #[derive(Apiv2Schema, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum VideoSourceGstType {
-    // TODO: local should have a pipeline also
-    Local(VideoSourceLocal),
+    // Source registered by the user, as a GStreamer description providing raw frames,
+    // e.g: "aravissrc camera-name=Basler-21234567" or "nvarguscamerasrc ! nvvidconv"
+    Local(String),
+    // videotestsrc pattern, optionally followed by its settings as a query,
+    // e.g: "smpte?timestamp=true" to show a moving timestamp, or "black?text=No+signal"
     Fake(String),
+    // Camera name used by libcamerasrc, e.g: "/base/soc/i2c0mux/i2c@1/imx708@1a".
+    // Raspberry Pi cameras are only available through libcamera since Bullseye.
+    Libcamera(String),
+    // Device path used by mfvideosrc, the Media Foundation source of Windows,
+    // e.g: "\\?\usb#vid_046d&pid_082d&mi_00#..."
+    MediaFoundation(String),
+    // Device index used by avfvideosrc, the AVFoundation source of macOS, e.g: "0" for the built-in camera
+    AVFoundation(String),
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -25,15 +39,22 @@ impl VideoSource for VideoSourceGst {
 
     fn source_string(&self) -> &str {
         match &self.source {
-            VideoSourceGstType::Local(local) => &local.source_string(),
+            VideoSourceGstType::Local(description) => &description,
             VideoSourceGstType::Fake(string) => &string,
+            VideoSourceGstType::Libcamera(camera_name) => &camera_name,
+            VideoSourceGstType::MediaFoundation(device_path) => &device_path,
+            VideoSourceGstType::AVFoundation(device_index) => &device_index,
         }
     }
 
     fn formats(&self) -> Vec<Format> {
         match &self.source {
-            VideoSourceGstType::Local(local) => local.formats(),
-            VideoSourceGstType::Fake(_) => {
+            // All provide raw frames, encoded by us
+            VideoSourceGstType::Local(_)
+            | VideoSourceGstType::Fake(_)
+            | VideoSourceGstType::Libcamera(_)
+            | VideoSourceGstType::MediaFoundation(_)
+            | VideoSourceGstType::AVFoundation(_) => {
                 let intervals: Vec<FrameInterval> = [60, 30, 24, 16, 10, 5]
                     .iter()
                     .map(|&frame_interval| FrameInterval {
@@ -92,6 +113,13 @@ impl VideoSource for VideoSourceGst {
         ))
     }
 
+    fn set_control_string_by_id(&self, _control_id: u64, _value: &str) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Source doesn't have controls.",
+        ))
+    }
+
     fn control_value_by_name(&self, _control_name: &str) -> std::io::Result<i64> {
         Err(std::io::Error::new(
             std::io::ErrorKind::NotFound,
@@ -112,14 +140,11 @@ impl VideoSource for VideoSourceGst {
 
     fn is_valid(&self) -> bool {
         match &self.source {
-            VideoSourceGstType::Local(local) => local.is_valid(),
-            VideoSourceGstType::Fake(string) => match string.as_str() {
-                // All valid members are from: https://gstreamer.freedesktop.org/documentation/videotestsrc/index.html?gi-language=c#members-2
-                "ball" | "bar" | "black" | "blink" | "blue" | "chroma" | "circular" | "gamut"
-                | "gradient" | "green" | "pinwheel" | "red" | "smpte" | "smpte100" | "smpte75"
-                | "snow" | "solid" | "spokes" | "white" | "zone" => true,
-                _ => false,
-            },
+            VideoSourceGstType::Local(description) => !description.is_empty(),
+            VideoSourceGstType::Fake(string) => FakeSettings::from_str(string).is_some(),
+            VideoSourceGstType::Libcamera(camera_name) => !camera_name.is_empty(),
+            VideoSourceGstType::MediaFoundation(device_path) => !device_path.is_empty(),
+            VideoSourceGstType::AVFoundation(device_index) => device_index.parse::<i32>().is_ok(),
         }
     }
 
@@ -128,11 +153,212 @@ impl VideoSource for VideoSourceGst {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct FakeSettings {
+    pub pattern: String,
+    // Shows the running time of the stream, useful to measure the latency
+    pub timestamp: bool,
+    // Shown in the middle of the pattern
+    pub text: Option<String>,
+}
+
+impl FakeSettings {
+    pub fn from_str(description: &str) -> Option<Self> {
+        let (pattern, query) = description.split_once('?').unwrap_or((description, ""));
+
+        // All valid members are from: https://gstreamer.freedesktop.org/documentation/videotestsrc/index.html?gi-language=c#members-2
+        if !matches!(
+            pattern,
+            "ball"
+                | "bar"
+                | "black"
+                | "blink"
+                | "blue"
+                | "chroma"
+                | "circular"
+                | "gamut"
+                | "gradient"
+                | "green"
+                | "pinwheel"
+                | "red"
+                | "smpte"
+                | "smpte100"
+                | "smpte75"
+                | "snow"
+                | "solid"
+                | "spokes"
+                | "white"
+                | "zone"
+        ) {
+            return None;
+        }
+
+        let mut settings = FakeSettings {
+            pattern: pattern.to_string(),
+            timestamp: false,
+            text: None,
+        };
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "timestamp" => settings.timestamp = value.parse().ok()?,
+                "text" => settings.text = Some(value.to_string()),
+                _ => return None,
+            }
+        }
+        Some(settings)
+    }
+}
+
+impl VideoSourceGst {
+    // Fake sources with any pattern can be used, not only the listed one
+    pub fn fake_from_str(description: &str) -> Option<Self> {
+        let settings = FakeSettings::from_str(description)?;
+        Some(VideoSourceGst {
+            name: format!("Fake source ({})", settings.pattern),
+            source: VideoSourceGstType::Fake(description.to_string()),
+        })
+    }
+}
+
 impl VideoSourceAvailable for VideoSourceGst {
     fn cameras_available() -> Vec<VideoSourceType> {
-        vec![VideoSourceType::Gst(VideoSourceGst {
+        let mut cameras = vec![VideoSourceType::Gst(VideoSourceGst {
             name: "Fake source".into(),
             source: VideoSourceGstType::Fake("ball".into()),
-        })]
+        })];
+        cameras.extend(
+            settings::manager::gst_sources()
+                .into_iter()
+                .map(VideoSourceType::Gst),
+        );
+        cameras
+    }
+}
+
+// Register a source described by the user, replacing any other with the same name
+pub fn add_custom_source(name: &str, description: &str) -> SimpleResult<()> {
+    let description = description.trim();
+    if name.is_empty() || description.is_empty() {
+        return Err(simple_error!(
+            "The source name and description should not be empty."
+        ));
+    }
+
+    if let Err(error) = gstreamer::init() {
+        return Err(simple_error!(format!("Failed to init GStreamer: {error}")));
+    }
+    // The elements are only created, the source is not started
+    if let Err(error) = gstreamer::parse_bin_from_description(description, true) {
+        return Err(simple_error!(format!(
+            "Invalid source description {description:?}: {error}"
+        )));
+    }
+
+    settings::manager::add_gst_source(VideoSourceGst {
+        name: name.to_string(),
+        source: VideoSourceGstType::Local(description.to_string()),
+    });
+    Ok(())
+}
+
+pub fn remove_custom_source(name: &str) -> SimpleResult<()> {
+    if !settings::manager::remove_gst_source(name) {
+        return Err(simple_error!(format!("Source {name:?} does not exist.")));
+    }
+    Ok(())
+}
+
+// Cameras listed by the libcamera device provider, available when the
+// libcamera GStreamer plugin is installed
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn libcamera_cameras() -> Vec<VideoSourceType> {
+    provider_cameras("libcameraprovider", |element| {
+        let camera_name = element.property::<Option<String>>("camera-name")?;
+        Some(VideoSourceGstType::Libcamera(camera_name))
+    })
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn media_foundation_cameras() -> Vec<VideoSourceType> {
+    provider_cameras("mfdeviceprovider", |element| {
+        let device_path = element.property::<Option<String>>("device-path")?;
+        Some(VideoSourceGstType::MediaFoundation(device_path))
+    })
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn avfoundation_cameras() -> Vec<VideoSourceType> {
+    provider_cameras("avfdeviceprovider", |element| {
+        let device_index = element.property::<i32>("device-index");
+        Some(VideoSourceGstType::AVFoundation(device_index.to_string()))
+    })
+}
+
+// Cameras listed by a GStreamer device provider, identified by the properties of their source element
+fn provider_cameras(
+    provider_name: &str,
+    source: impl Fn(&gstreamer::Element) -> Option<VideoSourceGstType>,
+) -> Vec<VideoSourceType> {
+    if let Err(error) = gstreamer::init() {
+        debug!("Failed to init GStreamer: {error}");
+        return vec![];
+    }
+
+    let provider = match gstreamer::DeviceProviderFactory::find(provider_name)
+        .and_then(|factory| factory.get())
+    {
+        Some(provider) => provider,
+        None => {
+            trace!("Device provider {provider_name:?} is not available.");
+            return vec![];
+        }
+    };
+
+    provider
+        .devices()
+        .iter()
+        .filter_map(|device| {
+            let element = device.create_element(None).ok()?;
+            Some(VideoSourceType::Gst(VideoSourceGst {
+                name: device.display_name().to_string(),
+                source: source(&element)?,
+            }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fake_settings_decode() {
+        assert_eq!(
+            FakeSettings::from_str("ball"),
+            Some(FakeSettings {
+                pattern: "ball".into(),
+                timestamp: false,
+                text: None,
+            })
+        );
+        assert_eq!(
+            FakeSettings::from_str("smpte?timestamp=true"),
+            Some(FakeSettings {
+                pattern: "smpte".into(),
+                timestamp: true,
+                text: None,
+            })
+        );
+        assert_eq!(
+            FakeSettings::from_str("black?text=Camera+lost"),
+            Some(FakeSettings {
+                pattern: "black".into(),
+                timestamp: false,
+                text: Some("Camera lost".into()),
+            })
+        );
+        assert_eq!(FakeSettings::from_str("potato"), None);
+        assert_eq!(FakeSettings::from_str("ball?timestamp=yes"), None);
+        assert_eq!(FakeSettings::from_str("ball?potato=true"), None);
     }
 }
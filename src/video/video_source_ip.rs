@@ -0,0 +1,158 @@
+use super::types::*;
+use super::video_source::{VideoSource, VideoSourceAvailable};
+
+use serde::{Deserialize, Serialize};
+
+// Network camera serving RTSP, e.g: "rtsp://192.168.2.10:554/stream",
+// or MJPEG over HTTP, e.g: "http://192.168.2.11:8080/video".
+// Its video is received and served again by our streams, without being decoded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VideoSourceIp {
+    pub name: String,
+    pub url: String,
+}
+
+impl VideoSourceIp {
+    // IP cameras are not discovered, they are created from the source string of the streams
+    pub fn from_url(url: &str) -> Option<Self> {
+        let parsed_url = url::Url::parse(url).ok()?;
+        let kind = match parsed_url.scheme() {
+            "rtsp" | "rtsps" => "IP",
+            "http" | "https" => "HTTP",
+            _ => return None,
+        };
+
+        Some(VideoSourceIp {
+            name: format!("{kind} camera {}", parsed_url.host_str()?),
+            url: url.to_string(),
+        })
+    }
+
+    // Cheap Wi-Fi cameras serve their frames as a multipart JPEG stream
+    pub fn is_http(&self) -> bool {
+        self.url.starts_with("http")
+    }
+}
+
+impl VideoSource for VideoSourceIp {
+    fn name(&self) -> &String {
+        return &self.name;
+    }
+
+    fn source_string(&self) -> &str {
+        return &self.url;
+    }
+
+    fn formats(&self) -> Vec<Format> {
+        // The size and frame rate are defined by the camera, these are only the usual ones
+        let intervals: Vec<FrameInterval> = [30, 25, 15]
+            .iter()
+            .map(|&frame_interval| FrameInterval {
+                denominator: frame_interval,
+                numerator: 1,
+            })
+            .collect();
+
+        let sizes: Vec<Size> = [(640, 480), (1280, 720), (1920, 1080), (3840, 2160)]
+            .iter()
+            .map(|&(width, height)| Size {
+                width,
+                height,
+                intervals: intervals.clone(),
+            })
+            .collect();
+
+        let encodes = match self.is_http() {
+            true => vec![VideoEncodeType::MJPG],
+            false => vec![
+                VideoEncodeType::H264,
+                VideoEncodeType::H265,
+                VideoEncodeType::MJPG,
+            ],
+        };
+
+        encodes
+            .into_iter()
+            .map(|encode| Format {
+                encode,
+                sizes: sizes.clone(),
+            })
+            .collect()
+    }
+
+    fn set_control_by_name(&self, _control_name: &str, _value: i64) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "IP source doesn't have controls.",
+        ))
+    }
+
+    fn set_control_by_id(&self, _control_id: u64, _value: i64) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "IP source doesn't have controls.",
+        ))
+    }
+
+    fn set_control_string_by_id(&self, _control_id: u64, _value: &str) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "IP source doesn't have controls.",
+        ))
+    }
+
+    fn control_value_by_name(&self, _control_name: &str) -> std::io::Result<i64> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "IP source doesn't have controls.",
+        ))
+    }
+
+    fn control_value_by_id(&self, _control_id: u64) -> std::io::Result<i64> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "IP source doesn't have controls.",
+        ))
+    }
+
+    fn controls(&self) -> Vec<Control> {
+        vec![]
+    }
+
+    fn is_valid(&self) -> bool {
+        VideoSourceIp::from_url(&self.url).is_some()
+    }
+
+    fn is_shareable(&self) -> bool {
+        // RTSP servers accept multiple clients
+        return true;
+    }
+}
+
+impl VideoSourceAvailable for VideoSourceIp {
+    fn cameras_available() -> Vec<VideoSourceType> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ip_source_from_url() {
+        let source = VideoSourceIp::from_url("rtsp://192.168.2.10:554/stream").unwrap();
+        assert_eq!(source.name, "IP camera 192.168.2.10");
+        assert_eq!(source.source_string(), "rtsp://192.168.2.10:554/stream");
+
+        assert!(!source.is_http());
+
+        let source = VideoSourceIp::from_url("http://192.168.2.11:8080/video").unwrap();
+        assert_eq!(source.name, "HTTP camera 192.168.2.11");
+        assert!(source.is_http());
+        assert_eq!(source.formats().len(), 1);
+
+        assert!(VideoSourceIp::from_url("udp://192.168.2.10:5600").is_none());
+        assert!(VideoSourceIp::from_url("/dev/video0").is_none());
+    }
+}
@@ -1,8 +1,11 @@
 use std::cmp::max;
 
+use crate::event::{self, manager::EventKind};
+use crate::settings::{self, manager::CameraFilter};
+
 use super::types::*;
 use super::{
-    video_source,
+    dv_timings, ext_controls, media_controller, quirks, uvc_h264, video_source,
     video_source::{VideoSource, VideoSourceAvailable},
 };
 use paperclip::actix::Apiv2Schema;
@@ -19,6 +22,8 @@ pub enum VideoSourceLocalType {
     Unknown(String),
     Usb(String),
     LegacyRpiCam(String),
+    // HDMI receivers, like tc358743 bridges, which are identified by their DV timings support
+    Hdmi(String),
 }
 
 #[derive(Apiv2Schema, Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -27,6 +32,69 @@ pub struct VideoSourceLocal {
     pub device_path: String,
     #[serde(rename = "type")]
     pub typ: VideoSourceLocalType,
+    // Only for USB cameras
+    #[serde(default)]
+    pub usb: Option<UsbDescriptor>,
+}
+
+// Identification of USB cameras, from the sysfs attributes of their USB device.
+// Unlike the bus information, the serial number doesn't change with the port.
+#[derive(Apiv2Schema, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct UsbDescriptor {
+    // Hexadecimal, e.g: "046d"
+    pub vendor_id: String,
+    pub product_id: String,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    // Device release number, e.g: "0.11"
+    pub firmware_version: Option<String>,
+    // Kernel driver, e.g: "uvcvideo", as reported by V4L2
+    pub driver: Option<String>,
+}
+
+impl UsbDescriptor {
+    pub fn from_device(device_path: &str) -> Option<Self> {
+        let usb_device_path = usb_device_path(device_path)?;
+        let read = |name: &str| -> Option<String> {
+            let value = std::fs::read_to_string(usb_device_path.join(name)).ok()?;
+            let value = value.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        };
+
+        Some(Self {
+            vendor_id: read("idVendor")?,
+            product_id: read("idProduct")?,
+            serial_number: read("serial"),
+            manufacturer: read("manufacturer"),
+            product: read("product"),
+            firmware_version: read("bcdDevice").map(|bcd| firmware_version(&bcd)),
+            driver: None,
+        })
+    }
+
+    // As "vid:pid"
+    pub fn usb_id(&self) -> String {
+        format!("{}:{}", self.vendor_id, self.product_id)
+    }
+
+    // Same camera model and serial number, cameras without serial number can't be told apart
+    fn is_same_camera(&self, other: &UsbDescriptor) -> bool {
+        self.serial_number.is_some()
+            && self.serial_number == other.serial_number
+            && self.vendor_id == other.vendor_id
+            && self.product_id == other.product_id
+    }
+}
+
+// The release number is binary coded decimal, e.g: "0011" for 0.11
+fn firmware_version(bcd: &str) -> String {
+    if bcd.len() != 4 {
+        return bcd.to_string();
+    }
+    let (major, minor) = bcd.split_at(2);
+    let major = major.trim_start_matches('0');
+    format!("{}.{minor}", if major.is_empty() { "0" } else { major })
 }
 
 impl VideoSourceLocalType {
@@ -72,6 +140,16 @@ impl VideoSourceLocalType {
         return None;
     }
 
+    // The bus information reported by the device, e.g: "usb-0000:08:00.3-1"
+    pub fn bus_info(&self) -> &str {
+        match self {
+            VideoSourceLocalType::Unknown(description)
+            | VideoSourceLocalType::Usb(description)
+            | VideoSourceLocalType::LegacyRpiCam(description)
+            | VideoSourceLocalType::Hdmi(description) => description,
+        }
+    }
+
     fn v4l2_from_str(description: &str) -> Option<Self> {
         let regex = Regex::new(r"platform:(?P<device>\S+)-v4l2-[0-9]").unwrap();
         if regex.is_match(description) {
@@ -85,33 +163,57 @@ impl VideoSourceLocal {
     pub fn update_device(&mut self) -> bool {
         if let VideoSourceLocalType::Usb(our_usb_bus) = &self.typ {
             let cameras = video_source::cameras_available();
-            let camera: Option<VideoSourceType> = cameras
-                .into_iter()
-                .filter(|camera| match camera {
-                    VideoSourceType::Local(camera) => match &camera.typ {
-                        VideoSourceLocalType::Usb(usb_bus) => *usb_bus == *our_usb_bus,
-                        _ => false,
-                    },
-                    _ => false,
+            // Cameras with serial number are followed to other ports
+            let same_serial = |camera: &VideoSourceLocal| match (&self.usb, &camera.usb) {
+                (Some(ours), Some(theirs)) => ours.is_same_camera(theirs),
+                _ => false,
+            };
+            let same_bus = |camera: &VideoSourceLocal| match &camera.typ {
+                VideoSourceLocalType::Usb(usb_bus) => *usb_bus == *our_usb_bus,
+                _ => false,
+            };
+            let local_cameras = || {
+                cameras.iter().filter_map(|camera| match camera {
+                    VideoSourceType::Local(camera) => Some(camera),
+                    _ => None,
                 })
-                .next();
+            };
+            let camera: Option<VideoSourceType> = local_cameras()
+                .find(|camera| same_serial(camera))
+                .or_else(|| local_cameras().find(|camera| same_bus(camera)))
+                .cloned()
+                .map(VideoSourceType::Local);
 
             match camera {
                 None => {
                     error!("Failed to find camera: {:#?}", self);
                     error!("Camera will be set as invalid.");
+                    event::manager::push(
+                        EventKind::Hotplug,
+                        format!("Camera {:#?} was not found", self.name),
+                    );
                     self.device_path = "".into();
                     return false;
                 }
                 Some(camera) => {
                     if let VideoSourceType::Local(camera) = camera {
-                        if camera.device_path == self.device_path {
+                        // Streams may be using another node of the same camera
+                        if camera.device_path == self.device_path
+                            || camera.capture_nodes().contains(&self.device_path)
+                        {
                             return true;
                         }
 
                         info!("Camera path changed.");
                         info!("Previous camera location: {:#?}", self);
                         info!("New camera location: {:#?}", camera);
+                        event::manager::push(
+                            EventKind::Hotplug,
+                            format!(
+                                "Camera {:#?} moved from {:#?} to {:#?}",
+                                self.name, self.device_path, camera.device_path
+                            ),
+                        );
                         *self = camera.clone();
                         return true;
                     }
@@ -121,66 +223,64 @@ impl VideoSourceLocal {
         }
         return true;
     }
-}
-
-fn convert_v4l_intervals(v4l_intervals: &[v4l::FrameInterval]) -> Vec<FrameInterval> {
-    let mut intervals: Vec<FrameInterval> = vec![];
-
-    v4l_intervals
-        .iter()
-        .for_each(|v4l_interval| match &v4l_interval.interval {
-            v4l::frameinterval::FrameIntervalEnum::Discrete(fraction) => {
-                intervals.push(FrameInterval {
-                    numerator: fraction.numerator,
-                    denominator: fraction.denominator,
-                })
-            }
-            v4l::frameinterval::FrameIntervalEnum::Stepwise(stepwise) => {
-                // To avoid a having a huge number of numerator/denominators, we
-                // arbitrarely set a minimum step of 5 units
-                let min_step = 5;
-                let numerator_step = max(stepwise.step.numerator, min_step);
-                let denominator_step = max(stepwise.step.denominator, min_step);
-
-                let numerators = (0..=stepwise.min.numerator)
-                    .step_by(numerator_step as usize)
-                    .chain(vec![stepwise.max.numerator])
-                    .collect::<Vec<u32>>();
-                let denominators = (0..=stepwise.min.denominator)
-                    .step_by(denominator_step as usize)
-                    .chain(vec![stepwise.max.denominator])
-                    .collect::<Vec<u32>>();
 
-                for numerator in &numerators {
-                    for denominator in &denominators {
-                        intervals.push(FrameInterval {
-                            numerator: max(1, *numerator),
-                            denominator: max(1, *denominator),
-                        });
-                    }
-                }
-            }
-        });
+    // Analog video capture devices (e.g: composite to USB converters) deliver interlaced frames
+    pub fn is_interlaced(&self) -> bool {
+        let format = match Device::with_path(&self.device_path).and_then(|device| device.format()) {
+            Ok(format) => format,
+            Err(_) => return false,
+        };
 
-    intervals.sort();
-    intervals.dedup();
-    intervals.reverse();
+        !matches!(
+            format.field_order,
+            v4l::format::FieldOrder::Any | v4l::format::FieldOrder::Progressive
+        )
+    }
 
-    intervals
-}
+    // Formats of this capture node only
+    pub fn node_formats(&self) -> Vec<Format> {
+        video_source::cached_formats(self.typ.bus_info(), &self.device_path, || {
+            self.enumerate_formats()
+        })
+    }
 
-impl VideoSource for VideoSourceLocal {
-    fn name(&self) -> &String {
-        return &self.name;
+    // Capture nodes of the same camera, the default one first. Cameras with multiple
+    // streaming interfaces (e.g: UVC cameras with H264 and MJPG ones) can capture from
+    // all of them at the same time.
+    pub fn capture_nodes(&self) -> Vec<String> {
+        let group = media_controller::video_node_groups()
+            .into_iter()
+            .find(|group| group.contains(&self.device_path));
+        match group {
+            Some(group) => group
+                .into_iter()
+                .filter(|path| *path == self.device_path || is_capture_node(path))
+                .collect(),
+            None => vec![self.device_path.clone()],
+        }
     }
 
-    fn source_string(&self) -> &str {
-        return &self.device_path;
+    // H264 is only received through the extension unit when there is no V4L2 format for it
+    pub fn uses_h264_extension_unit(&self) -> bool {
+        let has_h264_format = Device::with_path(&self.device_path)
+            .and_then(|device| enum_formats(&device, v4l::buffer::Type::VideoCapture))
+            .map(|descriptions| {
+                descriptions
+                    .iter()
+                    .any(|description| matches!(description.fourcc.str(), Ok("H264")))
+            })
+            .unwrap_or(false);
+
+        !has_h264_format && uvc_h264::extension_unit(&self.device_path).is_some()
     }
 
-    fn formats(&self) -> Vec<Format> {
+    fn enumerate_formats(&self) -> Vec<Format> {
         let device = Device::with_path(&self.device_path).unwrap();
-        let v4l_formats = device.enum_formats().unwrap_or_default();
+        let typ = match device.query_caps() {
+            Ok(caps) => buffer_type(caps.capabilities),
+            Err(_) => v4l::buffer::Type::VideoCapture,
+        };
+        let v4l_formats = enum_formats(&device, typ).unwrap_or_default();
         let mut formats = vec![];
 
         trace!("Checking resolutions for camera: {}", &self.device_path);
@@ -260,6 +360,46 @@ impl VideoSource for VideoSourceLocal {
             });
         }
 
+        // The H264 stream muxed by the extension unit has the same sizes of MJPG
+        if !formats
+            .iter()
+            .any(|format| format.encode == VideoEncodeType::H264)
+        {
+            let mjpg_sizes = formats
+                .iter()
+                .find(|format| format.encode == VideoEncodeType::MJPG)
+                .map(|format| format.sizes.clone());
+            if let Some(sizes) = mjpg_sizes {
+                if let Some(unit) = uvc_h264::extension_unit(&self.device_path) {
+                    debug!(
+                        "Camera {} has H264 through the extension unit {unit}.",
+                        &self.device_path
+                    );
+                    formats.push(Format {
+                        encode: VideoEncodeType::H264,
+                        sizes,
+                    });
+                }
+            }
+        }
+
+        // HDMI receivers only capture the mode sent by the HDMI source
+        if matches!(&self.typ, VideoSourceLocalType::Hdmi(_)) {
+            match dv_timings::prepare(&self.device_path) {
+                Ok(timings) => formats.iter_mut().for_each(|format| {
+                    format.sizes = vec![Size {
+                        width: timings.width,
+                        height: timings.height,
+                        intervals: vec![timings.frame_interval.clone()],
+                    }]
+                }),
+                Err(error) => warn!(
+                    "Failed to get the HDMI mode of {}, is the source connected? {error}",
+                    &self.device_path
+                ),
+            }
+        }
+
         // V4l2 reports unsupported sizes for Raspberry Pi
         // Cameras in Legacy Mode, showing the following:
         // > mmal: mmal_vc_port_enable: failed to enable port vc.ril.video_encode:in:0(OPQV): EINVAL
@@ -294,69 +434,18 @@ impl VideoSource for VideoSourceLocal {
             });
         }
 
+        let quirks = quirks::for_camera(self);
+        formats
+            .iter_mut()
+            .for_each(|format| quirks.filter_sizes(&mut format.sizes));
+
         formats.sort();
         formats.dedup();
 
         formats
     }
 
-    fn set_control_by_name(&self, _control_name: &str, _value: i64) -> std::io::Result<()> {
-        unimplemented!();
-    }
-
-    fn set_control_by_id(&self, control_id: u64, value: i64) -> std::io::Result<()> {
-        let control = self
-            .controls()
-            .into_iter()
-            .find(|control| control.id == control_id);
-
-        if control.is_none() {
-            let ids: Vec<u64> = self.controls().iter().map(|control| control.id).collect();
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                format!(
-                    "Control ID '{}' is not valid, options are: {:?}",
-                    control_id, ids
-                ),
-            ));
-        }
-        let control = control.unwrap();
-
-        //TODO: Add control validation
-        let device = Device::with_path(&self.device_path)?;
-        //TODO: we should handle value, value64 and string
-        match device.set_control(
-            control_id as u32,
-            v4l::control::Control::Value(value as i32),
-        ) {
-            ok @ Ok(_) => ok,
-            Err(error) => {
-                warn!("Failed to set control {:#?}, error: {:#?}", control, error);
-                Err(error)
-            }
-        }
-    }
-
-    fn control_value_by_name(&self, _control_name: &str) -> std::io::Result<i64> {
-        unimplemented!();
-    }
-
-    fn control_value_by_id(&self, control_id: u64) -> std::io::Result<i64> {
-        let device = Device::with_path(&self.device_path)?;
-        let value = device.control(control_id as u32)?;
-        match value {
-            v4l::control::Control::String(_) => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "String control type is not supported.",
-                ));
-            }
-            v4l::control::Control::Value(value) => return Ok(value as i64),
-            v4l::control::Control::Value64(value) => return Ok(value),
-        }
-    }
-
-    fn controls(&self) -> Vec<Control> {
+    fn enumerate_controls(&self) -> Vec<Control> {
         //TODO: create function to encapsulate device
         let device = Device::with_path(&self.device_path).unwrap();
         let v4l_controls = device.query_controls().unwrap_or_default();
@@ -380,6 +469,24 @@ impl VideoSource for VideoSourceLocal {
                 continue;
             }
 
+            if matches!(v4l_control.typ, v4l::control::Type::String) {
+                match self.control_string(v4l_control.id) {
+                    Ok(value) => {
+                        control.cpp_type = "string".to_string();
+                        control.configuration = ControlType::String(ControlString {
+                            value,
+                            max_length: v4l_control.maximum.max(0) as u32,
+                        });
+                        controls.push(control);
+                    }
+                    Err(error) => error!(
+                        "Failed to get control '{} ({})' from device {}: {error}",
+                        control.name, control.id, self.device_path
+                    ),
+                }
+                continue;
+            }
+
             let value = self.control_value_by_id(v4l_control.id as u64);
             if let Err(error) = value {
                 error!(
@@ -397,14 +504,36 @@ impl VideoSource for VideoSourceLocal {
                     control.configuration = ControlType::Bool(ControlBool { default, value });
                     controls.push(control);
                 }
-                v4l::control::Type::Integer | v4l::control::Type::Integer64 => {
+                v4l::control::Type::Integer => {
                     control.cpp_type = "int64".to_string();
                     control.configuration = ControlType::Slider(ControlSlider {
-                        default,
+                        default: default as i64,
                         value,
-                        step: v4l_control.step,
-                        max: v4l_control.maximum,
-                        min: v4l_control.minimum,
+                        step: v4l_control.step as i64,
+                        max: v4l_control.maximum as i64,
+                        min: v4l_control.minimum as i64,
+                    });
+                    controls.push(control);
+                }
+                // The 32-bit description has the ranges truncated
+                v4l::control::Type::Integer64 => {
+                    let description = match ext_controls::description(&device, v4l_control.id) {
+                        Ok(description) => description,
+                        Err(error) => {
+                            error!(
+                                "Failed to get description of control '{} ({})' from device {}: {error}",
+                                control.name, control.id, self.device_path
+                            );
+                            continue;
+                        }
+                    };
+                    control.cpp_type = "int64".to_string();
+                    control.configuration = ControlType::Slider(ControlSlider {
+                        default: description.default,
+                        value,
+                        step: description.step as i64,
+                        max: description.maximum,
+                        min: description.minimum,
                     });
                     controls.push(control);
                 }
@@ -435,6 +564,248 @@ impl VideoSource for VideoSourceLocal {
         return controls;
     }
 
+    fn control_string(&self, control_id: u32) -> std::io::Result<String> {
+        let device = Device::with_path(&self.device_path)?;
+        match ext_controls::control(&device, control_id)? {
+            v4l::control::Control::String(value) => Ok(value),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Control ID '{control_id}' is not a string control."),
+            )),
+        }
+    }
+}
+
+fn convert_v4l_intervals(v4l_intervals: &[v4l::FrameInterval]) -> Vec<FrameInterval> {
+    let mut intervals: Vec<FrameInterval> = vec![];
+
+    v4l_intervals
+        .iter()
+        .for_each(|v4l_interval| match &v4l_interval.interval {
+            v4l::frameinterval::FrameIntervalEnum::Discrete(fraction) => {
+                intervals.push(FrameInterval {
+                    numerator: fraction.numerator,
+                    denominator: fraction.denominator,
+                })
+            }
+            v4l::frameinterval::FrameIntervalEnum::Stepwise(stepwise) => {
+                // To avoid a having a huge number of numerator/denominators, we
+                // arbitrarely set a minimum step of 5 units
+                let min_step = 5;
+                let numerator_step = max(stepwise.step.numerator, min_step);
+                let denominator_step = max(stepwise.step.denominator, min_step);
+
+                let numerators = (0..=stepwise.min.numerator)
+                    .step_by(numerator_step as usize)
+                    .chain(vec![stepwise.max.numerator])
+                    .collect::<Vec<u32>>();
+                let denominators = (0..=stepwise.min.denominator)
+                    .step_by(denominator_step as usize)
+                    .chain(vec![stepwise.max.denominator])
+                    .collect::<Vec<u32>>();
+
+                for numerator in &numerators {
+                    for denominator in &denominators {
+                        intervals.push(FrameInterval {
+                            numerator: max(1, *numerator),
+                            denominator: max(1, *denominator),
+                        });
+                    }
+                }
+            }
+        });
+
+    intervals.sort();
+    intervals.dedup();
+    intervals.reverse();
+
+    intervals
+}
+
+impl VideoSource for VideoSourceLocal {
+    fn name(&self) -> &String {
+        return &self.name;
+    }
+
+    fn source_string(&self) -> &str {
+        return &self.device_path;
+    }
+
+    // Formats of all capture nodes of the camera, each stream is moved to the node
+    // providing its format, see video_source::stream_slots
+    fn formats(&self) -> Vec<Format> {
+        let mut formats = self.node_formats();
+        for device_path in self.capture_nodes() {
+            if device_path == self.device_path {
+                continue;
+            }
+            let node = VideoSourceLocal {
+                device_path,
+                ..self.clone()
+            };
+            for format in node.node_formats() {
+                if !formats.iter().any(|ours| ours.encode == format.encode) {
+                    formats.push(format);
+                }
+            }
+        }
+        formats
+    }
+
+    fn set_control_by_name(&self, _control_name: &str, _value: i64) -> std::io::Result<()> {
+        unimplemented!();
+    }
+
+    fn set_control_by_id(&self, control_id: u64, value: i64) -> std::io::Result<()> {
+        let control = self
+            .controls()
+            .into_iter()
+            .find(|control| control.id == control_id);
+
+        if control.is_none() {
+            let ids: Vec<u64> = self.controls().iter().map(|control| control.id).collect();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "Control ID '{}' is not valid, options are: {:?}",
+                    control_id, ids
+                ),
+            ));
+        }
+        let control = control.unwrap();
+
+        control
+            .validate(value)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidInput, error))?;
+
+        let device = Device::with_path(&self.device_path)?;
+        let result = match &control.configuration {
+            ControlType::String(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Control '{}' only accepts strings.", control.name),
+                ))
+            }
+            // 64-bit controls are only accessible with the extended controls API
+            _ if ext_controls::description(&device, control_id as u32)?.is_integer64 => {
+                ext_controls::set_control(
+                    &device,
+                    control_id as u32,
+                    v4l::control::Control::Value64(value),
+                )
+            }
+            _ => device.set_control(
+                control_id as u32,
+                v4l::control::Control::Value(value as i32),
+            ),
+        };
+        match result {
+            ok @ Ok(_) => ok,
+            Err(error) => {
+                warn!("Failed to set control {:#?}, error: {:#?}", control, error);
+                Err(error)
+            }
+        }
+    }
+
+    fn set_control_string_by_id(&self, control_id: u64, value: &str) -> std::io::Result<()> {
+        let control = self
+            .controls()
+            .into_iter()
+            .find(|control| control.id == control_id)
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("Control ID '{control_id}' is not valid."),
+                )
+            })?;
+
+        let max_length = match &control.configuration {
+            ControlType::String(string) => string.max_length,
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Control '{}' is not a string control.", control.name),
+                ))
+            }
+        };
+        if value.len() > max_length as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Control '{}' accepts up to {max_length} characters.",
+                    control.name
+                ),
+            ));
+        }
+
+        let device = Device::with_path(&self.device_path)?;
+        ext_controls::set_control(
+            &device,
+            control_id as u32,
+            v4l::control::Control::String(value.to_string()),
+        )
+        .map_err(|error| {
+            warn!("Failed to set control {:#?}, error: {:#?}", control, error);
+            error
+        })
+    }
+
+    fn control_value_by_name(&self, _control_name: &str) -> std::io::Result<i64> {
+        unimplemented!();
+    }
+
+    fn control_value_by_id(&self, control_id: u64) -> std::io::Result<i64> {
+        let device = Device::with_path(&self.device_path)?;
+        let value = ext_controls::control(&device, control_id as u32)?;
+        match value {
+            v4l::control::Control::String(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "String control type is not supported.",
+                ));
+            }
+            v4l::control::Control::Value(value) => return Ok(value as i64),
+            v4l::control::Control::Value64(value) => return Ok(value),
+        }
+    }
+
+    fn controls(&self) -> Vec<Control> {
+        let controls =
+            video_source::cached_controls(self.typ.bus_info(), &self.device_path, || {
+                self.enumerate_controls()
+            });
+
+        // Only the values are read again, which doesn't disturb running captures
+        controls
+            .into_iter()
+            .filter_map(|mut control| {
+                let result = match &mut control.configuration {
+                    ControlType::Bool(bool) => self
+                        .control_value_by_id(control.id)
+                        .map(|value| bool.value = value),
+                    ControlType::Slider(slider) => self
+                        .control_value_by_id(control.id)
+                        .map(|value| slider.value = value),
+                    ControlType::Menu(menu) => self
+                        .control_value_by_id(control.id)
+                        .map(|value| menu.value = value),
+                    ControlType::String(string) => self
+                        .control_string(control.id as u32)
+                        .map(|value| string.value = value),
+                };
+                if let Err(error) = result {
+                    debug!(
+                        "Failed to get control '{} ({})' from device {}: {error}",
+                        control.name, control.id, self.device_path
+                    );
+                    return None;
+                }
+                Some(control)
+            })
+            .collect()
+    }
+
     fn is_valid(&self) -> bool {
         return !self.device_path.is_empty();
     }
@@ -444,6 +815,131 @@ impl VideoSource for VideoSourceLocal {
     }
 }
 
+// Metadata, output and memory-to-memory nodes (e.g: ISPs, encoders and decoders)
+// also answer to VIDIOC_QUERYCAP, but they can never provide frames to our streams
+fn is_capture_device(capabilities: v4l::capability::Flags) -> bool {
+    use v4l::capability::Flags;
+
+    capabilities.intersects(Flags::VIDEO_CAPTURE | Flags::VIDEO_CAPTURE_MPLANE)
+        && capabilities.contains(Flags::STREAMING)
+        && !capabilities.intersects(
+            Flags::VIDEO_OUTPUT
+                | Flags::VIDEO_OUTPUT_MPLANE
+                | Flags::VIDEO_M2M
+                | Flags::VIDEO_M2M_MPLANE,
+        )
+}
+
+// Sysfs directory of the USB device of the video node, which belongs to one of its interfaces
+pub(super) fn usb_device_path(device_path: &str) -> Option<std::path::PathBuf> {
+    let name = std::path::Path::new(device_path).file_name()?;
+    let interface = std::fs::canonicalize(
+        std::path::Path::new("/sys/class/video4linux")
+            .join(name)
+            .join("device"),
+    )
+    .ok()?;
+    Some(interface.parent()?.to_path_buf())
+}
+
+fn is_capture_node(device_path: &str) -> bool {
+    let device = match Device::with_path(device_path) {
+        Ok(device) => device,
+        Err(_) => return false,
+    };
+    match device.query_caps() {
+        Ok(caps) => {
+            is_capture_device(caps.capabilities)
+                && check_format(&device, buffer_type(caps.capabilities)).is_ok()
+        }
+        Err(_) => false,
+    }
+}
+
+// Cameras excluded by the user, or not pinned when there is a whitelist
+fn is_allowed(camera: &VideoSourceLocal, camera_filter: &CameraFilter) -> bool {
+    let matches = |patterns: &Vec<String>| {
+        patterns.iter().any(|pattern| match Regex::new(pattern) {
+            Ok(regex) => [camera.typ.bus_info(), &camera.name, &camera.device_path]
+                .iter()
+                .any(|value| regex.is_match(value)),
+            Err(error) => {
+                warn!("Ignoring invalid camera pattern {pattern:?}: {error}");
+                false
+            }
+        })
+    };
+
+    if matches(&camera_filter.blacklist) {
+        trace!("Ignoring device {}, it is blacklisted.", camera.device_path);
+        return false;
+    }
+    if !camera_filter.whitelist.is_empty() && !matches(&camera_filter.whitelist) {
+        trace!(
+            "Ignoring device {}, it is not whitelisted.",
+            camera.device_path
+        );
+        return false;
+    }
+    true
+}
+
+// Multi-planar devices, like the CSI receivers of i.MX and Rockchip SoCs,
+// only accept the multi-planar buffer type
+fn buffer_type(capabilities: v4l::capability::Flags) -> v4l::buffer::Type {
+    use v4l::capability::Flags;
+
+    match capabilities.contains(Flags::VIDEO_CAPTURE) {
+        false if capabilities.contains(Flags::VIDEO_CAPTURE_MPLANE) => {
+            v4l::buffer::Type::VideoCaptureMplane
+        }
+        _ => v4l::buffer::Type::VideoCapture,
+    }
+}
+
+// Same as `Capture::enum_formats`, that only works with single-planar devices
+fn enum_formats(
+    device: &Device,
+    typ: v4l::buffer::Type,
+) -> std::io::Result<Vec<v4l::format::Description>> {
+    let mut formats = vec![];
+    let mut description: v4l::v4l_sys::v4l2_fmtdesc = unsafe { std::mem::zeroed() };
+    description.type_ = typ as u32;
+
+    loop {
+        let result = unsafe {
+            v4l::v4l2::ioctl(
+                device.handle().fd(),
+                v4l::v4l2::vidioc::VIDIOC_ENUM_FMT,
+                &mut description as *mut _ as *mut std::os::raw::c_void,
+            )
+        };
+        if let Err(error) = result {
+            // Enumeration ends with an error after the last format
+            if description.index == 0 {
+                return Err(error);
+            }
+            return Ok(formats);
+        }
+
+        formats.push(v4l::format::Description::from(description));
+        description.index += 1;
+    }
+}
+
+// Same as `Capture::format`, used only to check if the device accepts the buffer type
+fn check_format(device: &Device, typ: v4l::buffer::Type) -> std::io::Result<()> {
+    let mut format: v4l::v4l_sys::v4l2_format = unsafe { std::mem::zeroed() };
+    format.type_ = typ as u32;
+    unsafe {
+        v4l::v4l2::ioctl(
+            device.handle().fd(),
+            v4l::v4l2::vidioc::VIDIOC_G_FMT,
+            &mut format as *mut _ as *mut std::os::raw::c_void,
+        )
+    }
+}
+
 impl VideoSourceAvailable for VideoSourceLocal {
     fn cameras_available() -> Vec<VideoSourceType> {
         let cameras_path: Vec<String> = std::fs::read_dir("/dev/")
@@ -466,7 +962,15 @@ impl VideoSourceAvailable for VideoSourceLocal {
             }
             let caps = caps.unwrap();
 
-            if let Err(error) = camera.format() {
+            if !is_capture_device(caps.capabilities) {
+                debug!(
+                    "Ignoring device {camera_path}, it doesn't capture video: {:?}",
+                    caps.capabilities
+                );
+                continue;
+            }
+
+            if let Err(error) = check_format(&camera, buffer_type(caps.capabilities)) {
                 if error.kind() != std::io::ErrorKind::InvalidInput {
                     debug!(
                         "Failed to capture formats for device: {}\nError: {:#?}",
@@ -476,14 +980,63 @@ impl VideoSourceAvailable for VideoSourceLocal {
                 continue;
             }
 
+            let typ = match VideoSourceLocalType::from_str(&caps.bus) {
+                VideoSourceLocalType::Unknown(bus) if dv_timings::is_supported(camera_path) => {
+                    VideoSourceLocalType::Hdmi(bus)
+                }
+                typ => typ,
+            };
+            let usb = match typ {
+                VideoSourceLocalType::Usb(_) => {
+                    UsbDescriptor::from_device(camera_path).map(|usb| UsbDescriptor {
+                        driver: Some(caps.driver.clone()),
+                        ..usb
+                    })
+                }
+                _ => None,
+            };
             let source = VideoSourceLocal {
                 name: caps.card,
                 device_path: camera_path.clone(),
-                typ: VideoSourceLocalType::from_str(&caps.bus),
+                typ,
+                usb,
             };
             cameras.push(VideoSourceType::Local(source));
         }
 
+        // Cameras with multiple capture nodes are presented only once,
+        // using their default node, or the first one
+        let paths: Vec<String> = cameras
+            .iter()
+            .map(|camera| camera.inner().source_string().to_string())
+            .collect();
+        let groups = media_controller::video_node_groups();
+        cameras.retain(|camera| {
+            let path = camera.inner().source_string();
+            let group = match groups
+                .iter()
+                .find(|group| group.iter().any(|node| node == path))
+            {
+                Some(group) => group,
+                None => return true,
+            };
+            let primary = group.iter().find(|node| paths.contains(node));
+            if primary.map_or(true, |primary| primary == path) {
+                return true;
+            }
+            debug!(
+                "Ignoring device {path}, the same camera is available at {:?}.",
+                primary.unwrap()
+            );
+            false
+        });
+
+        let camera_filter = settings::manager::camera_filter();
+        cameras.retain(|camera| match camera {
+            VideoSourceType::Local(camera) => is_allowed(camera, &camera_filter),
+            _ => true,
+        });
+
         return cameras;
     }
 }
@@ -492,6 +1045,14 @@ impl VideoSourceAvailable for VideoSourceLocal {
 mod tests {
     use super::*;
 
+    #[test]
+    fn usb_firmware_version() {
+        assert_eq!(firmware_version("0011"), "0.11");
+        assert_eq!(firmware_version("1208"), "12.08");
+        assert_eq!(firmware_version("0100"), "1.00");
+        assert_eq!(firmware_version("abc"), "abc");
+    }
+
     #[test]
 
     fn bus_decode() {
@@ -528,6 +1089,63 @@ mod tests {
         }
     }
 
+    #[test]
+    fn capture_device_filter() {
+        use v4l::capability::Flags;
+
+        // USB camera
+        assert!(is_capture_device(
+            Flags::VIDEO_CAPTURE | Flags::STREAMING | Flags::EXT_PIX_FORMAT
+        ));
+        // UVC metadata node
+        assert!(!is_capture_device(
+            Flags::META_CAPTURE | Flags::STREAMING | Flags::EXT_PIX_FORMAT
+        ));
+        // Raspberry Pi hardware encoder
+        assert!(!is_capture_device(
+            Flags::VIDEO_M2M_MPLANE | Flags::STREAMING | Flags::EXT_PIX_FORMAT
+        ));
+        // Rockchip CSI receiver
+        assert!(is_capture_device(
+            Flags::VIDEO_CAPTURE_MPLANE | Flags::STREAMING
+        ));
+        assert_eq!(
+            buffer_type(Flags::VIDEO_CAPTURE_MPLANE | Flags::STREAMING),
+            v4l::buffer::Type::VideoCaptureMplane
+        );
+        // Capture without streaming I/O
+        assert!(!is_capture_device(Flags::VIDEO_CAPTURE | Flags::READ_WRITE));
+    }
+
+    #[test]
+    fn camera_filter() {
+        let camera = VideoSourceLocal {
+            name: "Flaky Capture Card".into(),
+            device_path: "/dev/video2".into(),
+            typ: VideoSourceLocalType::Usb("usb-0000:08:00.3-1".into()),
+            usb: None,
+        };
+        let filter = |blacklist: &[&str], whitelist: &[&str]| CameraFilter {
+            blacklist: blacklist
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+            whitelist: whitelist
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+        };
+
+        assert!(is_allowed(&camera, &filter(&[], &[])));
+        assert!(!is_allowed(&camera, &filter(&["Flaky"], &[])));
+        assert!(!is_allowed(&camera, &filter(&["^/dev/video2$"], &[])));
+        assert!(is_allowed(&camera, &filter(&["bcm2835-isp"], &[])));
+        assert!(is_allowed(&camera, &filter(&[], &["usb-0000:08:00.3"])));
+        assert!(!is_allowed(&camera, &filter(&[], &["/dev/video0"])));
+        // The blacklist wins
+        assert!(!is_allowed(&camera, &filter(&["Card"], &["Card"])));
+    }
+
     #[allow(dead_code)]
     fn simple_test() {
         for camera in VideoSourceLocal::cameras_available() {
@@ -48,6 +48,13 @@ impl VideoSource for VideoSourceRedirect {
         ))
     }
 
+    fn set_control_string_by_id(&self, _control_id: u64, _value: &str) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Redirect source doesn't have controls.",
+        ))
+    }
+
     fn control_value_by_name(&self, _control_name: &str) -> std::io::Result<i64> {
         Err(std::io::Error::new(
             std::io::ErrorKind::NotFound,
@@ -0,0 +1,208 @@
+use super::types::*;
+use super::video_source::{VideoSource, VideoSourceAvailable};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ScreenCapture {
+    // X11 display, e.g: ":0", and optionally the title of a single window to capture
+    X11 {
+        display: String,
+        window: Option<String>,
+    },
+    // Screen shared through PipeWire, used by Wayland desktops
+    PipeWire,
+}
+
+// Desktop of the companion computer, or a window like the one of a sonar application,
+// e.g: "screen::0", "screen::0?window=Ping%20Viewer" or "screen:pipewire"
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VideoSourceScreen {
+    pub name: String,
+    pub source: String,
+    pub capture: ScreenCapture,
+}
+
+impl VideoSourceScreen {
+    pub fn from_str(source: &str) -> Option<Self> {
+        let description = source.strip_prefix("screen:")?;
+        let (target, query) = description.split_once('?').unwrap_or((description, ""));
+
+        let mut window = None;
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "window" if !value.is_empty() => window = Some(value.to_string()),
+                _ => return None,
+            }
+        }
+
+        let (name, capture) = match target {
+            "pipewire" if window.is_none() => {
+                ("Screen (PipeWire)".to_string(), ScreenCapture::PipeWire)
+            }
+            display if display.starts_with(':') => (
+                match &window {
+                    Some(window) => format!("Window {window} ({display})"),
+                    None => format!("Screen {display}"),
+                },
+                ScreenCapture::X11 {
+                    display: display.to_string(),
+                    window,
+                },
+            ),
+            _ => return None,
+        };
+
+        Some(VideoSourceScreen {
+            name,
+            source: source.to_string(),
+            capture,
+        })
+    }
+}
+
+impl VideoSource for VideoSourceScreen {
+    fn name(&self) -> &String {
+        return &self.name;
+    }
+
+    fn source_string(&self) -> &str {
+        return &self.source;
+    }
+
+    fn formats(&self) -> Vec<Format> {
+        // The screen is scaled to the configured size
+        let intervals: Vec<FrameInterval> = [30, 15, 10, 5]
+            .iter()
+            .map(|&frame_interval| FrameInterval {
+                denominator: frame_interval,
+                numerator: 1,
+            })
+            .collect();
+
+        let sizes: Vec<Size> = [(640, 480), (1280, 720), (1920, 1080)]
+            .iter()
+            .map(|&(width, height)| Size {
+                width,
+                height,
+                intervals: intervals.clone(),
+            })
+            .collect();
+
+        [
+            VideoEncodeType::H264,
+            VideoEncodeType::YUYV,
+            VideoEncodeType::MJPG,
+        ]
+        .into_iter()
+        .map(|encode| Format {
+            encode,
+            sizes: sizes.clone(),
+        })
+        .collect()
+    }
+
+    fn set_control_by_name(&self, _control_name: &str, _value: i64) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Screen source doesn't have controls.",
+        ))
+    }
+
+    fn set_control_by_id(&self, _control_id: u64, _value: i64) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Screen source doesn't have controls.",
+        ))
+    }
+
+    fn set_control_string_by_id(&self, _control_id: u64, _value: &str) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Screen source doesn't have controls.",
+        ))
+    }
+
+    fn control_value_by_name(&self, _control_name: &str) -> std::io::Result<i64> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Screen source doesn't have controls.",
+        ))
+    }
+
+    fn control_value_by_id(&self, _control_id: u64) -> std::io::Result<i64> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Screen source doesn't have controls.",
+        ))
+    }
+
+    fn controls(&self) -> Vec<Control> {
+        vec![]
+    }
+
+    fn is_valid(&self) -> bool {
+        VideoSourceScreen::from_str(&self.source).is_some()
+    }
+
+    fn is_shareable(&self) -> bool {
+        return true;
+    }
+}
+
+impl VideoSourceAvailable for VideoSourceScreen {
+    // Only whole screens are listed, windows are created from the source string of the streams
+    fn cameras_available() -> Vec<VideoSourceType> {
+        let mut sources = vec![];
+
+        if let Ok(display) = std::env::var("DISPLAY") {
+            sources.extend(VideoSourceScreen::from_str(&format!("screen:{display}")));
+        }
+
+        let has_pipewire = std::env::var("XDG_RUNTIME_DIR").map_or(false, |runtime_dir| {
+            std::path::Path::new(&runtime_dir)
+                .join("pipewire-0")
+                .exists()
+        });
+        if has_pipewire {
+            sources.extend(VideoSourceScreen::from_str("screen:pipewire"));
+        }
+
+        sources.into_iter().map(VideoSourceType::Screen).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_source_from_str() {
+        let source = VideoSourceScreen::from_str("screen::0").unwrap();
+        assert_eq!(source.name, "Screen :0");
+        assert_eq!(
+            source.capture,
+            ScreenCapture::X11 {
+                display: ":0".into(),
+                window: None,
+            }
+        );
+
+        let source = VideoSourceScreen::from_str("screen::1?window=Ping%20Viewer").unwrap();
+        assert_eq!(source.name, "Window Ping Viewer (:1)");
+        assert_eq!(
+            source.capture,
+            ScreenCapture::X11 {
+                display: ":1".into(),
+                window: Some("Ping Viewer".into()),
+            }
+        );
+
+        let source = VideoSourceScreen::from_str("screen:pipewire").unwrap();
+        assert_eq!(source.capture, ScreenCapture::PipeWire);
+
+        assert!(VideoSourceScreen::from_str("screen:potato").is_none());
+        assert!(VideoSourceScreen::from_str("screen::0?potato=true").is_none());
+        assert!(VideoSourceScreen::from_str(":0").is_none());
+    }
+}
@@ -1,7 +1,8 @@
 use super::types::*;
-use super::video_source::VideoSource;
 use serde::Serialize;
 
+use std::hash::{Hash, Hasher};
+
 #[derive(Debug, Serialize)]
 #[serde(rename = "mavlinkcamera")]
 pub struct MavlinkCamera {
@@ -67,11 +68,11 @@ pub struct ParameterSlider {
     pub name: String,
     #[serde(rename = "type")]
     pub cpp_type: String,
-    pub default: i32,
+    pub default: i64,
     pub v4l_id: u64,
-    pub step: i32,
-    pub max: i32,
-    pub min: i32,
+    pub step: i64,
+    pub max: i64,
+    pub min: i64,
     pub description: Description,
 }
 
@@ -112,30 +113,76 @@ impl Description {
     }
 }
 
-pub fn from_video_source(video_source: &dyn VideoSource) -> String {
-    let controls = video_source.controls();
+// Stream parameter, added to the definition when the stream is encoded with a known bitrate
+pub const BITRATE_PARAMETER: &str = "VIDEO_BITRATE";
+pub const BITRATE_RANGE_KBPS: std::ops::RangeInclusive<i64> = 100..=50000;
 
+// Generated from the controls of the camera, so every camera has its settings in the GCS
+pub fn from_video_source(
+    video_source: &VideoSourceType,
+    bitrate_kbps: std::option::Option<u32>,
+) -> String {
+    let parameters = parameters(&video_source.inner().controls(), bitrate_kbps);
+
+    // USB cameras identify themselves
+    let (product, manufacturer) = match video_source {
+        #[cfg(target_os = "linux")]
+        VideoSourceType::Local(source) => match &source.usb {
+            Some(usb) => (usb.product.clone(), usb.manufacturer.clone()),
+            None => (None, None),
+        },
+        _ => (None, None),
+    };
     let definition = Definition {
-        version: 1,
+        version: version(&parameters).into(),
         model: Model {
-            body: video_source.name().clone(),
+            body: product.unwrap_or_else(|| video_source.inner().name().clone()),
         },
         vendor: Vendor {
-            body: "Missing".into(),
+            body: manufacturer.unwrap_or_else(|| "Missing".into()),
         },
     };
 
-    let parameters = controls
+    let mavlink_camera = MavlinkCamera {
+        definition,
+        parameters,
+    };
+
+    use quick_xml::se::to_string;
+    return to_string(&mavlink_camera).unwrap();
+}
+
+// Version of the definition generated for the camera, as advertised in CAMERA_INFORMATION
+#[cfg(feature = "mavlink")]
+pub fn definition_version(
+    video_source: &VideoSourceType,
+    bitrate_kbps: std::option::Option<u32>,
+) -> u16 {
+    version(&parameters(&video_source.inner().controls(), bitrate_kbps))
+}
+
+// GCSs cache the definitions by version, so it changes with the parameters
+fn version(parameters: &Parameters) -> u16 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    quick_xml::se::to_string(parameters)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    // Zero is not a valid version
+    (hasher.finish() as u16).max(1)
+}
+
+fn parameters(controls: &[Control], bitrate_kbps: std::option::Option<u32>) -> Parameters {
+    let mut parameters: Vec<ParameterType> = controls
         .iter()
-        .map(|control| match &control.configuration {
-            ControlType::Bool(bool_control) => ParameterType::Bool(ParameterBool {
+        .filter_map(|control| match &control.configuration {
+            ControlType::Bool(bool_control) => Some(ParameterType::Bool(ParameterBool {
                 name: control.id.to_string(),
                 cpp_type: control.cpp_type.clone(),
                 default: bool_control.default,
                 v4l_id: control.id,
                 description: Description::new(&control.name),
-            }),
-            ControlType::Slider(slider_control) => ParameterType::Slider(ParameterSlider {
+            })),
+            ControlType::Slider(slider_control) => Some(ParameterType::Slider(ParameterSlider {
                 name: control.id.to_string(),
                 cpp_type: control.cpp_type.clone(),
                 default: slider_control.default,
@@ -144,8 +191,8 @@ pub fn from_video_source(video_source: &dyn VideoSource) -> String {
                 step: slider_control.step,
                 max: slider_control.max,
                 min: slider_control.min,
-            }),
-            ControlType::Menu(menu_control) => ParameterType::Menu(ParameterMenu {
+            })),
+            ControlType::Menu(menu_control) => Some(ParameterType::Menu(ParameterMenu {
                 name: control.id.to_string(),
                 cpp_type: control.cpp_type.clone(),
                 default: menu_control.default,
@@ -161,19 +208,28 @@ pub fn from_video_source(video_source: &dyn VideoSource) -> String {
                         })
                         .collect(),
                 },
-            }),
+            })),
+            // Camera definitions only have numeric parameters
+            ControlType::String(_) => None,
         })
         .collect();
 
-    let mavlink_camera = MavlinkCamera {
-        definition,
-        parameters: Parameters {
-            parameter: parameters,
-        },
-    };
+    if let Some(bitrate_kbps) = bitrate_kbps {
+        parameters.push(ParameterType::Slider(ParameterSlider {
+            name: BITRATE_PARAMETER.into(),
+            cpp_type: "int32".into(),
+            default: bitrate_kbps as i64,
+            v4l_id: 0,
+            step: 100,
+            max: *BITRATE_RANGE_KBPS.end(),
+            min: *BITRATE_RANGE_KBPS.start(),
+            description: Description::new("Bitrate (kbps)"),
+        }));
+    }
 
-    use quick_xml::se::to_string;
-    return to_string(&mavlink_camera).unwrap();
+    Parameters {
+        parameter: parameters,
+    }
 }
 
 #[cfg(test)]
@@ -181,12 +237,13 @@ mod tests {
     use super::*;
     use quick_xml::se::to_string;
 
+    #[cfg(target_os = "linux")]
     #[test]
     fn test_device() {
         use crate::video::video_source;
         for camera in video_source::cameras_available() {
-            if let VideoSourceType::Local(camera) = camera {
-                let xml_string = from_video_source(&camera);
+            if let VideoSourceType::Local(_) = camera {
+                let xml_string = from_video_source(&camera, None);
                 println!("{}", xml_string);
             }
         }
@@ -53,6 +53,27 @@ impl VideoAndStreamInformation {
 
         return Ok(());
     }
+
+    // Copies of this information for each endpoint scheme, with only the endpoints using it
+    pub fn split_by_scheme(&self) -> Vec<VideoAndStreamInformation> {
+        let mut split: Vec<VideoAndStreamInformation> = vec![];
+        for endpoint in &self.stream_information.endpoints {
+            match split.iter_mut().find(|information| {
+                information.stream_information.endpoints[0].scheme() == endpoint.scheme()
+            }) {
+                Some(information) => information
+                    .stream_information
+                    .endpoints
+                    .push(endpoint.clone()),
+                None => {
+                    let mut information = self.clone();
+                    information.stream_information.endpoints = vec![endpoint.clone()];
+                    split.push(information);
+                }
+            }
+        }
+        split
+    }
 }
 
 //TODO: Add tests